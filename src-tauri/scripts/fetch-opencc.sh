@@ -0,0 +1,16 @@
+#!/bin/sh
+# 下载 OpenCC 指定版本的完整词典，覆盖 resources/opencc 中的同名文件
+set -eu
+
+OPENCC_VERSION="ver.1.1.9"
+BASE_URL="https://raw.githubusercontent.com/BYVoid/OpenCC/${OPENCC_VERSION}/data/dictionary"
+DEST="$(cd "$(dirname "$0")/.." && pwd)/resources/opencc"
+
+for file in \
+    STPhrases.txt STCharacters.txt \
+    TSPhrases.txt TSCharacters.txt \
+    TWPhrases.txt TWVariants.txt TWVariantsRevPhrases.txt \
+    HKVariants.txt; do
+    echo "下载 ${file}"
+    curl -fsSL "${BASE_URL}/${file}" -o "${DEST}/${file}"
+done
@@ -0,0 +1,934 @@
+// 跨书籍的操作：复制、合并、拆分书籍，以及在书籍之间移动章节
+use crate::database::{get_current_time_string, get_db_connection, Book, DbResponse};
+use crate::exporter::WrittenFiles;
+use crate::fileutil::{book_epub_dir, cover_path};
+use crate::importer::ImageNamer;
+use crate::setup::AppState;
+use crate::toc::{load_toc, sibling_ids, sync_toc, write_positions, TocItem};
+use regex::{Captures, Regex};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{command, AppHandle, Manager, State};
+
+// 章节移到其他书籍后两本书的目录树
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterMoveResult {
+    pub source_toc: Vec<TocItem>,
+    pub target_toc: Vec<TocItem>,
+}
+
+struct BookInfo {
+    title: String,
+    author: String,
+    description: String,
+}
+
+// 读取未放入回收站的书籍
+fn load_book_info(db: &Connection, book_id: i64) -> Result<BookInfo, String> {
+    db.query_row(
+        "SELECT title, author, description FROM ee_book WHERE id = ? AND COALESCE(isDel, 0) = 0",
+        params![book_id],
+        |row| {
+            Ok(BookInfo {
+                title: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                author: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                description: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            })
+        },
+    )
+    .map_err(|_| format!("书籍 {} 不存在", book_id))
+}
+
+fn insert_book(db: &Connection, info: &BookInfo, now: &str) -> Result<i64, String> {
+    db.execute(
+        "INSERT INTO ee_book (title, author, description, toc, isDel, createTime, updateTime) \
+         VALUES (?, ?, ?, '', 0, ?, ?)",
+        params![info.title, info.author, info.description, now, now],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(db.last_insert_rowid())
+}
+
+fn touch_book(db: &Connection, book_id: i64, now: &str) -> Result<(), String> {
+    db.execute(
+        "UPDATE ee_book SET updateTime = ? WHERE id = ?",
+        params![now, book_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// 重新生成目录 JSON 并返回书籍
+fn finish_book(db: &Connection, book_id: i64, info: BookInfo) -> Result<Book, String> {
+    let toc = sync_toc(db, book_id).map_err(|e| e.to_string())?;
+    Ok(Book {
+        id: book_id,
+        title: info.title,
+        author: info.author,
+        description: info.description,
+        toc: serde_json::to_string(&toc).unwrap_or_else(|_| "[]".to_string()),
+    })
+}
+
+// 目录树中每个章节的父章节和子章节，order 为按目录先序展开后的顺序
+#[derive(Default)]
+struct TocTree {
+    parents: HashMap<i64, Option<i64>>,
+    children: HashMap<i64, Vec<i64>>,
+    order: Vec<i64>,
+}
+
+impl TocTree {
+    fn new(items: &[TocItem]) -> Self {
+        let mut tree = TocTree::default();
+        tree.add(None, items);
+        tree
+    }
+
+    fn add(&mut self, parent_id: Option<i64>, items: &[TocItem]) {
+        for item in items {
+            self.parents.insert(item.href, parent_id);
+            self.order.push(item.href);
+            if let Some(subitems) = &item.subitems {
+                self.children
+                    .insert(item.href, subitems.iter().map(|sub| sub.href).collect());
+                self.add(Some(item.href), subitems);
+            }
+        }
+    }
+
+    fn contains(&self, id: i64) -> bool {
+        self.parents.contains_key(&id)
+    }
+
+    // 章节及其全部子孙章节，遇到 stop 中的章节时不再向下
+    fn subtree(&self, root: i64, stop: &HashSet<i64>) -> Vec<i64> {
+        let mut ids = vec![root];
+        let mut index = 0;
+        while index < ids.len() {
+            if let Some(children) = self.children.get(&ids[index]) {
+                ids.extend(children.iter().filter(|child| !stop.contains(child)));
+            }
+            index += 1;
+        }
+        ids
+    }
+
+    fn has_ancestor_in(&self, id: i64, ids: &HashSet<i64>) -> bool {
+        let mut current = self.parents.get(&id).copied().flatten();
+        while let Some(parent) = current {
+            if ids.contains(&parent) {
+                return true;
+            }
+            current = self.parents.get(&parent).copied().flatten();
+        }
+        false
+    }
+}
+
+// 一本书中已使用的 href，新加入的章节与已有章节重复时加上序号
+#[derive(Default)]
+pub struct HrefSet {
+    used: HashSet<String>,
+}
+
+impl HrefSet {
+    pub fn load(db: &Connection, book_id: i64) -> Result<Self, String> {
+        let mut stmt = db
+            .prepare("SELECT href FROM ee_chapter WHERE bookId = ?")
+            .map_err(|e| e.to_string())?;
+        let used = stmt
+            .query_map(params![book_id], |row| {
+                Ok(row.get::<_, Option<String>>(0)?.unwrap_or_default())
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<HashSet<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(HrefSet { used })
+    }
+
+    // 空 href 表示章节没有来源位置，不参与去重
+    pub fn unique(&mut self, href: &str) -> String {
+        if href.is_empty() {
+            return String::new();
+        }
+        let mut candidate = href.to_string();
+        let mut index = 2;
+        while !self.used.insert(candidate.clone()) {
+            candidate = format!("{}-{}", href, index);
+            index += 1;
+        }
+        candidate
+    }
+}
+
+fn images_dir(app_dir: &Path, book_id: i64) -> PathBuf {
+    book_epub_dir(app_dir, book_id).join("images")
+}
+
+fn image_names(dir: &Path) -> Result<Vec<String>, String> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| format!("读取图片目录失败: {}", e))? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_file() {
+            names.push(
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+            );
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+// 把图片复制到目标书籍，与已有图片重名且内容不同时改名
+struct ImageTransfer {
+    target_dir: PathBuf,
+    namer: ImageNamer,
+    existing: HashSet<String>,
+    pattern: Regex,
+}
+
+impl ImageTransfer {
+    fn new(app_dir: &Path, book_id: i64) -> Result<Self, String> {
+        let target_dir = images_dir(app_dir, book_id);
+        let existing: HashSet<String> = image_names(&target_dir)?.into_iter().collect();
+        let mut namer = ImageNamer::default();
+        for name in &existing {
+            namer.reserve(name);
+        }
+        Ok(ImageTransfer {
+            target_dir,
+            namer,
+            existing,
+            pattern: Regex::new(r#"((?:src|href)=")images/([^"]+)""#).expect("内置正则表达式有误"),
+        })
+    }
+
+    // 章节内容中引用的图片文件名
+    fn referenced(&self, content: &str) -> Vec<String> {
+        self.pattern
+            .captures_iter(content)
+            .map(|caps| caps[2].to_string())
+            .collect()
+    }
+
+    // 复制来源目录中的图片，返回原文件名到目标文件名的映射，来源中缺失的图片跳过
+    fn copy(
+        &mut self,
+        source_dir: &Path,
+        names: &[String],
+        written: &mut WrittenFiles,
+    ) -> Result<HashMap<String, String>, String> {
+        let mut renamed = HashMap::new();
+        for name in names {
+            if renamed.contains_key(name) || name.contains(['/', '\\']) {
+                continue;
+            }
+            let Ok(data) = fs::read(source_dir.join(name)) else {
+                continue;
+            };
+            let same_file = self.existing.contains(name)
+                && fs::read(self.target_dir.join(name)).is_ok_and(|existing| existing == data);
+            if same_file {
+                renamed.insert(name.clone(), name.clone());
+                continue;
+            }
+            let target = self.namer.name(name);
+            fs::create_dir_all(&self.target_dir).map_err(|e| format!("创建图片目录失败: {}", e))?;
+            written.write(self.target_dir.join(&target), &data)?;
+            self.existing.insert(target.clone());
+            renamed.insert(name.clone(), target);
+        }
+        Ok(renamed)
+    }
+
+    // 按映射改写内容中的图片引用
+    fn rewrite(&self, content: &str, renamed: &HashMap<String, String>) -> String {
+        if renamed.iter().all(|(from, to)| from == to) {
+            return content.to_string();
+        }
+        self.pattern
+            .replace_all(content, |caps: &Captures| {
+                let name = renamed
+                    .get(&caps[2])
+                    .map(String::as_str)
+                    .unwrap_or(&caps[2]);
+                format!("{}images/{}\"", &caps[1], name)
+            })
+            .into_owned()
+    }
+}
+
+fn copy_cover(
+    app_dir: &Path,
+    from_book: i64,
+    to_book: i64,
+    written: &mut WrittenFiles,
+) -> Result<bool, String> {
+    let Ok(data) = fs::read(cover_path(app_dir, from_book)) else {
+        return Ok(false);
+    };
+    let path = cover_path(app_dir, to_book);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("创建封面目录失败: {}", e))?;
+    }
+    written.write(path, &data)?;
+    Ok(true)
+}
+
+struct ChapterRow {
+    label: String,
+    href: String,
+    content: String,
+}
+
+fn load_chapter_rows(db: &Connection, book_id: i64) -> Result<Vec<(i64, ChapterRow)>, String> {
+    let mut stmt = db
+        .prepare("SELECT id, label, href, content FROM ee_chapter WHERE bookId = ? ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![book_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                ChapterRow {
+                    label: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    href: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    content: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+// 向目标书籍复制章节，href 与目标书籍中已有的章节去重
+struct ChapterCopy<'a> {
+    db: &'a Connection,
+    book_id: i64,
+    hrefs: HrefSet,
+    now: String,
+}
+
+impl<'a> ChapterCopy<'a> {
+    fn new(db: &'a Connection, book_id: i64, now: &str) -> Result<Self, String> {
+        Ok(ChapterCopy {
+            db,
+            book_id,
+            hrefs: HrefSet::load(db, book_id)?,
+            now: now.to_string(),
+        })
+    }
+
+    fn insert(
+        &mut self,
+        chapter: &ChapterRow,
+        parent_id: Option<i64>,
+        position: Option<i64>,
+    ) -> Result<i64, String> {
+        let href = self.hrefs.unique(&chapter.href);
+        self.db
+            .execute(
+                "INSERT INTO ee_chapter (bookId, label, href, content, parentId, position, createTime, updateTime) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    self.book_id,
+                    chapter.label,
+                    href,
+                    chapter.content,
+                    parent_id,
+                    position,
+                    self.now,
+                    self.now
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(self.db.last_insert_rowid())
+    }
+
+    // 复制来源书籍的全部章节到 parent_id 下：目录中的章节保持层级和顺序，不在目录中的章节复制后同样不在目录中
+    fn copy_book(
+        &mut self,
+        source_id: i64,
+        parent_id: Option<i64>,
+        images: &ImageTransfer,
+        renamed: &HashMap<String, String>,
+    ) -> Result<(), String> {
+        let mut rows: HashMap<i64, ChapterRow> = HashMap::new();
+        let mut order = Vec::new();
+        for (id, mut row) in load_chapter_rows(self.db, source_id)? {
+            row.content = images.rewrite(&row.content, renamed);
+            rows.insert(id, row);
+            order.push(id);
+        }
+        let toc = load_toc(self.db, source_id).map_err(|e| e.to_string())?;
+        self.copy_items(&toc, parent_id, &mut rows)?;
+        for id in order {
+            if let Some(row) = rows.remove(&id) {
+                self.insert(&row, None, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn copy_items(
+        &mut self,
+        items: &[TocItem],
+        parent_id: Option<i64>,
+        rows: &mut HashMap<i64, ChapterRow>,
+    ) -> Result<(), String> {
+        for (position, item) in items.iter().enumerate() {
+            let Some(row) = rows.remove(&item.href) else {
+                continue;
+            };
+            let id = self.insert(&row, parent_id, Some(position as i64))?;
+            if let Some(subitems) = &item.subitems {
+                self.copy_items(subitems, Some(id), rows)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// 把 ids 中的章节移到目标书籍：roots 挂到 parent_id 下的 position 处（为空时追加到末尾），其余章节保持原有层级；
+// 引用的图片复制到目标书籍，修订记录随章节一起移动
+#[allow(clippy::too_many_arguments)]
+fn move_chapter_rows(
+    db: &Connection,
+    app_dir: &Path,
+    source_id: i64,
+    ids: &[i64],
+    roots: &[i64],
+    target_id: i64,
+    parent_id: Option<i64>,
+    position: Option<i64>,
+    written: &mut WrittenFiles,
+) -> Result<(), String> {
+    let source_images = images_dir(app_dir, source_id);
+    let mut images = ImageTransfer::new(app_dir, target_id)?;
+    let mut hrefs = HrefSet::load(db, target_id)?;
+
+    let mut old_parents: Vec<Option<i64>> = Vec::new();
+    for root in roots {
+        let old_parent: Option<i64> = db
+            .query_row(
+                "SELECT parentId FROM ee_chapter WHERE id = ?",
+                params![root],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !old_parents.contains(&old_parent) {
+            old_parents.push(old_parent);
+        }
+    }
+
+    for id in ids {
+        let (href, content): (String, String) = db
+            .query_row(
+                "SELECT href, content FROM ee_chapter WHERE id = ?",
+                params![id],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                        row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    ))
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        let names = images.referenced(&content);
+        let renamed = images.copy(&source_images, &names, written)?;
+        let new_content = images.rewrite(&content, &renamed);
+        let href = hrefs.unique(&href);
+        if new_content == content {
+            db.execute(
+                "UPDATE ee_chapter SET bookId = ?, href = ? WHERE id = ?",
+                params![target_id, href, id],
+            )
+        } else {
+            db.execute(
+                "UPDATE ee_chapter SET bookId = ?, href = ?, content = ? WHERE id = ?",
+                params![target_id, href, new_content, id],
+            )
+        }
+        .map_err(|e| e.to_string())?;
+        db.execute(
+            "UPDATE ee_chapter_revision SET bookId = ? WHERE chapterId = ?",
+            params![target_id, id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    // 来源书籍中剩余的兄弟章节重新编号
+    for old_parent in old_parents {
+        let siblings = sibling_ids(db, source_id, old_parent).map_err(|e| e.to_string())?;
+        write_positions(db, old_parent, &siblings).map_err(|e| e.to_string())?;
+    }
+
+    let mut siblings: Vec<i64> = sibling_ids(db, target_id, parent_id)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|sibling| !roots.contains(sibling))
+        .collect();
+    let index = position
+        .map(|p| p.clamp(0, siblings.len() as i64) as usize)
+        .unwrap_or(siblings.len());
+    siblings.splice(index..index, roots.iter().copied());
+    write_positions(db, parent_id, &siblings).map_err(|e| e.to_string())
+}
+
+fn dedup_ids(ids: &[i64]) -> Vec<i64> {
+    let mut seen = HashSet::new();
+    ids.iter().copied().filter(|id| seen.insert(*id)).collect()
+}
+
+// 复制书籍：书籍信息、全部章节、图片和封面，章节修订记录不复制
+fn duplicate(
+    db: &Connection,
+    app_dir: &Path,
+    book_id: i64,
+    title: Option<String>,
+    written: &mut WrittenFiles,
+) -> Result<Book, String> {
+    let source = load_book_info(db, book_id)?;
+    let info = BookInfo {
+        title: title
+            .map(|title| title.trim().to_string())
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| format!("{}（副本）", source.title)),
+        author: source.author,
+        description: source.description,
+    };
+    let now = get_current_time_string();
+    let new_id = insert_book(db, &info, &now)?;
+
+    let mut images = ImageTransfer::new(app_dir, new_id)?;
+    let source_images = images_dir(app_dir, book_id);
+    let renamed = images.copy(&source_images, &image_names(&source_images)?, written)?;
+    ChapterCopy::new(db, new_id, &now)?.copy_book(book_id, None, &images, &renamed)?;
+    copy_cover(app_dir, book_id, new_id, written)?;
+    finish_book(db, new_id, info)
+}
+
+// 合并书籍为一本新书：每本来源书籍成为一个以书名为标题的顶级章节，原有目录挂在其下；来源书籍保持不变
+fn merge(
+    db: &Connection,
+    app_dir: &Path,
+    book_ids: &[i64],
+    title: Option<String>,
+    written: &mut WrittenFiles,
+) -> Result<Book, String> {
+    let book_ids = dedup_ids(book_ids);
+    if book_ids.len() < 2 {
+        return Err("至少选择两本书籍进行合并".to_string());
+    }
+    let mut sources = Vec::new();
+    for id in &book_ids {
+        sources.push((*id, load_book_info(db, *id)?));
+    }
+
+    let mut authors: Vec<&str> = Vec::new();
+    for (_, source) in &sources {
+        let author = source.author.trim();
+        if !author.is_empty() && !authors.contains(&author) {
+            authors.push(author);
+        }
+    }
+    let info = BookInfo {
+        title: title
+            .map(|title| title.trim().to_string())
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| sources[0].1.title.clone()),
+        author: authors.join("、"),
+        description: sources
+            .iter()
+            .map(|(_, source)| source.description.trim())
+            .find(|description| !description.is_empty())
+            .unwrap_or_default()
+            .to_string(),
+    };
+    let now = get_current_time_string();
+    let new_id = insert_book(db, &info, &now)?;
+
+    let mut images = ImageTransfer::new(app_dir, new_id)?;
+    let mut chapters = ChapterCopy::new(db, new_id, &now)?;
+    for (position, (source_id, source)) in sources.iter().enumerate() {
+        let source_images = images_dir(app_dir, *source_id);
+        let renamed = images.copy(&source_images, &image_names(&source_images)?, written)?;
+        let node = ChapterRow {
+            label: source.title.clone(),
+            href: format!("book-{}", source_id),
+            content: String::new(),
+        };
+        let node_id = chapters.insert(&node, None, Some(position as i64))?;
+        chapters.copy_book(*source_id, Some(node_id), &images, &renamed)?;
+    }
+    for (source_id, _) in &sources {
+        if copy_cover(app_dir, *source_id, new_id, written)? {
+            break;
+        }
+    }
+    finish_book(db, new_id, info)
+}
+
+// 在选中的目录节点处拆分书籍：按目录顺序展开后，从每个拆分点到下一个拆分点之前的章节移到一本以拆分点标题命名的新书中，
+// 第一个拆分点之前的章节留在原书；范围内的父子关系保持不变，父章节不在范围内的章节成为新书的顶级章节
+fn split(
+    db: &Connection,
+    app_dir: &Path,
+    book_id: i64,
+    chapter_ids: &[i64],
+    written: &mut WrittenFiles,
+) -> Result<Vec<Book>, String> {
+    let source = load_book_info(db, book_id)?;
+    let chapter_ids = dedup_ids(chapter_ids);
+    if chapter_ids.is_empty() {
+        return Err("请选择拆分位置的章节".to_string());
+    }
+    let tree = TocTree::new(&load_toc(db, book_id).map_err(|e| e.to_string())?);
+    if let Some(id) = chapter_ids.iter().find(|id| !tree.contains(**id)) {
+        return Err(format!("章节 {} 不在书籍 {} 的目录中", id, book_id));
+    }
+    let split_points: HashSet<i64> = chapter_ids.iter().copied().collect();
+    let starts: Vec<usize> = tree
+        .order
+        .iter()
+        .enumerate()
+        .filter(|(_, id)| split_points.contains(id))
+        .map(|(index, _)| index)
+        .collect();
+
+    let now = get_current_time_string();
+    let mut books = Vec::new();
+    for (index, start) in starts.iter().enumerate() {
+        let end = starts.get(index + 1).copied().unwrap_or(tree.order.len());
+        let ids = &tree.order[*start..end];
+        let label: String = db
+            .query_row(
+                "SELECT label FROM ee_chapter WHERE id = ?",
+                params![ids[0]],
+                |row| Ok(row.get::<_, Option<String>>(0)?.unwrap_or_default()),
+            )
+            .map_err(|e| e.to_string())?;
+        let info = BookInfo {
+            title: if label.trim().is_empty() {
+                source.title.clone()
+            } else {
+                label.trim().to_string()
+            },
+            author: source.author.clone(),
+            description: String::new(),
+        };
+        let new_id = insert_book(db, &info, &now)?;
+        let in_range: HashSet<i64> = ids.iter().copied().collect();
+        let roots: Vec<i64> = ids
+            .iter()
+            .copied()
+            .filter(|id| {
+                !tree
+                    .parents
+                    .get(id)
+                    .copied()
+                    .flatten()
+                    .is_some_and(|parent| in_range.contains(&parent))
+            })
+            .collect();
+        move_chapter_rows(
+            db, app_dir, book_id, ids, &roots, new_id, None, None, written,
+        )?;
+        books.push(finish_book(db, new_id, info)?);
+    }
+
+    touch_book(db, book_id, &now)?;
+    sync_toc(db, book_id).map_err(|e| e.to_string())?;
+    Ok(books)
+}
+
+// 把章节连同子章节移到另一本书；选中的章节互相嵌套时只按最外层移动
+fn move_to_book(
+    db: &Connection,
+    app_dir: &Path,
+    chapter_ids: &[i64],
+    target_id: i64,
+    parent_id: Option<i64>,
+    position: Option<i64>,
+    written: &mut WrittenFiles,
+) -> Result<ChapterMoveResult, String> {
+    let chapter_ids = dedup_ids(chapter_ids);
+    if chapter_ids.is_empty() {
+        return Err("请选择要移动的章节".to_string());
+    }
+    load_book_info(db, target_id)?;
+
+    let mut source_id = None;
+    for id in &chapter_ids {
+        let book_id: i64 = db
+            .query_row(
+                "SELECT bookId FROM ee_chapter WHERE id = ?",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("章节 {} 不存在", id))?;
+        if source_id.is_some_and(|source| source != book_id) {
+            return Err("只能同时移动同一本书中的章节".to_string());
+        }
+        source_id = Some(book_id);
+    }
+    let source_id = source_id.unwrap_or_default();
+    if source_id == target_id {
+        return Err("章节已在目标书籍中".to_string());
+    }
+    load_book_info(db, source_id)?;
+
+    let tree = TocTree::new(&load_toc(db, source_id).map_err(|e| e.to_string())?);
+    if let Some(id) = chapter_ids.iter().find(|id| !tree.contains(**id)) {
+        return Err(format!("章节 {} 不在书籍 {} 的目录中", id, source_id));
+    }
+    if let Some(parent) = parent_id {
+        let in_toc: Option<i64> = db
+            .query_row(
+                "SELECT position FROM ee_chapter WHERE id = ? AND bookId = ?",
+                params![parent, target_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .flatten();
+        if in_toc.is_none() {
+            return Err(format!("父章节 {} 不在目标书籍目录中", parent));
+        }
+    }
+
+    let selected: HashSet<i64> = chapter_ids.iter().copied().collect();
+    let roots: Vec<i64> = chapter_ids
+        .iter()
+        .copied()
+        .filter(|id| !tree.has_ancestor_in(*id, &selected))
+        .collect();
+    let ids: Vec<i64> = roots
+        .iter()
+        .flat_map(|root| tree.subtree(*root, &HashSet::new()))
+        .collect();
+    move_chapter_rows(
+        db, app_dir, source_id, &ids, &roots, target_id, parent_id, position, written,
+    )?;
+
+    let now = get_current_time_string();
+    touch_book(db, source_id, &now)?;
+    touch_book(db, target_id, &now)?;
+    Ok(ChapterMoveResult {
+        source_toc: sync_toc(db, source_id).map_err(|e| e.to_string())?,
+        target_toc: sync_toc(db, target_id).map_err(|e| e.to_string())?,
+    })
+}
+
+// 提交事务；操作或提交失败时删除已写入的图片和封面
+fn commit_or_clean<T: Serialize>(
+    tx: Transaction,
+    written: WrittenFiles,
+    result: Result<T, String>,
+) -> Result<DbResponse<T>, String> {
+    match result {
+        Ok(value) => match tx.commit() {
+            Ok(()) => Ok(DbResponse::success(value)),
+            Err(err) => {
+                written.remove_all();
+                Err(err.to_string())
+            }
+        },
+        Err(err) => {
+            written.remove_all();
+            Ok(DbResponse::error(err))
+        }
+    }
+}
+
+// 复制书籍，title 为空时使用「原书名（副本）」
+#[command]
+pub async fn duplicate_book(
+    book_id: i64,
+    title: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Book>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mut written = WrittenFiles::default();
+    let result = duplicate(&tx, &app_dir, book_id, title, &mut written);
+    commit_or_clean(tx, written, result)
+}
+
+// 按 book_ids 的顺序合并为一本新书，title 为空时使用第一本书的书名
+#[command]
+pub async fn merge_books(
+    book_ids: Vec<i64>,
+    title: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Book>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mut written = WrittenFiles::default();
+    let result = merge(&tx, &app_dir, &book_ids, title, &mut written);
+    commit_or_clean(tx, written, result)
+}
+
+// 在选中的目录节点处拆分书籍，返回拆分出的新书
+#[command]
+pub async fn split_book(
+    book_id: i64,
+    chapter_ids: Vec<i64>,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<Book>>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mut written = WrittenFiles::default();
+    let result = split(&tx, &app_dir, book_id, &chapter_ids, &mut written);
+    commit_or_clean(tx, written, result)
+}
+
+// 把章节移到另一本书的 parent_id 下第 position 个位置，返回两本书的目录树
+#[command]
+pub async fn move_chapters_to_book(
+    chapter_ids: Vec<i64>,
+    target_book_id: i64,
+    parent_id: Option<i64>,
+    position: Option<i64>,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<ChapterMoveResult>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mut written = WrittenFiles::default();
+    let result = move_to_book(
+        &tx,
+        &app_dir,
+        &chapter_ids,
+        target_book_id,
+        parent_id,
+        position,
+        &mut written,
+    );
+    commit_or_clean(tx, written, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::open_test_db;
+
+    // 目录树简写为 (标题, 子节点)
+    fn shape(items: &[TocItem]) -> Vec<(String, Vec<String>)> {
+        items
+            .iter()
+            .map(|item| {
+                let children = item.subitems.iter().flatten();
+                (
+                    item.label.clone(),
+                    children.map(|c| c.label.clone()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_toc_into_ranges() {
+        let db = open_test_db();
+        db.execute(
+            "INSERT INTO ee_book (id, title, author, isDel) VALUES (1, '全集', '作者', 0)",
+            [],
+        )
+        .unwrap();
+        // (标题, 内容, 父章节在本表中的下标, 位置)
+        let chapters = [
+            ("序", "<p>序言</p>", None, 0),
+            ("卷一", "<h1>卷一</h1>", None, 1),
+            ("第一章", "<p>一</p>", Some(1), 0),
+            ("第二章", "<p>二</p>", Some(1), 1),
+            ("卷二", "", None, 2),
+            ("第三章", "<p>三</p>", Some(4), 0),
+            ("第一节", "<p>节</p>", Some(5), 0),
+            ("第四章", "<p>四</p>", Some(4), 1),
+        ];
+        let mut ids: Vec<i64> = Vec::new();
+        for (label, content, parent, position) in chapters {
+            let parent_id = parent.map(|index: usize| ids[index]);
+            db.execute(
+                "INSERT INTO ee_chapter (bookId, label, href, content, parentId, position) \
+                 VALUES (1, ?, ?, ?, ?, ?)",
+                params![label, label, content, parent_id, position],
+            )
+            .unwrap();
+            ids.push(db.last_insert_rowid());
+        }
+        sync_toc(&db, 1).unwrap();
+
+        let app_dir = std::env::temp_dir().join("my-ebooks-split-test");
+        let mut written = WrittenFiles::default();
+        // 拆分点的顺序以目录为准
+        let books = split(&db, &app_dir, 1, &[ids[5], ids[1]], &mut written).unwrap();
+
+        assert_eq!(
+            books
+                .iter()
+                .map(|book| book.title.as_str())
+                .collect::<Vec<_>>(),
+            vec!["卷一", "第三章"]
+        );
+        assert_eq!(
+            shape(&load_toc(&db, 1).unwrap()),
+            vec![("序".into(), vec![])]
+        );
+        assert_eq!(
+            shape(&load_toc(&db, books[0].id).unwrap()),
+            vec![
+                ("卷一".into(), vec!["第一章".into(), "第二章".into()]),
+                ("卷二".into(), vec![]),
+            ]
+        );
+        assert_eq!(
+            shape(&load_toc(&db, books[1].id).unwrap()),
+            vec![
+                ("第三章".into(), vec!["第一节".into()]),
+                ("第四章".into(), vec![]),
+            ]
+        );
+
+        // 只有标题的章节保留在新书中
+        let book_id: i64 = db
+            .query_row(
+                "SELECT bookId FROM ee_chapter WHERE id = ?",
+                params![ids[4]],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(book_id, books[0].id);
+    }
+}
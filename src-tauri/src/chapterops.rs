@@ -0,0 +1,398 @@
+// 章节的拆分与合并：直接增删 ee_chapter 记录，并在同一事务中更新所属书籍的目录
+use crate::bookops::HrefSet;
+use crate::database::{get_current_time_string, get_db_connection, DbResponse};
+use crate::importer::append_content;
+use crate::replace::build_pattern;
+use crate::revision::update_chapter_with_revision;
+use crate::setup::AppState;
+use crate::textutil::line_text;
+use crate::toc::{sibling_ids, sync_toc, write_positions, TocItem};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{command, State};
+
+// 拆分方式：按行拆分，拆开的位置总在行首
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ChapterSplit {
+    // 去掉标签后与正则匹配的行作为新章节的标题，不再保留在内容中
+    Pattern {
+        pattern: String,
+        #[serde(default)]
+        ignore_case: bool,
+    },
+    // 从这些行（从 1 开始）起各为一个新章节
+    Lines {
+        lines: Vec<usize>,
+    },
+    // 每章不超过 size 个字（不计标签），单独一行超过时不再拆开
+    Chars {
+        size: usize,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterSplitResult {
+    // 拆分后的全部章节，第一个为原章节
+    pub chapter_ids: Vec<i64>,
+    pub toc: Vec<TocItem>,
+}
+
+struct ChapterRecord {
+    book_id: i64,
+    label: String,
+    href: String,
+    content: String,
+    parent_id: Option<i64>,
+    position: Option<i64>,
+}
+
+fn load_chapter(db: &Connection, id: i64) -> Result<ChapterRecord, String> {
+    db.query_row(
+        "SELECT bookId, label, href, content, parentId, position FROM ee_chapter WHERE id = ?",
+        params![id],
+        |row| {
+            Ok(ChapterRecord {
+                book_id: row.get(0)?,
+                label: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                href: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                content: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                parent_id: row.get(4)?,
+                position: row.get(5)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("章节 {} 不存在", id))
+}
+
+// 新章节开始的行号（从 0 开始），以及作为标题从内容中去掉的行
+type SplitPoint = (usize, Option<String>);
+
+fn split_points(lines: &[&str], split: &ChapterSplit) -> Result<Vec<SplitPoint>, String> {
+    match split {
+        ChapterSplit::Pattern {
+            pattern,
+            ignore_case,
+        } => {
+            let re = build_pattern(pattern, true, *ignore_case)?;
+            Ok(lines
+                .iter()
+                .enumerate()
+                .filter_map(|(index, line)| {
+                    let heading = line_text(line);
+                    (!heading.is_empty() && re.is_match(&heading)).then_some((index, Some(heading)))
+                })
+                .collect())
+        }
+        ChapterSplit::Lines { lines: numbers } => {
+            if lines.len() < 2 {
+                return Err("章节内容只有一行，无法按行拆分".to_string());
+            }
+            let mut numbers = numbers.clone();
+            numbers.sort_unstable();
+            numbers.dedup();
+            if let Some(number) = numbers
+                .iter()
+                .find(|number| **number < 2 || **number > lines.len())
+            {
+                return Err(format!("行号 {} 超出范围（2 - {}）", number, lines.len()));
+            }
+            Ok(numbers
+                .into_iter()
+                .map(|number| (number - 1, None))
+                .collect())
+        }
+        ChapterSplit::Chars { size } => {
+            if *size == 0 {
+                return Err("每章字数必须大于 0".to_string());
+            }
+            let mut points = Vec::new();
+            let mut count = 0;
+            for (index, line) in lines.iter().enumerate() {
+                let len = line_text(line).chars().count();
+                if count > 0 && count + len > *size {
+                    points.push((index, None));
+                    count = 0;
+                }
+                count += len;
+            }
+            Ok(points)
+        }
+    }
+}
+
+// 按拆分位置切开内容，第一段为 (None, 原章节剩下的内容)；
+// 第一个拆分位置之前没有内容时，该位置的标题作为原章节的新标题，不留下空的第一段
+fn split_content(
+    content: &str,
+    split: &ChapterSplit,
+) -> Result<Vec<(Option<String>, String)>, String> {
+    let lines: Vec<&str> = content.split('\n').collect();
+    let mut points = split_points(&lines, split)?;
+    let join =
+        |start: usize, end: usize| lines[start..end].join("\n").trim_matches('\n').to_string();
+
+    let (mut first_heading, mut first_start) = (None, 0);
+    if let Some((index, Some(_))) = points.first() {
+        if join(0, *index).trim().is_empty() {
+            let (index, heading) = points.remove(0);
+            (first_heading, first_start) = (heading, index + 1);
+        }
+    }
+    let mut pieces = Vec::with_capacity(points.len() + 1);
+    let first_end = points
+        .first()
+        .map(|(index, _)| *index)
+        .unwrap_or(lines.len());
+    pieces.push((first_heading, join(first_start, first_end)));
+    for (i, (start, heading)) in points.iter().enumerate() {
+        let end = points
+            .get(i + 1)
+            .map(|(index, _)| *index)
+            .unwrap_or(lines.len());
+        let start = if heading.is_some() { start + 1 } else { *start };
+        pieces.push((heading.clone(), join(start, end)));
+    }
+    Ok(pieces)
+}
+
+// 拆分章节：原章节保留第一段内容和子章节，其余各段作为紧随其后的同级章节；
+// 没有标题的段使用「原标题（序号）」，href 由原 href 加序号生成
+pub fn split_chapter_rows(
+    db: &Connection,
+    id: i64,
+    split: &ChapterSplit,
+) -> Result<ChapterSplitResult, String> {
+    let chapter = load_chapter(db, id)?;
+    let mut pieces = split_content(&chapter.content, split)?.into_iter();
+    if pieces.len() < 2 {
+        return Err("没有找到拆分位置".to_string());
+    }
+
+    let (first_heading, first) = pieces.next().unwrap_or_default();
+    let first_label = first_heading.unwrap_or_else(|| chapter.label.clone());
+    update_chapter_with_revision(db, id, &first_label, Some(&first)).map_err(|e| e.to_string())?;
+
+    let mut hrefs = HrefSet::load(db, chapter.book_id)?;
+    let base_href = if chapter.href.is_empty() {
+        format!("chapter-{}", id)
+    } else {
+        chapter.href.clone()
+    };
+    let now = get_current_time_string();
+    let mut chapter_ids = vec![id];
+    for (index, (heading, content)) in pieces.enumerate() {
+        let number = index + 2;
+        let label = heading.unwrap_or_else(|| format!("{}（{}）", chapter.label, number));
+        let href = hrefs.unique(&format!("{}-{}", base_href, number));
+        db.execute(
+            "INSERT INTO ee_chapter (bookId, label, href, content, parentId, position, createTime, updateTime) \
+             VALUES (?, ?, ?, ?, ?, NULL, ?, ?)",
+            params![chapter.book_id, label, href, content, chapter.parent_id, now, now],
+        )
+        .map_err(|e| e.to_string())?;
+        chapter_ids.push(db.last_insert_rowid());
+    }
+
+    // 原章节在目录中时，新章节插到它后面；不在目录中时新章节同样不进入目录
+    if chapter.position.is_some() {
+        let mut siblings =
+            sibling_ids(db, chapter.book_id, chapter.parent_id).map_err(|e| e.to_string())?;
+        let index = siblings
+            .iter()
+            .position(|sibling| *sibling == id)
+            .map(|index| index + 1)
+            .unwrap_or(siblings.len());
+        siblings.splice(index..index, chapter_ids[1..].iter().copied());
+        write_positions(db, chapter.parent_id, &siblings).map_err(|e| e.to_string())?;
+    }
+
+    let toc = sync_toc(db, chapter.book_id).map_err(|e| e.to_string())?;
+    Ok(ChapterSplitResult { chapter_ids, toc })
+}
+
+// 合并同一父章节下从 first_id 到 last_id 的连续章节：内容依次追加到第一个章节，keep_labels 为 true 时
+// 被合并章节的标题作为单独一行保留；被合并章节的子章节移到第一个章节下，其记录和修订记录删除
+pub fn join_chapter_rows(
+    db: &Connection,
+    first_id: i64,
+    last_id: i64,
+    keep_labels: bool,
+) -> Result<Vec<TocItem>, String> {
+    let first = load_chapter(db, first_id)?;
+    let last = load_chapter(db, last_id)?;
+    if first.book_id != last.book_id || first.parent_id != last.parent_id {
+        return Err("只能合并同一父章节下的章节".to_string());
+    }
+    if first.position.is_none() || last.position.is_none() {
+        return Err("只能合并目录中的章节".to_string());
+    }
+
+    let mut siblings =
+        sibling_ids(db, first.book_id, first.parent_id).map_err(|e| e.to_string())?;
+    let start = siblings
+        .iter()
+        .position(|id| *id == first_id)
+        .unwrap_or_default();
+    let end = siblings
+        .iter()
+        .position(|id| *id == last_id)
+        .unwrap_or_default();
+    if end <= start {
+        return Err("结束章节必须位于开始章节之后".to_string());
+    }
+    let joined: Vec<i64> = siblings.drain(start + 1..=end).collect();
+
+    let mut content = first.content.clone();
+    let mut children = sibling_ids(db, first.book_id, Some(first_id)).map_err(|e| e.to_string())?;
+    for id in &joined {
+        let chapter = load_chapter(db, *id)?;
+        if keep_labels && !chapter.label.trim().is_empty() {
+            append_content(&mut content, chapter.label.trim());
+        }
+        if !chapter.content.is_empty() {
+            append_content(&mut content, &chapter.content);
+        }
+        children.extend(sibling_ids(db, first.book_id, Some(*id)).map_err(|e| e.to_string())?);
+        // 不在目录中的子章节也改挂到第一个章节下
+        db.execute(
+            "UPDATE ee_chapter SET parentId = ? WHERE parentId = ? AND bookId = ?",
+            params![first_id, id, first.book_id],
+        )
+        .map_err(|e| e.to_string())?;
+        db.execute("DELETE FROM ee_chapter WHERE id = ?", params![id])
+            .map_err(|e| e.to_string())?;
+        db.execute(
+            "DELETE FROM ee_chapter_revision WHERE chapterId = ?",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    update_chapter_with_revision(db, first_id, &first.label, Some(&content))
+        .map_err(|e| e.to_string())?;
+    write_positions(db, Some(first_id), &children).map_err(|e| e.to_string())?;
+    write_positions(db, first.parent_id, &siblings).map_err(|e| e.to_string())?;
+    sync_toc(db, first.book_id).map_err(|e| e.to_string())
+}
+
+// 拆分章节，返回拆分后的章节和新的目录树
+#[command]
+pub fn split_chapter(
+    id: i64,
+    split: ChapterSplit,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<ChapterSplitResult>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match split_chapter_rows(&tx, id, &split) {
+        Ok(result) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(result))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 合并连续的同级章节，返回新的目录树
+#[command]
+pub fn join_chapters(
+    first_id: i64,
+    last_id: i64,
+    keep_labels: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<TocItem>>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match join_chapter_rows(&tx, first_id, last_id, keep_labels.unwrap_or(true)) {
+        Ok(toc) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(toc))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(pattern: &str, ignore_case: bool) -> ChapterSplit {
+        ChapterSplit::Pattern {
+            pattern: pattern.to_string(),
+            ignore_case,
+        }
+    }
+
+    fn pieces(items: &[(Option<&str>, &str)]) -> Vec<(Option<String>, String)> {
+        items
+            .iter()
+            .map(|(heading, content)| (heading.map(str::to_string), content.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn splits_content() {
+        let cases = [
+            // 标题在第一行时作为原章节的新标题
+            (
+                "<h2>第一章</h2>\n<p>一</p>\n\n<h2>第二章</h2>\n<p>二</p>",
+                pattern("^第.章$", false),
+                pieces(&[(Some("第一章"), "<p>一</p>"), (Some("第二章"), "<p>二</p>")]),
+            ),
+            (
+                "<p>前言</p>\n<h2>Chapter 1</h2>\n<p>一</p>",
+                pattern(r"^chapter \d+$", true),
+                pieces(&[(None, "<p>前言</p>"), (Some("Chapter 1"), "<p>一</p>")]),
+            ),
+            (
+                "a\nb\nc\nd",
+                ChapterSplit::Lines {
+                    lines: vec![3, 2, 3],
+                },
+                pieces(&[(None, "a"), (None, "b"), (None, "c\nd")]),
+            ),
+            // 单独一行超过 size 时不再拆开
+            (
+                "<p>12345</p>\n<p>1234567890</p>\n<p>12</p>\n<p>3</p>",
+                ChapterSplit::Chars { size: 6 },
+                pieces(&[
+                    (None, "<p>12345</p>"),
+                    (None, "<p>1234567890</p>"),
+                    (None, "<p>12</p>\n<p>3</p>"),
+                ]),
+            ),
+            (
+                "<p>1234567890</p>",
+                ChapterSplit::Chars { size: 3 },
+                pieces(&[(None, "<p>1234567890</p>")]),
+            ),
+        ];
+        for (content, split, expected) in cases {
+            assert_eq!(split_content(content, &split), Ok(expected), "{:?}", split);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_splits() {
+        let cases = [
+            ("a\nb", ChapterSplit::Lines { lines: vec![1] }),
+            ("a\nb", ChapterSplit::Lines { lines: vec![2, 3] }),
+            ("a", ChapterSplit::Lines { lines: vec![2] }),
+            ("a\nb", ChapterSplit::Chars { size: 0 }),
+            ("a\nb", pattern("(", false)),
+        ];
+        for (content, split) in cases {
+            assert!(split_content(content, &split).is_err(), "{:?}", split);
+        }
+    }
+}
@@ -1,10 +1,18 @@
+use crate::migration::run_migrations;
+use crate::revision::{
+    snapshot_before_update, update_chapter_with_revision, AUTOSAVE_COALESCE_SECS,
+};
+use crate::search::register_functions;
 use crate::setup::AppState;
-use rusqlite::{params, Connection};
+use crate::toc::{apply_toc, next_position, parse_toc, sync_toc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
 use serde::{Deserialize, Serialize};
+use std::error::Error;
 use std::fs;
 use std::sync::MutexGuard;
 use std::time::SystemTime;
-use tauri::{command, AppHandle, Manager, State};
+use tauri::{command, AppHandle, Emitter, Manager, State};
 
 // 定义通用的数据库响应结构体
 #[derive(Debug, Serialize, Deserialize)]
@@ -36,6 +44,7 @@ impl<T: Serialize> DbResponse<T> {
 }
 
 const DB_FILENAME: &str = "books.db";
+const BACKUP_DIRNAME: &str = "backups";
 
 pub fn get_db_connection<'a>(
     state: &'a State<'_, AppState>,
@@ -60,7 +69,7 @@ pub fn close_database(state: State<'_, AppState>) -> Result<DbResponse<()>, Stri
     ))
 }
 
-pub fn init_db(app_handle: &AppHandle) -> Result<Connection, rusqlite::Error> {
+pub fn init_db(app_handle: &AppHandle) -> Result<Connection, Box<dyn Error>> {
     // 获取应用数据目录并确保它存在
     let app_dir = app_handle
         .path()
@@ -73,44 +82,26 @@ pub fn init_db(app_handle: &AppHandle) -> Result<Connection, rusqlite::Error> {
 
     // 设置WAL模式以提高性能
     db.pragma_update(None, "journal_mode", "WAL")?;
+    // 全文索引的触发器依赖自定义函数，必须在迁移和写入之前注册
+    register_functions(&db)?;
 
-    // 直接创建表（如果不存在）
-    create_tables(&mut db)?;
+    // 按 user_version 执行数据库迁移（迁移前自动备份到 backups 目录）
+    run_migrations(&mut db, &app_dir.join(BACKUP_DIRNAME))?;
 
     Ok(db)
 }
 
-fn create_tables(db: &mut Connection) -> Result<(), rusqlite::Error> {
-    db.execute_batch(
-        "
-        CREATE TABLE IF NOT EXISTS ee_book (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            title TEXT,
-            author TEXT,
-            description TEXT,
-            toc TEXT,
-            isDel INTEGER,
-            createTime TEXT,
-            updateTime TEXT
-        );
-        
-        CREATE TABLE IF NOT EXISTS ee_chapter (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            bookId INTEGER,
-            label TEXT,
-            href TEXT,
-            content TEXT,
-            createTime TEXT,
-            updateTime TEXT
-        );
-    ",
-    )?;
-
-    Ok(())
+// 测试用的内存数据库，与 init_db 使用相同的函数注册和迁移
+#[cfg(test)]
+pub fn open_test_db() -> Connection {
+    let mut db = Connection::open_in_memory().unwrap();
+    register_functions(&db).unwrap();
+    run_migrations(&mut db, std::path::Path::new("")).unwrap();
+    db
 }
 
 // 辅助函数：获取当前时间的字符串表示
-fn get_current_time_string() -> String {
+pub fn get_current_time_string() -> String {
     // 将系统时间转换为RFC3339格式的字符串
     let now = SystemTime::now();
     now.duration_since(SystemTime::UNIX_EPOCH)
@@ -181,7 +172,7 @@ pub async fn add_book(
     }
 }
 
-// 更新章节内容（允许 content 为空）
+// 更新章节内容（允许 content 为空），更新前保存修订快照
 #[command]
 pub fn update_chapter(
     id: i64,
@@ -192,24 +183,97 @@ pub fn update_chapter(
     let db = get_db_connection(&state)?;
     let current_time = get_current_time_string();
 
-    let sql = if content.is_none() {
-        "UPDATE ee_chapter SET label = ?, updateTime = ? WHERE id = ?"
-    } else {
-        "UPDATE ee_chapter SET label = ?, content = ?, updateTime = ? WHERE id = ?"
-    };
+    // 编辑器会频繁自动保存，短时间内的多次保存只保留一份快照
+    let snapshot =
+        snapshot_before_update(&db, id, &label, content.as_deref(), AUTOSAVE_COALESCE_SECS);
+    if let Err(err) = snapshot {
+        return Ok(DbResponse::error(err.to_string()));
+    }
 
-    let params = if content.is_none() {
-        params![label, current_time, id]
-    } else {
-        params![label, content.unwrap(), current_time, id]
+    let result = match content {
+        Some(content) => db.execute(
+            "UPDATE ee_chapter SET label = ?, content = ?, updateTime = ? WHERE id = ?",
+            params![label, content, current_time, id],
+        ),
+        None => db.execute(
+            "UPDATE ee_chapter SET label = ?, updateTime = ? WHERE id = ?",
+            params![label, current_time, id],
+        ),
     };
 
-    match db.execute(sql, params) {
+    match result {
         Ok(_) => Ok(DbResponse::success(())),
         Err(err) => Ok(DbResponse::error(err.to_string())),
     }
 }
 
+// 批量更新中的一个章节修改，content 为空时只更新标题
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterChange {
+    pub id: i64,
+    pub label: String,
+    pub content: Option<String>,
+}
+
+// 批量更新的进度事件
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchProgress {
+    pub current: usize,
+    pub total: usize,
+    pub id: i64,
+    pub label: String,
+}
+
+pub const BATCH_PROGRESS_EVENT: &str = "chapter-batch-progress";
+
+// 在一个事务中更新多个章节，任何一个失败则全部回滚
+pub fn apply_chapter_changes(
+    db: &mut Connection,
+    changes: &[ChapterChange],
+    on_progress: impl Fn(BatchProgress),
+) -> Result<usize, String> {
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let total = changes.len();
+
+    for (index, change) in changes.iter().enumerate() {
+        let updated =
+            update_chapter_with_revision(&tx, change.id, &change.label, change.content.as_deref())
+                .map_err(|e| format!("更新章节 {} 失败: {}", change.id, e))?;
+        if updated == 0 {
+            return Err(format!("章节 {} 不存在", change.id));
+        }
+        on_progress(BatchProgress {
+            current: index + 1,
+            total,
+            id: change.id,
+            label: change.label.clone(),
+        });
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(total)
+}
+
+// 批量更新章节：单个事务，逐章发送进度事件，出错时全部回滚
+#[command]
+pub async fn update_chapters_batch(
+    changes: Vec<ChapterChange>,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<usize>, String> {
+    let mut db = get_db_connection(&state)?;
+
+    let result = apply_chapter_changes(&mut db, &changes, |progress| {
+        let _ = app_handle.emit(BATCH_PROGRESS_EVENT, progress);
+    });
+    match result {
+        Ok(count) => Ok(DbResponse::success(count)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
 // 获取所有书籍
 #[command]
 pub fn get_all_books(state: State<'_, AppState>) -> Result<DbResponse<Vec<Book>>, String> {
@@ -248,26 +312,35 @@ pub fn get_all_books(state: State<'_, AppState>) -> Result<DbResponse<Vec<Book>>
     Ok(DbResponse::success(books))
 }
 
+// 添加章节，parent_id 为空时添加到顶级目录末尾；章节和目录在同一事务中写入
 #[command]
 pub fn add_chapter(
     book_id: i64,
     label: String,
     href: String,
     content: String,
+    parent_id: Option<i64>,
     state: State<'_, AppState>,
 ) -> Result<DbResponse<i64>, String> {
-    let db = get_db_connection(&state)?;
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
 
     // 执行插入操作
-    match db.execute(
-        "INSERT INTO ee_chapter (bookId, label, href, content) \
-         VALUES (?, ?, ?, ?)",
-        params![book_id, label, href, content],
-    ) {
-        Ok(_) => {
-            // 获取最后插入的 ID
-            let last_id = db.last_insert_rowid();
-
+    let result = next_position(&tx, book_id, parent_id).and_then(|position| {
+        tx.execute(
+            "INSERT INTO ee_chapter (bookId, label, href, content, parentId, position) \
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![book_id, label, href, content, parent_id, position],
+        )?;
+        // 获取最后插入的 ID
+        let last_id = tx.last_insert_rowid();
+        sync_toc(&tx, book_id)?;
+        Ok(last_id)
+    });
+
+    match result {
+        Ok(last_id) => {
+            tx.commit().map_err(|e| e.to_string())?;
             Ok(DbResponse::success(last_id))
         }
         Err(err) => {
@@ -318,17 +391,20 @@ pub fn get_chapter(
     Ok(DbResponse::success(chapters))
 }
 
+// 保存前端的目录 JSON：同步写入章节的 parentId / position，再由章节表重新生成 toc
 #[command]
 pub fn update_toc(
     id: i64,
     toc: String,
     state: State<'_, AppState>,
 ) -> Result<DbResponse<i64>, String> {
-    let db = get_db_connection(&state)?;
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
 
     // 执行更新操作
-    match db.execute("UPDATE ee_book SET toc = ? WHERE id = ?", params![toc, id]) {
+    match apply_toc(&tx, id, &parse_toc(&toc)).and_then(|_| sync_toc(&tx, id)) {
         Ok(_) => {
+            tx.commit().map_err(|e| e.to_string())?;
             // 返回成功响应，包含更新的行数
             Ok(DbResponse::success(1))
         }
@@ -339,76 +415,178 @@ pub fn update_toc(
     }
 }
 
-#[command]
-pub fn get_chapter_where(
-    where_str: String, // 已修改为 String 类型
-    state: State<'_, AppState>,
-) -> Result<DbResponse<Vec<Chapter>>, String> {
-    let db = get_db_connection(&state)?;
+// 章节查询可排序的字段（白名单，避免拼接任意 SQL）
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChapterSortField {
+    #[default]
+    Id,
+    Position,
+    Label,
+    Href,
+    CreateTime,
+    UpdateTime,
+}
+
+impl ChapterSortField {
+    fn column(self) -> &'static str {
+        match self {
+            ChapterSortField::Id => "id",
+            ChapterSortField::Position => "position",
+            ChapterSortField::Label => "label",
+            ChapterSortField::Href => "href",
+            ChapterSortField::CreateTime => "createTime",
+            ChapterSortField::UpdateTime => "updateTime",
+        }
+    }
+}
 
-    // 构建 SQL 查询语句
-    let sql = format!("SELECT * FROM ee_chapter WHERE {}", where_str);
+// 章节查询可返回的列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChapterColumn {
+    BookId,
+    Label,
+    Href,
+    Content,
+}
 
-    // 打印 SQL 语句到终端
-    println!("[SQL] 执行查询: {}", sql);
+// 结构化的章节查询条件，所有值都通过参数绑定传入
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChapterQuery {
+    pub book_id: Option<i64>,
+    pub ids: Option<Vec<i64>>,
+    // 标题包含的子串
+    pub label: Option<String>,
+    pub href: Option<String>,
+    pub sort_by: ChapterSortField,
+    pub descending: bool,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    // 需要返回的列，为空时返回全部列；id 总是返回
+    pub columns: Option<Vec<ChapterColumn>>,
+}
 
-    // 如果需要更详细的日志，也可以打印传入的 where 条件
-    println!("[SQL] WHERE 条件: {}", where_str);
+// 按列投影后的章节记录，未选择的列不会出现在结果中
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterRecord {
+    pub id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub book_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub href: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
 
-    // 执行查询操作
-    let mut stmt = match db.prepare(&sql) {
-        Ok(statement) => statement,
-        Err(err) => {
-            // 打印错误信息
-            println!("[SQL ERROR] 准备语句失败: {}", err);
-            return Err(err.to_string());
+pub fn query_chapter_records(
+    db: &Connection,
+    query: &ChapterQuery,
+) -> Result<Vec<ChapterRecord>, rusqlite::Error> {
+    let columns = match &query.columns {
+        Some(columns) if !columns.is_empty() => columns.clone(),
+        _ => vec![
+            ChapterColumn::BookId,
+            ChapterColumn::Label,
+            ChapterColumn::Href,
+            ChapterColumn::Content,
+        ],
+    };
+    // 未选择的列用 NULL 占位，保持结果列的下标不变
+    let select = |column: ChapterColumn, name: &'static str| {
+        if columns.contains(&column) {
+            name
+        } else {
+            "NULL"
         }
     };
 
-    // 执行查询并映射结果到Chapter结构体
-    let chapters = match stmt.query_map([], |row| {
-        Ok(Chapter {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+
+    if let Some(book_id) = query.book_id {
+        conditions.push("bookId = ?".to_string());
+        values.push(Value::Integer(book_id));
+    }
+    if let Some(ids) = &query.ids {
+        // 空的 id 列表不会匹配任何章节
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        conditions.push(format!("id IN ({})", vec!["?"; ids.len()].join(", ")));
+        values.extend(ids.iter().map(|id| Value::Integer(*id)));
+    }
+    if let Some(label) = &query.label {
+        conditions.push("instr(label, ?) > 0".to_string());
+        values.push(Value::Text(label.clone()));
+    }
+    if let Some(href) = &query.href {
+        conditions.push("href = ?".to_string());
+        values.push(Value::Text(href.clone()));
+    }
+
+    let mut sql = format!(
+        "SELECT id, {}, {}, {}, {} FROM ee_chapter",
+        select(ChapterColumn::BookId, "bookId"),
+        select(ChapterColumn::Label, "label"),
+        select(ChapterColumn::Href, "href"),
+        select(ChapterColumn::Content, "content"),
+    );
+    if !conditions.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&conditions.join(" AND "));
+    }
+    sql.push_str(&format!(
+        " ORDER BY {} {}",
+        query.sort_by.column(),
+        if query.descending { "DESC" } else { "ASC" }
+    ));
+    if query.limit.is_some() || query.offset.is_some() {
+        // SQLite 中 LIMIT -1 表示不限制条数
+        sql.push_str(" LIMIT ? OFFSET ?");
+        values.push(Value::Integer(query.limit.unwrap_or(-1)));
+        values.push(Value::Integer(query.offset.unwrap_or(0)));
+    }
+
+    let mut stmt = db.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values), |row| {
+        Ok(ChapterRecord {
             id: row.get(0)?,
             book_id: row.get(1)?,
             label: row.get(2)?,
             href: row.get(3)?,
             content: row.get(4)?,
         })
-    }) {
-        Ok(rows) => {
-            // 收集查询结果，处理可能的映射错误
-            let mut results = Vec::new();
-            for chapter_result in rows {
-                match chapter_result {
-                    Ok(chapter) => results.push(chapter),
-                    Err(err) => {
-                        println!("[SQL ERROR] 映射结果失败: {}", err);
-                        return Err(err.to_string());
-                    }
-                }
-            }
-            // 打印查询结果数量
-            println!("[SQL] 查询完成，返回 {} 条记录", results.len());
-            results
-        }
-        Err(err) => {
-            println!("[SQL ERROR] 执行查询失败: {}", err);
-            return Err(err.to_string());
-        }
-    };
+    })?;
+    rows.collect()
+}
 
-    // 返回成功响应，包含章节列表
-    Ok(DbResponse::success(chapters))
+// 按结构化条件查询章节
+#[command]
+pub fn query_chapters(
+    query: ChapterQuery,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<ChapterRecord>>, String> {
+    let db = get_db_connection(&state)?;
+
+    match query_chapter_records(&db, &query) {
+        Ok(chapters) => Ok(DbResponse::success(chapters)),
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
 }
 
 #[command]
 pub fn delete_book(id: i64, state: State<'_, AppState>) -> Result<DbResponse<i64>, String> {
     let db = get_db_connection(&state)?;
 
-    // 执行删除操作（逻辑删除，将 isDel 设置为 1）
+    // 执行删除操作（逻辑删除，将 isDel 设置为 1，并记录放入回收站的时间）
     match db.execute(
-        "UPDATE ee_book SET isDel = 1, updateTime = datetime('now', 'localtime') WHERE id = ?",
-        params![id],
+        "UPDATE ee_book SET isDel = 1, deleteTime = ?, updateTime = datetime('now', 'localtime') WHERE id = ?",
+        params![get_current_time_string(), id],
     ) {
         Ok(_) => {
             // 返回成功响应，包含更新的行数
@@ -0,0 +1,754 @@
+use crate::database::{get_current_time_string, get_db_connection, Book, DbResponse};
+use crate::exporter::{
+    format_utc_time, image_media_type, image_size, load_export_book, write_zip_file, ExportBook,
+    ExportChapter,
+};
+use crate::fileutil::book_epub_dir;
+use crate::importer::{
+    save_imported_book, ChapterOutline, ImageNamer, ImportedBook, ImportedImage,
+};
+use crate::markup::{content_to_html, escape, parse_markup, parse_xml, Element, Node};
+use crate::setup::AppState;
+use crate::textutil::line_text;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use tauri::{command, AppHandle, Manager, State};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+// 图片的最大显示宽度（EMU，约 15 厘米）
+const MAX_IMAGE_WIDTH: u64 = 5_400_000;
+// 每像素对应的 EMU（按 96 DPI）
+const EMU_PER_PIXEL: u64 = 9525;
+
+struct DocxArchive {
+    zip: ZipArchive<File>,
+}
+
+impl DocxArchive {
+    fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("打开文件失败: {}", e))?;
+        let zip = ZipArchive::new(file).map_err(|e| format!("不是有效的 DOCX 文件: {}", e))?;
+        Ok(DocxArchive { zip })
+    }
+
+    fn read(&mut self, name: &str) -> Option<Vec<u8>> {
+        let mut entry = self.zip.by_name(name).ok()?;
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).ok()?;
+        Some(data)
+    }
+
+    fn read_xml(&mut self, name: &str) -> Option<Element> {
+        self.read(name)
+            .map(|data| parse_xml(&String::from_utf8_lossy(&data)))
+    }
+}
+
+// w:b、w:i 等开关属性：没有 w:val 或值不为 0/false 时为开
+fn toggle(properties: Option<&Element>, name: &str) -> bool {
+    properties
+        .and_then(|properties| properties.child(name))
+        .is_some_and(|element| {
+            !matches!(
+                element.attr("val"),
+                Some("0") | Some("false") | Some("none")
+            )
+        })
+}
+
+// 样式 id 对应的标题级别：样式名为 heading N / 标题 N，或带有大纲级别，基于标题样式的样式同样视为标题
+fn heading_levels(styles: Option<&Element>) -> HashMap<String, u8> {
+    let mut direct: HashMap<String, Option<u8>> = HashMap::new();
+    let mut based_on: HashMap<String, String> = HashMap::new();
+    let mut titles = Vec::new();
+    for style in styles
+        .map(|styles| styles.children_named("style").collect::<Vec<_>>())
+        .unwrap_or_default()
+    {
+        let Some(id) = style.attr("styleid") else {
+            continue;
+        };
+        let name = style
+            .child("name")
+            .and_then(|name| name.attr("val"))
+            .unwrap_or_default()
+            .to_lowercase();
+        if name == "title" {
+            titles.push(id.to_string());
+        }
+        let level = name
+            .strip_prefix("heading ")
+            .or_else(|| name.strip_prefix("标题 "))
+            .and_then(|level| level.trim().parse::<u8>().ok())
+            .or_else(|| {
+                style
+                    .child("ppr")
+                    .and_then(|properties| properties.child("outlinelvl"))
+                    .and_then(|outline| outline.attr("val"))
+                    .and_then(|level| level.parse::<u8>().ok())
+                    .filter(|level| *level < 9)
+                    .map(|level| level + 1)
+            });
+        direct.insert(id.to_string(), level);
+        if let Some(parent) = style.child("basedon").and_then(|based| based.attr("val")) {
+            based_on.insert(id.to_string(), parent.to_string());
+        }
+    }
+
+    let mut levels = HashMap::new();
+    for id in direct.keys() {
+        let mut current = id.as_str();
+        // 限制继承链长度，避免样式循环引用
+        for _ in 0..10 {
+            if let Some(level) = direct.get(current).copied().flatten() {
+                levels.insert(id.clone(), level);
+                break;
+            }
+            match based_on.get(current) {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+    }
+    // 标题样式（Title）用 0 表示
+    for id in titles {
+        levels.insert(id, 0);
+    }
+    levels
+}
+
+// 段落转换为章节内容的一行，相邻的相同格式合并为一组标签
+struct ParagraphWriter<'a> {
+    relations: &'a HashMap<String, String>,
+    line: String,
+    // 当前打开的格式标签
+    open: Vec<&'static str>,
+}
+
+impl ParagraphWriter<'_> {
+    fn set_format(&mut self, format: Vec<&'static str>) {
+        if format == self.open {
+            return;
+        }
+        self.close_format();
+        for tag in &format {
+            self.line.push_str(&format!("<{}>", tag));
+        }
+        self.open = format;
+    }
+
+    fn close_format(&mut self) {
+        while let Some(tag) = self.open.pop() {
+            self.line.push_str(&format!("</{}>", tag));
+        }
+    }
+
+    fn run(&mut self, run: &Element) {
+        let properties = run.child("rpr");
+        let mut format = Vec::new();
+        if toggle(properties, "b") {
+            format.push("b");
+        }
+        if toggle(properties, "i") {
+            format.push("i");
+        }
+        if toggle(properties, "u") {
+            format.push("u");
+        }
+
+        for child in run.elements() {
+            match child.name.as_str() {
+                "t" => {
+                    let text = child.text();
+                    if !text.is_empty() {
+                        self.set_format(format.clone());
+                        self.line.push_str(&text);
+                    }
+                }
+                "tab" => {
+                    self.set_format(format.clone());
+                    self.line.push(' ');
+                }
+                "nobreakhyphen" => {
+                    self.set_format(format.clone());
+                    self.line.push('-');
+                }
+                "br" | "cr" if child.attr("type") != Some("page") => {
+                    self.close_format();
+                    self.line.push('\n');
+                }
+                "drawing" | "pict" | "object" => self.image(child),
+                _ => {}
+            }
+        }
+    }
+
+    fn image(&mut self, element: &Element) {
+        let id = element
+            .find("blip")
+            .and_then(|blip| blip.attr("embed"))
+            .or_else(|| element.find("imagedata").and_then(|image| image.attr("id")));
+        if let Some(name) = id.and_then(|id| self.relations.get(id)) {
+            self.close_format();
+            self.line
+                .push_str(&format!("<img src=\"images/{}\" />", escape(name)));
+        }
+    }
+
+    fn children(&mut self, element: &Element) {
+        for child in element.elements() {
+            match child.name.as_str() {
+                "r" => self.run(child),
+                // 修订中删除的内容不导入
+                "del" | "ppr" | "movefrom" => {}
+                _ => self.children(child),
+            }
+        }
+    }
+}
+
+// 读取一个段落：(标题级别, 内容)
+fn read_paragraph(
+    paragraph: &Element,
+    levels: &HashMap<String, u8>,
+    relations: &HashMap<String, String>,
+) -> (Option<u8>, String) {
+    let properties = paragraph.child("ppr");
+    let level = properties
+        .and_then(|properties| properties.child("pstyle"))
+        .and_then(|style| style.attr("val"))
+        .and_then(|style| levels.get(style).copied())
+        .or_else(|| {
+            properties
+                .and_then(|properties| properties.child("outlinelvl"))
+                .and_then(|outline| outline.attr("val"))
+                .and_then(|level| level.parse::<u8>().ok())
+                .filter(|level| *level < 9)
+                .map(|level| level + 1)
+        });
+
+    let mut writer = ParagraphWriter {
+        relations,
+        line: String::new(),
+        open: Vec::new(),
+    };
+    writer.children(paragraph);
+    writer.close_format();
+    let line = writer
+        .line
+        .split('\n')
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (level, line)
+}
+
+// 正文中的段落，表格中的段落按单元格顺序展开
+fn collect_paragraphs<'a>(element: &'a Element, paragraphs: &mut Vec<&'a Element>) {
+    for child in element.elements() {
+        match child.name.as_str() {
+            "p" => paragraphs.push(child),
+            "sectpr" => {}
+            _ => collect_paragraphs(child, paragraphs),
+        }
+    }
+}
+
+fn plain_text(content: &str) -> String {
+    line_text(content)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// 解析 DOCX 文件：按标题 1、标题 2 样式切分章节，更低级别的标题保留在正文中
+pub fn read_docx(path: &Path) -> Result<ImportedBook, String> {
+    let mut archive = DocxArchive::open(path)?;
+    let document = archive
+        .read_xml("word/document.xml")
+        .ok_or("DOCX 中缺少 word/document.xml")?;
+    let styles = archive.read_xml("word/styles.xml");
+    let levels = heading_levels(styles.as_ref().and_then(|styles| styles.find("styles")));
+
+    // 图片：document.xml.rels 中的图片关系，文件保存在 word/media 下
+    let mut namer = ImageNamer::default();
+    let mut images = Vec::new();
+    let mut relations = HashMap::new();
+    if let Some(rels) = archive.read_xml("word/_rels/document.xml.rels") {
+        for relation in rels.find_all("relationship") {
+            let (Some(id), Some(target)) = (relation.attr("id"), relation.attr("target")) else {
+                continue;
+            };
+            if !relation
+                .attr("type")
+                .unwrap_or_default()
+                .ends_with("/image")
+                || relation.attr("targetmode") == Some("External")
+            {
+                continue;
+            }
+            let entry = match target.strip_prefix('/') {
+                Some(absolute) => absolute.to_string(),
+                None => format!("word/{}", target.trim_start_matches("./")),
+            };
+            let Some(data) = archive.read(&entry).filter(|data| !data.is_empty()) else {
+                continue;
+            };
+            let name = namer.name(&entry);
+            relations.insert(id.to_string(), name.clone());
+            images.push(ImportedImage { name, data });
+        }
+    }
+
+    // 元数据
+    let core = archive.read_xml("docProps/core.xml");
+    let core_text = |name: &str| {
+        core.as_ref()
+            .and_then(|core| core.find(name))
+            .map(|element| element.text().trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+    let file_stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let body = document.find("body").ok_or("document.xml 中没有正文")?;
+    let mut paragraphs = Vec::new();
+    collect_paragraphs(body, &mut paragraphs);
+
+    let mut title_line = None;
+    let mut outline = ChapterOutline::default();
+    for paragraph in paragraphs {
+        let (level, line) = read_paragraph(paragraph, &levels, &relations);
+        if line.is_empty() {
+            continue;
+        }
+        match level {
+            Some(0) if title_line.is_none() && !outline.has_chapters() => {
+                title_line = Some(plain_text(&line));
+            }
+            Some(level @ 1..=2) => outline.heading(level, plain_text(&line)),
+            Some(level @ 3..) => {
+                let level = level.min(6);
+                outline.line(&format!("<h{}>{}</h{}>", level, line, level));
+            }
+            _ => outline.line(&line),
+        }
+    }
+
+    let title = core_text("title")
+        .or(title_line)
+        .filter(|title| !title.is_empty())
+        .unwrap_or(file_stem);
+    let chapters = outline.finish(&title);
+
+    Ok(ImportedBook {
+        title,
+        author: core_text("creator").unwrap_or_else(|| "佚名".to_string()),
+        description: core_text("description").unwrap_or_else(|| "暂缺".to_string()),
+        chapters,
+        images,
+        cover: None,
+    })
+}
+
+// 导入 DOCX 文件：在 Rust 中解析并在一个事务中写入书籍和章节，返回新书籍
+#[command]
+pub async fn import_docx(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Book>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    let book = match read_docx(Path::new(&path)) {
+        Ok(book) => book,
+        Err(err) => return Ok(DbResponse::error(err)),
+    };
+
+    let mut db = get_db_connection(&state)?;
+    match save_imported_book(&mut db, &app_dir, &book) {
+        Ok(book) => Ok(DbResponse::success(book)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+const DOCX_NAMESPACES: &str =
+    "xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\" \
+xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\" \
+xmlns:wp=\"http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing\" \
+xmlns:a=\"http://schemas.openxmlformats.org/drawingml/2006/main\" \
+xmlns:pic=\"http://schemas.openxmlformats.org/drawingml/2006/picture\"";
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>
+<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="docProps/core.xml"/>
+</Relationships>
+"#;
+
+// 正文、标题 1-6 和书名的样式，标题带大纲级别以便 Word 生成目录和导航窗格
+fn styles_xml() -> String {
+    let mut styles = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+<w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii="Times New Roman" w:hAnsi="Times New Roman" w:eastAsia="宋体" w:cs="Times New Roman"/><w:sz w:val="24"/><w:lang w:val="en-US" w:eastAsia="zh-CN"/></w:rPr></w:rPrDefault><w:pPrDefault><w:pPr><w:spacing w:after="120" w:line="360" w:lineRule="auto"/></w:pPr></w:pPrDefault></w:docDefaults>
+<w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/><w:qFormat/><w:pPr><w:ind w:firstLineChars="200" w:firstLine="480"/><w:jc w:val="both"/></w:pPr></w:style>
+<w:style w:type="paragraph" w:styleId="Title"><w:name w:val="Title"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:spacing w:before="480" w:after="480"/><w:ind w:firstLineChars="0" w:firstLine="0"/><w:jc w:val="center"/></w:pPr><w:rPr><w:b/><w:sz w:val="44"/></w:rPr></w:style>
+"#,
+    );
+    for (level, size) in [(1, 36), (2, 32), (3, 28), (4, 26), (5, 24), (6, 24)] {
+        let page_break = if level == 1 {
+            "<w:pageBreakBefore/>"
+        } else {
+            ""
+        };
+        styles.push_str(&format!(
+            "<w:style w:type=\"paragraph\" w:styleId=\"Heading{level}\"><w:name w:val=\"heading {level}\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/>{page_break}<w:spacing w:before=\"240\" w:after=\"120\"/><w:ind w:firstLineChars=\"0\" w:firstLine=\"0\"/><w:jc w:val=\"left\"/><w:outlineLvl w:val=\"{outline}\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"{size}\"/></w:rPr></w:style>\n",
+            outline = level - 1,
+        ));
+    }
+    styles.push_str("</w:styles>\n");
+    styles
+}
+
+// 导出时引用到的图片：(关系 id, 文件名, 数据)
+struct DocxImages<'a> {
+    images_dir: &'a Path,
+    used: Vec<(String, String, Vec<u8>)>,
+    // 文档中图片（docPr）的序号，需要唯一
+    drawings: usize,
+}
+
+impl DocxImages<'_> {
+    // 章节内容中的 images/{name} 转换为图片段，图片文件不存在时返回 None
+    fn drawing(&mut self, src: &str) -> Option<String> {
+        let name = src.strip_prefix("images/")?;
+        if name.contains(['/', '\\']) {
+            return None;
+        }
+        let index = match self.used.iter().position(|(_, used, _)| used == name) {
+            Some(index) => index,
+            None => {
+                let data = fs::read(self.images_dir.join(name)).ok()?;
+                // rId1 为样式表
+                let id = format!("rId{}", self.used.len() + 2);
+                self.used.push((id, name.to_string(), data));
+                self.used.len() - 1
+            }
+        };
+        let (id, name, data) = &self.used[index];
+
+        let (width, height) = image_size(data).unwrap_or((400, 300));
+        let mut cx = width.max(1) as u64 * EMU_PER_PIXEL;
+        let mut cy = height.max(1) as u64 * EMU_PER_PIXEL;
+        if cx > MAX_IMAGE_WIDTH {
+            cy = cy * MAX_IMAGE_WIDTH / cx;
+            cx = MAX_IMAGE_WIDTH;
+        }
+        self.drawings += 1;
+        Some(format!(
+            "<w:r><w:drawing><wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\"><wp:extent cx=\"{cx}\" cy=\"{cy}\"/><wp:docPr id=\"{n}\" name=\"图片 {n}\"/><a:graphic><a:graphicData uri=\"http://schemas.openxmlformats.org/drawingml/2006/picture\"><pic:pic><pic:nvPicPr><pic:cNvPr id=\"{n}\" name=\"{name}\"/><pic:cNvPicPr/></pic:nvPicPr><pic:blipFill><a:blip r:embed=\"{id}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill><pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm><a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic></a:graphicData></a:graphic></wp:inline></w:drawing></w:r>",
+            n = self.drawings,
+            name = escape(name),
+        ))
+    }
+}
+
+fn text_run(text: &str, format: &[&str]) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let properties: String = format
+        .iter()
+        .map(|tag| match *tag {
+            "u" => "<w:u w:val=\"single\"/>".to_string(),
+            tag => format!("<w:{}/>", tag),
+        })
+        .collect();
+    let properties = if properties.is_empty() {
+        properties
+    } else {
+        format!("<w:rPr>{}</w:rPr>", properties)
+    };
+    format!(
+        "<w:r>{}<w:t xml:space=\"preserve\">{}</w:t></w:r>",
+        properties,
+        escape(text)
+    )
+}
+
+// 行内内容转换为 w:r，b/strong、i/em、u 转换为对应的字符格式
+fn docx_runs(element: &Element, format: &mut Vec<&'static str>, images: &mut DocxImages) -> String {
+    let mut runs = String::new();
+    for node in &element.children {
+        match node {
+            Node::Text(text) => runs.push_str(&text_run(text, format)),
+            Node::Element(child) => {
+                let tag = match child.name.as_str() {
+                    "b" | "strong" => Some("b"),
+                    "i" | "em" => Some("i"),
+                    "u" => Some("u"),
+                    _ => None,
+                };
+                match child.name.as_str() {
+                    "img" => {
+                        if let Some(drawing) = child.attr("src").and_then(|src| images.drawing(src))
+                        {
+                            runs.push_str(&drawing);
+                        }
+                    }
+                    "br" => runs.push_str("<w:r><w:br/></w:r>"),
+                    _ => {
+                        let pushed = tag.is_some_and(|tag| !format.contains(&tag));
+                        if let (true, Some(tag)) = (pushed, tag) {
+                            format.push(tag);
+                        }
+                        runs.push_str(&docx_runs(child, format, images));
+                        if pushed {
+                            format.pop();
+                        }
+                    }
+                }
+            }
+        }
+    }
+    runs
+}
+
+fn paragraph(style: Option<&str>, properties: &str, runs: &str) -> String {
+    let style = style
+        .map(|style| format!("<w:pStyle w:val=\"{}\"/>", style))
+        .unwrap_or_default();
+    if style.is_empty() && properties.is_empty() {
+        format!("<w:p>{}</w:p>\n", runs)
+    } else {
+        format!(
+            "<w:p><w:pPr>{}{}</w:pPr>{}</w:p>\n",
+            style, properties, runs
+        )
+    }
+}
+
+// 章节内容转换为段落：先转换为 XHTML 再映射元素；与章节标题相同的开头标题不重复输出
+fn content_to_docx(content: &str, label: &str, images: &mut DocxImages) -> String {
+    let html = parse_markup(&content_to_html(content));
+    let mut out = String::new();
+    for (index, element) in html.elements().enumerate() {
+        let name = element.name.as_str();
+        match name {
+            "p" => {
+                let only_images = element.children.iter().all(|node| match node {
+                    Node::Text(text) => text.trim().is_empty(),
+                    Node::Element(child) => child.name == "img",
+                });
+                let properties = if only_images {
+                    "<w:ind w:firstLineChars=\"0\" w:firstLine=\"0\"/><w:jc w:val=\"center\"/>"
+                } else {
+                    ""
+                };
+                let runs = docx_runs(element, &mut Vec::new(), images);
+                out.push_str(&paragraph(None, properties, &runs));
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if index == 0 && plain_text(&element.text()) == plain_text(label) {
+                    continue;
+                }
+                // 正文中的标题不参与章节切分，至少为标题 3
+                let level = name[1..].parse::<usize>().unwrap_or(3).max(3);
+                let runs = docx_runs(element, &mut Vec::new(), images);
+                out.push_str(&paragraph(Some(&format!("Heading{}", level)), "", &runs));
+            }
+            "ul" | "ol" => {
+                for (number, item) in element.children_named("li").enumerate() {
+                    let prefix = if name == "ol" {
+                        format!("{}. ", number + 1)
+                    } else {
+                        "• ".to_string()
+                    };
+                    let runs = text_run(&prefix, &[]) + &docx_runs(item, &mut Vec::new(), images);
+                    out.push_str(&paragraph(None, "", &runs));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+// 章节标题按目录层级使用标题 1-6 样式
+fn write_chapter(chapter: &ExportChapter, depth: usize, images: &mut DocxImages, out: &mut String) {
+    let level = (depth + 1).min(6);
+    out.push_str(&paragraph(
+        Some(&format!("Heading{}", level)),
+        "",
+        &text_run(&chapter.label, &[]),
+    ));
+    out.push_str(&content_to_docx(&chapter.content, &chapter.label, images));
+    for child in &chapter.children {
+        write_chapter(child, depth + 1, images, out);
+    }
+}
+
+fn write_docx_entries(
+    zip: &mut ZipWriter<File>,
+    book: &ExportBook,
+    app_dir: &Path,
+) -> Result<(), String> {
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let images_dir = book_epub_dir(app_dir, book.id).join("images");
+    let mut images = DocxImages {
+        images_dir: &images_dir,
+        used: Vec::new(),
+        drawings: 0,
+    };
+
+    let mut body = paragraph(Some("Title"), "", &text_run(&book.title, &[]));
+    for chapter in &book.chapters {
+        write_chapter(chapter, 0, &mut images, &mut body);
+    }
+    let document = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:document {}>\n<w:body>\n{}<w:sectPr><w:pgSz w:w=\"11906\" w:h=\"16838\"/><w:pgMar w:top=\"1440\" w:right=\"1440\" w:bottom=\"1440\" w:left=\"1440\" w:header=\"851\" w:footer=\"992\" w:gutter=\"0\"/></w:sectPr>\n</w:body>\n</w:document>\n",
+        DOCX_NAMESPACES, body
+    );
+
+    let mut extensions: Vec<(String, &str)> = Vec::new();
+    let mut relations = String::from(
+        "<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" Target=\"styles.xml\"/>\n",
+    );
+    for (id, name, data) in &images.used {
+        let ext = name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+        if !extensions.iter().any(|(existing, _)| *existing == ext) {
+            extensions.push((ext, image_media_type(name, data)));
+        }
+        relations.push_str(&format!(
+            "<Relationship Id=\"{}\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/image\" Target=\"media/{}\"/>\n",
+            id,
+            escape(name)
+        ));
+    }
+
+    let defaults: String = extensions
+        .iter()
+        .filter(|(ext, _)| !ext.is_empty())
+        .map(|(ext, media_type)| {
+            format!(
+                "<Default Extension=\"{}\" ContentType=\"{}\"/>\n",
+                escape(ext),
+                media_type
+            )
+        })
+        .collect();
+    let content_types = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>
+<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">
+<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>
+<Default Extension=\"xml\" ContentType=\"application/xml\"/>
+{}<Override PartName=\"/word/document.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>
+<Override PartName=\"/word/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml\"/>
+<Override PartName=\"/docProps/core.xml\" ContentType=\"application/vnd.openxmlformats-package.core-properties+xml\"/>
+</Types>
+",
+        defaults
+    );
+
+    let now = get_current_time_string().parse::<i64>().unwrap_or(0);
+    let created = book.create_time.parse::<i64>().unwrap_or(now);
+    let description = if book.description.trim() == "暂缺" {
+        ""
+    } else {
+        book.description.trim()
+    };
+    let core = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>
+<cp:coreProperties xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:dcterms=\"http://purl.org/dc/terms/\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">
+<dc:title>{}</dc:title>
+<dc:creator>{}</dc:creator>
+<dc:description>{}</dc:description>
+<dc:language>zh-CN</dc:language>
+<dcterms:created xsi:type=\"dcterms:W3CDTF\">{}</dcterms:created>
+<dcterms:modified xsi:type=\"dcterms:W3CDTF\">{}</dcterms:modified>
+</cp:coreProperties>
+",
+        escape(&book.title),
+        escape(&book.author),
+        escape(description),
+        format_utc_time(created),
+        format_utc_time(now)
+    );
+
+    write_zip_file(
+        zip,
+        "[Content_Types].xml",
+        deflated,
+        content_types.as_bytes(),
+    )?;
+    write_zip_file(zip, "_rels/.rels", deflated, ROOT_RELS.as_bytes())?;
+    write_zip_file(zip, "docProps/core.xml", deflated, core.as_bytes())?;
+    write_zip_file(zip, "word/document.xml", deflated, document.as_bytes())?;
+    write_zip_file(zip, "word/styles.xml", deflated, styles_xml().as_bytes())?;
+    write_zip_file(
+        zip,
+        "word/_rels/document.xml.rels",
+        deflated,
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\n{}</Relationships>\n",
+            relations
+        )
+        .as_bytes(),
+    )?;
+    for (_, name, data) in &images.used {
+        write_zip_file(zip, &format!("word/media/{}", name), deflated, data)?;
+    }
+    Ok(())
+}
+
+// 写入 DOCX 文件，失败时删除不完整的文件
+pub fn write_docx(book: &ExportBook, app_dir: &Path, output: &Path) -> Result<(), String> {
+    let file = File::create(output).map_err(|e| format!("创建文件失败: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+    let result = write_docx_entries(&mut zip, book, app_dir)
+        .and_then(|_| zip.finish().map(|_| ()).map_err(|e| e.to_string()));
+    if result.is_err() {
+        let _ = fs::remove_file(output);
+    }
+    result
+}
+
+// 导出书籍为 DOCX 文件，返回导出的文件路径
+#[command]
+pub async fn export_docx(
+    book_id: i64,
+    output_path: String,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<String>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    // 读取完数据后释放数据库锁，再写入文件
+    let book = {
+        let db = get_db_connection(&state)?;
+        match load_export_book(&db, book_id) {
+            Ok(book) => book,
+            Err(err) => return Ok(DbResponse::error(err)),
+        }
+    };
+
+    match write_docx(&book, &app_dir, Path::new(&output_path)) {
+        Ok(_) => Ok(DbResponse::success(output_path)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
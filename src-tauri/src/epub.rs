@@ -0,0 +1,823 @@
+use crate::database::{get_current_time_string, get_db_connection, Book, DbResponse};
+use crate::exporter::{
+    book_uuid, cover_extension, format_utc_time, image_media_type, load_export_book,
+    write_zip_file, ExportBook, ExportChapter,
+};
+use crate::fileutil::{book_epub_dir, cover_path};
+use crate::importer::{
+    append_content, has_text, is_image_path, save_imported_book, ImageNamer, ImportedBook,
+    ImportedChapter, ImportedImage,
+};
+use crate::markup::{content_to_html, escape, html_to_content_segments, parse_markup, Element};
+use crate::setup::AppState;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use tauri::{command, AppHandle, Manager, State};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+// EPUB 压缩包，按不区分大小写的路径读取文件
+struct EpubArchive {
+    archive: ZipArchive<File>,
+    names: HashMap<String, String>,
+}
+
+impl EpubArchive {
+    fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("无法打开EPUB文件: {}", e))?;
+        let archive = ZipArchive::new(file).map_err(|e| format!("无效的EPUB文件: {}", e))?;
+        let names = archive
+            .file_names()
+            .map(|name| (name.to_lowercase(), name.to_string()))
+            .collect();
+        Ok(Self { archive, names })
+    }
+
+    fn entry_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.names.values().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn read(&mut self, path: &str) -> Option<Vec<u8>> {
+        let name = self.names.get(&path.to_lowercase())?.clone();
+        let mut entry = self.archive.by_name(&name).ok()?;
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).ok()?;
+        Some(data)
+    }
+
+    fn read_text(&mut self, path: &str) -> Option<String> {
+        self.read(path)
+            .map(|data| String::from_utf8_lossy(&data).into_owned())
+    }
+}
+
+// OPF manifest 中的一项
+struct ManifestItem {
+    id: String,
+    path: String,
+    media_type: String,
+    properties: String,
+}
+
+// 展开后的目录项，parent 为父目录项的下标
+struct TocEntry {
+    label: String,
+    path: String,
+    fragment: Option<String>,
+    parent: Option<usize>,
+    content: String,
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(Ok(byte)) = text
+                .get(i + 1..i + 3)
+                .map(|hex| u8::from_str_radix(hex, 16))
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// 把相对于 base（压缩包内文件路径）的链接解析为压缩包内的路径和锚点
+fn resolve_href(base: &str, href: &str) -> (String, Option<String>) {
+    let (href, fragment) = match href.split_once('#') {
+        Some((href, fragment)) => (href, Some(percent_decode(fragment))),
+        None => (href, None),
+    };
+    let fragment = fragment.filter(|fragment| !fragment.is_empty());
+    if href.is_empty() {
+        return (base.to_string(), fragment);
+    }
+
+    let href = percent_decode(href);
+    let mut parts: Vec<&str> = match (href.starts_with('/'), base.rsplit_once('/')) {
+        (false, Some((dir, _))) => dir.split('/').collect(),
+        _ => Vec::new(),
+    };
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    (parts.join("/"), fragment)
+}
+
+fn normalize_label(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parse_ncx(ncx: &Element, ncx_path: &str) -> Vec<TocEntry> {
+    let mut entries = Vec::new();
+    if let Some(nav_map) = ncx.find("navmap") {
+        parse_nav_points(nav_map, ncx_path, None, &mut entries);
+    }
+    entries
+}
+
+fn parse_nav_points(
+    parent_element: &Element,
+    ncx_path: &str,
+    parent: Option<usize>,
+    entries: &mut Vec<TocEntry>,
+) {
+    for point in parent_element.children_named("navpoint") {
+        let label = point
+            .child("navlabel")
+            .and_then(|label| label.child("text"))
+            .map(|text| normalize_label(&text.text()))
+            .unwrap_or_default();
+        let (path, fragment) = point
+            .child("content")
+            .and_then(|content| content.attr("src"))
+            .map(|src| resolve_href(ncx_path, src))
+            .unwrap_or_default();
+        entries.push(TocEntry {
+            label,
+            path,
+            fragment,
+            parent,
+            content: String::new(),
+        });
+        let index = entries.len() - 1;
+        parse_nav_points(point, ncx_path, Some(index), entries);
+    }
+}
+
+// EPUB 3 导航文档：优先使用 epub:type="toc" 的 nav
+fn parse_nav(nav_doc: &Element, nav_path: &str) -> Vec<TocEntry> {
+    let navs = nav_doc.find_all("nav");
+    let nav = navs
+        .iter()
+        .find(|nav| {
+            nav.attr("type")
+                .is_some_and(|kind| kind.split_whitespace().any(|kind| kind == "toc"))
+        })
+        .or_else(|| navs.first());
+    let mut entries = Vec::new();
+    if let Some(list) = nav.and_then(|nav| nav.find("ol")) {
+        parse_nav_list(list, nav_path, None, &mut entries);
+    }
+    entries
+}
+
+fn parse_nav_list(
+    list: &Element,
+    nav_path: &str,
+    parent: Option<usize>,
+    entries: &mut Vec<TocEntry>,
+) {
+    for item in list.children_named("li") {
+        let link = item.child("a").or_else(|| item.child("span"));
+        let label = link
+            .map(|link| normalize_label(&link.text()))
+            .unwrap_or_default();
+        let (path, fragment) = link
+            .and_then(|link| link.attr("href"))
+            .map(|href| resolve_href(nav_path, href))
+            .unwrap_or_default();
+        entries.push(TocEntry {
+            label,
+            path,
+            fragment,
+            parent,
+            content: String::new(),
+        });
+        let index = entries.len() - 1;
+        if let Some(children) = item.child("ol") {
+            parse_nav_list(children, nav_path, Some(index), entries);
+        }
+    }
+}
+
+fn build_chapters(entries: &[TocEntry], parent: Option<usize>) -> Vec<ImportedChapter> {
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.parent == parent)
+        .map(|(index, entry)| ImportedChapter {
+            label: entry.label.clone(),
+            href: match &entry.fragment {
+                Some(fragment) => format!("{}#{}", entry.path, fragment),
+                None => entry.path.clone(),
+            },
+            content: entry.content.clone(),
+            children: build_chapters(entries, Some(index)),
+        })
+        .collect()
+}
+
+// 不在目录中的文档作为独立章节时使用的标题
+fn document_label(document: &Element, path: &str) -> String {
+    ["title", "h1", "h2", "h3"]
+        .iter()
+        .filter_map(|name| document.find(name))
+        .map(|element| normalize_label(&element.text()))
+        .find(|label| !label.is_empty())
+        .unwrap_or_else(|| {
+            let file_name = path.rsplit('/').next().unwrap_or(path);
+            file_name
+                .rsplit_once('.')
+                .map(|(stem, _)| stem)
+                .unwrap_or(file_name)
+                .to_string()
+        })
+}
+
+// 解析 EPUB 文件：container.xml -> OPF -> 目录（nav 或 NCX）-> 按 spine 顺序读取章节内容
+pub fn read_epub(path: &Path) -> Result<ImportedBook, String> {
+    let mut archive = EpubArchive::open(path)?;
+
+    let container = archive
+        .read_text("META-INF/container.xml")
+        .map(|text| parse_markup(&text))
+        .ok_or("缺少 META-INF/container.xml")?;
+    let opf_path = container
+        .find("rootfile")
+        .and_then(|rootfile| rootfile.attr("full-path"))
+        .map(|path| resolve_href("", path).0)
+        .ok_or("container.xml 中没有找到 OPF 文件")?;
+    let opf = archive
+        .read_text(&opf_path)
+        .map(|text| parse_markup(&text))
+        .ok_or_else(|| format!("无法读取 OPF 文件 {}", opf_path))?;
+
+    // 元数据
+    let metadata = opf.find("metadata");
+    let meta_text = |name: &str| {
+        metadata
+            .and_then(|metadata| metadata.find(name))
+            .map(|element| element.text().trim().to_string())
+            .unwrap_or_default()
+    };
+    let file_stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let title = Some(meta_text("title"))
+        .filter(|title| !title.is_empty())
+        .unwrap_or(file_stem);
+    let author = Some(meta_text("creator"))
+        .filter(|author| !author.is_empty())
+        .unwrap_or_else(|| "佚名".to_string());
+    // 简介中可能包含转义后的 HTML
+    let description = parse_markup(&meta_text("description"))
+        .text()
+        .trim()
+        .to_string();
+    let description = if description.is_empty() {
+        "暂缺".to_string()
+    } else {
+        description
+    };
+
+    let manifest: Vec<ManifestItem> = opf
+        .find("manifest")
+        .map(|manifest| {
+            manifest
+                .children_named("item")
+                .map(|item| ManifestItem {
+                    id: item.attr("id").unwrap_or_default().to_string(),
+                    path: resolve_href(&opf_path, item.attr("href").unwrap_or_default()).0,
+                    media_type: item.attr("media-type").unwrap_or_default().to_string(),
+                    properties: item.attr("properties").unwrap_or_default().to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let item_by_id = |id: &str| manifest.iter().find(|item| item.id == id);
+
+    let spine = opf.find("spine");
+    let spine_paths: Vec<String> = spine
+        .map(|spine| {
+            spine
+                .children_named("itemref")
+                .filter_map(|itemref| item_by_id(itemref.attr("idref").unwrap_or_default()))
+                .filter(|item| item.media_type.contains("html") || item.media_type.is_empty())
+                .map(|item| item.path.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    if spine_paths.is_empty() {
+        return Err("EPUB 中没有可读取的章节".to_string());
+    }
+
+    // 目录：EPUB 3 的 nav 优先，其次是 NCX
+    let nav_item = manifest
+        .iter()
+        .find(|item| item.properties.split_whitespace().any(|p| p == "nav"));
+    let ncx_item = spine
+        .and_then(|spine| spine.attr("toc"))
+        .and_then(item_by_id)
+        .or_else(|| {
+            manifest
+                .iter()
+                .find(|item| item.media_type == "application/x-dtbncx+xml")
+        });
+    let mut entries = Vec::new();
+    if let Some(item) = nav_item {
+        if let Some(text) = archive.read_text(&item.path) {
+            entries = parse_nav(&parse_markup(&text), &item.path);
+        }
+    }
+    if entries.is_empty() {
+        if let Some(item) = ncx_item {
+            if let Some(text) = archive.read_text(&item.path) {
+                entries = parse_ncx(&parse_markup(&text), &item.path);
+            }
+        }
+    }
+
+    // 图片：与原先前端导入一致，解压包内所有图片
+    let mut namer = ImageNamer::default();
+    let mut images = Vec::new();
+    let mut image_names: HashMap<String, String> = HashMap::new();
+    for entry_name in archive.entry_names() {
+        if !is_image_path(&entry_name) {
+            continue;
+        }
+        let Some(data) = archive.read(&entry_name) else {
+            continue;
+        };
+        if data.is_empty() {
+            continue;
+        }
+        let name = namer.name(&entry_name);
+        image_names.insert(entry_name.to_lowercase(), name.clone());
+        images.push(ImportedImage { name, data });
+    }
+
+    // 封面：EPUB 3 的 cover-image，其次是 <meta name="cover">，最后是名称中带 cover 的图片
+    let cover_meta = metadata.and_then(|metadata| {
+        metadata
+            .children_named("meta")
+            .find(|meta| meta.attr("name") == Some("cover"))
+            .and_then(|meta| meta.attr("content"))
+    });
+    let cover_item = manifest
+        .iter()
+        .find(|item| {
+            item.properties
+                .split_whitespace()
+                .any(|p| p == "cover-image")
+        })
+        .or_else(|| cover_meta.and_then(item_by_id))
+        .or_else(|| {
+            manifest.iter().find(|item| {
+                item.media_type.starts_with("image/")
+                    && (item.id.to_lowercase().contains("cover")
+                        || item.path.to_lowercase().contains("cover"))
+            })
+        });
+    let cover = cover_item
+        .and_then(|item| image_names.get(&item.path.to_lowercase()))
+        .and_then(|name| images.iter().find(|image| &image.name == name))
+        .map(|image| image.data.clone());
+
+    // 章节内容：每个 spine 文档按目录项的锚点切分，不在目录中的内容并入前一个目录项
+    let mut entries_by_path: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        entries_by_path
+            .entry(entry.path.to_lowercase())
+            .or_default()
+            .push(index);
+    }
+    let mut leading = Vec::new();
+    let mut current: Option<usize> = None;
+    for doc_path in &spine_paths {
+        let Some(text) = archive.read_text(doc_path) else {
+            continue;
+        };
+        let document = parse_markup(&text);
+        let indices = entries_by_path
+            .get(&doc_path.to_lowercase())
+            .cloned()
+            .unwrap_or_default();
+        let split_ids: Vec<String> = indices
+            .iter()
+            .filter_map(|index| entries[*index].fragment.clone())
+            .collect();
+
+        let mut resolve_image = |src: &str| {
+            let (path, _) = resolve_href(doc_path, src);
+            image_names
+                .get(&path.to_lowercase())
+                .map(|name| format!("images/{}", name))
+        };
+        let segments = html_to_content_segments(&document, &split_ids, &mut resolve_image);
+
+        // 锚点不存在的目录项按指向文档开头处理
+        let found: HashSet<&str> = segments
+            .iter()
+            .filter_map(|(id, _)| id.as_deref())
+            .collect();
+        let start_entry = indices.iter().copied().find(|index| {
+            entries[*index]
+                .fragment
+                .as_deref()
+                .is_none_or(|fragment| !found.contains(fragment))
+        });
+
+        for (id, content) in &segments {
+            let target = match id {
+                None => start_entry,
+                Some(id) => indices
+                    .iter()
+                    .copied()
+                    .find(|index| entries[*index].fragment.as_deref() == Some(id.as_str())),
+            };
+            if target.is_some() {
+                current = target;
+            }
+            if content.is_empty() {
+                continue;
+            }
+            match current {
+                Some(index) => append_content(&mut entries[index].content, content),
+                // 目录之前（或没有目录时）的文档作为独立章节，只有图片的封面页等会被跳过
+                None if entries.is_empty() || has_text(content) => {
+                    leading.push(ImportedChapter {
+                        label: document_label(&document, doc_path),
+                        href: doc_path.clone(),
+                        content: content.clone(),
+                        children: Vec::new(),
+                    });
+                }
+                None => {}
+            }
+        }
+    }
+
+    let mut chapters = leading;
+    chapters.extend(build_chapters(&entries, None));
+    Ok(ImportedBook {
+        title,
+        author,
+        description,
+        chapters,
+        images,
+        cover,
+    })
+}
+
+// 导入 EPUB 文件：在 Rust 中解析并在一个事务中写入书籍和章节，返回新书籍
+#[command]
+pub async fn import_epub(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Book>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    let book = match read_epub(Path::new(&path)) {
+        Ok(book) => book,
+        Err(err) => return Ok(DbResponse::error(err)),
+    };
+
+    let mut db = get_db_connection(&state)?;
+    match save_imported_book(&mut db, &app_dir, &book) {
+        Ok(book) => Ok(DbResponse::success(book)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+const EPUB_STYLE: &str = "body { margin: 0 0.5em; line-height: 1.6; }
+p { margin: 0.4em 0; text-indent: 2em; }
+h1, h2, h3, h4, h5, h6 { text-align: center; margin: 1em 0; }
+img { max-width: 100%; }
+p.cover { text-indent: 0; text-align: center; }
+";
+
+fn xhtml_document(title: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"zh\" xml:lang=\"zh\">\n\
+         <head>\n<title>{}</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\" />\n</head>\n\
+         <body>\n{}\n</body>\n</html>\n",
+        escape(title),
+        body
+    )
+}
+
+fn chapter_file_name(id: i64) -> String {
+    format!("chapter{}.xhtml", id)
+}
+
+// 章节页面：内容为空的章节（如卷标题）输出标题
+fn chapter_body(chapter: &ExportChapter) -> String {
+    let body = content_to_html(&chapter.content);
+    if body.is_empty() {
+        format!("<h2>{}</h2>", escape(&chapter.label))
+    } else {
+        body
+    }
+}
+
+fn nav_list(chapters: &[ExportChapter], depth: usize) -> String {
+    let indent = "  ".repeat(depth + 1);
+    let mut html = format!("{}<ol>\n", indent);
+    for chapter in chapters {
+        html.push_str(&format!(
+            "{}  <li><a href=\"{}\">{}</a>",
+            indent,
+            chapter_file_name(chapter.id),
+            escape(&chapter.label)
+        ));
+        if !chapter.children.is_empty() {
+            html.push('\n');
+            html.push_str(&nav_list(&chapter.children, depth + 1));
+            html.push_str(&format!("{}  ", indent));
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str(&format!("{}</ol>\n", indent));
+    html
+}
+
+fn nav_points(chapters: &[ExportChapter], play_order: &mut usize, depth: usize) -> String {
+    let indent = "  ".repeat(depth + 2);
+    let mut xml = String::new();
+    for chapter in chapters {
+        *play_order += 1;
+        xml.push_str(&format!(
+            "{indent}<navPoint id=\"navPoint-{order}\" playOrder=\"{order}\">\n\
+             {indent}  <navLabel><text>{label}</text></navLabel>\n\
+             {indent}  <content src=\"{src}\" />\n",
+            indent = indent,
+            order = play_order,
+            label = escape(&chapter.label),
+            src = chapter_file_name(chapter.id),
+        ));
+        xml.push_str(&nav_points(&chapter.children, play_order, depth + 1));
+        xml.push_str(&format!("{}</navPoint>\n", indent));
+    }
+    xml
+}
+
+// 图片文件：(压缩包内路径, 媒体类型, 本地路径)
+type ImageFile = (String, &'static str, PathBuf);
+
+fn list_book_images(app_dir: &Path, book_id: i64) -> Result<Vec<ImageFile>, String> {
+    let images_dir = book_epub_dir(app_dir, book_id).join("images");
+    if !images_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut paths: Vec<PathBuf> = fs::read_dir(&images_dir)
+        .map_err(|e| format!("读取图片目录失败: {}", e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+
+    let mut images = Vec::new();
+    for path in paths {
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        // 只读取文件头判断类型，内容在写入压缩包时再复制
+        let mut header = [0u8; 12];
+        let len = File::open(&path)
+            .and_then(|mut file| file.read(&mut header))
+            .map_err(|e| format!("读取图片 {} 失败: {}", name, e))?;
+        images.push((
+            format!("images/{}", name),
+            image_media_type(&name, &header[..len]),
+            path,
+        ));
+    }
+    Ok(images)
+}
+
+fn write_epub_entries(
+    zip: &mut ZipWriter<File>,
+    book: &ExportBook,
+    app_dir: &Path,
+) -> Result<(), String> {
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // mimetype 必须是第一个且不压缩的文件
+    write_zip_file(
+        zip,
+        "mimetype",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+        b"application/epub+zip",
+    )?;
+    write_zip_file(
+        zip,
+        "META-INF/container.xml",
+        deflated,
+        b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+          <container xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\" version=\"1.0\">\n\
+          <rootfiles>\n\
+          <rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/>\n\
+          </rootfiles>\n\
+          </container>\n",
+    )?;
+
+    let uuid = book_uuid(book);
+    let chapters = book.flatten();
+    let mut manifest = vec![
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>".to_string(),
+        "<item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\"/>".to_string(),
+        "<item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>".to_string(),
+    ];
+    let mut spine = Vec::new();
+    write_zip_file(zip, "OEBPS/style.css", deflated, EPUB_STYLE.as_bytes())?;
+
+    // 封面
+    let cover = cover_path(app_dir, book.id);
+    let has_cover = cover.is_file();
+    if has_cover {
+        let data = fs::read(&cover).map_err(|e| format!("读取封面失败: {}", e))?;
+        let media_type = image_media_type("cover.jpg", &data);
+        let name = format!("cover.{}", cover_extension(media_type));
+        write_zip_file(zip, &format!("OEBPS/{}", name), deflated, &data)?;
+        let page = xhtml_document(
+            "封面",
+            &format!(
+                "<p class=\"cover\"><img src=\"{}\" alt=\"封面\" /></p>",
+                name
+            ),
+        );
+        write_zip_file(zip, "OEBPS/cover.xhtml", deflated, page.as_bytes())?;
+        manifest.push(format!(
+            "<item id=\"cover-image\" href=\"{}\" media-type=\"{}\" properties=\"cover-image\"/>",
+            name, media_type
+        ));
+        manifest.push(
+            "<item id=\"cover\" href=\"cover.xhtml\" media-type=\"application/xhtml+xml\"/>"
+                .to_string(),
+        );
+        spine.push("<itemref idref=\"cover\" linear=\"yes\"/>".to_string());
+    }
+
+    // 章节
+    for (_, chapter) in &chapters {
+        let name = chapter_file_name(chapter.id);
+        let page = xhtml_document(&chapter.label, &chapter_body(chapter));
+        write_zip_file(zip, &format!("OEBPS/{}", name), deflated, page.as_bytes())?;
+        manifest.push(format!(
+            "<item id=\"chapter{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>",
+            chapter.id, name
+        ));
+        spine.push(format!("<itemref idref=\"chapter{}\"/>", chapter.id));
+    }
+
+    // 内容图片，直接从文件流式写入
+    for (index, (href, media_type, path)) in
+        list_book_images(app_dir, book.id)?.into_iter().enumerate()
+    {
+        let file = File::open(&path).map_err(|e| format!("读取图片 {} 失败: {}", href, e))?;
+        zip.start_file(format!("OEBPS/{}", href), deflated)
+            .map_err(|e| format!("写入 {} 失败: {}", href, e))?;
+        io::copy(&mut io::BufReader::new(file), zip)
+            .map_err(|e| format!("写入 {} 失败: {}", href, e))?;
+        manifest.push(format!(
+            "<item id=\"image{}\" href=\"{}\" media-type=\"{}\"/>",
+            index + 1,
+            escape(&href),
+            media_type
+        ));
+    }
+
+    // EPUB 3 导航文档
+    let nav = xhtml_document(
+        "目录",
+        &format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n  <h1>目录</h1>\n{}</nav>",
+            nav_list(&book.chapters, 0)
+        ),
+    );
+    write_zip_file(zip, "OEBPS/nav.xhtml", deflated, nav.as_bytes())?;
+
+    // 供 EPUB 2 阅读器使用的 NCX 目录
+    let depth = chapters
+        .iter()
+        .map(|(depth, _)| depth + 1)
+        .max()
+        .unwrap_or(1);
+    let ncx = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n\
+         <head>\n\
+         \x20 <meta name=\"dtb:uid\" content=\"urn:uuid:{uuid}\"/>\n\
+         \x20 <meta name=\"dtb:depth\" content=\"{depth}\"/>\n\
+         \x20 <meta name=\"dtb:totalPageCount\" content=\"0\"/>\n\
+         \x20 <meta name=\"dtb:maxPageNumber\" content=\"0\"/>\n\
+         </head>\n\
+         <docTitle><text>{title}</text></docTitle>\n\
+         <docAuthor><text>{author}</text></docAuthor>\n\
+         <navMap>\n{points}</navMap>\n\
+         </ncx>\n",
+        uuid = uuid,
+        depth = depth,
+        title = escape(&book.title),
+        author = escape(&book.author),
+        points = nav_points(&book.chapters, &mut 0, 0),
+    );
+    write_zip_file(zip, "OEBPS/toc.ncx", deflated, ncx.as_bytes())?;
+
+    // OPF
+    let modified = format_utc_time(get_current_time_string().parse().unwrap_or(0));
+    let mut metadata = vec![
+        format!(
+            "<dc:identifier id=\"book-id\">urn:uuid:{}</dc:identifier>",
+            uuid
+        ),
+        format!("<dc:title>{}</dc:title>", escape(&book.title)),
+        format!("<dc:creator>{}</dc:creator>", escape(&book.author)),
+        "<dc:language>zh</dc:language>".to_string(),
+        format!("<meta property=\"dcterms:modified\">{}</meta>", modified),
+    ];
+    if !book.description.trim().is_empty() {
+        metadata.push(format!(
+            "<dc:description>{}</dc:description>",
+            escape(book.description.trim())
+        ));
+    }
+    if has_cover {
+        metadata.push("<meta name=\"cover\" content=\"cover-image\"/>".to_string());
+    }
+    let guide = if has_cover {
+        "  <guide>\n    <reference type=\"cover\" title=\"封面\" href=\"cover.xhtml\"/>\n  </guide>\n"
+    } else {
+        ""
+    };
+    let opf = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" xml:lang=\"zh\">\n\
+         \x20 <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n    {}\n  </metadata>\n\
+         \x20 <manifest>\n    {}\n  </manifest>\n\
+         \x20 <spine toc=\"ncx\">\n    {}\n  </spine>\n\
+         {}</package>\n",
+        metadata.join("\n    "),
+        manifest.join("\n    "),
+        spine.join("\n    "),
+        guide
+    );
+    write_zip_file(zip, "OEBPS/content.opf", deflated, opf.as_bytes())
+}
+
+// 导出 EPUB 3 文件（同时包含 NCX 目录以兼容 EPUB 2 阅读器），失败时删除未写完的文件
+pub fn write_epub(book: &ExportBook, app_dir: &Path, output: &Path) -> Result<(), String> {
+    let file = File::create(output).map_err(|e| format!("创建文件失败: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+    let result = write_epub_entries(&mut zip, book, app_dir)
+        .and_then(|_| zip.finish().map(|_| ()).map_err(|e| e.to_string()));
+    if result.is_err() {
+        let _ = fs::remove_file(output);
+    }
+    result
+}
+
+// 导出书籍为 EPUB 文件，返回导出的文件路径
+#[command]
+pub async fn export_epub(
+    book_id: i64,
+    output_path: String,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<String>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    // 读取完数据后释放数据库锁，再写入文件
+    let book = {
+        let db = get_db_connection(&state)?;
+        match load_export_book(&db, book_id) {
+            Ok(book) => book,
+            Err(err) => return Ok(DbResponse::error(err)),
+        }
+    };
+
+    match write_epub(&book, &app_dir, Path::new(&output_path)) {
+        Ok(_) => Ok(DbResponse::success(output_path)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
@@ -0,0 +1,330 @@
+use crate::toc::{load_toc, TocItem};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+// 导出用的章节树，只包含目录中的章节
+#[derive(Debug, Clone)]
+pub struct ExportChapter {
+    pub id: i64,
+    pub label: String,
+    pub content: String,
+    pub children: Vec<ExportChapter>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportBook {
+    pub id: i64,
+    pub title: String,
+    pub author: String,
+    pub description: String,
+    pub create_time: String,
+    pub chapters: Vec<ExportChapter>,
+}
+
+impl ExportBook {
+    // 按目录顺序展开的章节及其层级（顶级为 0）
+    pub fn flatten(&self) -> Vec<(usize, &ExportChapter)> {
+        let mut result = Vec::new();
+        flatten_chapters(&self.chapters, 0, &mut result);
+        result
+    }
+}
+
+fn flatten_chapters<'a>(
+    chapters: &'a [ExportChapter],
+    depth: usize,
+    result: &mut Vec<(usize, &'a ExportChapter)>,
+) {
+    for chapter in chapters {
+        result.push((depth, chapter));
+        flatten_chapters(&chapter.children, depth + 1, result);
+    }
+}
+
+fn build_export_chapters(
+    items: &[TocItem],
+    contents: &mut HashMap<i64, String>,
+) -> Vec<ExportChapter> {
+    items
+        .iter()
+        .map(|item| ExportChapter {
+            id: item.href,
+            label: item.label.clone(),
+            content: contents.remove(&item.href).unwrap_or_default(),
+            children: item
+                .subitems
+                .as_deref()
+                .map(|children| build_export_chapters(children, contents))
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+// 读取书籍信息和目录树中的全部章节内容
+pub fn load_export_book(db: &Connection, book_id: i64) -> Result<ExportBook, String> {
+    let book = db
+        .query_row(
+            "SELECT title, author, description, createTime FROM ee_book WHERE id = ?",
+            params![book_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some((title, author, description, create_time)) = book else {
+        return Err(format!("书籍 {} 不存在", book_id));
+    };
+
+    let toc = load_toc(db, book_id).map_err(|e| e.to_string())?;
+    let mut contents: HashMap<i64, String> = {
+        let mut stmt = db
+            .prepare("SELECT id, content FROM ee_chapter WHERE bookId = ? AND position IS NOT NULL")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![book_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+
+    Ok(ExportBook {
+        id: book_id,
+        title,
+        author,
+        description,
+        create_time,
+        chapters: build_export_chapters(&toc, &mut contents),
+    })
+}
+
+// 64 位 FNV-1a 哈希，算法固定，不随 Rust 版本变化
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        // 各字段之后加一个 0 字节分隔，避免拼接后相同
+        for byte in part.iter().chain(&[0]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+// 根据书籍 id、创建时间和书名生成稳定的 UUID，同一本书多次导出时标识不变
+pub fn book_uuid(book: &ExportBook) -> String {
+    let id = book.id.to_le_bytes();
+    let hash = |salt: u8| {
+        fnv1a(&[
+            &[salt],
+            &id,
+            book.create_time.as_bytes(),
+            book.title.as_bytes(),
+        ])
+    };
+    let (high, low) = (hash(0), hash(1));
+    // 按 UUID v4 的格式设置版本和变体位
+    let high = (high & 0xFFFF_FFFF_FFFF_0FFF) | 0x0000_0000_0000_4000;
+    let low = (low & 0x3FFF_FFFF_FFFF_FFFF) | 0x8000_0000_0000_0000;
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xFFFF,
+        high & 0xFFFF,
+        low >> 48,
+        low & 0xFFFF_FFFF_FFFF
+    )
+}
+
+// 把秒级时间戳格式化为 UTC 时间，如 2024-01-02T03:04:05Z
+pub fn format_utc_time(secs: i64) -> String {
+    let days = secs.div_euclid(86_400);
+    let rest = secs.rem_euclid(86_400);
+    // 公历日期换算（Howard Hinnant 的 civil_from_days 算法）
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+// 根据文件头判断图片类型，无法识别时按扩展名判断
+pub fn image_media_type(name: &str, data: &[u8]) -> &'static str {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return "image/jpeg";
+    }
+    if data.starts_with(b"\x89PNG") {
+        return "image/png";
+    }
+    if data.starts_with(b"GIF8") {
+        return "image/gif";
+    }
+    if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    if data.starts_with(b"BM") {
+        return "image/bmp";
+    }
+    let ext = name
+        .rsplit('.')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        _ => "image/jpeg",
+    }
+}
+
+// 图片的像素尺寸，支持 PNG、JPEG、GIF、BMP 和 WebP
+pub fn image_size(data: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |i: usize| Some(u16::from_be_bytes(data.get(i..i + 2)?.try_into().ok()?) as u32);
+    let le16 = |i: usize| Some(u16::from_le_bytes(data.get(i..i + 2)?.try_into().ok()?) as u32);
+    let be32 = |i: usize| Some(u32::from_be_bytes(data.get(i..i + 4)?.try_into().ok()?));
+    let le32 = |i: usize| Some(i32::from_le_bytes(data.get(i..i + 4)?.try_into().ok()?));
+    let le24 = |i: usize| {
+        let bytes = data.get(i..i + 3)?;
+        Some(bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16)
+    };
+
+    if data.starts_with(b"\x89PNG") {
+        return Some((be32(16)?, be32(20)?));
+    }
+    if data.starts_with(b"GIF8") {
+        return Some((le16(6)?, le16(8)?));
+    }
+    if data.starts_with(b"BM") {
+        return Some((le32(18)?.unsigned_abs(), le32(22)?.unsigned_abs()));
+    }
+    if data.len() >= 30 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return match &data[12..16] {
+            b"VP8 " => Some((le16(26)? & 0x3FFF, le16(28)? & 0x3FFF)),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(data.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+            }
+            b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+            _ => None,
+        };
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        // 依次跳过 JPEG 的各个段，直到帧头（SOF）
+        let mut i = 2;
+        while i + 9 < data.len() {
+            if data[i] != 0xFF {
+                i += 1;
+                continue;
+            }
+            let marker = data[i + 1];
+            if marker == 0xFF {
+                i += 1;
+                continue;
+            }
+            if matches!(marker, 0x01 | 0xD0..=0xD8) {
+                i += 2;
+                continue;
+            }
+            if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                return Some((be16(i + 7)?, be16(i + 5)?));
+            }
+            i += 2 + be16(i + 2)? as usize;
+        }
+    }
+    None
+}
+
+// 向压缩包写入一个文件
+pub fn write_zip_file(
+    zip: &mut ZipWriter<File>,
+    name: &str,
+    options: FileOptions,
+    data: &[u8],
+) -> Result<(), String> {
+    zip.start_file(name, options)
+        .map_err(|e| format!("写入 {} 失败: {}", name, e))?;
+    zip.write_all(data)
+        .map_err(|e| format!("写入 {} 失败: {}", name, e))
+}
+
+// 封面图片按媒体类型选择扩展名
+pub fn cover_extension(media_type: &str) -> &'static str {
+    match media_type {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        _ => "jpg",
+    }
+}
+
+// 导出时写入的文件，失败时全部删除
+#[derive(Default)]
+pub struct WrittenFiles(Vec<PathBuf>);
+
+impl WrittenFiles {
+    pub fn write(&mut self, path: PathBuf, data: &[u8]) -> Result<(), String> {
+        fs::write(&path, data).map_err(|e| format!("写入文件失败: {}", e))?;
+        self.0.push(path);
+        Ok(())
+    }
+
+    pub fn remove_all(&self) {
+        for path in &self.0 {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// 引用到的图片复制到输出目录的 images 子目录，缺失的图片跳过
+pub fn copy_images(
+    images: &[String],
+    source_dir: &Path,
+    output_dir: &Path,
+    written: &mut WrittenFiles,
+) -> Result<(), String> {
+    if images.is_empty() {
+        return Ok(());
+    }
+    let images_dir = output_dir.join("images");
+    fs::create_dir_all(&images_dir).map_err(|e| format!("创建图片目录失败: {}", e))?;
+    for name in images {
+        if name.contains(['/', '\\']) {
+            continue;
+        }
+        if let Ok(data) = fs::read(source_dir.join(name)) {
+            written.write(images_dir.join(name), &data)?;
+        }
+    }
+    Ok(())
+}
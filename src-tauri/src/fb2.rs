@@ -0,0 +1,730 @@
+use crate::database::{get_current_time_string, get_db_connection, Book, DbResponse};
+use crate::exporter::{
+    book_uuid, format_utc_time, image_media_type, load_export_book, ExportBook, ExportChapter,
+};
+use crate::fileutil::{book_epub_dir, cover_path};
+use crate::importer::{
+    save_imported_book, ImageNamer, ImportedBook, ImportedChapter, ImportedImage,
+};
+use crate::markup::{content_to_html, escape, is_wide, parse_markup, Element, Node};
+use crate::setup::AppState;
+use crate::textutil::{split_tags, Segment};
+use crate::txt::decode_text;
+use base64::engine::general_purpose;
+use base64::engine::Engine as _;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use tauri::{command, AppHandle, Manager, State};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+// 没有标题的章节使用正文开头作为标题时的最大长度
+const LABEL_CHARS: usize = 20;
+
+// 读取 FB2 文本：FB2.ZIP 中取第一个 .fb2 文件，编码按 XML 声明解码
+fn read_fb2_text(path: &Path) -> Result<String, String> {
+    let data = fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let data = if data.starts_with(b"PK") {
+        let mut archive =
+            ZipArchive::new(Cursor::new(data)).map_err(|e| format!("无法打开压缩包: {}", e))?;
+        let names: Vec<String> = archive.file_names().map(|name| name.to_string()).collect();
+        let name = names
+            .iter()
+            .find(|name| name.to_lowercase().ends_with(".fb2"))
+            .ok_or("压缩包中没有 FB2 文件")?;
+        let mut entry = archive
+            .by_name(name)
+            .map_err(|e| format!("读取 {} 失败: {}", name, e))?;
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| format!("读取 {} 失败: {}", name, e))?;
+        data
+    } else {
+        data
+    };
+
+    let head = String::from_utf8_lossy(&data[..data.len().min(200)]).into_owned();
+    let declared = head
+        .split("?>")
+        .next()
+        .filter(|decl| decl.trim_start_matches('\u{FEFF}').starts_with("<?xml"))
+        .and_then(|decl| decl.split("encoding").nth(1))
+        .and_then(|rest| {
+            let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+            let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            rest[1..].split(quote).next().map(|label| label.to_string())
+        });
+    let (text, _) = decode_text(&data, declared.as_deref())?;
+    Ok(text)
+}
+
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// 作者姓名：有昵称时用昵称，中文姓名按“姓名”顺序连写，其余按“名 中间名 姓”以空格连接
+fn author_name(author: &Element) -> String {
+    let part = |name: &str| {
+        author
+            .child(name)
+            .map(|element| normalize_text(&element.text()))
+            .unwrap_or_default()
+    };
+    let nickname = part("nickname");
+    let (first, middle, last) = (part("first-name"), part("middle-name"), part("last-name"));
+    let parts: Vec<&String> = [&first, &middle, &last]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect();
+    if parts.is_empty() {
+        return nickname;
+    }
+    if parts.iter().any(|part| part.chars().any(is_wide)) {
+        format!("{}{}{}", last, first, middle)
+    } else {
+        parts
+            .iter()
+            .map(|part| part.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// 标题中的各段落以空格连接
+fn title_text(title: &Element) -> String {
+    let paragraphs: Vec<String> = title
+        .elements()
+        .map(|element| normalize_text(&element.text()))
+        .filter(|text| !text.is_empty())
+        .collect();
+    if paragraphs.is_empty() {
+        normalize_text(&title.text())
+    } else {
+        paragraphs.join(" ")
+    }
+}
+
+// FB2 正文转换为章节内容：段落、诗行按行分隔，strong/emphasis 转为 b/i，图片引用 binary 保存的文件
+struct Fb2ContentWriter<'a> {
+    images: &'a HashMap<String, String>,
+    lines: Vec<String>,
+}
+
+impl Fb2ContentWriter<'_> {
+    fn image_tag(&self, element: &Element) -> Option<String> {
+        let href = element.attr("href")?;
+        let name = self.images.get(href.trim_start_matches('#'))?;
+        Some(format!("<img src=\"images/{}\" />", escape(name)))
+    }
+
+    fn inline(&self, element: &Element, out: &mut String) {
+        for node in &element.children {
+            match node {
+                Node::Text(text) => push_collapsed(out, text),
+                Node::Element(child) => match child.name.as_str() {
+                    "strong" => {
+                        out.push_str("<b>");
+                        self.inline(child, out);
+                        out.push_str("</b>");
+                    }
+                    "emphasis" => {
+                        out.push_str("<i>");
+                        self.inline(child, out);
+                        out.push_str("</i>");
+                    }
+                    "image" => {
+                        if let Some(tag) = self.image_tag(child) {
+                            out.push_str(&tag);
+                        }
+                    }
+                    _ => self.inline(child, out),
+                },
+            }
+        }
+    }
+
+    fn line(&mut self, element: &Element, wrap: Option<&str>) {
+        let mut line = String::new();
+        self.inline(element, &mut line);
+        let line = line.trim().to_string();
+        if line.is_empty() {
+            return;
+        }
+        self.lines.push(match wrap {
+            Some(tag) => format!("<{}>{}</{}>", tag, line, tag),
+            None => line,
+        });
+    }
+
+    fn block(&mut self, element: &Element) {
+        for child in element.elements() {
+            match child.name.as_str() {
+                "p" | "v" | "text-author" | "td" | "th" => self.line(child, None),
+                "subtitle" => self.line(child, Some("h3")),
+                // section 和 body 的标题作为章节标题，诗歌等内部的标题保留为小标题
+                "title" if !matches!(element.name.as_str(), "section" | "body") => {
+                    for paragraph in child.elements() {
+                        self.line(paragraph, Some("h3"));
+                    }
+                }
+                "title" | "section" => {}
+                "empty-line" => self.lines.push(String::new()),
+                "image" => {
+                    if let Some(tag) = self.image_tag(child) {
+                        self.lines.push(tag);
+                    }
+                }
+                _ => self.block(child),
+            }
+        }
+    }
+}
+
+// 输出中最后一个文字字符，跳过末尾的标签
+fn last_text_char(out: &str) -> Option<char> {
+    let mut text = out;
+    while text.ends_with('>') {
+        text = &text[..text.rfind('<')?];
+    }
+    text.chars().next_back()
+}
+
+// 连续空白折叠为一个空格，行首不输出空格；中文之间由换行产生的空白直接去掉
+fn push_collapsed(out: &mut String, text: &str) {
+    let mut pending: Option<bool> = None;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            let newline = c == '\n' || pending == Some(true);
+            pending = Some(newline);
+            continue;
+        }
+        if let Some(newline) = pending.take() {
+            let soft = newline && is_wide(c) && last_text_char(out).is_some_and(is_wide);
+            if !soft && !out.is_empty() && !out.ends_with(' ') {
+                out.push(' ');
+            }
+        }
+        out.push(c);
+    }
+    if pending.is_some() && !out.is_empty() && !out.ends_with(' ') {
+        out.push(' ');
+    }
+}
+
+fn section_content(section: &Element, images: &HashMap<String, String>) -> String {
+    let mut writer = Fb2ContentWriter {
+        images,
+        lines: Vec::new(),
+    };
+    writer.block(section);
+    writer.lines.join("\n").trim_matches('\n').to_string()
+}
+
+// 没有标题的章节取正文第一行作为标题
+fn fallback_label(content: &str) -> String {
+    let first_line = content
+        .lines()
+        .map(|line| {
+            split_tags(line)
+                .iter()
+                .filter_map(|segment| match segment {
+                    Segment::Text(text) => Some(*text),
+                    Segment::Tag(_) => None,
+                })
+                .collect::<String>()
+        })
+        .map(|line| line.trim().to_string())
+        .find(|line| !line.is_empty());
+    match first_line {
+        Some(line) => line.chars().take(LABEL_CHARS).collect(),
+        None => "无标题".to_string(),
+    }
+}
+
+// section 的嵌套对应章节层级；没有标题的第一个子 section 是导出时为父章节正文添加的，并入父章节
+fn read_section(section: &Element, images: &HashMap<String, String>) -> ImportedChapter {
+    let mut content = section_content(section, images);
+    let mut sections: Vec<&Element> = section.children_named("section").collect();
+    if let Some(first) = sections.first() {
+        if first.child("title").is_none() && first.child("section").is_none() {
+            let own = section_content(first, images);
+            if !own.is_empty() {
+                if !content.is_empty() {
+                    content.push('\n');
+                }
+                content.push_str(&own);
+            }
+            sections.remove(0);
+        }
+    }
+
+    let label = section
+        .child("title")
+        .map(title_text)
+        .filter(|label| !label.is_empty())
+        .unwrap_or_else(|| fallback_label(&content));
+    ImportedChapter {
+        label,
+        href: section.attr("id").unwrap_or_default().to_string(),
+        content,
+        children: sections
+            .into_iter()
+            .map(|child| read_section(child, images))
+            .collect(),
+    }
+}
+
+fn image_extension(content_type: &str, data: &[u8]) -> &'static str {
+    match image_media_type(content_type, data) {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        "image/svg+xml" => "svg",
+        _ => "jpg",
+    }
+}
+
+// 解析 FB2 / FB2.ZIP 文件：description 为书籍信息，body 中的 section 为章节，binary 为图片
+pub fn read_fb2(path: &Path) -> Result<ImportedBook, String> {
+    let text = read_fb2_text(path)?;
+    let document = parse_markup(&text);
+    let root = document.find("fictionbook").ok_or("不是有效的 FB2 文件")?;
+
+    // 图片：binary 中的 base64 数据，章节内容中按 id 引用
+    let mut namer = ImageNamer::default();
+    let mut images = Vec::new();
+    let mut image_names: HashMap<String, String> = HashMap::new();
+    for binary in root.children_named("binary") {
+        let Some(id) = binary.attr("id") else {
+            continue;
+        };
+        let encoded: String = binary.text().split_whitespace().collect();
+        let Ok(data) = general_purpose::STANDARD.decode(encoded.as_bytes()) else {
+            continue;
+        };
+        if data.is_empty() {
+            continue;
+        }
+        let content_type = binary.attr("content-type").unwrap_or_default();
+        let file_name = if id.contains('.') {
+            id.to_string()
+        } else {
+            format!("{}.{}", id, image_extension(content_type, &data))
+        };
+        let name = namer.name(&file_name);
+        image_names.insert(id.to_string(), name.clone());
+        images.push(ImportedImage { name, data });
+    }
+
+    // 元数据
+    let title_info = root
+        .child("description")
+        .and_then(|description| description.child("title-info"));
+    let file_stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_stem = file_stem
+        .strip_suffix(".fb2")
+        .map(|stem| stem.to_string())
+        .unwrap_or(file_stem);
+    let title = title_info
+        .and_then(|info| info.child("book-title"))
+        .map(|title| normalize_text(&title.text()))
+        .filter(|title| !title.is_empty())
+        .unwrap_or(file_stem);
+    let authors: Vec<String> = title_info
+        .map(|info| {
+            info.children_named("author")
+                .map(author_name)
+                .filter(|name| !name.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let author = if authors.is_empty() {
+        "佚名".to_string()
+    } else {
+        authors.join("、")
+    };
+    let description = title_info
+        .and_then(|info| info.child("annotation"))
+        .map(|annotation| {
+            let lines: Vec<String> = annotation
+                .elements()
+                .map(|element| normalize_text(&element.text()))
+                .filter(|line| !line.is_empty())
+                .collect();
+            if lines.is_empty() {
+                normalize_text(&annotation.text())
+            } else {
+                lines.join("\n")
+            }
+        })
+        .filter(|description| !description.is_empty())
+        .unwrap_or_else(|| "暂缺".to_string());
+    let cover = title_info
+        .and_then(|info| info.child("coverpage"))
+        .and_then(|coverpage| coverpage.find("image"))
+        .and_then(|image| image.attr("href"))
+        .and_then(|href| image_names.get(href.trim_start_matches('#')))
+        .and_then(|name| images.iter().find(|image| &image.name == name))
+        .map(|image| image.data.clone());
+
+    // 章节：主 body 的 section 为顶级章节，注释等其他 body 各作为一个顶级章节
+    let mut chapters = Vec::new();
+    for (index, body) in root.children_named("body").enumerate() {
+        let content = section_content(body, &image_names);
+        let sections: Vec<ImportedChapter> = body
+            .children_named("section")
+            .map(|section| read_section(section, &image_names))
+            .collect();
+        if index == 0 && body.attr("name").is_none() {
+            if !content.is_empty() {
+                chapters.push(ImportedChapter {
+                    label: title.clone(),
+                    href: String::new(),
+                    content,
+                    children: Vec::new(),
+                });
+            }
+            chapters.extend(sections);
+        } else if !content.is_empty() || !sections.is_empty() {
+            let label = body
+                .child("title")
+                .map(title_text)
+                .filter(|label| !label.is_empty())
+                .unwrap_or_else(|| "注释".to_string());
+            chapters.push(ImportedChapter {
+                label,
+                href: body.attr("name").unwrap_or_default().to_string(),
+                content,
+                children: sections,
+            });
+        }
+    }
+    if chapters.is_empty() {
+        return Err("FB2 中没有可读取的章节".to_string());
+    }
+
+    Ok(ImportedBook {
+        title,
+        author,
+        description,
+        chapters,
+        images,
+        cover,
+    })
+}
+
+// 导入 FB2 / FB2.ZIP 文件：在 Rust 中解析并在一个事务中写入书籍和章节，返回新书籍
+#[command]
+pub async fn import_fb2(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Book>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    let book = match read_fb2(Path::new(&path)) {
+        Ok(book) => book,
+        Err(err) => return Ok(DbResponse::error(err)),
+    };
+
+    let mut db = get_db_connection(&state)?;
+    match save_imported_book(&mut db, &app_dir, &book) {
+        Ok(book) => Ok(DbResponse::success(book)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 导出时引用到的图片：(binary id, 文件名)，按首次引用的顺序
+struct Fb2Images<'a> {
+    images_dir: &'a Path,
+    used: Vec<(String, String)>,
+}
+
+impl Fb2Images<'_> {
+    // 章节内容中的 images/{name} 转换为 binary id，图片文件不存在时返回 None
+    fn id(&mut self, src: &str) -> Option<String> {
+        let name = src.strip_prefix("images/")?;
+        if let Some((id, _)) = self.used.iter().find(|(_, used)| used == name) {
+            return Some(id.clone());
+        }
+        if name.contains(['/', '\\']) || !self.images_dir.join(name).is_file() {
+            return None;
+        }
+        // binary id 必须以字母开头
+        let id = format!("img_{}", name);
+        self.used.push((id.clone(), name.to_string()));
+        Some(id)
+    }
+}
+
+fn fb2_inline(element: &Element, images: &mut Fb2Images, out: &mut String) {
+    for node in &element.children {
+        match node {
+            Node::Text(text) => out.push_str(&escape(text)),
+            Node::Element(child) => match child.name.as_str() {
+                "b" | "strong" => {
+                    out.push_str("<strong>");
+                    fb2_inline(child, images, out);
+                    out.push_str("</strong>");
+                }
+                "i" | "em" => {
+                    out.push_str("<emphasis>");
+                    fb2_inline(child, images, out);
+                    out.push_str("</emphasis>");
+                }
+                "img" => {
+                    if let Some(id) = child.attr("src").and_then(|src| images.id(src)) {
+                        out.push_str(&format!("<image l:href=\"#{}\"/>", id));
+                    }
+                }
+                "br" => out.push(' '),
+                _ => fb2_inline(child, images, out),
+            },
+        }
+    }
+}
+
+// 章节内容转换为 FB2 段落：先转换为 XHTML 再映射元素，标题转为 subtitle，列表项转为段落
+fn content_to_fb2(content: &str, images: &mut Fb2Images) -> String {
+    let html = parse_markup(&content_to_html(content));
+    let mut out = String::new();
+    for element in html.elements() {
+        match element.name.as_str() {
+            "p" => {
+                // 只有图片的段落输出为块级图片
+                let only_images = element.children.iter().all(|node| match node {
+                    Node::Text(text) => text.trim().is_empty(),
+                    Node::Element(child) => child.name == "img",
+                });
+                if only_images {
+                    for img in element.children_named("img") {
+                        if let Some(id) = img.attr("src").and_then(|src| images.id(src)) {
+                            out.push_str(&format!("<image l:href=\"#{}\"/>\n", id));
+                        }
+                    }
+                    continue;
+                }
+                let mut line = String::new();
+                fb2_inline(element, images, &mut line);
+                out.push_str(&format!("<p>{}</p>\n", line));
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let mut line = String::new();
+                fb2_inline(element, images, &mut line);
+                out.push_str(&format!("<subtitle>{}</subtitle>\n", line));
+            }
+            "ul" | "ol" => {
+                let ordered = element.name == "ol";
+                for (index, item) in element.children_named("li").enumerate() {
+                    let mut line = if ordered {
+                        format!("{}. ", index + 1)
+                    } else {
+                        "• ".to_string()
+                    };
+                    fb2_inline(item, images, &mut line);
+                    out.push_str(&format!("<p>{}</p>\n", line));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+// FB2 的 section 不能同时包含段落和子 section，有子章节时正文放入第一个没有标题的子 section
+fn write_section(chapter: &ExportChapter, images: &mut Fb2Images, out: &mut String) {
+    out.push_str(&format!(
+        "<section>\n<title><p>{}</p></title>\n",
+        escape(&chapter.label)
+    ));
+    let body = content_to_fb2(&chapter.content, images);
+    if chapter.children.is_empty() {
+        out.push_str(if body.is_empty() {
+            "<empty-line/>\n"
+        } else {
+            &body
+        });
+    } else {
+        if !body.is_empty() {
+            out.push_str("<section>\n");
+            out.push_str(&body);
+            out.push_str("</section>\n");
+        }
+        for child in &chapter.children {
+            write_section(child, images, out);
+        }
+    }
+    out.push_str("</section>\n");
+}
+
+fn build_fb2(book: &ExportBook, app_dir: &Path) -> Result<String, String> {
+    let images_dir = book_epub_dir(app_dir, book.id).join("images");
+    let mut images = Fb2Images {
+        images_dir: &images_dir,
+        used: Vec::new(),
+    };
+
+    let mut body = format!("<body>\n<title><p>{}</p></title>\n", escape(&book.title));
+    for chapter in &book.chapters {
+        write_section(chapter, &mut images, &mut body);
+    }
+    if book.chapters.is_empty() {
+        body.push_str("<section>\n<empty-line/>\n</section>\n");
+    }
+    body.push_str("</body>\n");
+
+    let mut binaries = String::new();
+    let cover = cover_path(app_dir, book.id);
+    let cover_id = if cover.is_file() {
+        let data = fs::read(&cover).map_err(|e| format!("读取封面失败: {}", e))?;
+        let media_type = image_media_type("cover.jpg", &data);
+        let id = format!("cover.{}", image_extension(media_type, &data));
+        binaries.push_str(&format!(
+            "<binary id=\"{}\" content-type=\"{}\">{}</binary>\n",
+            id,
+            media_type,
+            general_purpose::STANDARD.encode(&data)
+        ));
+        Some(id)
+    } else {
+        None
+    };
+    for (id, name) in &images.used {
+        let data = fs::read(images_dir.join(name))
+            .map_err(|e| format!("读取图片 {} 失败: {}", name, e))?;
+        binaries.push_str(&format!(
+            "<binary id=\"{}\" content-type=\"{}\">{}</binary>\n",
+            escape(id),
+            image_media_type(name, &data),
+            general_purpose::STANDARD.encode(&data)
+        ));
+    }
+
+    let author = if book.author.trim().is_empty() {
+        "佚名"
+    } else {
+        book.author.trim()
+    };
+    let annotation: String = book
+        .description
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && *line != "暂缺")
+        .map(|line| format!("<p>{}</p>", escape(line)))
+        .collect();
+    let now = get_current_time_string().parse::<i64>().unwrap_or(0);
+    let date = format_utc_time(now)[..10].to_string();
+
+    let mut title_info = vec![
+        "<genre>prose_contemporary</genre>".to_string(),
+        format!("<author><nickname>{}</nickname></author>", escape(author)),
+        format!("<book-title>{}</book-title>", escape(&book.title)),
+    ];
+    if !annotation.is_empty() {
+        title_info.push(format!("<annotation>{}</annotation>", annotation));
+    }
+    if let Some(id) = &cover_id {
+        title_info.push(format!(
+            "<coverpage><image l:href=\"#{}\"/></coverpage>",
+            id
+        ));
+    }
+    title_info.push("<lang>zh</lang>".to_string());
+
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<FictionBook xmlns=\"http://www.gribuser.ru/xml/fictionbook/2.0\" xmlns:l=\"http://www.w3.org/1999/xlink\">
+<description>
+<title-info>
+{}
+</title-info>
+<document-info>
+<author><nickname>{}</nickname></author>
+<program-used>My Ebooks</program-used>
+<date value=\"{}\">{}</date>
+<id>{}</id>
+<version>1.0</version>
+</document-info>
+</description>
+{}{}</FictionBook>
+",
+        title_info.join("\n"),
+        escape(author),
+        date,
+        date,
+        book_uuid(book),
+        body,
+        binaries
+    ))
+}
+
+// 写入 FB2 文件，输出路径以 .zip 结尾时写入 FB2.ZIP；失败时删除不完整的文件
+pub fn write_fb2(book: &ExportBook, app_dir: &Path, output: &Path) -> Result<(), String> {
+    let xml = build_fb2(book, app_dir)?;
+    let is_zip = output
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+
+    let result = if is_zip {
+        let entry_name = output
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .filter(|stem| stem.to_lowercase().ends_with(".fb2"))
+            .unwrap_or_else(|| format!("book{}.fb2", book.id));
+        File::create(output)
+            .map_err(|e| format!("创建文件失败: {}", e))
+            .and_then(|file| {
+                let mut zip = ZipWriter::new(file);
+                let options =
+                    FileOptions::default().compression_method(CompressionMethod::Deflated);
+                zip.start_file(entry_name.as_str(), options)
+                    .and_then(|_| zip.write_all(xml.as_bytes()).map_err(Into::into))
+                    .and_then(|_| zip.finish().map(|_| ()))
+                    .map_err(|e| format!("写入 {} 失败: {}", entry_name, e))
+            })
+    } else {
+        fs::write(output, xml).map_err(|e| format!("写入文件失败: {}", e))
+    };
+    if result.is_err() {
+        let _ = fs::remove_file(output);
+    }
+    result
+}
+
+// 导出书籍为 FB2 或 FB2.ZIP 文件，返回导出的文件路径
+#[command]
+pub async fn export_fb2(
+    book_id: i64,
+    output_path: String,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<String>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    // 读取完数据后释放数据库锁，再写入文件
+    let book = {
+        let db = get_db_connection(&state)?;
+        match load_export_book(&db, book_id) {
+            Ok(book) => book,
+            Err(err) => return Ok(DbResponse::error(err)),
+        }
+    };
+
+    match write_fb2(&book, &app_dir, Path::new(&output_path)) {
+        Ok(_) => Ok(DbResponse::success(output_path)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
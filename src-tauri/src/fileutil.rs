@@ -2,7 +2,7 @@ use base64::engine::general_purpose;
 use base64::engine::Engine as _;
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tauri::{command, AppHandle, Manager};
 use tauri_plugin_shell::ShellExt;
 use zip::result::ZipResult;
@@ -11,12 +11,27 @@ use zip::write::FileOptions;
 use zip::read::ZipFile;
 use zip::result::ZipError;
 
+// 书籍封面路径：covers/{bookId}.jpg
+pub fn cover_path(app_dir: &Path, book_id: i64) -> PathBuf {
+    app_dir.join("covers").join(format!("{}.jpg", book_id))
+}
+
+// 书籍资源目录：epub/{bookId}，章节图片保存在其下的 images 目录
+pub fn book_epub_dir(app_dir: &Path, book_id: i64) -> PathBuf {
+    app_dir.join("epub").join(book_id.to_string())
+}
+
+// 标准 Base64 编码，用于前端显示图片和 HTML 中的 data URI
+pub fn encode_base64(data: &[u8]) -> String {
+    general_purpose::STANDARD.encode(data)
+}
+
 #[command]
 pub fn read_image(path: String) -> Result<String, String> {
     // 读取图片文件
     let image_data = fs::read(path).map_err(|e| e.to_string())?;
     // 转换为 Base64
-    let base64_data = general_purpose::STANDARD.encode(&image_data);
+    let base64_data = encode_base64(&image_data);
     Ok(base64_data)
 }
 
@@ -40,7 +55,7 @@ pub async fn restart_app(app_handle: AppHandle) -> Result<(), String> {
     };
 
     // 使用链式调用直接创建并启动进程，避免所有权问题
-    match app_handle.shell().command(exe_path).args(&["--restart"]).spawn()
+    match app_handle.shell().command(exe_path).args(["--restart"]).spawn()
     {
         Ok(_) => {
             // 延迟一小段时间后关闭当前应用
@@ -0,0 +1,898 @@
+// TrueType / OpenType 字体解析与子集化：PDF 导出时嵌入用户选择的字体，只保留用到的字形
+
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+// CFF 字典操作符，双字节操作符记为 1200 + 第二个字节
+const CFF_CHARSET: u16 = 15;
+const CFF_ENCODING: u16 = 16;
+const CFF_CHARSTRINGS: u16 = 17;
+const CFF_PRIVATE: u16 = 18;
+const CFF_SUBRS: u16 = 19;
+const CFF_ROS: u16 = 1230;
+const CFF_FDARRAY: u16 = 1236;
+const CFF_FDSELECT: u16 = 1237;
+// Type 2 字符串中的 endchar，子集中未使用的字形替换为只有它的空字形
+const CFF_ENDCHAR: u8 = 14;
+// 子集中需要保留的 TrueType 表，cmap 等其余表在 PDF 中用不到
+const TRUETYPE_TABLES: &[&[u8; 4]] = &[
+    b"head", b"hhea", b"maxp", b"hmtx", b"cvt ", b"fpgm", b"prep",
+];
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn i16_at(data: &[u8], offset: usize) -> Option<i16> {
+    u16_at(data, offset).map(|value| value as i16)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outlines {
+    TrueType,
+    Cff,
+}
+
+pub struct Font {
+    data: Vec<u8>,
+    tables: HashMap<[u8; 4], Range<usize>>,
+    pub outlines: Outlines,
+    pub units_per_em: u16,
+    pub num_glyphs: u16,
+    // 选用的 cmap 子表：(偏移, 格式)
+    cmap: Option<(usize, u16)>,
+    advances: Vec<u16>,
+    long_loca: bool,
+    pub ascent: i16,
+    pub descent: i16,
+    pub cap_height: i16,
+    pub bbox: [i16; 4],
+    pub postscript_name: String,
+    // CID 字体中字形序号对应的 CID，非 CID 字体为空
+    cids: Vec<u16>,
+}
+
+impl Font {
+    // 解析 TTF/OTF 文件，字体集合（TTC）使用其中第一个字体
+    pub fn parse(data: Vec<u8>) -> Result<Font, String> {
+        let invalid = || "字体文件已损坏或格式不受支持".to_string();
+        let base = if data.starts_with(b"ttcf") {
+            u32_at(&data, 12).ok_or_else(invalid)? as usize
+        } else {
+            0
+        };
+        let outlines = match u32_at(&data, base).ok_or_else(invalid)? {
+            0x0001_0000 | 0x7472_7565 => Outlines::TrueType,
+            0x4F54_544F => Outlines::Cff,
+            _ => return Err("不支持的字体格式，请选择 TTF、OTF 或 TTC 字体".to_string()),
+        };
+
+        let count = u16_at(&data, base + 4).ok_or_else(invalid)? as usize;
+        let mut tables = HashMap::new();
+        for index in 0..count {
+            let record = base + 12 + index * 16;
+            let tag: [u8; 4] = data
+                .get(record..record + 4)
+                .and_then(|tag| tag.try_into().ok())
+                .ok_or_else(invalid)?;
+            let offset = u32_at(&data, record + 8).ok_or_else(invalid)? as usize;
+            let length = u32_at(&data, record + 12).ok_or_else(invalid)? as usize;
+            if offset + length > data.len() {
+                return Err(invalid());
+            }
+            tables.insert(tag, offset..offset + length);
+        }
+
+        let mut font = Font {
+            data,
+            tables,
+            outlines,
+            units_per_em: 1000,
+            num_glyphs: 0,
+            cmap: None,
+            advances: Vec::new(),
+            long_loca: false,
+            ascent: 880,
+            descent: -120,
+            cap_height: 700,
+            bbox: [0, -120, 1000, 880],
+            postscript_name: String::new(),
+            cids: Vec::new(),
+        };
+        font.read_tables().ok_or_else(invalid)?;
+        if font.cmap.is_none() {
+            return Err("字体中没有 Unicode 字符映射表".to_string());
+        }
+        Ok(font)
+    }
+
+    fn table(&self, tag: &[u8; 4]) -> Option<&[u8]> {
+        self.tables.get(tag).map(|range| &self.data[range.clone()])
+    }
+
+    fn read_tables(&mut self) -> Option<()> {
+        let head = self.table(b"head")?;
+        let units_per_em = u16_at(head, 18).filter(|units| *units > 0)?;
+        let bbox = [
+            i16_at(head, 36)?,
+            i16_at(head, 38)?,
+            i16_at(head, 40)?,
+            i16_at(head, 42)?,
+        ];
+        let long_loca = i16_at(head, 50)? == 1;
+        self.units_per_em = units_per_em;
+        self.bbox = bbox;
+        self.long_loca = long_loca;
+        self.num_glyphs = u16_at(self.table(b"maxp")?, 4)?;
+
+        let hhea = self.table(b"hhea")?;
+        let (ascent, descent) = (i16_at(hhea, 4)?, i16_at(hhea, 6)?);
+        let metrics = u16_at(hhea, 34)? as usize;
+        self.ascent = ascent;
+        self.descent = descent;
+        let hmtx = self.table(b"hmtx")?;
+        self.advances = (0..metrics)
+            .map_while(|index| u16_at(hmtx, index * 4))
+            .collect();
+        self.cap_height = self
+            .table(b"OS/2")
+            .filter(|os2| u16_at(os2, 0).is_some_and(|version| version >= 2))
+            .and_then(|os2| i16_at(os2, 88))
+            .unwrap_or((self.ascent as f64 * 0.8) as i16);
+
+        self.postscript_name = self.read_postscript_name().unwrap_or_default();
+        self.cmap = self.select_cmap();
+
+        match self.outlines {
+            Outlines::TrueType => {
+                self.table(b"glyf")?;
+                self.table(b"loca")?;
+            }
+            Outlines::Cff => self.cids = self.read_cff_cids()?,
+        }
+        Some(())
+    }
+
+    // name 表中的 PostScript 名称（nameID 6）
+    fn read_postscript_name(&self) -> Option<String> {
+        let name = self.table(b"name")?;
+        let count = u16_at(name, 2)? as usize;
+        let strings = u16_at(name, 4)? as usize;
+        for index in 0..count {
+            let record = 6 + index * 12;
+            let platform = u16_at(name, record)?;
+            if u16_at(name, record + 6)? != 6 {
+                continue;
+            }
+            let length = u16_at(name, record + 8)? as usize;
+            let offset = strings + u16_at(name, record + 10)? as usize;
+            let bytes = name.get(offset..offset + length)?;
+            let text = if platform == 1 {
+                bytes.iter().map(|b| *b as char).collect::<String>()
+            } else {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                String::from_utf16_lossy(&units)
+            };
+            let text: String = text
+                .chars()
+                .filter(|c| c.is_ascii_graphic() && !"[](){}<>/%#".contains(*c))
+                .collect();
+            if !text.is_empty() {
+                return Some(text);
+            }
+        }
+        None
+    }
+
+    // 优先使用支持全部 Unicode 的格式 12 子表，其次为格式 4
+    fn select_cmap(&self) -> Option<(usize, u16)> {
+        let range = self.tables.get(b"cmap")?.clone();
+        let cmap = &self.data[range.clone()];
+        let count = u16_at(cmap, 2)? as usize;
+        let mut best: Option<(u8, usize, u16)> = None;
+        for index in 0..count {
+            let record = 4 + index * 8;
+            let platform = u16_at(cmap, record)?;
+            let encoding = u16_at(cmap, record + 2)?;
+            let offset = u32_at(cmap, record + 4)? as usize;
+            let format = u16_at(cmap, offset)?;
+            let unicode = platform == 0 || (platform == 3 && matches!(encoding, 1 | 10));
+            let score = match (format, unicode) {
+                (12, true) => 3,
+                (4, true) => 2,
+                (4, false) if platform == 3 && encoding == 0 => 1,
+                _ => continue,
+            };
+            if best.is_none_or(|(best, _, _)| score > best) {
+                best = Some((score, range.start + offset, format));
+            }
+        }
+        best.map(|(_, offset, format)| (offset, format))
+    }
+
+    // 字符对应的字形序号，字体中没有的字符返回 0（.notdef）
+    pub fn glyph_id(&self, c: char) -> u16 {
+        let Some((offset, format)) = self.cmap else {
+            return 0;
+        };
+        let code = c as u32;
+        let data = &self.data;
+        let glyph = match format {
+            4 => (|| {
+                if code > 0xFFFF {
+                    return None;
+                }
+                let segments = u16_at(data, offset + 6)? as usize / 2;
+                let ends = offset + 14;
+                let starts = ends + segments * 2 + 2;
+                let deltas = starts + segments * 2;
+                let range_offsets = deltas + segments * 2;
+                // 二分查找第一个 endCode >= code 的段
+                let (mut low, mut high) = (0, segments);
+                while low < high {
+                    let mid = (low + high) / 2;
+                    if (u16_at(data, ends + mid * 2)? as u32) < code {
+                        low = mid + 1;
+                    } else {
+                        high = mid;
+                    }
+                }
+                if low >= segments {
+                    return None;
+                }
+                let start = u16_at(data, starts + low * 2)? as u32;
+                if code < start {
+                    return None;
+                }
+                let delta = u16_at(data, deltas + low * 2)?;
+                let range_offset_pos = range_offsets + low * 2;
+                let range_offset = u16_at(data, range_offset_pos)? as usize;
+                if range_offset == 0 {
+                    return Some((code as u16).wrapping_add(delta));
+                }
+                let address = range_offset_pos + range_offset + (code - start) as usize * 2;
+                let glyph = u16_at(data, address)?;
+                (glyph != 0).then(|| glyph.wrapping_add(delta))
+            })(),
+            12 => (|| {
+                let groups = u32_at(data, offset + 12)? as usize;
+                let (mut low, mut high) = (0, groups);
+                while low < high {
+                    let mid = (low + high) / 2;
+                    let group = offset + 16 + mid * 12;
+                    let start = u32_at(data, group)?;
+                    let end = u32_at(data, group + 4)?;
+                    if code < start {
+                        high = mid;
+                    } else if code > end {
+                        low = mid + 1;
+                    } else {
+                        let glyph = u32_at(data, group + 8)? + code - start;
+                        return u16::try_from(glyph).ok();
+                    }
+                }
+                None
+            })(),
+            _ => None,
+        };
+        glyph.filter(|glyph| *glyph < self.num_glyphs).unwrap_or(0)
+    }
+
+    // 字形的前进宽度（字体单位）
+    pub fn advance(&self, glyph: u16) -> u16 {
+        self.advances
+            .get(glyph as usize)
+            .or(self.advances.last())
+            .copied()
+            .unwrap_or(self.units_per_em)
+    }
+
+    // PDF 中字形的编码：CID 字体为 CID，其余为字形序号
+    pub fn code(&self, glyph: u16) -> u16 {
+        self.cids.get(glyph as usize).copied().unwrap_or(glyph)
+    }
+
+    // 按字形序号保留的子集：未使用字形的数据被清空，字形序号不变，因此不需要重新映射
+    pub fn subset(&self, glyphs: &BTreeSet<u16>) -> Result<Vec<u8>, String> {
+        let subset = match self.outlines {
+            Outlines::TrueType => self.subset_truetype(glyphs),
+            Outlines::Cff => self.subset_cff(glyphs),
+        };
+        subset.ok_or_else(|| "生成字体子集失败，字体文件可能已损坏".to_string())
+    }
+
+    fn glyph_range(&self, glyph: u16) -> Option<Range<usize>> {
+        let loca = self.table(b"loca")?;
+        let index = glyph as usize;
+        let (start, end) = if self.long_loca {
+            (
+                u32_at(loca, index * 4)? as usize,
+                u32_at(loca, index * 4 + 4)? as usize,
+            )
+        } else {
+            (
+                u16_at(loca, index * 2)? as usize * 2,
+                u16_at(loca, index * 2 + 2)? as usize * 2,
+            )
+        };
+        let glyf = self.tables.get(b"glyf")?;
+        (start <= end && glyf.start + end <= glyf.end).then(|| glyf.start + start..glyf.start + end)
+    }
+
+    // 组合字形引用的部件字形
+    fn components(&self, glyph: u16) -> Vec<u16> {
+        let mut components = Vec::new();
+        let Some(range) = self.glyph_range(glyph) else {
+            return components;
+        };
+        let data = &self.data[range];
+        if i16_at(data, 0).is_none_or(|contours| contours >= 0) {
+            return components;
+        }
+        let mut pos = 10;
+        while let (Some(flags), Some(component)) = (u16_at(data, pos), u16_at(data, pos + 2)) {
+            components.push(component);
+            pos += 4;
+            pos += if flags & 0x0001 != 0 { 4 } else { 2 };
+            if flags & 0x0008 != 0 {
+                pos += 2;
+            } else if flags & 0x0040 != 0 {
+                pos += 4;
+            } else if flags & 0x0080 != 0 {
+                pos += 8;
+            }
+            if flags & 0x0020 == 0 {
+                break;
+            }
+        }
+        components
+    }
+
+    fn subset_truetype(&self, glyphs: &BTreeSet<u16>) -> Option<Vec<u8>> {
+        let mut keep = glyphs.clone();
+        keep.insert(0);
+        let mut pending: Vec<u16> = keep.iter().copied().collect();
+        while let Some(glyph) = pending.pop() {
+            for component in self.components(glyph) {
+                if keep.insert(component) {
+                    pending.push(component);
+                }
+            }
+        }
+
+        let mut glyf = Vec::new();
+        let mut loca = Vec::with_capacity((self.num_glyphs as usize + 1) * 4);
+        for glyph in 0..self.num_glyphs {
+            loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+            if keep.contains(&glyph) {
+                if let Some(range) = self.glyph_range(glyph) {
+                    glyf.extend_from_slice(&self.data[range]);
+                    glyf.resize(glyf.len().next_multiple_of(4), 0);
+                }
+            }
+        }
+        loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+
+        let mut tables: Vec<([u8; 4], Vec<u8>)> = Vec::new();
+        for tag in TRUETYPE_TABLES {
+            if let Some(table) = self.table(tag) {
+                tables.push((**tag, table.to_vec()));
+            }
+        }
+        // 新的 loca 使用长格式
+        let head = &mut tables.iter_mut().find(|(tag, _)| tag == b"head")?.1;
+        head.get_mut(8..12)?.fill(0);
+        head.get_mut(50..52)?.copy_from_slice(&1i16.to_be_bytes());
+        tables.push((*b"loca", loca));
+        tables.push((*b"glyf", glyf));
+        Some(write_sfnt(tables))
+    }
+
+    // CID 字体中字形序号到 CID 的映射（CFF charset）
+    fn read_cff_cids(&self) -> Option<Vec<u16>> {
+        let cff = self.table(b"CFF ")?;
+        let top = CffTop::parse(cff)?;
+        if top.value(CFF_ROS).is_none() {
+            return Some(Vec::new());
+        }
+        let glyphs = cff_index(cff, top.value(CFF_CHARSTRINGS)?.first().copied()? as usize)?
+            .0
+            .len();
+        let mut cids = vec![0u16; glyphs];
+        let offset = top.value(CFF_CHARSET)?.first().copied()? as usize;
+        let format = *cff.get(offset)?;
+        let mut pos = offset + 1;
+        let mut glyph = 1;
+        while glyph < glyphs {
+            match format {
+                0 => {
+                    cids[glyph] = u16_at(cff, pos)?;
+                    pos += 2;
+                    glyph += 1;
+                }
+                1 | 2 => {
+                    let first = u16_at(cff, pos)?;
+                    let left = if format == 1 {
+                        *cff.get(pos + 2)? as u16
+                    } else {
+                        u16_at(cff, pos + 2)?
+                    };
+                    pos += if format == 1 { 3 } else { 4 };
+                    for step in 0..=left {
+                        if glyph >= glyphs {
+                            break;
+                        }
+                        cids[glyph] = first.wrapping_add(step);
+                        glyph += 1;
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some(cids)
+    }
+
+    // CFF 子集：未使用字形的字符串替换为 endchar，其余结构原样复制并重新计算偏移
+    fn subset_cff(&self, glyphs: &BTreeSet<u16>) -> Option<Vec<u8>> {
+        let cff = self.table(b"CFF ")?;
+        let header_size = *cff.get(2)? as usize;
+        let top = CffTop::parse(cff)?;
+        let offset = |op: u16| top.value(op).and_then(|values| values.first().copied());
+
+        let (charstrings, _) = cff_index(cff, offset(CFF_CHARSTRINGS)? as usize)?;
+        let glyph_count = charstrings.len();
+        let empty = [CFF_ENDCHAR];
+        let items: Vec<&[u8]> = charstrings
+            .iter()
+            .enumerate()
+            .map(|(glyph, range)| {
+                if glyph == 0 || glyphs.contains(&(glyph as u16)) {
+                    &cff[range.clone()]
+                } else {
+                    &empty[..]
+                }
+            })
+            .collect();
+        let charstrings = write_cff_index(&items);
+
+        // 预定义的 charset（0-2）和 encoding（0-1）不是偏移
+        let charset = match offset(CFF_CHARSET) {
+            Some(start) if start > 2 => {
+                let start = start as usize;
+                Some(cff.get(start..start + charset_length(cff, start, glyph_count)?)?)
+            }
+            _ => None,
+        };
+        let encoding = match offset(CFF_ENCODING) {
+            Some(start) if start > 1 => {
+                let start = start as usize;
+                Some(cff.get(start..start + encoding_length(cff, start)?)?)
+            }
+            _ => None,
+        };
+        let fd_select = match offset(CFF_FDSELECT) {
+            Some(start) => {
+                let start = start as usize;
+                Some(cff.get(start..start + fd_select_length(cff, start, glyph_count)?)?)
+            }
+            None => None,
+        };
+        let private = match top.value(CFF_PRIVATE) {
+            Some(values) => Some(PrivateDict::parse(cff, values)?),
+            None => None,
+        };
+        let mut font_dicts = Vec::new();
+        if let Some(start) = offset(CFF_FDARRAY) {
+            for range in cff_index(cff, start as usize)?.0 {
+                let entries = parse_dict(&cff[range])?;
+                let private = match dict_value(&entries, CFF_PRIVATE) {
+                    Some(values) => Some(PrivateDict::parse(cff, values)?),
+                    None => None,
+                };
+                font_dicts.push((entries, private));
+            }
+        }
+
+        // 依次排列：头部、Name、Top DICT、String、Global Subr，之后是各结构
+        let leading = &cff[..header_size];
+        let names_end = cff_index(cff, header_size)?.1;
+        let names = &cff[header_size..names_end];
+        let top_end = cff_index(cff, names_end)?.1;
+        let strings_end = cff_index(cff, top_end)?.1;
+        let global_subrs_end = cff_index(cff, strings_end)?.1;
+        let shared = &cff[top_end..global_subrs_end];
+
+        // 偏移使用定长编码，先用占位值算出 Top DICT 的长度
+        let mut top_offsets: HashMap<u16, Vec<i64>> = HashMap::new();
+        for op in [
+            CFF_CHARSET,
+            CFF_ENCODING,
+            CFF_CHARSTRINGS,
+            CFF_FDSELECT,
+            CFF_FDARRAY,
+        ] {
+            let present = match op {
+                CFF_CHARSET => charset.is_some(),
+                CFF_ENCODING => encoding.is_some(),
+                CFF_FDSELECT => fd_select.is_some(),
+                CFF_FDARRAY => !font_dicts.is_empty(),
+                _ => true,
+            };
+            if present {
+                top_offsets.insert(op, vec![0]);
+            }
+        }
+        if private.is_some() {
+            top_offsets.insert(CFF_PRIVATE, vec![0, 0]);
+        }
+        let top_length = write_cff_index(&[&encode_dict(&top.entries, &top_offsets)]).len();
+
+        let mut pos = leading.len() + names.len() + top_length + shared.len();
+        let mut body = Vec::new();
+        let mut place = |data: &[u8], pos: &mut usize| {
+            let start = *pos;
+            body.extend_from_slice(data);
+            *pos += data.len();
+            start as i64
+        };
+        if let Some(data) = charset {
+            top_offsets.insert(CFF_CHARSET, vec![place(data, &mut pos)]);
+        }
+        if let Some(data) = encoding {
+            top_offsets.insert(CFF_ENCODING, vec![place(data, &mut pos)]);
+        }
+        if let Some(data) = fd_select {
+            top_offsets.insert(CFF_FDSELECT, vec![place(data, &mut pos)]);
+        }
+        top_offsets.insert(CFF_CHARSTRINGS, vec![place(&charstrings, &mut pos)]);
+        if let Some(private) = &private {
+            let data = private.encode();
+            let start = place(&data, &mut pos);
+            top_offsets.insert(CFF_PRIVATE, vec![private.dict_length() as i64, start]);
+        }
+        if !font_dicts.is_empty() {
+            let mut dicts = Vec::new();
+            for (entries, private) in &font_dicts {
+                let mut offsets = HashMap::new();
+                if let Some(private) = private {
+                    let data = private.encode();
+                    let start = place(&data, &mut pos);
+                    offsets.insert(CFF_PRIVATE, vec![private.dict_length() as i64, start]);
+                }
+                dicts.push(encode_dict(entries, &offsets));
+            }
+            let items: Vec<&[u8]> = dicts.iter().map(|dict| dict.as_slice()).collect();
+            top_offsets.insert(CFF_FDARRAY, vec![place(&write_cff_index(&items), &mut pos)]);
+        }
+
+        let mut out = Vec::with_capacity(pos);
+        out.extend_from_slice(leading);
+        out.extend_from_slice(names);
+        out.extend_from_slice(&write_cff_index(&[&encode_dict(
+            &top.entries,
+            &top_offsets,
+        )]));
+        out.extend_from_slice(shared);
+        out.extend_from_slice(&body);
+        Some(out)
+    }
+}
+
+// 按表名排序写出 sfnt 文件，并计算各表和整个文件的校验和
+fn write_sfnt(mut tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|table| table.0);
+    let count = tables.len() as u16;
+    let entry_selector = 15 - count.max(1).leading_zeros() as u16;
+    let search_range = 16u16 << entry_selector;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    out.extend_from_slice(&count.to_be_bytes());
+    out.extend_from_slice(&search_range.to_be_bytes());
+    out.extend_from_slice(&entry_selector.to_be_bytes());
+    out.extend_from_slice(&(count * 16 - search_range).to_be_bytes());
+
+    let mut offset = 12 + tables.len() * 16;
+    let mut head_offset = None;
+    for (tag, data) in &tables {
+        if tag == b"head" {
+            head_offset = Some(offset);
+        }
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&checksum(data).to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    for (_, data) in &tables {
+        out.extend_from_slice(data);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+    if let Some(head) = head_offset {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&out));
+        out[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    out
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+// CFF INDEX：各项在数据中的范围和 INDEX 的结束位置
+fn cff_index(data: &[u8], pos: usize) -> Option<(Vec<Range<usize>>, usize)> {
+    let count = u16_at(data, pos)? as usize;
+    if count == 0 {
+        return Some((Vec::new(), pos + 2));
+    }
+    let offset_size = *data.get(pos + 2)? as usize;
+    if !(1..=4).contains(&offset_size) {
+        return None;
+    }
+    let offsets = pos + 3;
+    let read = |index: usize| -> Option<usize> {
+        let start = offsets + index * offset_size;
+        let bytes = data.get(start..start + offset_size)?;
+        Some(
+            bytes
+                .iter()
+                .fold(0usize, |value, b| value << 8 | *b as usize),
+        )
+    };
+    // 偏移从 1 开始计算
+    let base = offsets + (count + 1) * offset_size - 1;
+    let mut items = Vec::with_capacity(count);
+    let mut previous = read(0)?;
+    for index in 1..=count {
+        let next = read(index)?;
+        if next < previous {
+            return None;
+        }
+        items.push(base + previous..base + next);
+        previous = next;
+    }
+    let end = base + previous;
+    (end <= data.len()).then_some((items, end))
+}
+
+fn write_cff_index(items: &[&[u8]]) -> Vec<u8> {
+    let mut out = (items.len() as u16).to_be_bytes().to_vec();
+    if items.is_empty() {
+        return out;
+    }
+    let total = items.iter().map(|item| item.len()).sum::<usize>() + 1;
+    let offset_size = match total {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x1_0000..=0xFF_FFFF => 3,
+        _ => 4,
+    };
+    out.push(offset_size as u8);
+    let mut offset = 1usize;
+    let push_offset = |out: &mut Vec<u8>, offset: usize| {
+        out.extend_from_slice(&(offset as u32).to_be_bytes()[4 - offset_size..]);
+    };
+    push_offset(&mut out, offset);
+    for item in items {
+        offset += item.len();
+        push_offset(&mut out, offset);
+    }
+    for item in items {
+        out.extend_from_slice(item);
+    }
+    out
+}
+
+// DICT 中的一项：操作符、原始操作数字节和解析出的整数操作数
+struct DictEntry {
+    op: u16,
+    operands: Vec<u8>,
+    values: Vec<i64>,
+}
+
+fn parse_dict(data: &[u8]) -> Option<Vec<DictEntry>> {
+    let mut entries = Vec::new();
+    let mut values = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+    while pos < data.len() {
+        let b = data[pos];
+        match b {
+            0..=21 => {
+                let operands = data[start..pos].to_vec();
+                let op = if b == 12 {
+                    pos += 1;
+                    1200 + *data.get(pos)? as u16
+                } else {
+                    b as u16
+                };
+                pos += 1;
+                entries.push(DictEntry {
+                    op,
+                    operands,
+                    values: std::mem::take(&mut values),
+                });
+                start = pos;
+            }
+            28 => {
+                values.push(i16_at(data, pos + 1)? as i64);
+                pos += 3;
+            }
+            29 => {
+                values.push(u32_at(data, pos + 1)? as i32 as i64);
+                pos += 5;
+            }
+            30 => {
+                // 实数：以半字节 0xf 结束，子集化时不需要它的值
+                pos += 1;
+                while let Some(byte) = data.get(pos) {
+                    pos += 1;
+                    if byte & 0x0F == 0x0F || byte >> 4 == 0x0F {
+                        break;
+                    }
+                }
+                values.push(0);
+            }
+            32..=246 => {
+                values.push(b as i64 - 139);
+                pos += 1;
+            }
+            247..=250 => {
+                values.push((b as i64 - 247) * 256 + *data.get(pos + 1)? as i64 + 108);
+                pos += 2;
+            }
+            251..=254 => {
+                values.push(-(b as i64 - 251) * 256 - *data.get(pos + 1)? as i64 - 108);
+                pos += 2;
+            }
+            _ => return None,
+        }
+    }
+    Some(entries)
+}
+
+fn dict_value(entries: &[DictEntry], op: u16) -> Option<&[i64]> {
+    entries
+        .iter()
+        .find(|entry| entry.op == op)
+        .map(|entry| entry.values.as_slice())
+}
+
+// 重新编码 DICT：overrides 中的操作数用 5 字节定长整数写出，其余原样复制
+fn encode_dict(entries: &[DictEntry], overrides: &HashMap<u16, Vec<i64>>) -> Vec<u8> {
+    let mut out = Vec::new();
+    for entry in entries {
+        match overrides.get(&entry.op) {
+            Some(values) => {
+                for value in values {
+                    out.push(29);
+                    out.extend_from_slice(&(*value as i32).to_be_bytes());
+                }
+            }
+            None => out.extend_from_slice(&entry.operands),
+        }
+        if entry.op >= 1200 {
+            out.push(12);
+            out.push((entry.op - 1200) as u8);
+        } else {
+            out.push(entry.op as u8);
+        }
+    }
+    out
+}
+
+struct CffTop {
+    entries: Vec<DictEntry>,
+}
+
+impl CffTop {
+    fn parse(cff: &[u8]) -> Option<CffTop> {
+        let header_size = *cff.get(2)? as usize;
+        let names_end = cff_index(cff, header_size)?.1;
+        let (tops, _) = cff_index(cff, names_end)?;
+        let entries = parse_dict(&cff[tops.first()?.clone()])?;
+        Some(CffTop { entries })
+    }
+
+    fn value(&self, op: u16) -> Option<&[i64]> {
+        dict_value(&self.entries, op)
+    }
+}
+
+// Private DICT 及其局部 Subrs，写出时 Subrs 紧跟在字典之后
+struct PrivateDict {
+    entries: Vec<DictEntry>,
+    subrs: Vec<u8>,
+}
+
+impl PrivateDict {
+    fn parse(cff: &[u8], values: &[i64]) -> Option<PrivateDict> {
+        let [size, offset] = values else {
+            return None;
+        };
+        let (size, offset) = (*size as usize, *offset as usize);
+        let entries = parse_dict(cff.get(offset..offset + size)?)?;
+        let subrs = match dict_value(&entries, CFF_SUBRS).and_then(|values| values.first()) {
+            Some(relative) => {
+                let start = offset + *relative as usize;
+                let end = cff_index(cff, start)?.1;
+                cff[start..end].to_vec()
+            }
+            None => Vec::new(),
+        };
+        Some(PrivateDict { entries, subrs })
+    }
+
+    fn overrides(&self, length: usize) -> HashMap<u16, Vec<i64>> {
+        let mut overrides = HashMap::new();
+        if !self.subrs.is_empty() {
+            overrides.insert(CFF_SUBRS, vec![length as i64]);
+        }
+        overrides
+    }
+
+    fn dict_length(&self) -> usize {
+        encode_dict(&self.entries, &self.overrides(0)).len()
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = encode_dict(&self.entries, &self.overrides(self.dict_length()));
+        out.extend_from_slice(&self.subrs);
+        out
+    }
+}
+
+fn charset_length(cff: &[u8], start: usize, glyphs: usize) -> Option<usize> {
+    let format = *cff.get(start)?;
+    if format == 0 {
+        return Some(1 + 2 * glyphs.saturating_sub(1));
+    }
+    let mut pos = start + 1;
+    let mut covered = 1;
+    while covered < glyphs {
+        let left = match format {
+            1 => *cff.get(pos + 2)? as usize,
+            2 => u16_at(cff, pos + 2)? as usize,
+            _ => return None,
+        };
+        pos += if format == 1 { 3 } else { 4 };
+        covered += left + 1;
+    }
+    Some(pos - start)
+}
+
+fn encoding_length(cff: &[u8], start: usize) -> Option<usize> {
+    let format = *cff.get(start)?;
+    let count = *cff.get(start + 1)? as usize;
+    let mut length = match format & 0x7F {
+        0 => 2 + count,
+        1 => 2 + count * 2,
+        _ => return None,
+    };
+    // 最高位表示带有补充编码
+    if format & 0x80 != 0 {
+        length += 1 + *cff.get(start + length)? as usize * 3;
+    }
+    Some(length)
+}
+
+fn fd_select_length(cff: &[u8], start: usize, glyphs: usize) -> Option<usize> {
+    match *cff.get(start)? {
+        0 => Some(1 + glyphs),
+        3 => Some(1 + 2 + u16_at(cff, start + 1)? as usize * 3 + 2),
+        _ => None,
+    }
+}
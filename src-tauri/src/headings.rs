@@ -0,0 +1,590 @@
+// 已有书籍的章节标题识别：按标题规则扫描全书内容，预览识别结果，确认后按新的章节划分重建目录
+use crate::bookops::HrefSet;
+use crate::database::{get_current_time_string, get_db_connection, DbResponse};
+use crate::importer::{has_text, PREFACE_LABEL};
+use crate::revision::update_chapter_with_revision;
+use crate::setup::AppState;
+use crate::textutil::line_text;
+use crate::toc::{load_toc, sync_toc, TocItem};
+use crate::txt::{
+    compile_patterns, default_heading_patterns, HeadingPattern, DEFAULT_MAX_HEADING_LENGTH,
+};
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use tauri::{command, State};
+
+// 预览中每个章节返回的正文摘要长度
+const EXCERPT_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HeadingDetectOptions {
+    // 为空时使用内置规则
+    pub patterns: Option<Vec<HeadingPattern>>,
+    pub max_heading_length: Option<usize>,
+    // 预览后不采用的标题（headings 中的下标），这些行按正文处理
+    pub exclude: Vec<usize>,
+}
+
+// 识别出的一个标题
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectedHeading {
+    pub label: String,
+    pub volume: bool,
+    // 标题中的编号，如「第一百二十三章」为 123
+    pub number: Option<u64>,
+    // 标题所在的原章节
+    pub chapter_id: i64,
+    pub chars: usize,
+    pub excerpt: String,
+    // 编号与上一个同级标题重复或不连续时的提示
+    pub warning: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TocRebuildPreview {
+    // 第一个标题之前的正文字数，为 0 时不生成「前言」章节
+    pub preface_chars: usize,
+    pub headings: Vec<DetectedHeading>,
+}
+
+fn digit_value(c: char) -> Option<u64> {
+    let value = match c {
+        '零' | '〇' => 0,
+        '一' | '壹' => 1,
+        '二' | '两' | '贰' | '兩' | '貳' => 2,
+        '三' | '叁' | '參' => 3,
+        '四' | '肆' => 4,
+        '五' | '伍' => 5,
+        '六' | '陆' | '陸' => 6,
+        '七' | '柒' => 7,
+        '八' | '捌' => 8,
+        '九' | '玖' => 9,
+        '0'..='9' => c as u64 - '0' as u64,
+        '０'..='９' => c as u64 - '０' as u64,
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn unit_value(c: char) -> Option<u64> {
+    let value = match c {
+        '十' | '拾' => 10,
+        '百' | '佰' => 100,
+        '千' | '仟' => 1_000,
+        '万' | '萬' => 10_000,
+        '亿' | '億' => 100_000_000,
+        _ => return None,
+    };
+    Some(value)
+}
+
+// 解析阿拉伯数字或中文数字：「一百二十三」「一千零一」「十五」「壹佰贰拾」；
+// 没有单位时逐位读，如「二〇二四」「１２」
+pub fn parse_chinese_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if !text.chars().any(|c| unit_value(c).is_some()) {
+        return text.chars().try_fold(0u64, |number, c| {
+            number.checked_mul(10)?.checked_add(digit_value(c)?)
+        });
+    }
+
+    let mut total: u64 = 0;
+    let mut section: u64 = 0;
+    let mut digit: Option<u64> = None;
+    for c in text.chars() {
+        if let Some(value) = digit_value(c) {
+            digit = Some(value);
+            continue;
+        }
+        match unit_value(c)? {
+            unit @ (10 | 100 | 1_000) => {
+                section = section.checked_add(digit.take().unwrap_or(1).checked_mul(unit)?)?;
+            }
+            10_000 => {
+                let value = section.checked_add(digit.take().unwrap_or(0))?;
+                total = total.checked_add(value.checked_mul(10_000)?)?;
+                section = 0;
+            }
+            unit => {
+                let value = total
+                    .checked_add(section)?
+                    .checked_add(digit.take().unwrap_or(0))?;
+                total = value.checked_mul(unit)?;
+                section = 0;
+            }
+        }
+    }
+    total.checked_add(section)?.checked_add(digit.unwrap_or(0))
+}
+
+// 标题中的编号，title 为去掉编号后的标题
+pub struct LabelNumber {
+    pub number: u64,
+    pub volume: bool,
+    pub title: String,
+}
+
+static LABEL_NUMBER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^[\s　]*(?:第[\s　]*([0-9０-９零〇一二两三四五六七八九十百千万亿壹贰叁肆伍陆柒捌玖拾佰仟兩貳參陸萬億]+)[\s　]*([卷部集篇章回节節])|((?i:chapter|part))\s+([0-9]+)\b|([0-9０-９]{1,4})[、.．])[\s　]*[:：·.．、\-—]*[\s　]*(.*)$",
+    )
+    .expect("内置正则表达式有误")
+});
+
+// 识别「第X章」「第X卷」「Chapter N」「Part N」「N、」形式的编号
+pub fn parse_label_number(label: &str) -> Option<LabelNumber> {
+    let caps = LABEL_NUMBER.captures(label)?;
+    let (number, volume) = if let Some(number) = caps.get(1) {
+        let unit = caps.get(2).map(|unit| unit.as_str()).unwrap_or_default();
+        (number.as_str(), matches!(unit, "卷" | "部" | "集" | "篇"))
+    } else if let Some(number) = caps.get(4) {
+        let word = caps.get(3).map(|word| word.as_str()).unwrap_or_default();
+        (number.as_str(), word.eq_ignore_ascii_case("part"))
+    } else {
+        (caps.get(5)?.as_str(), false)
+    };
+    Some(LabelNumber {
+        number: parse_chinese_number(number)?,
+        volume,
+        title: caps
+            .get(6)
+            .map(|title| title.as_str().trim().to_string())
+            .unwrap_or_default(),
+    })
+}
+
+struct HeadingRules {
+    rules: Vec<(Regex, bool)>,
+    max_length: usize,
+}
+
+impl HeadingRules {
+    fn new(options: &HeadingDetectOptions) -> Result<Self, String> {
+        let patterns = options
+            .patterns
+            .clone()
+            .unwrap_or_else(default_heading_patterns);
+        Ok(HeadingRules {
+            rules: compile_patterns(&patterns)?,
+            max_length: options
+                .max_heading_length
+                .unwrap_or(DEFAULT_MAX_HEADING_LENGTH),
+        })
+    }
+
+    // 匹配时返回是否为卷
+    fn matches(&self, text: &str) -> Option<bool> {
+        if text.is_empty() || text.chars().count() > self.max_length {
+            return None;
+        }
+        self.rules
+            .iter()
+            .find(|(re, _)| re.is_match(text))
+            .map(|(_, volume)| *volume)
+    }
+}
+
+// 目录中的原章节
+struct SourceChapter {
+    id: i64,
+    href: String,
+}
+
+// 全书按目录顺序展开后的一行，heading 为 (标题, 是否为卷)
+struct SourceLine {
+    source: usize,
+    text: String,
+    heading: Option<(String, bool)>,
+}
+
+fn flatten_ids(items: &[TocItem], ids: &mut Vec<i64>) {
+    for item in items {
+        ids.push(item.href);
+        if let Some(subitems) = &item.subitems {
+            flatten_ids(subitems, ids);
+        }
+    }
+}
+
+// 读取目录中的全部章节并逐行识别标题。导入时标题行常被移到章节标题中，
+// 所以原章节标题本身符合规则、且不是内容第一行时，也作为一行标题参与识别
+fn scan_book(
+    db: &Connection,
+    book_id: i64,
+    options: &HeadingDetectOptions,
+) -> Result<(Vec<SourceChapter>, Vec<SourceLine>), String> {
+    let rules = HeadingRules::new(options)?;
+    let mut ids = Vec::new();
+    flatten_ids(&load_toc(db, book_id).map_err(|e| e.to_string())?, &mut ids);
+    if ids.is_empty() {
+        return Err(format!("书籍 {} 没有目录中的章节", book_id));
+    }
+
+    let mut sources = Vec::new();
+    let mut lines = Vec::new();
+    for id in ids {
+        let (label, href, content): (String, String, String) = db
+            .query_row(
+                "SELECT label, href, content FROM ee_chapter WHERE id = ?",
+                params![id],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                        row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                        row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    ))
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        let source = sources.len();
+        sources.push(SourceChapter { id, href });
+
+        let label = label.trim().to_string();
+        let first_line = content
+            .split('\n')
+            .map(line_text)
+            .find(|text| !text.is_empty())
+            .unwrap_or_default();
+        if first_line != label {
+            if let Some(volume) = rules.matches(&label) {
+                lines.push(SourceLine {
+                    source,
+                    text: label.clone(),
+                    heading: Some((label, volume)),
+                });
+            }
+        }
+        for line in content.split('\n') {
+            let text = line_text(line);
+            let heading = rules.matches(&text).map(|volume| (text, volume));
+            lines.push(SourceLine {
+                source,
+                text: line.to_string(),
+                heading,
+            });
+        }
+    }
+
+    // 预览后排除的标题按正文处理
+    let exclude: HashSet<usize> = options.exclude.iter().copied().collect();
+    let mut index = 0;
+    for line in lines.iter_mut() {
+        if line.heading.is_some() {
+            if exclude.contains(&index) {
+                line.heading = None;
+            }
+            index += 1;
+        }
+    }
+    Ok((sources, lines))
+}
+
+// 按识别出的标题切分后的章节，heading 为空的是第一个标题之前的内容
+struct ProposedChapter {
+    heading: Option<(String, bool)>,
+    source: usize,
+    content: String,
+}
+
+fn propose_chapters(lines: &[SourceLine]) -> Vec<ProposedChapter> {
+    let mut chapters = vec![ProposedChapter {
+        heading: None,
+        source: 0,
+        content: String::new(),
+    }];
+    let mut body: Vec<&str> = Vec::new();
+    for line in lines {
+        match &line.heading {
+            Some(heading) => {
+                if let Some(chapter) = chapters.last_mut() {
+                    chapter.content = body.join("\n").trim_matches('\n').to_string();
+                }
+                body.clear();
+                chapters.push(ProposedChapter {
+                    heading: Some(heading.clone()),
+                    source: line.source,
+                    content: String::new(),
+                });
+            }
+            None => body.push(&line.text),
+        }
+    }
+    if let Some(chapter) = chapters.last_mut() {
+        chapter.content = body.join("\n").trim_matches('\n').to_string();
+    }
+    chapters
+}
+
+fn excerpt(content: &str) -> String {
+    content
+        .split('\n')
+        .map(line_text)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(EXCERPT_CHARS)
+        .collect()
+}
+
+fn text_chars(content: &str) -> usize {
+    content
+        .split('\n')
+        .map(|line| line_text(line).chars().count())
+        .sum()
+}
+
+// 识别标题并返回预览，不修改数据库
+pub fn preview_headings(
+    db: &Connection,
+    book_id: i64,
+    options: &HeadingDetectOptions,
+) -> Result<TocRebuildPreview, String> {
+    // 预览时列出全部标题，排除项只在重建时生效
+    let options = HeadingDetectOptions {
+        exclude: Vec::new(),
+        ..options.clone()
+    };
+    let (sources, lines) = scan_book(db, book_id, &options)?;
+    let chapters = propose_chapters(&lines);
+
+    let mut headings = Vec::new();
+    let mut last_volume: Option<u64> = None;
+    let mut last_chapter: Option<u64> = None;
+    for chapter in &chapters[1..] {
+        let Some((label, volume)) = &chapter.heading else {
+            continue;
+        };
+        let number = parse_label_number(label).map(|parsed| parsed.number);
+        let previous = if *volume {
+            &mut last_volume
+        } else {
+            &mut last_chapter
+        };
+        let warning = match (number, *previous) {
+            (Some(number), Some(previous)) if number == previous => {
+                Some(format!("编号与上一个标题重复（{}）", previous))
+            }
+            (Some(number), Some(previous)) if number != previous + 1 => {
+                Some(format!("编号不连续，上一个为 {}", previous))
+            }
+            _ => None,
+        };
+        if number.is_some() {
+            *previous = number;
+        }
+        // 每卷的章节编号可能从头开始
+        if *volume {
+            last_chapter = None;
+        }
+        headings.push(DetectedHeading {
+            label: label.clone(),
+            volume: *volume,
+            number,
+            chapter_id: sources[chapter.source].id,
+            chars: text_chars(&chapter.content),
+            excerpt: excerpt(&chapter.content),
+            warning,
+        });
+    }
+
+    Ok(TocRebuildPreview {
+        preface_chars: text_chars(&chapters[0].content),
+        headings,
+    })
+}
+
+// 按识别结果重建目录：卷为顶级章节，其后的章节挂在卷下；第一个标题之前有文字时生成「前言」章节。
+// 新章节依次沿用原章节的记录（修改前保存修订记录），多出的新增，剩余的原章节连同修订记录删除；
+// 不在目录中的章节保持不变
+pub fn rebuild_book_toc(
+    db: &Connection,
+    book_id: i64,
+    options: &HeadingDetectOptions,
+) -> Result<Vec<TocItem>, String> {
+    let (sources, lines) = scan_book(db, book_id, options)?;
+    let mut chapters = propose_chapters(&lines);
+    if chapters.len() < 2 {
+        return Err("没有识别到章节标题".to_string());
+    }
+    if !has_text(&chapters[0].content) {
+        chapters.remove(0);
+    }
+
+    // 原章节先移出目录并清空 href，之后按新的划分重新分配
+    for source in &sources {
+        db.execute(
+            "UPDATE ee_chapter SET parentId = NULL, position = NULL, href = '' WHERE id = ?",
+            params![source.id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    let mut hrefs = HrefSet::load(db, book_id)?;
+    let mut reusable = sources.iter().map(|source| source.id);
+
+    let now = get_current_time_string();
+    let mut volume_id: Option<i64> = None;
+    let mut top_position = 0;
+    let mut child_position = 0;
+    for chapter in &chapters {
+        let (label, volume) = chapter
+            .heading
+            .clone()
+            .unwrap_or_else(|| (PREFACE_LABEL.to_string(), false));
+        let source = &sources[chapter.source];
+        let href = if source.href.is_empty() {
+            hrefs.unique(&format!("chapter-{}", source.id))
+        } else {
+            hrefs.unique(&source.href)
+        };
+        let (parent_id, position) = match (volume, volume_id) {
+            (false, Some(parent)) => {
+                child_position += 1;
+                (Some(parent), child_position - 1)
+            }
+            _ => {
+                top_position += 1;
+                (None, top_position - 1)
+            }
+        };
+
+        let id = match reusable.next() {
+            Some(id) => {
+                update_chapter_with_revision(db, id, &label, Some(&chapter.content))
+                    .map_err(|e| e.to_string())?;
+                db.execute(
+                    "UPDATE ee_chapter SET href = ?, parentId = ?, position = ? WHERE id = ?",
+                    params![href, parent_id, position, id],
+                )
+                .map_err(|e| e.to_string())?;
+                id
+            }
+            None => {
+                db.execute(
+                    "INSERT INTO ee_chapter (bookId, label, href, content, parentId, position, createTime, updateTime) \
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                    params![book_id, label, href, chapter.content, parent_id, position, now, now],
+                )
+                .map_err(|e| e.to_string())?;
+                db.last_insert_rowid()
+            }
+        };
+        if volume {
+            volume_id = Some(id);
+            child_position = 0;
+        }
+    }
+
+    for id in reusable {
+        db.execute("DELETE FROM ee_chapter WHERE id = ?", params![id])
+            .map_err(|e| e.to_string())?;
+        db.execute(
+            "DELETE FROM ee_chapter_revision WHERE chapterId = ?",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    sync_toc(db, book_id).map_err(|e| e.to_string())
+}
+
+// 识别书籍内容中的章节标题，返回预览
+#[command]
+pub async fn preview_toc_rebuild(
+    book_id: i64,
+    options: Option<HeadingDetectOptions>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<TocRebuildPreview>, String> {
+    let db = get_db_connection(&state)?;
+    match preview_headings(&db, book_id, &options.unwrap_or_default()) {
+        Ok(preview) => Ok(DbResponse::success(preview)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 按识别出的标题重建章节和目录，返回新的目录树；options 应与预览时相同，exclude 为预览后取消的标题
+#[command]
+pub async fn rebuild_toc(
+    book_id: i64,
+    options: Option<HeadingDetectOptions>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<TocItem>>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match rebuild_book_toc(&tx, book_id, &options.unwrap_or_default()) {
+        Ok(toc) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(toc))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_chinese_number, parse_label_number};
+
+    #[test]
+    fn parses_chinese_numbers() {
+        let cases = [
+            ("一", Some(1)),
+            ("十", Some(10)),
+            ("十五", Some(15)),
+            ("二十", Some(20)),
+            ("一百二十三", Some(123)),
+            ("一百零五", Some(105)),
+            ("一千零一", Some(1001)),
+            ("两百", Some(200)),
+            ("三万五千", Some(35_000)),
+            ("一万零一十", Some(10_010)),
+            ("一亿二千万", Some(120_000_000)),
+            ("壹佰贰拾叁", Some(123)),
+            ("貳拾", Some(20)),
+            ("二〇二四", Some(2024)),
+            ("１２", Some(12)),
+            ("12", Some(12)),
+            (" 七 ", Some(7)),
+            ("", None),
+            ("十x", None),
+            ("第", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_chinese_number(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parses_label_numbers() {
+        let cases = [
+            ("第一百二十三章 风起", Some((123, false, "风起"))),
+            ("第12回", Some((12, false, ""))),
+            ("　第三卷：终", Some((3, true, "终"))),
+            ("Chapter 7: x", Some((7, false, "x"))),
+            ("PART 2", Some((2, true, ""))),
+            ("3、归来", Some((3, false, "归来"))),
+            ("楔子", None),
+            ("第一次", None),
+            ("2024年", None),
+        ];
+        for (label, expected) in cases {
+            let parsed = parse_label_number(label)
+                .map(|parsed| (parsed.number, parsed.volume, parsed.title));
+            assert_eq!(
+                parsed,
+                expected.map(|(number, volume, title)| (number, volume, title.to_string())),
+                "{}",
+                label
+            );
+        }
+    }
+}
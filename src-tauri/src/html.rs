@@ -0,0 +1,311 @@
+use crate::database::{get_db_connection, DbResponse};
+use crate::exporter::{
+    copy_images, cover_extension, image_media_type, load_export_book, ExportBook, ExportChapter,
+    WrittenFiles,
+};
+use crate::fileutil::{book_epub_dir, cover_path, encode_base64};
+use crate::markup::{content_to_html, escape};
+use crate::setup::AppState;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tauri::{command, AppHandle, Manager, State};
+
+const HTML_STYLE: &str = "body { max-width: 46em; margin: 0 auto; padding: 1em 1.5em; line-height: 1.8; color: #222; background: #fdfdfb; font-family: -apple-system, \"PingFang SC\", \"Microsoft YaHei\", \"Noto Sans CJK SC\", sans-serif; }
+p { margin: 0.5em 0; text-indent: 2em; }
+h1, h2, h3, h4, h5, h6 { text-align: center; margin: 1.2em 0 0.8em; }
+img { max-width: 100%; }
+a { color: #2b6cb0; text-decoration: none; }
+a:hover { text-decoration: underline; }
+.book { text-align: center; margin-bottom: 2em; }
+.book p { text-indent: 0; }
+.book .cover img { max-height: 60vh; box-shadow: 0 2px 8px rgba(0, 0, 0, 0.2); }
+.book .author { color: #666; }
+.book .description { text-align: left; text-indent: 2em; }
+.start { text-align: center; text-indent: 0; }
+.toc ol { list-style: none; padding-left: 1.5em; }
+.toc > ol { padding-left: 0; }
+.toc li { margin: 0.3em 0; }
+.pager { display: flex; justify-content: space-between; margin: 1.5em 0; padding: 0.5em 0; border-top: 1px solid #ddd; border-bottom: 1px solid #ddd; }
+.pager .disabled { color: #aaa; }
+.chapter { margin-bottom: 3em; }
+.back { text-align: right; text-indent: 0; font-size: 0.9em; }
+@media (prefers-color-scheme: dark) {
+  body { color: #ddd; background: #1e1e1e; }
+  a { color: #7fb2f0; }
+  .pager { border-color: #444; }
+}
+";
+
+fn html_document(title: &str, head: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"zh\">\n<head>\n<meta charset=\"utf-8\" />\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\" />\n\
+         <title>{}</title>\n{}\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        escape(title),
+        head,
+        body
+    )
+}
+
+// 章节正文：内容不以标题开头时补上章节标题
+fn chapter_html(chapter: &ExportChapter, depth: usize) -> String {
+    let body = content_to_html(&chapter.content);
+    if body.trim_start().starts_with("<h") {
+        return body;
+    }
+    let level = (depth + 2).min(6);
+    let heading = format!("<h{}>{}</h{}>", level, escape(&chapter.label), level);
+    if body.is_empty() {
+        heading
+    } else {
+        format!("{}\n{}", heading, body)
+    }
+}
+
+fn toc_list(chapters: &[ExportChapter], href: &dyn Fn(&ExportChapter) -> String) -> String {
+    let mut html = String::from("<ol>\n");
+    for chapter in chapters {
+        html.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>",
+            escape(&href(chapter)),
+            escape(&chapter.label)
+        ));
+        if !chapter.children.is_empty() {
+            html.push('\n');
+            html.push_str(&toc_list(&chapter.children, href));
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ol>\n");
+    html
+}
+
+// 书名、作者、简介和封面
+fn book_header(book: &ExportBook, cover_src: Option<&str>) -> String {
+    let mut html = String::from("<header class=\"book\">\n");
+    if let Some(src) = cover_src {
+        html.push_str(&format!(
+            "<p class=\"cover\"><img src=\"{}\" alt=\"封面\" /></p>\n",
+            src
+        ));
+    }
+    html.push_str(&format!("<h1>{}</h1>\n", escape(&book.title)));
+    html.push_str(&format!(
+        "<p class=\"author\">{}</p>\n",
+        escape(&book.author)
+    ));
+    let description = book.description.trim();
+    if !description.is_empty() && description != "暂缺" {
+        for line in description.lines().filter(|line| !line.trim().is_empty()) {
+            html.push_str(&format!(
+                "<p class=\"description\">{}</p>\n",
+                escape(line.trim())
+            ));
+        }
+    }
+    html.push_str("</header>\n");
+    html
+}
+
+fn image_pattern() -> Regex {
+    Regex::new(r#"src="images/([^"]+)""#).expect("内置正则表达式有误")
+}
+
+fn data_uri(name: &str, data: &[u8]) -> String {
+    format!(
+        "data:{};base64,{}",
+        image_media_type(name, data),
+        encode_base64(data)
+    )
+}
+
+fn chapter_anchor(chapter: &ExportChapter) -> String {
+    format!("chapter-{}", chapter.id)
+}
+
+// 单个 HTML 文件：目录链接到页内锚点，图片和封面以 data URI 内嵌
+fn single_page(book: &ExportBook, app_dir: &Path) -> String {
+    let cover = fs::read(cover_path(app_dir, book.id))
+        .ok()
+        .map(|data| data_uri("cover.jpg", &data));
+
+    let mut body = book_header(book, cover.as_deref());
+    body.push_str("<nav class=\"toc\" id=\"toc\">\n<h2>目录</h2>\n");
+    body.push_str(&toc_list(&book.chapters, &|chapter| {
+        format!("#{}", chapter_anchor(chapter))
+    }));
+    body.push_str("</nav>\n");
+
+    let images_dir = book_epub_dir(app_dir, book.id).join("images");
+    let pattern = image_pattern();
+    // 同一图片只编码一次
+    let mut encoded: HashMap<String, Option<String>> = HashMap::new();
+    for (depth, chapter) in book.flatten() {
+        let html = chapter_html(chapter, depth);
+        let html = pattern.replace_all(&html, |caps: &Captures| {
+            let name = &caps[1];
+            let uri = encoded.entry(name.to_string()).or_insert_with(|| {
+                if name.contains(['/', '\\']) {
+                    return None;
+                }
+                fs::read(images_dir.join(name))
+                    .ok()
+                    .map(|data| data_uri(name, &data))
+            });
+            match uri {
+                Some(uri) => format!("src=\"{}\"", uri),
+                None => caps[0].to_string(),
+            }
+        });
+        body.push_str(&format!(
+            "<section class=\"chapter\" id=\"{}\">\n{}\n<p class=\"back\"><a href=\"#toc\">返回目录</a></p>\n</section>\n",
+            chapter_anchor(chapter),
+            html
+        ));
+    }
+
+    html_document(
+        &book.title,
+        &format!("<style>\n{}</style>", HTML_STYLE),
+        &body,
+    )
+}
+
+fn chapter_file_name(chapter: &ExportChapter) -> String {
+    format!("chapter{}.html", chapter.id)
+}
+
+// 章节页面上下的导航：上一章、目录、下一章
+fn pager(previous: Option<&ExportChapter>, next: Option<&ExportChapter>) -> String {
+    let link = |chapter: Option<&ExportChapter>, text: &str| match chapter {
+        Some(chapter) => format!(
+            "<a href=\"{}\" title=\"{}\">{}</a>",
+            chapter_file_name(chapter),
+            escape(&chapter.label),
+            text
+        ),
+        None => format!("<span class=\"disabled\">{}</span>", text),
+    };
+    format!(
+        "<nav class=\"pager\">{}<a href=\"index.html\">目录</a>{}</nav>",
+        link(previous, "上一章"),
+        link(next, "下一章")
+    )
+}
+
+// 多页面站点：index.html 为封面和目录，每章一个页面，图片复制到 images 目录
+fn write_site(
+    book: &ExportBook,
+    app_dir: &Path,
+    output: &Path,
+    written: &mut WrittenFiles,
+) -> Result<(), String> {
+    fs::create_dir_all(output).map_err(|e| format!("创建目录失败: {}", e))?;
+    written.write(output.join("style.css"), HTML_STYLE.as_bytes())?;
+    let head = "<link rel=\"stylesheet\" href=\"style.css\" />";
+
+    let cover = match fs::read(cover_path(app_dir, book.id)) {
+        Ok(data) => {
+            let name = format!(
+                "cover.{}",
+                cover_extension(image_media_type("cover.jpg", &data))
+            );
+            written.write(output.join(&name), &data)?;
+            Some(name)
+        }
+        Err(_) => None,
+    };
+
+    let chapters = book.flatten();
+    let mut index = book_header(book, cover.as_deref());
+    if let Some((_, first)) = chapters.first() {
+        index.push_str(&format!(
+            "<p class=\"start\"><a href=\"{}\">开始阅读</a></p>\n",
+            chapter_file_name(first)
+        ));
+    }
+    index.push_str("<nav class=\"toc\">\n<h2>目录</h2>\n");
+    index.push_str(&toc_list(&book.chapters, &chapter_file_name));
+    index.push_str("</nav>\n");
+    written.write(
+        output.join("index.html"),
+        html_document(&book.title, head, &index).as_bytes(),
+    )?;
+
+    let pattern = image_pattern();
+    let mut images: Vec<String> = Vec::new();
+    for (position, (depth, chapter)) in chapters.iter().enumerate() {
+        let html = chapter_html(chapter, *depth);
+        for caps in pattern.captures_iter(&html) {
+            if !images.iter().any(|name| *name == caps[1]) {
+                images.push(caps[1].to_string());
+            }
+        }
+        let previous = position.checked_sub(1).map(|index| chapters[index].1);
+        let next = chapters.get(position + 1).map(|(_, chapter)| *chapter);
+        let nav = pager(previous, next);
+        let page = html_document(
+            &format!("{} - {}", chapter.label, book.title),
+            head,
+            &format!("{}\n<main>\n{}\n</main>\n{}", nav, html, nav),
+        );
+        written.write(output.join(chapter_file_name(chapter)), page.as_bytes())?;
+    }
+
+    copy_images(
+        &images,
+        &book_epub_dir(app_dir, book.id).join("images"),
+        output,
+        written,
+    )
+}
+
+// 写入 HTML：single_file 时输出为一个内嵌图片的文件，否则输出为静态站点目录；失败时删除已写入的文件
+pub fn write_html(
+    book: &ExportBook,
+    app_dir: &Path,
+    output: &Path,
+    single_file: bool,
+) -> Result<(), String> {
+    let mut written = WrittenFiles::default();
+    let result = if single_file {
+        written.write(output.to_path_buf(), single_page(book, app_dir).as_bytes())
+    } else {
+        write_site(book, app_dir, output, &mut written)
+    };
+    if result.is_err() {
+        written.remove_all();
+    }
+    result
+}
+
+// 导出书籍为 HTML 单文件或多页面站点，返回导出路径
+#[command]
+pub async fn export_html(
+    book_id: i64,
+    output_path: String,
+    single_file: bool,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<String>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    // 读取完数据后释放数据库锁，再写入文件
+    let book = {
+        let db = get_db_connection(&state)?;
+        match load_export_book(&db, book_id) {
+            Ok(book) => book,
+            Err(err) => return Ok(DbResponse::error(err)),
+        }
+    };
+
+    match write_html(&book, &app_dir, Path::new(&output_path), single_file) {
+        Ok(_) => Ok(DbResponse::success(output_path)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
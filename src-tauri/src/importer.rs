@@ -0,0 +1,289 @@
+use crate::database::{get_current_time_string, Book};
+use crate::fileutil::{book_epub_dir, cover_path};
+use crate::textutil::{split_tags, Segment};
+use crate::toc::sync_toc;
+use rusqlite::{params, Connection};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+// 导入的章节，children 为子章节
+#[derive(Debug, Clone, Default)]
+pub struct ImportedChapter {
+    pub label: String,
+    // 章节在源文件中的位置，写入 ee_chapter.href
+    pub href: String,
+    pub content: String,
+    pub children: Vec<ImportedChapter>,
+}
+
+// 导入的图片，章节内容中以 images/{name} 引用
+#[derive(Debug, Clone)]
+pub struct ImportedImage {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+// 各种格式解析后的书籍，由 save_imported_book 统一写入数据库和应用数据目录
+#[derive(Debug, Clone, Default)]
+pub struct ImportedBook {
+    pub title: String,
+    pub author: String,
+    pub description: String,
+    pub chapters: Vec<ImportedChapter>,
+    pub images: Vec<ImportedImage>,
+    pub cover: Option<Vec<u8>>,
+}
+
+// 为图片分配不重复的文件名，只保留文件名中的安全字符
+#[derive(Default)]
+pub struct ImageNamer {
+    used: HashSet<String>,
+}
+
+impl ImageNamer {
+    // 目标目录中已有的文件名，之后不再分配
+    pub fn reserve(&mut self, name: &str) {
+        self.used.insert(name.to_string());
+    }
+
+    pub fn name(&mut self, file_name: &str) -> String {
+        let file_name = file_name.rsplit(['/', '\\']).next().unwrap_or(file_name);
+        let (stem, ext) = match file_name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem, ext.to_ascii_lowercase()),
+            _ => (file_name, "jpg".to_string()),
+        };
+        let stem: String = stem
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let stem = if stem.is_empty() {
+            "image".to_string()
+        } else {
+            stem
+        };
+
+        let mut name = format!("{}.{}", stem, ext);
+        let mut index = 1;
+        while !self.used.insert(name.clone()) {
+            name = format!("{}-{}.{}", stem, index, ext);
+            index += 1;
+        }
+        name
+    }
+}
+
+// 根据文件扩展名判断是否为图片
+pub fn is_image_path(path: &str) -> bool {
+    let ext = path
+        .rsplit('.')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    matches!(
+        ext.as_str(),
+        "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" | "svg"
+    )
+}
+
+// 把一段内容追加到章节末尾，中间换行
+pub fn append_content(target: &mut String, content: &str) {
+    if !target.is_empty() {
+        target.push('\n');
+    }
+    target.push_str(content);
+}
+
+// 内容中除标签外是否还有文字
+pub fn has_text(content: &str) -> bool {
+    split_tags(content).iter().any(|segment| match segment {
+        Segment::Text(text) => !text.trim().is_empty(),
+        Segment::Tag(_) => false,
+    })
+}
+
+// 第一个标题之前的内容作为独立章节时使用的标题
+pub const PREFACE_LABEL: &str = "前言";
+
+// 按标题组织章节：一级标题为顶级章节，二级标题为最近一个一级标题的子章节
+#[derive(Default)]
+pub struct ChapterOutline {
+    preface: String,
+    chapters: Vec<ImportedChapter>,
+    // 当前内容写入的位置：(顶级章节下标, 二级章节下标)
+    current: Option<(usize, Option<usize>)>,
+}
+
+impl ChapterOutline {
+    pub fn heading(&mut self, level: u8, label: String) {
+        let chapter = ImportedChapter {
+            label,
+            href: String::new(),
+            content: String::new(),
+            children: Vec::new(),
+        };
+        match (level, self.current) {
+            (2, Some((parent, _))) => {
+                let children = &mut self.chapters[parent].children;
+                children.push(chapter);
+                self.current = Some((parent, Some(children.len() - 1)));
+            }
+            _ => {
+                self.chapters.push(chapter);
+                self.current = Some((self.chapters.len() - 1, None));
+            }
+        }
+    }
+
+    pub fn line(&mut self, line: &str) {
+        let content = match self.current {
+            Some((parent, Some(child))) => &mut self.chapters[parent].children[child].content,
+            Some((parent, None)) => &mut self.chapters[parent].content,
+            None => &mut self.preface,
+        };
+        append_content(content, line);
+    }
+
+    // 记录当前章节在源文件中的位置
+    pub fn set_href(&mut self, href: String) {
+        match self.current {
+            Some((parent, Some(child))) => self.chapters[parent].children[child].href = href,
+            Some((parent, None)) => self.chapters[parent].href = href,
+            None => {}
+        }
+    }
+
+    pub fn has_chapters(&self) -> bool {
+        !self.chapters.is_empty()
+    }
+
+    // 第一个标题之前有文字时作为「前言」章节；没有任何标题时全书作为一个以书名命名的章节
+    pub fn finish(self, title: &str) -> Vec<ImportedChapter> {
+        let mut chapters = self.chapters;
+        if chapters.is_empty() || has_text(&self.preface) {
+            let label = if chapters.is_empty() {
+                title.to_string()
+            } else {
+                PREFACE_LABEL.to_string()
+            };
+            chapters.insert(
+                0,
+                ImportedChapter {
+                    label,
+                    href: String::new(),
+                    content: self.preface,
+                    children: Vec::new(),
+                },
+            );
+        }
+        chapters
+    }
+}
+
+fn insert_chapters(
+    db: &Connection,
+    book_id: i64,
+    parent_id: Option<i64>,
+    chapters: &[ImportedChapter],
+    now: &str,
+) -> Result<(), rusqlite::Error> {
+    for (position, chapter) in chapters.iter().enumerate() {
+        db.execute(
+            "INSERT INTO ee_chapter (bookId, label, href, content, parentId, position, createTime, updateTime) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                book_id,
+                chapter.label,
+                chapter.href,
+                chapter.content,
+                parent_id,
+                position as i64,
+                now,
+                now
+            ],
+        )?;
+        let id = db.last_insert_rowid();
+        insert_chapters(db, book_id, Some(id), &chapter.children, now)?;
+    }
+    Ok(())
+}
+
+fn write_book_files(app_dir: &Path, book_id: i64, book: &ImportedBook) -> Result<(), String> {
+    if !book.images.is_empty() {
+        let images_dir = book_epub_dir(app_dir, book_id).join("images");
+        fs::create_dir_all(&images_dir).map_err(|e| format!("创建图片目录失败: {}", e))?;
+        for image in &book.images {
+            fs::write(images_dir.join(&image.name), &image.data)
+                .map_err(|e| format!("保存图片 {} 失败: {}", image.name, e))?;
+        }
+    }
+    if let Some(cover) = &book.cover {
+        let path = cover_path(app_dir, book_id);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("创建封面目录失败: {}", e))?;
+        }
+        fs::write(&path, cover).map_err(|e| format!("保存封面失败: {}", e))?;
+    }
+    Ok(())
+}
+
+// 读取书籍资源目录中的全部图片，用于以已有书籍为来源生成新书
+pub fn load_book_images(app_dir: &Path, book_id: i64) -> Result<Vec<ImportedImage>, String> {
+    let images_dir = book_epub_dir(app_dir, book_id).join("images");
+    if !images_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut images = Vec::new();
+    for entry in fs::read_dir(&images_dir).map_err(|e| format!("读取图片目录失败: {}", e))? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let data = fs::read(&path).map_err(|e| format!("读取图片 {} 失败: {}", name, e))?;
+        images.push(ImportedImage { name, data });
+    }
+    Ok(images)
+}
+
+// 在一个事务中写入书籍和全部章节，并保存图片和封面；文件保存失败时回滚并清理已写入的文件
+pub fn save_imported_book(
+    db: &mut Connection,
+    app_dir: &Path,
+    book: &ImportedBook,
+) -> Result<Book, String> {
+    let now = get_current_time_string();
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO ee_book (title, author, description, toc, isDel, createTime, updateTime) \
+         VALUES (?, ?, ?, '', 0, ?, ?)",
+        params![book.title, book.author, book.description, now, now],
+    )
+    .map_err(|e| e.to_string())?;
+    let book_id = tx.last_insert_rowid();
+
+    insert_chapters(&tx, book_id, None, &book.chapters, &now).map_err(|e| e.to_string())?;
+    let toc = sync_toc(&tx, book_id).map_err(|e| e.to_string())?;
+
+    let result = write_book_files(app_dir, book_id, book)
+        .and_then(|_| tx.commit().map_err(|e| e.to_string()));
+    if let Err(err) = result {
+        let _ = fs::remove_dir_all(book_epub_dir(app_dir, book_id));
+        let _ = fs::remove_file(cover_path(app_dir, book_id));
+        return Err(err);
+    }
+
+    Ok(Book {
+        id: book_id,
+        title: book.title.clone(),
+        author: book.author.clone(),
+        description: book.description.clone(),
+        toc: serde_json::to_string(&toc).unwrap_or_else(|_| "[]".to_string()),
+    })
+}
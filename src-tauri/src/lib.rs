@@ -1,8 +1,34 @@
+mod bookops;
+mod chapterops;
 mod database;
+mod docx;
+mod epub;
+mod exporter;
+mod fb2;
 mod fileutil;
+mod font;
+mod headings;
+mod html;
+mod importer;
+mod markdown;
+mod markup;
+mod migration;
+mod mobi;
+mod numbering;
+mod opencc;
+mod pdf;
+mod pdftext;
+mod revision;
+mod rules;
+mod replace;
+mod search;
 mod setup;
-use tauri::{ Emitter};
+mod textutil;
+mod toc;
+mod trash;
+mod txt;
 
+#[allow(dead_code)]
 #[derive(Clone, serde::Serialize)]
 struct Payload {
     args: Vec<String>,
@@ -16,6 +42,7 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_os::init())
+        .plugin(tauri_plugin_log::Builder::new().build())
         .invoke_handler(tauri::generate_handler![
             database::close_database,
             database::add_book,
@@ -23,10 +50,61 @@ pub fn run() {
             database::add_chapter,
             database::get_chapter,
             database::update_toc,
-            database::get_chapter_where,
+            database::query_chapters,
             database::update_chapter,
+            database::update_chapters_batch,
             database::delete_book,
             database::update_book,
+            revision::get_chapter_revisions,
+            revision::get_revision,
+            revision::diff_revisions,
+            revision::rollback_chapter,
+            revision::rollback_book,
+            replace::find_replace,
+            rules::get_rule_sets,
+            rules::save_rule_set,
+            rules::delete_rule_set,
+            rules::apply_rule_set,
+            rules::import_rule_sets,
+            rules::export_rule_sets,
+            search::search_chapters,
+            textutil::transform_book_text,
+            toc::get_chapter_tree,
+            toc::move_chapter,
+            toc::reorder_chapters,
+            chapterops::split_chapter,
+            chapterops::join_chapters,
+            headings::preview_toc_rebuild,
+            headings::rebuild_toc,
+            numbering::check_chapter_numbering,
+            numbering::renumber_chapters,
+            trash::get_trashed_books,
+            trash::restore_book,
+            trash::purge_book,
+            trash::purge_expired_trash,
+            trash::get_trash_retention,
+            trash::set_trash_retention,
+            bookops::duplicate_book,
+            bookops::merge_books,
+            bookops::split_book,
+            bookops::move_chapters_to_book,
+            epub::import_epub,
+            epub::export_epub,
+            docx::import_docx,
+            docx::export_docx,
+            markdown::import_markdown,
+            markdown::export_markdown,
+            html::export_html,
+            pdf::export_pdf,
+            pdftext::import_pdf,
+            mobi::import_mobi,
+            fb2::import_fb2,
+            fb2::export_fb2,
+            opencc::convert_chinese,
+            opencc::convert_book_chinese,
+            txt::get_txt_heading_patterns,
+            txt::preview_txt,
+            txt::import_txt,
             fileutil::read_image,
             fileutil::clear_app_data,
             fileutil::restart_app,
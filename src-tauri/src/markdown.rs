@@ -0,0 +1,1024 @@
+use crate::database::{get_db_connection, Book, DbResponse};
+use crate::exporter::{copy_images, load_export_book, ExportBook, ExportChapter, WrittenFiles};
+use crate::fileutil::book_epub_dir;
+use crate::importer::{
+    is_image_path, save_imported_book, ChapterOutline, ImageNamer, ImportedBook, ImportedImage,
+};
+use crate::markup::{content_to_html, escape, is_wide, parse_markup, Element, Node};
+use crate::setup::AppState;
+use crate::txt::decode_text;
+use regex::{Captures, Regex};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{command, AppHandle, Manager, State};
+
+// 目录导入时作为索引的文件，按优先级排列；索引只提供元数据和章节文件顺序
+const INDEX_FILES: &[&str] = &["index.md", "readme.md", "_index.md"];
+// 导出的章节文件名中标题部分的最大长度
+const MAX_FILE_LABEL_CHARS: usize = 30;
+
+// YAML front matter 中与书籍相关的字段
+#[derive(Default)]
+struct FrontMatter {
+    title: Option<String>,
+    author: Option<String>,
+    description: Option<String>,
+}
+
+impl FrontMatter {
+    fn set(&mut self, key: &str, value: String) {
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            return;
+        }
+        let field = match key {
+            "title" => &mut self.title,
+            "author" | "authors" | "creator" => &mut self.author,
+            "description" | "summary" | "abstract" => &mut self.description,
+            _ => return,
+        };
+        field.get_or_insert(value);
+    }
+}
+
+// 去掉引号并处理转义，普通值去掉行尾注释
+fn yaml_scalar(value: &str) -> String {
+    let value = value.trim();
+    if let Some(body) = value.strip_prefix('"') {
+        let body = body.rsplit_once('"').map(|(body, _)| body).unwrap_or(body);
+        let mut result = String::new();
+        let mut chars = body.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                result.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some('u') => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    if let Some(c) = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                        result.push(c);
+                    }
+                }
+                Some(other) => result.push(other),
+                None => {}
+            }
+        }
+        result
+    } else if let Some(body) = value.strip_prefix('\'') {
+        let body = body.rsplit_once('\'').map(|(body, _)| body).unwrap_or(body);
+        body.replace("''", "'")
+    } else {
+        value
+            .split_once(" #")
+            .map(|(value, _)| value)
+            .unwrap_or(value)
+            .trim()
+            .to_string()
+    }
+}
+
+// 多行文本按行拼接，中文之间不加空格
+fn join_wrapped(target: &mut String, line: &str) {
+    let line = line.trim();
+    if line.is_empty() {
+        return;
+    }
+    if let (Some(last), Some(first)) = (target.chars().last(), line.chars().next()) {
+        if !(is_wide(last) && is_wide(first)) {
+            target.push(' ');
+        }
+    }
+    target.push_str(line);
+}
+
+// 解析文件开头 --- 包围的 YAML front matter，只支持书籍元数据用到的简单写法：
+// 标量、引号字符串、| 和 > 多行文本、列表（作者之间用顿号连接）
+fn split_front_matter(text: &str) -> (FrontMatter, &str) {
+    let mut front = FrontMatter::default();
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (front, text);
+    };
+
+    let mut lines = Vec::new();
+    let mut body = None;
+    let mut offset = text.len() - rest.len();
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            body = Some(&text[offset..]);
+            break;
+        }
+        lines.push(trimmed);
+    }
+    // 没有结束标记时不是 front matter
+    let Some(body) = body else {
+        return (front, text);
+    };
+
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
+        if line.starts_with([' ', '\t', '#', '-']) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+
+        // 缩进的后续行
+        let mut nested = Vec::new();
+        while index < lines.len()
+            && (lines[index].is_empty() || lines[index].starts_with([' ', '\t', '-']))
+        {
+            nested.push(lines[index].trim());
+            index += 1;
+        }
+
+        let value = if value.starts_with('|') {
+            nested.join("\n").trim().to_string()
+        } else if value.starts_with('>') {
+            let mut folded = String::new();
+            for line in nested {
+                join_wrapped(&mut folded, line);
+            }
+            folded
+        } else if let Some(items) = value.strip_prefix('[') {
+            items
+                .trim_end_matches(']')
+                .split(',')
+                .map(yaml_scalar)
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<_>>()
+                .join("、")
+        } else if value.is_empty() {
+            nested
+                .iter()
+                .filter_map(|line| line.strip_prefix('-'))
+                .map(yaml_scalar)
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<_>>()
+                .join("、")
+        } else {
+            let mut scalar = yaml_scalar(value);
+            // 未加引号的普通值可以折行
+            if !value.starts_with(['"', '\'']) {
+                for line in nested {
+                    join_wrapped(&mut scalar, line);
+                }
+            }
+            scalar
+        };
+        front.set(&key, value);
+    }
+    (front, body)
+}
+
+fn yaml_string(value: &str) -> String {
+    let mut result = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => {}
+            '\t' => result.push_str("\\t"),
+            _ => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn front_matter(book: &ExportBook) -> String {
+    let mut front = format!(
+        "---\ntitle: {}\nauthor: {}\n",
+        yaml_string(&book.title),
+        yaml_string(&book.author)
+    );
+    let description = book.description.trim();
+    if !description.is_empty() && description != "暂缺" {
+        front.push_str(&format!("description: {}\n", yaml_string(description)));
+    }
+    front.push_str("---\n\n");
+    front
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                result.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+// Markdown 中引用的本地图片，同一文件只导入一次
+#[derive(Default)]
+struct MarkdownImages {
+    namer: ImageNamer,
+    images: Vec<ImportedImage>,
+    names: HashMap<PathBuf, String>,
+}
+
+impl MarkdownImages {
+    // 按 Markdown 文件所在目录解析图片路径，返回导入后的文件名；网络图片和不存在的文件返回 None
+    fn import(&mut self, base: &Path, target: &str) -> Option<String> {
+        let target = target.split(['#', '?']).next().unwrap_or(target);
+        if target.is_empty() || target.contains("://") || target.starts_with("data:") {
+            return None;
+        }
+        let target = percent_decode(target);
+        if !is_image_path(&target) {
+            return None;
+        }
+        let path = base.join(&target);
+        let key = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if let Some(name) = self.names.get(&key) {
+            return Some(name.clone());
+        }
+        let data = fs::read(&path).ok().filter(|data| !data.is_empty())?;
+        let name = self.namer.name(&target);
+        self.names.insert(key, name.clone());
+        self.images.push(ImportedImage {
+            name: name.clone(),
+            data,
+        });
+        Some(name)
+    }
+}
+
+struct InlinePatterns {
+    escape: Regex,
+    code: Regex,
+    image: Regex,
+    link: Regex,
+    autolink: Regex,
+    line_break: Regex,
+    bold_italic: Regex,
+    bold: Regex,
+    italic: Regex,
+    underscore_italic: Regex,
+    strike: Regex,
+}
+
+impl InlinePatterns {
+    fn new() -> Self {
+        let pattern = |source: &str| Regex::new(source).expect("内置正则表达式有误");
+        InlinePatterns {
+            escape: pattern(r"\\([!-/:-@\[-`{-~])"),
+            code: pattern(r"`+([^`]+?)`+"),
+            image: pattern(r#"!\[([^\]]*)\]\(\s*<?([^)\s>]*)>?(?:\s+["'(][^)]*)?\s*\)"#),
+            link: pattern(r"\[([^\]]*)\]\([^)]*\)"),
+            autolink: pattern(r"<((?:https?|mailto):[^>\s]+)>"),
+            line_break: pattern(r"(?i)<br\s*/?>"),
+            bold_italic: pattern(r"\*\*\*(\S(?:.*?\S)?)\*\*\*|___(\S(?:.*?\S)?)___"),
+            bold: pattern(r"\*\*(\S(?:.*?\S)?)\*\*|__(\S(?:.*?\S)?)__"),
+            italic: pattern(r"\*(\S(?:.*?\S)?)\*"),
+            underscore_italic: pattern(r"(^|[^\w])_(\S(?:.*?\S)?)_($|[^\w])"),
+            strike: pattern(r"~~(\S(?:.*?\S)?)~~"),
+        }
+    }
+}
+
+// 转义的标点和行内代码中的标点暂时换成私用区字符，避免被当作格式标记
+fn protect(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '!'..='/' | ':'..='@' | '['..='`' | '{'..='~' => {
+                char::from_u32(0xF0000 + c as u32).unwrap_or(c)
+            }
+            _ => c,
+        })
+        .collect()
+}
+
+fn unprotect(text: &str) -> String {
+    text.chars()
+        .map(|c| match c as u32 {
+            code @ 0xF0000..=0xF007F => char::from_u32(code - 0xF0000).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+// 行内 Markdown 转换为章节内容：粗体、斜体转换为 b/i 标签，本地图片转换为 img 标签，链接只保留文字
+fn inline_to_content(
+    text: &str,
+    base: &Path,
+    patterns: &InlinePatterns,
+    images: &mut MarkdownImages,
+) -> String {
+    let text = patterns
+        .escape
+        .replace_all(text, |caps: &Captures| protect(&caps[1]));
+    let text = patterns
+        .code
+        .replace_all(&text, |caps: &Captures| protect(&caps[1]));
+    let text = patterns.image.replace_all(&text, |caps: &Captures| {
+        let alt = unprotect(&caps[1]);
+        match images.import(base, &unprotect(&caps[2])) {
+            Some(name) if alt.trim().is_empty() => {
+                format!("<img src=\"images/{}\" />", escape(&name))
+            }
+            Some(name) => format!(
+                "<img src=\"images/{}\" alt=\"{}\" />",
+                escape(&name),
+                escape(alt.trim())
+            ),
+            None => protect(&alt),
+        }
+    });
+    let text = patterns.link.replace_all(&text, "$1");
+    let text = patterns.autolink.replace_all(&text, "$1");
+    let text = patterns.line_break.replace_all(&text, "\n");
+    let text = patterns
+        .bold_italic
+        .replace_all(&text, "<b><i>$1$2</i></b>");
+    let text = patterns.bold.replace_all(&text, "<b>$1$2</b>");
+    let text = patterns.italic.replace_all(&text, "<i>$1</i>");
+    let text = patterns
+        .underscore_italic
+        .replace_all(&text, "$1<i>$2</i>$3");
+    let text = patterns.strike.replace_all(&text, "$1");
+    unprotect(&text)
+}
+
+// 标题中只保留文字
+fn inline_to_label(text: &str, patterns: &InlinePatterns) -> String {
+    let text = patterns
+        .escape
+        .replace_all(text, |caps: &Captures| protect(&caps[1]));
+    let text = patterns.code.replace_all(&text, "$1");
+    let text = patterns.image.replace_all(&text, "$1");
+    let text = patterns.link.replace_all(&text, "$1");
+    let text = patterns.bold_italic.replace_all(&text, "$1$2");
+    let text = patterns.bold.replace_all(&text, "$1$2");
+    let text = patterns.italic.replace_all(&text, "$1");
+    let text = patterns.underscore_italic.replace_all(&text, "$1$2$3");
+    let text = patterns.strike.replace_all(&text, "$1");
+    unprotect(text.trim())
+}
+
+enum Block {
+    Heading(u8, String),
+    Line(String),
+}
+
+// 一个 Markdown 文件的块级解析：标题、段落、列表和代码块，段落内的软换行合并为一行
+struct BlockReader<'a> {
+    base: &'a Path,
+    patterns: &'a InlinePatterns,
+    images: &'a mut MarkdownImages,
+    blocks: Vec<Block>,
+    // 当前段落，每项为一个硬换行分隔的行
+    paragraph: Vec<String>,
+    hard_break: bool,
+    // 当前列表：(标签名, 列表项)
+    list: Option<(&'static str, Vec<String>)>,
+}
+
+impl BlockReader<'_> {
+    fn flush_paragraph(&mut self) {
+        let text = std::mem::take(&mut self.paragraph).join("\n");
+        self.hard_break = false;
+        if text.trim().is_empty() {
+            return;
+        }
+        let content = inline_to_content(&text, self.base, self.patterns, self.images);
+        for line in content.split('\n') {
+            let line = line.trim();
+            if !line.is_empty() {
+                self.blocks.push(Block::Line(line.to_string()));
+            }
+        }
+    }
+
+    fn flush_list(&mut self) {
+        let Some((tag, items)) = self.list.take() else {
+            return;
+        };
+        self.blocks.push(Block::Line(format!("<{}>", tag)));
+        for item in items {
+            let item = inline_to_content(&item, self.base, self.patterns, self.images);
+            self.blocks.push(Block::Line(format!(
+                "<li>{}</li>",
+                item.replace('\n', " ").trim()
+            )));
+        }
+        self.blocks.push(Block::Line(format!("</{}>", tag)));
+    }
+
+    fn flush(&mut self) {
+        self.flush_paragraph();
+        self.flush_list();
+    }
+
+    fn text_line(&mut self, line: &str) {
+        let hard_break = line.ends_with("  ") || line.ends_with('\\');
+        let text = line
+            .trim_end()
+            .trim_end_matches('\\')
+            .trim_matches([' ', '\t']);
+        match self.paragraph.last_mut() {
+            Some(last) if !self.hard_break => join_wrapped(last, text),
+            _ => self.paragraph.push(text.to_string()),
+        }
+        self.hard_break = hard_break;
+    }
+
+    fn read(&mut self, text: &str) {
+        let heading = Regex::new(r"^ {0,3}(#{1,6})(?:[ \t]+(.*?))?(?:[ \t]+#+)?[ \t]*$")
+            .expect("内置正则表达式有误");
+        let list_item =
+            Regex::new(r"^[ \t]*([-*+]|\d{1,9}[.)])[ \t]+(.*)$").expect("内置正则表达式有误");
+        let thematic_break = Regex::new(r"^ {0,3}((\*[ \t]*){3,}|(-[ \t]*){3,}|(_[ \t]*){3,})$")
+            .expect("内置正则表达式有误");
+
+        let mut fence: Option<String> = None;
+        for line in text.lines() {
+            // 代码块内容原样保留
+            if let Some(marker) = &fence {
+                if line.trim_start().starts_with(marker.as_str()) {
+                    fence = None;
+                } else if !line.trim().is_empty() {
+                    self.blocks.push(Block::Line(line.trim_end().to_string()));
+                }
+                continue;
+            }
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                self.flush();
+                fence = Some(trimmed[..3].to_string());
+                continue;
+            }
+
+            if line.trim().is_empty() {
+                self.flush();
+                continue;
+            }
+            if let Some(caps) = heading.captures(line) {
+                self.flush();
+                let level = caps[1].len() as u8;
+                let label = inline_to_label(caps.get(2).map_or("", |m| m.as_str()), self.patterns);
+                self.blocks.push(Block::Heading(level, label));
+                continue;
+            }
+            // Setext 标题：单行段落下的 === 或 ---
+            let underline = line.trim();
+            if self.paragraph.len() == 1
+                && self.list.is_none()
+                && (underline.chars().all(|c| c == '=') || underline.chars().all(|c| c == '-'))
+            {
+                let level = if underline.starts_with('=') { 1 } else { 2 };
+                let label = inline_to_label(&self.paragraph.remove(0), self.patterns);
+                self.hard_break = false;
+                self.blocks.push(Block::Heading(level, label));
+                continue;
+            }
+            if thematic_break.is_match(line) {
+                self.flush();
+                continue;
+            }
+            if let Some(caps) = list_item.captures(line) {
+                self.flush_paragraph();
+                let tag = if caps[1].ends_with(['.', ')']) {
+                    "ol"
+                } else {
+                    "ul"
+                };
+                if self
+                    .list
+                    .as_ref()
+                    .is_some_and(|(current, _)| *current != tag)
+                {
+                    self.flush_list();
+                }
+                self.list
+                    .get_or_insert_with(|| (tag, Vec::new()))
+                    .1
+                    .push(caps[2].trim().to_string());
+                continue;
+            }
+            // 列表项的续行
+            if let Some((_, items)) = &mut self.list {
+                if let Some(last) = items.last_mut() {
+                    join_wrapped(last, line);
+                    continue;
+                }
+            }
+
+            // 引用只去掉标记，按普通段落处理
+            let mut text = line;
+            while let Some(rest) = text.trim_start().strip_prefix('>') {
+                text = rest;
+            }
+            if text.trim().is_empty() {
+                self.flush();
+                continue;
+            }
+            self.text_line(text);
+        }
+        self.flush();
+    }
+}
+
+fn read_blocks(
+    text: &str,
+    base: &Path,
+    patterns: &InlinePatterns,
+    images: &mut MarkdownImages,
+) -> Vec<Block> {
+    let mut reader = BlockReader {
+        base,
+        patterns,
+        images,
+        blocks: Vec::new(),
+        paragraph: Vec::new(),
+        hard_break: false,
+        list: None,
+    };
+    reader.read(text);
+    reader.blocks
+}
+
+fn is_markdown_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .is_some_and(|ext| ext == "md" || ext == "markdown")
+}
+
+// 文件名按自然顺序排序，数字部分按数值比较（2 排在 10 之前）
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+                        digits.push(c);
+                        chars.next();
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                let order = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let order = x.to_lowercase().cmp(y.to_lowercase());
+                if order != Ordering::Equal {
+                    return order;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn read_markdown_text(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    Ok(decode_text(&bytes, None)?.0)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// 目录中的章节文件：有索引文件时按索引中的链接顺序，否则按文件名自然排序
+fn directory_files(dir: &Path) -> Result<(Option<PathBuf>, Vec<PathBuf>), String> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("读取目录失败: {}", e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_markdown_file(path))
+        .collect();
+    let index = INDEX_FILES.iter().find_map(|name| {
+        files
+            .iter()
+            .find(|path| {
+                path.file_name()
+                    .is_some_and(|file| file.to_string_lossy().to_lowercase() == *name)
+            })
+            .cloned()
+    });
+    files.retain(|path| Some(path) != index.as_ref());
+    files.sort_by(|a, b| {
+        natural_cmp(
+            &a.file_name().unwrap_or_default().to_string_lossy(),
+            &b.file_name().unwrap_or_default().to_string_lossy(),
+        )
+    });
+    Ok((index, files))
+}
+
+fn indexed_files(index_body: &str, dir: &Path, files: &[PathBuf]) -> Vec<PathBuf> {
+    let link =
+        Regex::new(r"\]\(\s*<?([^)>]+?)>?(?:\s+[\x22'][^)]*)?\s*\)").expect("内置正则表达式有误");
+    let mut ordered: Vec<PathBuf> = Vec::new();
+    for caps in link.captures_iter(index_body) {
+        let target = percent_decode(caps[1].split('#').next().unwrap_or_default());
+        let path = dir.join(target.trim());
+        let Some(file) = files.iter().find(|file| {
+            *file == &path
+                || file
+                    .canonicalize()
+                    .ok()
+                    .is_some_and(|file| Some(file) == path.canonicalize().ok())
+        }) else {
+            continue;
+        };
+        if !ordered.contains(file) {
+            ordered.push(file.clone());
+        }
+    }
+    ordered
+}
+
+// 解析 Markdown 文件或目录：# 和 ## 标题切分章节，front matter 提供书名、作者和简介
+pub fn read_markdown(path: &Path) -> Result<ImportedBook, String> {
+    let patterns = InlinePatterns::new();
+    let mut images = MarkdownImages::default();
+    let mut blocks = Vec::new();
+
+    let (front, fallback_title) = if path.is_dir() {
+        let (index, files) = directory_files(path)?;
+        let mut front = FrontMatter::default();
+        let mut files = files;
+        if let Some(index) = &index {
+            let text = read_markdown_text(index)?;
+            let (index_front, body) = split_front_matter(&text);
+            front = index_front;
+            let ordered = indexed_files(body, path, &files);
+            if !ordered.is_empty() {
+                files = ordered;
+            }
+        }
+        if files.is_empty() {
+            return Err("目录中没有 Markdown 章节文件".to_string());
+        }
+        for file in &files {
+            let text = read_markdown_text(file)?;
+            let (file_front, body) = split_front_matter(&text);
+            let base = file.parent().unwrap_or(path);
+            let mut file_blocks = read_blocks(body, base, &patterns, &mut images);
+            // 不以标题开头的文件作为一章，标题取 front matter 中的 title 或文件名
+            if file_blocks
+                .first()
+                .is_some_and(|block| !matches!(block, Block::Heading(..)))
+            {
+                let label = file_front.title.unwrap_or_else(|| file_stem(file));
+                file_blocks.insert(0, Block::Heading(1, label));
+            }
+            blocks.extend(file_blocks);
+        }
+        (front, file_stem(path))
+    } else {
+        let text = read_markdown_text(path)?;
+        let (front, body) = split_front_matter(&text);
+        let base = path.parent().unwrap_or(Path::new("."));
+        blocks = read_blocks(body, base, &patterns, &mut images);
+        (front, file_stem(path))
+    };
+
+    // 唯一的一级标题位于开头时作为书名，其余标题各提升一级
+    let mut title_heading = None;
+    let top_headings = blocks
+        .iter()
+        .filter(|block| matches!(block, Block::Heading(1, _)))
+        .count();
+    if let (1, Some(Block::Heading(1, label))) = (top_headings, blocks.first()) {
+        if front.title.as_ref().is_none_or(|title| title == label) {
+            title_heading = Some(label.clone());
+            blocks.remove(0);
+            for block in &mut blocks {
+                if let Block::Heading(level, _) = block {
+                    *level -= 1;
+                }
+            }
+        }
+    }
+
+    let mut outline = ChapterOutline::default();
+    for block in blocks {
+        match block {
+            Block::Heading(level @ 1..=2, label) => outline.heading(level, label),
+            Block::Heading(level, label) => {
+                let level = level.clamp(3, 6);
+                outline.line(&format!("<h{}>{}</h{}>", level, label, level));
+            }
+            Block::Line(line) => outline.line(&line),
+        }
+    }
+
+    let title = front
+        .title
+        .or(title_heading)
+        .filter(|title| !title.is_empty())
+        .unwrap_or(fallback_title);
+    let chapters = outline.finish(&title);
+    Ok(ImportedBook {
+        title,
+        author: front.author.unwrap_or_else(|| "佚名".to_string()),
+        description: front.description.unwrap_or_else(|| "暂缺".to_string()),
+        chapters,
+        images: images.images,
+        cover: None,
+    })
+}
+
+// 导入单个 Markdown 文件或 Markdown 文件目录，在一个事务中写入书籍和章节，返回新书籍
+#[command]
+pub async fn import_markdown(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Book>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    let book = match read_markdown(Path::new(&path)) {
+        Ok(book) => book,
+        Err(err) => return Ok(DbResponse::error(err)),
+    };
+
+    let mut db = get_db_connection(&state)?;
+    match save_imported_book(&mut db, &app_dir, &book) {
+        Ok(book) => Ok(DbResponse::success(book)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 转义文字中会被当作 Markdown 格式的字符
+fn escape_markdown(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '~') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+// 行首的 #、>、列表标记等需要转义，避免段落被识别为标题、引用或列表
+fn escape_line_start(line: &str) -> String {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with(['#', '>', '-', '+', '=']) {
+        return format!("\\{}", trimmed);
+    }
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && trimmed[digits..].starts_with(['.', ')']) {
+        return format!("{}\\{}", &trimmed[..digits], &trimmed[digits..]);
+    }
+    trimmed.to_string()
+}
+
+// 行内元素转换为 Markdown，同时记录引用到的图片
+fn markdown_inline(element: &Element, images: &mut Vec<String>) -> String {
+    let mut out = String::new();
+    for node in &element.children {
+        match node {
+            Node::Text(text) => out.push_str(&escape_markdown(text)),
+            Node::Element(child) => match child.name.as_str() {
+                "img" => {
+                    let src = child.attr("src").unwrap_or_default();
+                    if let Some(name) = src.strip_prefix("images/") {
+                        if !images.iter().any(|used| used == name) {
+                            images.push(name.to_string());
+                        }
+                    }
+                    out.push_str(&format!(
+                        "![{}]({})",
+                        escape_markdown(child.attr("alt").unwrap_or_default()),
+                        src.replace(' ', "%20")
+                    ));
+                }
+                "b" | "strong" => out.push_str(&format!("**{}**", markdown_inline(child, images))),
+                "i" | "em" => out.push_str(&format!("*{}*", markdown_inline(child, images))),
+                "u" => out.push_str(&format!("<u>{}</u>", markdown_inline(child, images))),
+                "br" => out.push_str("  \n"),
+                _ => out.push_str(&markdown_inline(child, images)),
+            },
+        }
+    }
+    out
+}
+
+// 章节内容转换为 Markdown 块，块之间空一行；与章节标题相同的开头标题不重复输出
+fn content_to_markdown(content: &str, label: &str, images: &mut Vec<String>) -> String {
+    let html = parse_markup(&content_to_html(content));
+    let mut out = String::new();
+    for (index, element) in html.elements().enumerate() {
+        let name = element.name.as_str();
+        match name {
+            "p" => {
+                let text = markdown_inline(element, images);
+                if !text.trim().is_empty() {
+                    out.push_str(&escape_line_start(&text));
+                    out.push_str("\n\n");
+                }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if index == 0 && element.text().trim() == label.trim() {
+                    continue;
+                }
+                // 正文中的标题不参与章节切分，至少为三级标题
+                let level = name[1..].parse::<usize>().unwrap_or(3).max(3);
+                out.push_str(&format!(
+                    "{} {}\n\n",
+                    "#".repeat(level),
+                    markdown_inline(element, images).trim()
+                ));
+            }
+            "ul" | "ol" => {
+                for (number, item) in element.children_named("li").enumerate() {
+                    let marker = if name == "ol" {
+                        format!("{}.", number + 1)
+                    } else {
+                        "-".to_string()
+                    };
+                    out.push_str(&format!(
+                        "{} {}\n",
+                        marker,
+                        markdown_inline(item, images).trim()
+                    ));
+                }
+                out.push('\n');
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn chapter_markdown(chapter: &ExportChapter, depth: usize, images: &mut Vec<String>) -> String {
+    format!(
+        "{} {}\n\n{}",
+        "#".repeat((depth + 1).min(6)),
+        escape_markdown(chapter.label.trim()),
+        content_to_markdown(&chapter.content, &chapter.label, images)
+    )
+}
+
+// 章节文件名：序号加标题，去掉文件名和链接中不能使用的字符
+fn chapter_file_name(index: usize, label: &str) -> String {
+    let label: String = label
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_whitespace()
+                || c.is_control()
+                || matches!(
+                    c,
+                    '/' | '\\'
+                        | ':'
+                        | '*'
+                        | '?'
+                        | '"'
+                        | '<'
+                        | '>'
+                        | '|'
+                        | '['
+                        | ']'
+                        | '('
+                        | ')'
+                        | '#'
+                        | '%'
+                )
+            {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(MAX_FILE_LABEL_CHARS)
+        .collect();
+    let label = label.trim_matches(['_', '.']);
+    if label.is_empty() {
+        format!("{:03}.md", index + 1)
+    } else {
+        format!("{:03}-{}.md", index + 1, label)
+    }
+}
+
+fn write_markdown_files(
+    book: &ExportBook,
+    app_dir: &Path,
+    output: &Path,
+    single_file: bool,
+    written: &mut WrittenFiles,
+) -> Result<(), String> {
+    let mut images = Vec::new();
+    let output_dir = if single_file {
+        let mut text = front_matter(book);
+        for (depth, chapter) in book.flatten() {
+            text.push_str(&chapter_markdown(chapter, depth, &mut images));
+        }
+        written.write(output.to_path_buf(), text.trim_end().as_bytes())?;
+        output.parent().unwrap_or(Path::new(".")).to_path_buf()
+    } else {
+        // 每章一个文件，索引文件中按目录层级列出章节链接
+        fs::create_dir_all(output).map_err(|e| format!("创建目录失败: {}", e))?;
+        let mut index = front_matter(book);
+        index.push_str(&format!("# {}\n\n", escape_markdown(&book.title)));
+        for (position, (depth, chapter)) in book.flatten().into_iter().enumerate() {
+            let file_name = chapter_file_name(position, &chapter.label);
+            index.push_str(&format!(
+                "{}- [{}]({})\n",
+                "  ".repeat(depth),
+                escape_markdown(chapter.label.trim()),
+                file_name
+            ));
+            let text = chapter_markdown(chapter, depth, &mut images);
+            written.write(output.join(&file_name), text.trim_end().as_bytes())?;
+        }
+        written.write(output.join("index.md"), index.as_bytes())?;
+        output.to_path_buf()
+    };
+    copy_images(
+        &images,
+        &book_epub_dir(app_dir, book.id).join("images"),
+        &output_dir,
+        written,
+    )
+}
+
+// 写入 Markdown：single_file 时输出为一个文件，否则输出为每章一个文件加 index.md 的目录；失败时删除已写入的文件
+pub fn write_markdown(
+    book: &ExportBook,
+    app_dir: &Path,
+    output: &Path,
+    single_file: bool,
+) -> Result<(), String> {
+    let mut written = WrittenFiles::default();
+    let result = write_markdown_files(book, app_dir, output, single_file, &mut written);
+    if result.is_err() {
+        written.remove_all();
+    }
+    result
+}
+
+// 导出书籍为 Markdown，图片复制到输出位置旁的 images 目录，返回导出路径
+#[command]
+pub async fn export_markdown(
+    book_id: i64,
+    output_path: String,
+    single_file: bool,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<String>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    // 读取完数据后释放数据库锁，再写入文件
+    let book = {
+        let db = get_db_connection(&state)?;
+        match load_export_book(&db, book_id) {
+            Ok(book) => book,
+            Err(err) => return Ok(DbResponse::error(err)),
+        }
+    };
+
+    match write_markdown(&book, &app_dir, Path::new(&output_path), single_file) {
+        Ok(_) => Ok(DbResponse::success(output_path)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
@@ -0,0 +1,680 @@
+// 轻量的 HTML / XML 解析：容错地构建元素树，用于读取电子书中的 OPF、NCX、XHTML 等文件
+
+use crate::textutil::{split_tags, Segment};
+
+// 元素名统一为去掉命名空间前缀的小写名称，属性名为小写并保留前缀
+#[derive(Debug, Clone, Default)]
+pub struct Element {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+// 没有结束标签的 HTML 元素
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+// 内容不解析也不保留的元素
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+fn local_name(name: &str) -> String {
+    name.rsplit(':').next().unwrap_or(name).to_ascii_lowercase()
+}
+
+impl Element {
+    // 按名称读取属性，带前缀的属性（如 epub:type、xlink:href）也可以用本地名称读取
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .or_else(|| self.attrs.iter().find(|(key, _)| local_name(key) == name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    // 直接子元素中第一个名为 name 的元素
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements().filter(move |element| element.name == name)
+    }
+
+    // 深度优先查找第一个名为 name 的后代元素
+    pub fn find(&self, name: &str) -> Option<&Element> {
+        for element in self.elements() {
+            if element.name == name {
+                return Some(element);
+            }
+            if let Some(found) = element.find(name) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    // 按文档顺序返回所有名为 name 的后代元素
+    pub fn find_all<'a>(&'a self, name: &str) -> Vec<&'a Element> {
+        let mut result = Vec::new();
+        self.collect_named(name, &mut result);
+        result
+    }
+
+    fn collect_named<'a>(&'a self, name: &str, result: &mut Vec<&'a Element>) {
+        for element in self.elements() {
+            if element.name == name {
+                result.push(element);
+            }
+            element.collect_named(name, result);
+        }
+    }
+
+    // 所有后代文本拼接的结果
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.collect_text(&mut text);
+        text
+    }
+
+    fn collect_text(&self, text: &mut String) {
+        for node in &self.children {
+            match node {
+                Node::Text(value) => text.push_str(value),
+                Node::Element(element) => element.collect_text(text),
+            }
+        }
+    }
+}
+
+// 解码字符实体，无法识别的实体原样保留
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|end| *end > 0 && *end <= 32)
+            .and_then(|end| decode_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                result.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{00A0}',
+        "ensp" => '\u{2002}',
+        "emsp" => '\u{2003}',
+        "thinsp" => '\u{2009}',
+        "shy" => '\u{00AD}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "middot" => '·',
+        "bull" => '•',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "times" => '×',
+        "divide" => '÷',
+        "deg" => '°',
+        _ => return None,
+    })
+}
+
+// 转义文本和属性值中的特殊字符
+pub fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+// 查找标签结束的 '>'，忽略引号中的 '>'
+fn find_tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_attrs(mut rest: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            break;
+        }
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            match after.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let body = &after[1..];
+                    let end = body.find(q).unwrap_or(body.len());
+                    value = decode_entities(&body[..end]);
+                    rest = body.get(end + 1..).unwrap_or("");
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_whitespace())
+                        .unwrap_or(after.len());
+                    value = decode_entities(&after[..end]);
+                    rest = &after[end..];
+                }
+            }
+        }
+        if !name.is_empty() {
+            attrs.push((name, value));
+        }
+    }
+    attrs
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(Node::Text(decode_entities(text)));
+    }
+}
+
+// 把栈顶元素弹出并挂到新的栈顶元素下
+fn close_top(stack: &mut Vec<Element>) {
+    if stack.len() > 1 {
+        if let Some(element) = stack.pop() {
+            if let Some(parent) = stack.last_mut() {
+                parent.children.push(Node::Element(element));
+            }
+        }
+    }
+}
+
+// 解析 HTML / XML 文本，返回名称为空的根元素；不匹配的结束标签会被忽略，未闭合的元素在文档末尾自动闭合
+pub fn parse_markup(source: &str) -> Element {
+    parse(source, true)
+}
+
+// 按严格的 XML 解析：不识别 HTML 的空元素和 script/style 等原始文本元素，用于 DOCX 等以 style 为元素名的文档
+pub fn parse_xml(source: &str) -> Element {
+    parse(source, false)
+}
+
+fn parse(source: &str, html: bool) -> Element {
+    let mut stack = vec![Element::default()];
+    let mut rest = source.trim_start_matches('\u{FEFF}');
+
+    while let Some(start) = rest.find('<') {
+        push_text(&mut stack, &rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(body) = rest.strip_prefix("<!--") {
+            rest = body.find("-->").map(|end| &body[end + 3..]).unwrap_or("");
+        } else if let Some(body) = rest.strip_prefix("<![CDATA[") {
+            let end = body.find("]]>").unwrap_or(body.len());
+            if let Some(parent) = stack.last_mut() {
+                parent.children.push(Node::Text(body[..end].to_string()));
+            }
+            rest = body.get(end + 3..).unwrap_or("");
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map(|end| &rest[end + 1..]).unwrap_or("");
+        } else if let Some(body) = rest.strip_prefix("</") {
+            let end = body.find('>').unwrap_or(body.len());
+            let name = local_name(body[..end].trim());
+            if let Some(index) = stack.iter().rposition(|element| element.name == name) {
+                if index > 0 {
+                    while stack.len() > index {
+                        close_top(&mut stack);
+                    }
+                }
+            }
+            rest = body.get(end + 1..).unwrap_or("");
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let Some(end) = find_tag_end(rest) else {
+                push_text(&mut stack, rest);
+                rest = "";
+                break;
+            };
+            let inner = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            let self_closing = inner.trim_end().ends_with('/');
+            let name_end = inner
+                .find(|c: char| c.is_whitespace() || c == '/')
+                .unwrap_or(inner.len());
+            let name = local_name(&inner[..name_end]);
+            let element = Element {
+                attrs: parse_attrs(&inner[name_end..]),
+                name,
+                children: Vec::new(),
+            };
+
+            if html && RAW_TEXT_ELEMENTS.contains(&element.name.as_str()) && !self_closing {
+                let close = format!("</{}", element.name);
+                let lower = rest.to_ascii_lowercase();
+                rest = match lower.find(&close) {
+                    Some(index) => rest[index..]
+                        .find('>')
+                        .map(|end| &rest[index + end + 1..])
+                        .unwrap_or(""),
+                    None => "",
+                };
+                continue;
+            }
+            if self_closing || (html && VOID_ELEMENTS.contains(&element.name.as_str())) {
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(Node::Element(element));
+                }
+            } else {
+                stack.push(element);
+            }
+        } else {
+            push_text(&mut stack, "<");
+            rest = &rest[1..];
+        }
+    }
+    push_text(&mut stack, rest);
+
+    while stack.len() > 1 {
+        close_top(&mut stack);
+    }
+    stack.pop().unwrap_or_default()
+}
+
+// 转换为章节内容时保留的行内格式标签
+const INLINE_TAGS: &[&str] = &["b", "strong", "i", "em", "u"];
+// 转换为章节内容时保留并独占一行的标签
+const BLOCK_TAGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "li"];
+// 转换为换行、不保留标签的块级元素
+const LINE_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "blockquote",
+    "pre",
+    "table",
+    "tr",
+    "dl",
+    "dt",
+    "dd",
+    "figure",
+    "figcaption",
+    "header",
+    "footer",
+    "aside",
+    "center",
+    "hr",
+];
+// 不输出任何内容的元素
+const SKIPPED_TAGS: &[&str] = &["head", "title", "script", "style", "noscript"];
+
+// HTML 转换为章节内容：段落按行分隔，保留加粗、斜体、标题、列表和图片标签，其余标签去掉
+struct ContentWriter<'a> {
+    // 遇到这些 id 的元素时开始新的片段
+    split_ids: &'a [String],
+    resolve_image: &'a mut dyn FnMut(&str) -> Option<String>,
+    segments: Vec<(Option<String>, String)>,
+    // 末尾的空格是否由源码中的换行折叠而来，中文之间的这种空格会被去掉
+    soft_space: bool,
+}
+
+// 中日韩文字和全角标点
+pub fn is_wide(c: char) -> bool {
+    matches!(c as u32, 0x2E80..=0x9FFF | 0xF900..=0xFAFF | 0xFF00..=0xFFEF | 0x20000..=0x2FA1F)
+}
+
+impl ContentWriter<'_> {
+    fn out(&mut self) -> &mut String {
+        &mut self.segments.last_mut().expect("至少有一个片段").1
+    }
+
+    fn trim_trailing_space(&mut self) {
+        let out = self.out();
+        let len = out.trim_end_matches(' ').len();
+        out.truncate(len);
+    }
+
+    fn line_break(&mut self) {
+        self.trim_trailing_space();
+        let out = self.out();
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let mut soft_space = self.soft_space;
+        let out = self.out();
+        for c in text.chars() {
+            if c.is_ascii_whitespace() {
+                if out.is_empty() || out.ends_with('\n') {
+                    continue;
+                }
+                if out.ends_with(' ') {
+                    soft_space |= c == '\n';
+                } else {
+                    out.push(' ');
+                    soft_space = c == '\n';
+                }
+            } else {
+                if soft_space && is_wide(c) {
+                    let mut chars = out.chars().rev();
+                    if chars.next() == Some(' ') && chars.next().is_some_and(is_wide) {
+                        out.pop();
+                    }
+                }
+                out.push(c);
+                soft_space = false;
+            }
+        }
+        self.soft_space = soft_space;
+    }
+
+    fn image(&mut self, element: &Element) {
+        let Some(src) = element.attr("src").or_else(|| element.attr("href")) else {
+            return;
+        };
+        let src = (self.resolve_image)(src).unwrap_or_else(|| src.to_string());
+        let mut tag = format!("<img src=\"{}\"", escape(&src));
+        if let Some(alt) = element.attr("alt").filter(|alt| !alt.is_empty()) {
+            tag.push_str(&format!(" alt=\"{}\"", escape(alt)));
+        }
+        tag.push_str(" />");
+        self.out().push_str(&tag);
+    }
+
+    fn element(&mut self, element: &Element) {
+        if let Some(id) = element.attr("id") {
+            if self.split_ids.iter().any(|split| split == id) {
+                self.trim_trailing_space();
+                self.segments.push((Some(id.to_string()), String::new()));
+            }
+        }
+
+        let name = element.name.as_str();
+        if SKIPPED_TAGS.contains(&name) {
+            return;
+        }
+        match name {
+            "br" => {
+                self.trim_trailing_space();
+                self.out().push('\n');
+            }
+            "img" | "image" => self.image(element),
+            _ if INLINE_TAGS.contains(&name) => {
+                self.out().push_str(&format!("<{}>", name));
+                self.children(element);
+                self.out().push_str(&format!("</{}>", name));
+            }
+            _ if BLOCK_TAGS.contains(&name) => {
+                self.line_break();
+                self.out().push_str(&format!("<{}>", name));
+                self.children(element);
+                self.trim_trailing_space();
+                self.out().push_str(&format!("</{}>", name));
+                self.line_break();
+            }
+            _ if LINE_TAGS.contains(&name) => {
+                self.line_break();
+                self.children(element);
+                self.line_break();
+            }
+            _ => self.children(element),
+        }
+    }
+
+    fn children(&mut self, element: &Element) {
+        for node in &element.children {
+            match node {
+                Node::Text(text) => self.text(text),
+                Node::Element(child) => self.element(child),
+            }
+        }
+    }
+}
+
+fn finish_content(content: &str) -> String {
+    content
+        .split('\n')
+        .map(|line| line.trim_end_matches(' '))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+// 把 HTML 文档转换为章节内容，并在 split_ids 指定的锚点处切分。
+// 返回的第一个片段是第一个锚点之前的内容（id 为 None），resolve_image 用于改写图片地址
+pub fn html_to_content_segments(
+    document: &Element,
+    split_ids: &[String],
+    resolve_image: &mut dyn FnMut(&str) -> Option<String>,
+) -> Vec<(Option<String>, String)> {
+    let body = document.find("body").unwrap_or(document);
+    let mut writer = ContentWriter {
+        split_ids,
+        resolve_image,
+        segments: vec![(None, String::new())],
+        soft_space: false,
+    };
+    writer.children(body);
+    writer
+        .segments
+        .into_iter()
+        .map(|(id, content)| (id, finish_content(&content)))
+        .collect()
+}
+
+// 章节内容转换为 HTML 时保留的标签，其余标签丢弃
+const HEADING_TAGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+const LIST_TAGS: &[&str] = &["ul", "ol"];
+
+// 章节内容转换为格式良好的 XHTML 片段：每行一个段落，标题、列表和图片原样保留，未闭合的标签会被补全
+struct HtmlWriter {
+    html: String,
+    // 当前打开的段落级元素：p、h1-h6 或 li
+    block: Option<String>,
+    inline: Vec<String>,
+    lists: Vec<String>,
+}
+
+impl HtmlWriter {
+    fn close_inline(&mut self) {
+        while let Some(tag) = self.inline.pop() {
+            self.html.push_str(&format!("</{}>", tag));
+        }
+    }
+
+    fn close_block(&mut self) {
+        self.close_inline();
+        if let Some(tag) = self.block.take() {
+            let len = self.html.trim_end().len();
+            self.html.truncate(len);
+            self.html.push_str(&format!("</{}>\n", tag));
+        }
+    }
+
+    // 需要输出行内内容时确保有一个打开的段落，列表中使用 li
+    fn ensure_block(&mut self) {
+        if self.block.is_none() {
+            let tag = if self.lists.is_empty() { "p" } else { "li" };
+            self.open_block(tag);
+        }
+    }
+
+    fn open_block(&mut self, tag: &str) {
+        self.close_block();
+        self.html.push_str(&format!("<{}>", tag));
+        self.block = Some(tag.to_string());
+    }
+
+    fn text(&mut self, text: &str) {
+        // 段落开头的缩进由样式表控制
+        let at_start = match &self.block {
+            None => true,
+            Some(tag) => self.html.ends_with(&format!("<{}>", tag)),
+        };
+        let text = if at_start { text.trim_start() } else { text };
+        if text.is_empty() {
+            return;
+        }
+        self.ensure_block();
+        self.html.push_str(&escape(text));
+    }
+
+    fn tag(&mut self, tag: &str) {
+        let closing = tag.starts_with("</");
+        let element = parse_markup(tag);
+        let name = if closing {
+            local_name(tag.trim_start_matches("</").trim_end_matches('>').trim())
+        } else {
+            match element.elements().next() {
+                Some(element) => element.name.clone(),
+                None => return,
+            }
+        };
+        let name = name.as_str();
+
+        match (name, closing) {
+            ("br", _) if self.block.is_some() => self.html.push_str("<br />"),
+            ("img", false) => {
+                let Some(img) = element.elements().next() else {
+                    return;
+                };
+                let src = img.attr("src").unwrap_or_default();
+                let alt = img.attr("alt").unwrap_or_default();
+                self.ensure_block();
+                self.html.push_str(&format!(
+                    "<img src=\"{}\" alt=\"{}\" />",
+                    escape(src),
+                    escape(alt)
+                ));
+            }
+            (_, false) if INLINE_TAGS.contains(&name) => {
+                self.ensure_block();
+                self.html.push_str(&format!("<{}>", name));
+                self.inline.push(name.to_string());
+            }
+            (_, true) if INLINE_TAGS.contains(&name) => {
+                if let Some(index) = self.inline.iter().rposition(|open| open == name) {
+                    while self.inline.len() > index {
+                        if let Some(open) = self.inline.pop() {
+                            self.html.push_str(&format!("</{}>", open));
+                        }
+                    }
+                }
+            }
+            (_, false) if HEADING_TAGS.contains(&name) => self.open_block(name),
+            ("li", false) if !self.lists.is_empty() => self.open_block("li"),
+            (_, true) if self.block.as_deref() == Some(name) && name != "p" => self.close_block(),
+            (_, false) if LIST_TAGS.contains(&name) => {
+                self.close_block();
+                self.html.push_str(&format!("<{}>\n", name));
+                self.lists.push(name.to_string());
+            }
+            (_, true) if LIST_TAGS.contains(&name) => {
+                if let Some(index) = self.lists.iter().rposition(|open| open == name) {
+                    self.close_block();
+                    while self.lists.len() > index {
+                        if let Some(open) = self.lists.pop() {
+                            self.html.push_str(&format!("</{}>\n", open));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn content_to_html(content: &str) -> String {
+    let mut writer = HtmlWriter {
+        html: String::new(),
+        block: None,
+        inline: Vec::new(),
+        lists: Vec::new(),
+    };
+    for line in content.split('\n') {
+        for segment in split_tags(line) {
+            match segment {
+                Segment::Text(text) => writer.text(text),
+                Segment::Tag(tag) => writer.tag(tag),
+            }
+        }
+        // 段落和标题不跨行
+        writer.close_block();
+    }
+    while let Some(list) = writer.lists.pop() {
+        writer.html.push_str(&format!("</{}>\n", list));
+    }
+    writer.html.trim_end().to_string()
+}
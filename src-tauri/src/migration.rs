@@ -0,0 +1,277 @@
+use crate::toc::{apply_toc, parse_toc, sync_toc};
+use rusqlite::{params, Connection, Transaction};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+// 单个迁移步骤：version 为执行后写入 PRAGMA user_version 的值
+struct Migration {
+    version: i32,
+    description: &'static str,
+    up: fn(&Transaction) -> Result<(), rusqlite::Error>,
+}
+
+// 所有迁移步骤，必须按 version 递增排列；已发布的步骤不要再修改，只能追加
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "初始表结构 ee_book / ee_chapter",
+        up: v1_initial_schema,
+    },
+    Migration {
+        version: 2,
+        description: "章节全文索引 ee_chapter_fts",
+        up: v2_chapter_fts,
+    },
+    Migration {
+        version: 3,
+        description: "章节排序与层级 position / parentId",
+        up: v3_chapter_hierarchy,
+    },
+    Migration {
+        version: 4,
+        description: "回收站删除时间 deleteTime 与设置表 ee_setting",
+        up: v4_trash,
+    },
+    Migration {
+        version: 5,
+        description: "章节修订记录 ee_chapter_revision",
+        up: v5_chapter_revision,
+    },
+    Migration {
+        version: 6,
+        description: "清理规则集 ee_rule_set / ee_rule",
+        up: v6_cleanup_rules,
+    },
+];
+
+// 当前应用支持的最新数据库版本
+pub fn latest_version() -> i32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+fn get_user_version(db: &Connection) -> Result<i32, rusqlite::Error> {
+    db.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+// 执行所有未执行的迁移，执行前先备份数据库到 backup_dir
+pub fn run_migrations(db: &mut Connection, backup_dir: &Path) -> Result<(), String> {
+    let current = get_user_version(db).map_err(|e| format!("读取数据库版本失败: {}", e))?;
+    let latest = latest_version();
+
+    // 数据库来自更新版本的应用，拒绝打开以免损坏数据
+    if current > latest {
+        return Err(format!(
+            "数据库版本({})高于当前应用支持的版本({})，请升级应用后再打开",
+            current, latest
+        ));
+    }
+    if current == latest {
+        return Ok(());
+    }
+
+    backup_before_migrate(db, backup_dir, current)?;
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        // 每个迁移步骤在独立事务中执行，失败则整体回滚，user_version 保持不变
+        let tx = db
+            .transaction()
+            .map_err(|e| format!("开启迁移事务失败: {}", e))?;
+        (migration.up)(&tx).map_err(|e| {
+            format!(
+                "数据库迁移 v{} ({}) 失败: {}",
+                migration.version, migration.description, e
+            )
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)
+            .map_err(|e| format!("写入数据库版本失败: {}", e))?;
+        tx.commit()
+            .map_err(|e| format!("提交迁移 v{} 失败: {}", migration.version, e))?;
+    }
+
+    Ok(())
+}
+
+// 迁移前备份：使用 VACUUM INTO 生成一致的数据库副本（包含 WAL 中未落盘的数据）
+fn backup_before_migrate(db: &Connection, backup_dir: &Path, version: i32) -> Result<(), String> {
+    // 全新的空数据库无需备份
+    let table_count: i64 = db
+        .query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get(0))
+        .map_err(|e| format!("读取数据库结构失败: {}", e))?;
+    if table_count == 0 {
+        return Ok(());
+    }
+
+    fs::create_dir_all(backup_dir).map_err(|e| format!("创建备份目录失败: {}", e))?;
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|dur| dur.as_secs())
+        .unwrap_or(0);
+    let backup_path = backup_dir.join(format!("books-v{}-{}.db", version, timestamp));
+
+    db.execute(
+        "VACUUM INTO ?",
+        params![backup_path.to_string_lossy().to_string()],
+    )
+    .map_err(|e| format!("迁移前备份数据库失败: {}", e))?;
+
+    Ok(())
+}
+
+// v1: 原有的建表语句，旧数据库中表已存在时保持不变
+fn v1_initial_schema(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS ee_book (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title TEXT,
+            author TEXT,
+            description TEXT,
+            toc TEXT,
+            isDel INTEGER,
+            createTime TEXT,
+            updateTime TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS ee_chapter (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            bookId INTEGER,
+            label TEXT,
+            href TEXT,
+            content TEXT,
+            createTime TEXT,
+            updateTime TEXT
+        );
+    ",
+    )
+}
+
+// v2: 章节标题和内容的 FTS5 全文索引，trigram 分词支持中文子串检索，由触发器与 ee_chapter 保持同步；
+// 索引中保存去掉标签后的内容（strip_tags 由 search::register_functions 注册），标签和属性不参与检索
+fn v2_chapter_fts(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        CREATE VIRTUAL TABLE ee_chapter_fts USING fts5(
+            label,
+            content,
+            tokenize = 'trigram'
+        );
+
+        CREATE TRIGGER ee_chapter_fts_insert AFTER INSERT ON ee_chapter BEGIN
+            INSERT INTO ee_chapter_fts (rowid, label, content)
+            VALUES (new.id, new.label, strip_tags(new.content));
+        END;
+
+        CREATE TRIGGER ee_chapter_fts_delete AFTER DELETE ON ee_chapter BEGIN
+            DELETE FROM ee_chapter_fts WHERE rowid = old.id;
+        END;
+
+        CREATE TRIGGER ee_chapter_fts_update AFTER UPDATE OF label, content ON ee_chapter BEGIN
+            DELETE FROM ee_chapter_fts WHERE rowid = old.id;
+            INSERT INTO ee_chapter_fts (rowid, label, content)
+            VALUES (new.id, new.label, strip_tags(new.content));
+        END;
+
+        INSERT INTO ee_chapter_fts (rowid, label, content)
+        SELECT id, label, strip_tags(content) FROM ee_chapter;
+    ",
+    )
+}
+
+// v3: 章节的顺序和层级保存到 ee_chapter，并按原有的 toc JSON 回填
+fn v3_chapter_hierarchy(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        ALTER TABLE ee_chapter ADD COLUMN parentId INTEGER;
+        ALTER TABLE ee_chapter ADD COLUMN position INTEGER;
+        CREATE INDEX idx_ee_chapter_tree ON ee_chapter (bookId, parentId, position);
+    ",
+    )?;
+
+    let books = {
+        let mut stmt = tx.prepare("SELECT id, toc FROM ee_book")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            ))
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+    for (book_id, toc) in books {
+        apply_toc(tx, book_id, &parse_toc(&toc))?;
+        sync_toc(tx, book_id)?;
+    }
+
+    Ok(())
+}
+
+// v4: 记录书籍放入回收站的时间（秒级时间戳），以及保存应用设置的键值表
+fn v4_trash(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        ALTER TABLE ee_book ADD COLUMN deleteTime TEXT;
+
+        -- 旧数据的删除时间取自 updateTime（本地时间字符串），无法解析时按迁移时间计算
+        UPDATE ee_book
+        SET deleteTime = COALESCE(strftime('%s', updateTime, 'utc'), strftime('%s', 'now'))
+        WHERE isDel = 1;
+
+        CREATE TABLE IF NOT EXISTS ee_setting (
+            key TEXT PRIMARY KEY,
+            value TEXT
+        );
+    ",
+    )
+}
+
+// v5: 章节修改前的快照，用于查看历史、比较差异和回滚
+fn v5_chapter_revision(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS ee_chapter_revision (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            chapterId INTEGER,
+            bookId INTEGER,
+            label TEXT,
+            content TEXT,
+            createTime TEXT,
+            updateTime TEXT
+        );
+
+        CREATE INDEX idx_ee_chapter_revision_chapter ON ee_chapter_revision (chapterId, createTime);
+        CREATE INDEX idx_ee_chapter_revision_book ON ee_chapter_revision (bookId);
+    ",
+    )
+}
+
+// v6: 可复用的清理规则集，每个规则集包含按 position 排序的查找替换规则
+fn v6_cleanup_rules(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS ee_rule_set (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            description TEXT,
+            createTime TEXT,
+            updateTime TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS ee_rule (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            ruleSetId INTEGER,
+            position INTEGER,
+            name TEXT,
+            pattern TEXT,
+            replacement TEXT,
+            isRegex INTEGER,
+            ignoreCase INTEGER,
+            field TEXT,
+            enabled INTEGER
+        );
+
+        CREATE INDEX idx_ee_rule_set ON ee_rule (ruleSetId, position);
+    ",
+    )
+}
+
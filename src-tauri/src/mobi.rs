@@ -0,0 +1,1208 @@
+use crate::database::{get_db_connection, Book, DbResponse};
+use crate::importer::{
+    append_content, has_text, save_imported_book, ImportedBook, ImportedChapter, ImportedImage,
+};
+use crate::markup::{html_to_content_segments, parse_markup, Element};
+use crate::setup::AppState;
+use crate::txt::decode_text;
+use encoding_rs::WINDOWS_1252;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tauri::{command, AppHandle, Manager, State};
+
+// 索引、记录号等字段中表示“不存在”的值
+const NULL_INDEX: u32 = 0xFFFF_FFFF;
+// 目录项锚点的 id 前缀，解析时插入到 HTML 中用于切分章节
+const MARKER_PREFIX: &str = "mobi-toc-";
+// HUFF/CDIC 字典递归展开的最大深度，防止损坏的文件导致无限递归
+const MAX_HUFF_DEPTH: usize = 32;
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// PalmDB 容器：文件头之后是记录偏移表，每条记录到下一条记录的偏移为止
+struct PalmDb {
+    data: Vec<u8>,
+    records: Vec<(usize, usize)>,
+}
+
+impl PalmDb {
+    fn parse(data: Vec<u8>) -> Result<Self, String> {
+        let count = u16_at(&data, 76).ok_or("文件过小，不是有效的 MOBI 文件")? as usize;
+        let mut offsets = Vec::with_capacity(count);
+        for index in 0..count {
+            let offset = u32_at(&data, 78 + index * 8).ok_or("PalmDB 记录表已损坏")? as usize;
+            offsets.push(offset);
+        }
+        let mut records = Vec::with_capacity(count);
+        for (index, start) in offsets.iter().enumerate() {
+            let end = offsets.get(index + 1).copied().unwrap_or(data.len());
+            if *start > end || end > data.len() {
+                return Err("PalmDB 记录表已损坏".to_string());
+            }
+            records.push((*start, end));
+        }
+        Ok(PalmDb { data, records })
+    }
+
+    fn kind(&self) -> &[u8] {
+        self.data.get(60..68).unwrap_or_default()
+    }
+
+    fn name(&self) -> String {
+        let name = self.data.get(0..32).unwrap_or_default();
+        let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+        String::from_utf8_lossy(&name[..end]).replace('_', " ")
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn record(&self, index: usize) -> Option<&[u8]> {
+        self.records
+            .get(index)
+            .map(|(start, end)| &self.data[*start..*end])
+    }
+}
+
+// 把相对于 base 的记录号转换为绝对记录号
+fn record_index(value: Option<u32>, base: usize) -> Option<usize> {
+    value
+        .filter(|value| *value != NULL_INDEX)
+        .map(|value| value as usize + base)
+}
+
+// 记录 0 中的 PalmDOC 头、MOBI 头和 EXTH 元数据
+struct MobiHeader {
+    // 该头所在的记录号，KF8 合并文件中 KF8 部分的记录号都相对于它
+    base: usize,
+    compression: u16,
+    text_length: usize,
+    text_records: usize,
+    encryption: u16,
+    is_mobi: bool,
+    utf8: bool,
+    version: u32,
+    full_name: Vec<u8>,
+    first_resource: Option<usize>,
+    huff_record: Option<usize>,
+    huff_count: usize,
+    extra_flags: u16,
+    ncx_index: Option<usize>,
+    fdst_index: Option<usize>,
+    fragment_index: Option<usize>,
+    skeleton_index: Option<usize>,
+    exth: Vec<(u32, Vec<u8>)>,
+}
+
+impl MobiHeader {
+    fn parse(db: &PalmDb, base: usize) -> Result<Self, String> {
+        let record = db.record(base).ok_or("缺少 MOBI 头记录")?;
+        let field = |offset: usize| u32_at(record, offset);
+        let is_mobi = record.get(16..20) == Some(b"MOBI".as_slice());
+        let header_length = if is_mobi {
+            field(0x14).unwrap_or(0) as usize
+        } else {
+            0
+        };
+        let in_header = |offset: usize| offset + 4 <= 16 + header_length;
+        let index_field = |offset: usize| {
+            if in_header(offset) {
+                record_index(field(offset), base)
+            } else {
+                None
+            }
+        };
+
+        let mut exth = Vec::new();
+        if is_mobi && field(0x80).unwrap_or(0) & 0x40 != 0 {
+            let start = 16 + header_length;
+            if record.get(start..start + 4) == Some(b"EXTH".as_slice()) {
+                let count = u32_at(record, start + 8).unwrap_or(0);
+                let mut offset = start + 12;
+                for _ in 0..count {
+                    let (Some(kind), Some(length)) =
+                        (u32_at(record, offset), u32_at(record, offset + 4))
+                    else {
+                        break;
+                    };
+                    let length = length as usize;
+                    let Some(value) = record.get(offset + 8..offset + length.max(8)) else {
+                        break;
+                    };
+                    exth.push((kind, value.to_vec()));
+                    offset += length.max(8);
+                }
+            }
+        }
+
+        let full_name = match (field(0x54), field(0x58)) {
+            (Some(offset), Some(length)) if is_mobi => record
+                .get(offset as usize..(offset as usize).saturating_add(length as usize))
+                .unwrap_or_default()
+                .to_vec(),
+            _ => Vec::new(),
+        };
+        let version = if is_mobi { field(0x24).unwrap_or(0) } else { 0 };
+
+        Ok(MobiHeader {
+            base,
+            compression: u16_at(record, 0).unwrap_or(0),
+            text_length: field(4).unwrap_or(0) as usize,
+            text_records: u16_at(record, 8).unwrap_or(0) as usize,
+            encryption: u16_at(record, 12).unwrap_or(0),
+            is_mobi,
+            utf8: is_mobi && field(0x1C) == Some(65001),
+            version,
+            full_name,
+            first_resource: index_field(0x6C),
+            huff_record: index_field(0x70),
+            huff_count: if in_header(0x74) {
+                field(0x74).unwrap_or(0) as usize
+            } else {
+                0
+            },
+            extra_flags: if header_length >= 0xE4 {
+                u16_at(record, 0xF2).unwrap_or(0)
+            } else {
+                0
+            },
+            ncx_index: index_field(0xF4),
+            fdst_index: if version >= 8 {
+                index_field(0xC0)
+            } else {
+                None
+            },
+            fragment_index: if version >= 8 {
+                index_field(0xF8)
+            } else {
+                None
+            },
+            skeleton_index: if version >= 8 {
+                index_field(0xFC)
+            } else {
+                None
+            },
+            exth,
+        })
+    }
+
+    fn exth_values(&self, kind: u32) -> impl Iterator<Item = &[u8]> {
+        self.exth
+            .iter()
+            .filter(move |(key, _)| *key == kind)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    fn exth_u32(&self, kind: u32) -> Option<u32> {
+        self.exth_values(kind)
+            .next()
+            .and_then(|value| u32_at(value, 0))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        if self.utf8 {
+            String::from_utf8_lossy(bytes).into_owned()
+        } else {
+            WINDOWS_1252.decode(bytes).0.into_owned()
+        }
+    }
+
+    fn exth_text(&self, kind: u32) -> Vec<String> {
+        self.exth_values(kind)
+            .map(|value| self.decode(value).trim().to_string())
+            .filter(|value| !value.is_empty())
+            .collect()
+    }
+}
+
+// 文本记录末尾的附加数据长度，由 MOBI 头中的 extra_flags 决定
+fn trailing_size(record: &[u8], flags: u16) -> usize {
+    let mut size = 0;
+    let mut bits = flags >> 1;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            // 从末尾向前读取的变长整数
+            let end = record.len().saturating_sub(size);
+            let mut value = 0usize;
+            let mut shift = 0;
+            for byte in record[..end].iter().rev() {
+                value |= ((byte & 0x7F) as usize) << shift;
+                shift += 7;
+                if byte & 0x80 != 0 || shift >= 28 {
+                    break;
+                }
+            }
+            size += value;
+        }
+        bits >>= 1;
+    }
+    if flags & 1 != 0 {
+        if let Some(byte) = record.len().checked_sub(size + 1).map(|i| record[i]) {
+            size += (byte & 0x3) as usize + 1;
+        }
+    }
+    size.min(record.len())
+}
+
+// PalmDOC 压缩（LZ77 变体）解压
+fn palmdoc_decompress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() * 2);
+    let mut i = 0;
+    while i < data.len() {
+        let c = data[i];
+        i += 1;
+        match c {
+            1..=8 => {
+                let end = (i + c as usize).min(data.len());
+                out.extend_from_slice(&data[i..end]);
+                i = end;
+            }
+            0x80..=0xBF => {
+                let Some(next) = data.get(i) else {
+                    break;
+                };
+                i += 1;
+                let pair = ((c as usize) << 8) | *next as usize;
+                let distance = (pair >> 3) & 0x07FF;
+                let length = (pair & 0x7) + 3;
+                if distance == 0 || distance > out.len() {
+                    continue;
+                }
+                let start = out.len() - distance;
+                for k in 0..length {
+                    out.push(out[start + k]);
+                }
+            }
+            0xC0..=0xFF => {
+                out.push(b' ');
+                out.push(c ^ 0x80);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// HUFF/CDIC 压缩的解码器
+struct HuffReader {
+    // 按编码前 8 位查表：(码长, 是否为完整码, 最大码值)
+    dict1: Vec<(u32, bool, u64)>,
+    min_codes: Vec<u64>,
+    max_codes: Vec<u64>,
+    // 短语字典，标志为 false 时短语本身还需要解码
+    dictionary: Vec<(Vec<u8>, bool)>,
+}
+
+impl HuffReader {
+    fn new(huff: &[u8], cdics: &[&[u8]]) -> Result<Self, String> {
+        if huff.get(0..8) != Some(b"HUFF\x00\x00\x00\x18".as_slice()) {
+            return Err("HUFF 记录格式错误".to_string());
+        }
+        let off1 = u32_at(huff, 8).ok_or("HUFF 记录格式错误")? as usize;
+        let off2 = u32_at(huff, 12).ok_or("HUFF 记录格式错误")? as usize;
+
+        let mut dict1 = Vec::with_capacity(256);
+        for index in 0..256 {
+            let value = u32_at(huff, off1 + index * 4).ok_or("HUFF 记录格式错误")?;
+            let code_length = value & 0x1F;
+            if code_length == 0 {
+                return Err("HUFF 记录格式错误".to_string());
+            }
+            let max_code = (((value >> 8) as u64 + 1) << (32 - code_length)) - 1;
+            dict1.push((code_length, value & 0x80 != 0, max_code));
+        }
+        let mut min_codes = vec![0u64];
+        let mut max_codes = vec![u32::MAX as u64];
+        for code_length in 1..=32u32 {
+            let offset = off2 + (code_length as usize - 1) * 8;
+            let min = u32_at(huff, offset).ok_or("HUFF 记录格式错误")? as u64;
+            let max = u32_at(huff, offset + 4).ok_or("HUFF 记录格式错误")? as u64;
+            min_codes.push(min << (32 - code_length));
+            max_codes.push(((max + 1) << (32 - code_length)) - 1);
+        }
+
+        let mut dictionary = Vec::new();
+        for cdic in cdics {
+            if cdic.get(0..8) != Some(b"CDIC\x00\x00\x00\x10".as_slice()) {
+                return Err("CDIC 记录格式错误".to_string());
+            }
+            let phrases = u32_at(cdic, 8).ok_or("CDIC 记录格式错误")? as usize;
+            let bits = u32_at(cdic, 12).ok_or("CDIC 记录格式错误")?.min(31);
+            let count = (1usize << bits).min(phrases.saturating_sub(dictionary.len()));
+            for index in 0..count {
+                let offset = u16_at(cdic, 16 + index * 2).ok_or("CDIC 记录格式错误")? as usize;
+                let length = u16_at(cdic, 16 + offset).ok_or("CDIC 记录格式错误")?;
+                let start = 18 + offset;
+                let slice = cdic
+                    .get(start..start + (length & 0x7FFF) as usize)
+                    .ok_or("CDIC 记录格式错误")?;
+                dictionary.push((slice.to_vec(), length & 0x8000 != 0));
+            }
+        }
+
+        Ok(HuffReader {
+            dict1,
+            min_codes,
+            max_codes,
+            dictionary,
+        })
+    }
+
+    fn unpack(&mut self, data: &[u8], depth: usize) -> Result<Vec<u8>, String> {
+        if depth > MAX_HUFF_DEPTH {
+            return Err("HUFF 字典嵌套过深".to_string());
+        }
+        let mut padded = data.to_vec();
+        padded.extend_from_slice(&[0; 8]);
+        let read = |pos: usize| u64::from_be_bytes(padded[pos..pos + 8].try_into().unwrap());
+
+        let mut bits_left = data.len() as i64 * 8;
+        let mut pos = 0;
+        let mut x = read(pos);
+        let mut n: i64 = 32;
+        let mut out = Vec::new();
+        loop {
+            if n <= 0 {
+                pos += 4;
+                x = read(pos);
+                n += 32;
+            }
+            let code = (x >> n) & 0xFFFF_FFFF;
+
+            let (mut code_length, term, mut max_code) = self.dict1[(code >> 24) as usize];
+            if !term {
+                while code_length < 32 && code < self.min_codes[code_length as usize] {
+                    code_length += 1;
+                }
+                max_code = self.max_codes[code_length as usize];
+            }
+
+            n -= code_length as i64;
+            bits_left -= code_length as i64;
+            if bits_left < 0 {
+                break;
+            }
+
+            let index = (max_code.wrapping_sub(code) >> (32 - code_length)) as usize;
+            let (phrase, decoded) = self
+                .dictionary
+                .get(index)
+                .cloned()
+                .ok_or("HUFF 数据已损坏")?;
+            if decoded {
+                out.extend_from_slice(&phrase);
+            } else {
+                let phrase = self.unpack(&phrase, depth + 1)?;
+                out.extend_from_slice(&phrase);
+                self.dictionary[index] = (phrase, true);
+            }
+        }
+        Ok(out)
+    }
+}
+
+// 读取并解压全部文本记录
+fn read_text(db: &PalmDb, header: &MobiHeader) -> Result<Vec<u8>, String> {
+    let mut huff = match header.compression {
+        1 | 2 => None,
+        17480 => {
+            let start = header.huff_record.ok_or("缺少 HUFF 记录")?;
+            let huff = db.record(start).ok_or("缺少 HUFF 记录")?;
+            let cdics: Vec<&[u8]> = (start + 1..start + header.huff_count.max(1))
+                .filter_map(|index| db.record(index))
+                .collect();
+            Some(HuffReader::new(huff, &cdics)?)
+        }
+        other => return Err(format!("不支持的压缩格式: {}", other)),
+    };
+
+    let mut text = Vec::with_capacity(header.text_length);
+    for index in 1..=header.text_records {
+        let record = db.record(header.base + index).ok_or("文本记录不完整")?;
+        let record = &record[..record.len() - trailing_size(record, header.extra_flags)];
+        match (&mut huff, header.compression) {
+            (Some(reader), _) => text.extend(reader.unpack(record, 0)?),
+            (None, 2) => text.extend(palmdoc_decompress(record)),
+            (None, _) => text.extend_from_slice(record),
+        }
+    }
+    text.truncate(header.text_length);
+    Ok(text)
+}
+
+// 索引（INDX）中的一项：名称和标签值
+struct IndexEntry {
+    text: Vec<u8>,
+    tags: HashMap<u8, Vec<u32>>,
+}
+
+impl IndexEntry {
+    fn tag(&self, tag: u8, index: usize) -> Option<u32> {
+        self.tags
+            .get(&tag)
+            .and_then(|values| values.get(index))
+            .copied()
+    }
+}
+
+// 变长整数，每字节 7 位，最高位为 1 的字节结束
+fn read_varint(data: &[u8], offset: &mut usize) -> Option<u32> {
+    let mut value = 0u32;
+    loop {
+        let byte = *data.get(*offset)?;
+        *offset += 1;
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 != 0 {
+            return Some(value);
+        }
+    }
+}
+
+fn read_tag_values(
+    data: &[u8],
+    start: usize,
+    control_count: usize,
+    tag_table: &[(u8, u8, u8, u8)],
+) -> HashMap<u8, Vec<u32>> {
+    // (标签, 值个数, 值字节数, 每项值个数)
+    let mut tags = Vec::new();
+    let mut control_index = 0;
+    for (tag, per_entry, mask, end_flag) in tag_table {
+        if *end_flag == 1 {
+            control_index += 1;
+            continue;
+        }
+        let Some(control) = data.get(start + control_index) else {
+            break;
+        };
+        let value = control & mask;
+        if value == 0 {
+            continue;
+        }
+        if value == *mask && mask.count_ones() > 1 {
+            tags.push((*tag, None, *per_entry));
+        } else {
+            tags.push((*tag, Some(value >> mask.trailing_zeros()), *per_entry));
+        }
+    }
+
+    let mut offset = start + control_count;
+    let mut result = HashMap::new();
+    for (tag, count, per_entry) in tags {
+        let mut values = Vec::new();
+        match count {
+            Some(count) => {
+                for _ in 0..count as usize * per_entry as usize {
+                    match read_varint(data, &mut offset) {
+                        Some(value) => values.push(value),
+                        None => break,
+                    }
+                }
+            }
+            None => {
+                // 值的总字节数由一个变长整数给出
+                let Some(length) = read_varint(data, &mut offset) else {
+                    break;
+                };
+                let end = offset + length as usize;
+                while offset < end {
+                    match read_varint(data, &mut offset) {
+                        Some(value) => values.push(value),
+                        None => break,
+                    }
+                }
+            }
+        }
+        result.insert(tag, values);
+    }
+    result
+}
+
+// CNCX 字符串，键为 (记录序号 << 16) + 偏移
+type CncxStrings = HashMap<usize, Vec<u8>>;
+
+// 读取一个索引：主 INDX 记录描述标签表，其后是各索引记录和 CNCX 字符串记录
+fn read_index(db: &PalmDb, index: usize) -> Result<(Vec<IndexEntry>, CncxStrings), String> {
+    let main = db.record(index).ok_or("缺少索引记录")?;
+    if main.get(0..4) != Some(b"INDX".as_slice()) {
+        return Err("索引记录格式错误".to_string());
+    }
+    let header_length = u32_at(main, 4).unwrap_or(0) as usize;
+    let record_count = u32_at(main, 24).unwrap_or(0) as usize;
+    let cncx_count = u32_at(main, 52).unwrap_or(0) as usize;
+
+    let mut tag_table = Vec::new();
+    let mut control_count = 0;
+    if main.get(header_length..header_length + 4) == Some(b"TAGX".as_slice()) {
+        let first_entry = u32_at(main, header_length + 4).unwrap_or(0) as usize;
+        control_count = u32_at(main, header_length + 8).unwrap_or(0) as usize;
+        for offset in (12..first_entry).step_by(4) {
+            if let Some(bytes) = main.get(header_length + offset..header_length + offset + 4) {
+                tag_table.push((bytes[0], bytes[1], bytes[2], bytes[3]));
+            }
+        }
+    }
+
+    // CNCX：目录标题等字符串
+    let mut strings = HashMap::new();
+    for k in 0..cncx_count {
+        let Some(data) = db.record(index + record_count + 1 + k) else {
+            break;
+        };
+        let mut offset = 0;
+        while offset < data.len() && data[offset] != 0 {
+            let start = offset;
+            let Some(length) = read_varint(data, &mut offset) else {
+                break;
+            };
+            let end = (offset + length as usize).min(data.len());
+            strings.insert((k << 16) + start, data[offset..end].to_vec());
+            offset = end;
+        }
+    }
+
+    let mut entries = Vec::new();
+    for record in index + 1..=index + record_count {
+        let data = db.record(record).ok_or("索引记录不完整")?;
+        if data.get(0..4) != Some(b"INDX".as_slice()) {
+            return Err("索引记录格式错误".to_string());
+        }
+        let idxt = u32_at(data, 20).unwrap_or(0) as usize;
+        let count = u32_at(data, 24).unwrap_or(0) as usize;
+        let mut positions: Vec<usize> = (0..count)
+            .filter_map(|j| u16_at(data, idxt + 4 + j * 2).map(|pos| pos as usize))
+            .collect();
+        positions.push(idxt);
+        for pair in positions.windows(2) {
+            let start = pair[0];
+            let Some(length) = data.get(start).map(|length| *length as usize) else {
+                continue;
+            };
+            let Some(text) = data.get(start + 1..start + 1 + length) else {
+                continue;
+            };
+            let tag_start = start + 1 + length;
+            let end = pair[1].min(data.len());
+            let tags = read_tag_values(
+                &data[..end.max(tag_start)],
+                tag_start,
+                control_count,
+                &tag_table,
+            );
+            entries.push(IndexEntry {
+                text: text.to_vec(),
+                tags,
+            });
+        }
+    }
+    Ok((entries, strings))
+}
+
+// KF8 中用于 kindle:pos:fid、kindle:embed 的 32 进制数（0-9A-V）
+fn from_base32(text: &str) -> Option<usize> {
+    text.chars().try_fold(0usize, |value, c| {
+        c.to_digit(32).map(|digit| value * 32 + digit as usize)
+    })
+}
+
+// 图片资源的扩展名，非图片资源（字体、FLIS 等）返回 None
+fn image_extension(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("jpg")
+    } else if data.starts_with(b"\x89PNG") {
+        Some("png")
+    } else if data.starts_with(b"GIF8") {
+        Some("gif")
+    } else {
+        None
+    }
+}
+
+// 目录项，parent 为父目录项的下标
+struct TocEntry {
+    label: String,
+    href: String,
+    parent: Option<usize>,
+    content: String,
+}
+
+// 解析后的 HTML 文档，markers 为要插入锚点的 (字节位置, 目录项下标)
+struct MobiDocument {
+    name: String,
+    // 目录之前的内容作为独立章节时的标题，为空时取文档中的标题
+    label: Option<String>,
+    html: Vec<u8>,
+    markers: Vec<(usize, usize)>,
+}
+
+// 在字节位置插入目录项锚点：位置落在标签内部时移到标签开头，落在 <body> 之前时移到 <body> 之后
+fn insert_markers(html: &[u8], markers: &[(usize, usize)]) -> Vec<u8> {
+    let body_start = Regex::new(r"(?i)<body[^>]*>")
+        .ok()
+        .and_then(|re| {
+            let lossy = String::from_utf8_lossy(html);
+            re.find(&lossy).map(|m| m.end())
+        })
+        .filter(|end| *end <= html.len())
+        .unwrap_or(0);
+
+    let mut positions: Vec<(usize, usize)> = markers
+        .iter()
+        .map(|(pos, index)| {
+            let mut pos = (*pos).min(html.len());
+            let head = &html[..pos];
+            if let Some(open) = head.iter().rposition(|b| *b == b'<') {
+                if head
+                    .iter()
+                    .rposition(|b| *b == b'>')
+                    .is_none_or(|close| close < open)
+                {
+                    pos = open;
+                }
+            }
+            (pos.max(body_start), *index)
+        })
+        .collect();
+    positions.sort_by(|a, b| b.cmp(a));
+
+    let mut result = html.to_vec();
+    for (pos, index) in positions {
+        let marker = format!("<a id=\"{}{}\"></a>", MARKER_PREFIX, index);
+        result.splice(pos..pos, marker.into_bytes());
+    }
+    result
+}
+
+fn normalize_label(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// 不在目录中的文档作为独立章节时使用的标题
+fn document_label(document: &Element, fallback: &str) -> String {
+    ["title", "h1", "h2", "h3"]
+        .iter()
+        .filter_map(|name| document.find(name))
+        .map(|element| normalize_label(&element.text()))
+        .find(|label| !label.is_empty())
+        .unwrap_or_else(|| fallback.to_string())
+}
+
+fn build_chapters(entries: &[TocEntry], parent: Option<usize>) -> Vec<ImportedChapter> {
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.parent == parent)
+        .map(|(index, entry)| ImportedChapter {
+            label: entry.label.clone(),
+            href: entry.href.clone(),
+            content: entry.content.clone(),
+            children: build_chapters(entries, Some(index)),
+        })
+        .collect()
+}
+
+// 按目录项锚点切分各文档，不在目录中的内容并入前一个目录项，目录之前有文字的文档作为独立章节
+fn assemble_chapters(
+    header: &MobiHeader,
+    documents: Vec<MobiDocument>,
+    mut entries: Vec<TocEntry>,
+    image_names: &HashMap<usize, String>,
+    fallback_label: &str,
+) -> Vec<ImportedChapter> {
+    let recindex = Regex::new(r#"(?i)(<img\b[^>]*?)\brecindex\s*=\s*["']?0*(\d+)["']?"#)
+        .expect("recindex 正则表达式有效");
+    let mut resolve_image = |src: &str| {
+        let number = if let Some(rest) = src.strip_prefix("recindex:") {
+            rest.parse::<usize>().ok()
+        } else {
+            src.strip_prefix("kindle:embed:")
+                .map(|rest| rest.split(['?', '#']).next().unwrap_or(rest))
+                .and_then(from_base32)
+        }?;
+        image_names
+            .get(&number.checked_sub(1)?)
+            .map(|name| format!("images/{}", name))
+    };
+
+    let mut leading = Vec::new();
+    let mut current: Option<usize> = None;
+    for document in documents {
+        let html = insert_markers(&document.html, &document.markers);
+        let html = header.decode(&html);
+        let html = recindex.replace_all(&html, r#"${1}src="recindex:${2}""#);
+        let parsed = parse_markup(&html);
+        let split_ids: Vec<String> = document
+            .markers
+            .iter()
+            .map(|(_, index)| format!("{}{}", MARKER_PREFIX, index))
+            .collect();
+        let segments = html_to_content_segments(&parsed, &split_ids, &mut resolve_image);
+
+        for (id, content) in &segments {
+            if let Some(index) = id
+                .as_deref()
+                .and_then(|id| id.strip_prefix(MARKER_PREFIX))
+                .and_then(|index| index.parse::<usize>().ok())
+            {
+                current = Some(index);
+            }
+            if content.is_empty() {
+                continue;
+            }
+            match current {
+                Some(index) => append_content(&mut entries[index].content, content),
+                None if entries.is_empty() || has_text(content) => {
+                    leading.push(ImportedChapter {
+                        label: document
+                            .label
+                            .clone()
+                            .unwrap_or_else(|| document_label(&parsed, fallback_label)),
+                        href: document.name.clone(),
+                        content: content.clone(),
+                        children: Vec::new(),
+                    });
+                }
+                None => {}
+            }
+        }
+    }
+
+    let mut chapters = leading;
+    chapters.extend(build_chapters(&entries, None));
+    chapters
+}
+
+// 读取 NCX 索引，返回目录项和各项的位置标签值
+fn read_ncx(db: &PalmDb, header: &MobiHeader) -> Vec<(TocEntry, Vec<u32>)> {
+    let Some(index) = header.ncx_index else {
+        return Vec::new();
+    };
+    let Ok((entries, strings)) = read_index(db, index) else {
+        return Vec::new();
+    };
+    let count = entries.len();
+    entries
+        .iter()
+        .enumerate()
+        .map(|(number, entry)| {
+            let label = entry
+                .tag(3, 0)
+                .and_then(|offset| strings.get(&(offset as usize)))
+                .map(|label| normalize_label(&header.decode(label)))
+                .unwrap_or_default();
+            let parent = entry
+                .tag(21, 0)
+                .map(|parent| parent as usize)
+                .filter(|parent| *parent < number && *parent < count);
+            let position = if header.version >= 8 {
+                entry.tags.get(&6).cloned().unwrap_or_default()
+            } else {
+                entry.tag(1, 0).into_iter().collect()
+            };
+            (
+                TocEntry {
+                    label,
+                    href: String::new(),
+                    parent,
+                    content: String::new(),
+                },
+                position,
+            )
+        })
+        .collect()
+}
+
+// MOBI 6：整本书是一个 HTML 文档，目录项按 filepos（文本中的字节位置）定位
+fn mobi6_chapters(
+    db: &PalmDb,
+    header: &MobiHeader,
+    text: Vec<u8>,
+    image_names: &HashMap<usize, String>,
+    title: &str,
+) -> Vec<ImportedChapter> {
+    let mut entries = Vec::new();
+    let mut markers = Vec::new();
+    for (mut entry, position) in read_ncx(db, header) {
+        let Some(pos) = position.first().map(|pos| *pos as usize) else {
+            continue;
+        };
+        // 被跳过的父项之后，子项的 parent 下标不再可靠
+        entry.parent = entry.parent.filter(|parent| *parent < entries.len());
+        entry.href = format!("filepos:{}", pos);
+        markers.push((pos, entries.len()));
+        entries.push(entry);
+    }
+
+    // 没有 NCX 索引时使用文中目录页的 <a filepos=...> 链接
+    if entries.is_empty() {
+        let link = regex::bytes::Regex::new(
+            r#"(?is)<a\s[^>]*?filepos\s*=\s*["']?0*(\d+)["']?[^>]*>(.*?)</a>"#,
+        )
+        .expect("filepos 正则表达式有效");
+        for captures in link.captures_iter(&text) {
+            let Some(pos) = std::str::from_utf8(&captures[1])
+                .ok()
+                .and_then(|pos| pos.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            let label = normalize_label(&parse_markup(&header.decode(&captures[2])).text());
+            if label.is_empty() || markers.iter().any(|(existing, _)| *existing == pos) {
+                continue;
+            }
+            markers.push((pos, entries.len()));
+            entries.push(TocEntry {
+                label,
+                href: format!("filepos:{}", pos),
+                parent: None,
+                content: String::new(),
+            });
+        }
+    }
+
+    let document = MobiDocument {
+        name: "text.html".to_string(),
+        // 整本书只有一个文档，文中的标题属于后面的章节
+        label: Some(title.to_string()),
+        html: text,
+        markers,
+    };
+    assemble_chapters(header, vec![document], entries, image_names, title)
+}
+
+// KF8：按骨架表和片段表还原各个 XHTML 文件，目录项按 kindle:pos:fid 定位
+fn kf8_chapters(
+    db: &PalmDb,
+    header: &MobiHeader,
+    text: Vec<u8>,
+    image_names: &HashMap<usize, String>,
+    title: &str,
+) -> Result<Vec<ImportedChapter>, String> {
+    // FDST 中的第一段是正文 HTML，其余为 CSS、SVG 等
+    let flow_end = header
+        .fdst_index
+        .and_then(|index| db.record(index))
+        .filter(|record| record.get(0..4) == Some(b"FDST".as_slice()))
+        .and_then(|record| u32_at(record, 16))
+        .map(|end| (end as usize).min(text.len()))
+        .unwrap_or(text.len());
+    let text = &text[..flow_end];
+
+    // 片段表：(插入位置, 长度)
+    let fragments: Vec<(usize, usize)> = match header.fragment_index {
+        Some(index) => read_index(db, index)?
+            .0
+            .iter()
+            .map(|entry| {
+                let insert = std::str::from_utf8(&entry.text)
+                    .ok()
+                    .and_then(|text| text.parse::<usize>().ok())
+                    .unwrap_or(0);
+                (insert, entry.tag(6, 1).unwrap_or(0) as usize)
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    let skeletons = match header.skeleton_index {
+        Some(index) => read_index(db, index)?.0,
+        None => Vec::new(),
+    };
+
+    // 各文件的内容以及在还原后文本中的起止位置
+    let mut parts: Vec<(Vec<u8>, usize, usize)> = Vec::new();
+    let mut fragment_index = 0;
+    for skeleton in &skeletons {
+        let count = skeleton.tag(1, 0).unwrap_or(0) as usize;
+        let start = skeleton.tag(6, 0).unwrap_or(0) as usize;
+        let length = skeleton.tag(6, 1).unwrap_or(0) as usize;
+        let mut base = (start + length).min(text.len());
+        let mut html = text[start.min(base)..base].to_vec();
+        for _ in 0..count {
+            let Some((insert, length)) = fragments.get(fragment_index).copied() else {
+                break;
+            };
+            fragment_index += 1;
+            let end = (base + length).min(text.len());
+            let insert = insert.saturating_sub(start).min(html.len());
+            html.splice(insert..insert, text[base..end].iter().copied());
+            base = end;
+        }
+        let end = start + html.len();
+        parts.push((html, start, end));
+    }
+    if parts.is_empty() {
+        parts.push((text.to_vec(), 0, text.len()));
+    }
+
+    let mut documents: Vec<MobiDocument> = parts
+        .iter()
+        .enumerate()
+        .map(|(number, (html, _, _))| MobiDocument {
+            name: format!("part{:04}.xhtml", number),
+            label: None,
+            html: html.clone(),
+            markers: Vec::new(),
+        })
+        .collect();
+
+    let mut entries = Vec::new();
+    for (mut entry, position) in read_ncx(db, header) {
+        let (Some(fid), Some(offset)) = (position.first(), position.get(1)) else {
+            continue;
+        };
+        let Some((insert, _)) = fragments.get(*fid as usize) else {
+            continue;
+        };
+        let pos = insert + *offset as usize;
+        let Some(part) = parts
+            .iter()
+            .position(|(_, start, end)| pos >= *start && pos < *end)
+        else {
+            continue;
+        };
+        entry.parent = entry.parent.filter(|parent| *parent < entries.len());
+        let offset = pos - parts[part].1;
+        entry.href = format!("{}#{}", documents[part].name, offset);
+        documents[part].markers.push((offset, entries.len()));
+        entries.push(entry);
+    }
+
+    Ok(assemble_chapters(
+        header,
+        documents,
+        entries,
+        image_names,
+        title,
+    ))
+}
+
+// 解析 MOBI/AZW3 文件：PalmDB -> MOBI 头和 EXTH -> 解压文本 -> 按 NCX 索引切分章节
+pub fn read_mobi(path: &Path) -> Result<ImportedBook, String> {
+    let data = fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let db = PalmDb::parse(data)?;
+    if !matches!(db.kind(), b"BOOKMOBI" | b"TEXtREAd") {
+        return Err("不是有效的 MOBI/AZW3 文件".to_string());
+    }
+
+    let mut header = MobiHeader::parse(&db, 0)?;
+    // KF8 与 MOBI 6 的合并文件，优先读取其中的 KF8 部分
+    if header.version < 8 {
+        if let Some(boundary) = header
+            .exth_u32(121)
+            .filter(|index| *index != NULL_INDEX)
+            .map(|index| index as usize)
+        {
+            let base = if db
+                .record(boundary)
+                .is_some_and(|r| r.starts_with(b"BOUNDARY"))
+            {
+                boundary + 1
+            } else {
+                boundary
+            };
+            if base < db.len() {
+                if let Ok(kf8) = MobiHeader::parse(&db, base) {
+                    if kf8.is_mobi && kf8.version >= 8 {
+                        let exth = std::mem::take(&mut header.exth);
+                        header = kf8;
+                        if header.exth.is_empty() {
+                            header.exth = exth;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if header.encryption != 0 {
+        return Err("该文件受 DRM 保护，无法导入".to_string());
+    }
+
+    // 元数据
+    let file_stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let title = header
+        .exth_text(503)
+        .into_iter()
+        .next()
+        .or_else(|| Some(header.decode(&header.full_name).trim().to_string()))
+        .filter(|title| !title.is_empty())
+        .or_else(|| Some(db.name().trim().to_string()))
+        .filter(|title| !title.is_empty())
+        .unwrap_or(file_stem);
+    let authors = header.exth_text(100);
+    let author = if authors.is_empty() {
+        "佚名".to_string()
+    } else {
+        authors.join("、")
+    };
+    let description = header
+        .exth_text(103)
+        .first()
+        .map(|text| parse_markup(text).text().trim().to_string())
+        .filter(|text| !text.is_empty())
+        .unwrap_or_else(|| "暂缺".to_string());
+
+    // 图片：从第一个资源记录开始，按资源序号命名
+    let mut images = Vec::new();
+    let mut image_names = HashMap::new();
+    if let Some(first) = header.first_resource {
+        for index in first..db.len() {
+            let Some(record) = db.record(index) else {
+                break;
+            };
+            if let Some(ext) = image_extension(record) {
+                let name = format!("image{:05}.{}", index - first + 1, ext);
+                image_names.insert(index - first, name.clone());
+                images.push(ImportedImage {
+                    name,
+                    data: record.to_vec(),
+                });
+            }
+        }
+    }
+    let cover = header
+        .exth_u32(201)
+        .filter(|offset| *offset != NULL_INDEX)
+        .and_then(|offset| header.first_resource.map(|first| first + offset as usize))
+        .and_then(|index| db.record(index))
+        .filter(|record| image_extension(record).is_some())
+        .map(|record| record.to_vec());
+
+    let text = read_text(&db, &header)?;
+    let chapters = if !header.is_mobi {
+        // 没有 MOBI 头的 PalmDOC 文本文件，没有记录编码，与 TXT 一样按内容检测
+        let (content, _) = decode_text(&text, None)?;
+        vec![ImportedChapter {
+            label: title.clone(),
+            href: String::new(),
+            content: content.replace("\r\n", "\n"),
+            children: Vec::new(),
+        }]
+    } else if header.version >= 8 {
+        kf8_chapters(&db, &header, text, &image_names, &title)?
+    } else {
+        mobi6_chapters(&db, &header, text, &image_names, &title)
+    };
+    if chapters.is_empty() {
+        return Err("文件中没有可读取的章节".to_string());
+    }
+
+    Ok(ImportedBook {
+        title,
+        author,
+        description,
+        chapters,
+        images,
+        cover,
+    })
+}
+
+// 导入 MOBI/AZW3 文件：在 Rust 中解析并在一个事务中写入书籍和章节，返回新书籍
+#[command]
+pub async fn import_mobi(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Book>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    let book = match read_mobi(Path::new(&path)) {
+        Ok(book) => book,
+        Err(err) => return Ok(DbResponse::error(err)),
+    };
+
+    let mut db = get_db_connection(&state)?;
+    match save_imported_book(&mut db, &app_dir, &book) {
+        Ok(book) => Ok(DbResponse::success(book)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::palmdoc_decompress;
+
+    // 测试用的简单 PalmDOC 压缩：依次尝试回溯引用、空格加字符和原样输出
+    fn palmdoc_compress(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < data.len() {
+            let mut best = (0, 0);
+            for distance in 1..=i.min(2047) {
+                let length = (0..10)
+                    .take_while(|k| i + k < data.len() && data[i + k - distance] == data[i + k])
+                    .count();
+                if length > best.1 {
+                    best = (distance, length);
+                }
+            }
+            if best.1 >= 3 {
+                let pair = 0x8000 | (best.0 << 3) | (best.1 - 3);
+                out.extend([(pair >> 8) as u8, pair as u8]);
+                i += best.1;
+            } else if data[i] == b' ' && data.get(i + 1).is_some_and(|c| (0x40..0x80).contains(c)) {
+                out.push(data[i + 1] ^ 0x80);
+                i += 2;
+            } else if data[i] == 0 || (0x09..0x80).contains(&data[i]) {
+                out.push(data[i]);
+                i += 1;
+            } else {
+                let end = (i + 8).min(data.len());
+                out.push((end - i) as u8);
+                out.extend_from_slice(&data[i..end]);
+                i = end;
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn decompresses_each_token_kind() {
+        let cases: [(&[u8], &[u8]); 4] = [
+            (b"abc", b"abc"),
+            (&[b'a', b'b', b'c', 0x80, 0x18], b"abcabc"),
+            (&[b'x', 0xC1, 0xE2], b"x A b"),
+            (&[2, 0xE4, 0xB8, b'!'], &[0xE4, 0xB8, b'!']),
+        ];
+        for (compressed, expected) in cases {
+            assert_eq!(palmdoc_decompress(compressed), expected);
+        }
+    }
+
+    #[test]
+    fn ignores_invalid_back_reference() {
+        assert_eq!(palmdoc_decompress(&[b'a', 0x80, 0x50, b'b']), b"ab");
+    }
+
+    #[test]
+    fn round_trips_text() {
+        let samples = [
+            "The quick brown fox jumps over the lazy dog. The quick brown fox again.",
+            "第一章 风起\r\n　　天下大势，分久必合，合久必分。天下大势，分久必合。",
+            "<p>Alpha beta</p><p>Alpha beta gamma</p>\0tail",
+            "",
+        ];
+        for sample in samples {
+            let data = sample.as_bytes();
+            assert_eq!(palmdoc_decompress(&palmdoc_compress(data)), data);
+        }
+        let long: Vec<u8> = (0..20_000u32).map(|n| (n * 7 % 251) as u8).collect();
+        assert_eq!(palmdoc_decompress(&palmdoc_compress(&long)), long);
+    }
+}
@@ -0,0 +1,351 @@
+// 章节编号检查与重新编号：识别标题中的阿拉伯数字和中文数字编号，报告跳号、重号和乱序，并可按模板统一改写标题
+use crate::database::{get_db_connection, DbResponse};
+use crate::headings::parse_label_number;
+use crate::revision::update_chapter_with_revision;
+use crate::setup::AppState;
+use crate::toc::{load_toc, sync_toc, TocItem};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{command, State};
+
+// 按目录顺序列出的章节及其编号
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NumberedChapter {
+    pub id: i64,
+    pub label: String,
+    // 目录层级，顶级为 0
+    pub depth: usize,
+    pub volume: bool,
+    pub number: Option<u64>,
+    // 去掉编号后的标题
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NumberingIssueKind {
+    // 跳过了编号
+    Gap,
+    // 与前一个编号相同
+    Duplicate,
+    // 比前面的编号小
+    OutOfOrder,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NumberingIssue {
+    pub chapter_id: i64,
+    pub label: String,
+    pub kind: NumberingIssueKind,
+    pub number: u64,
+    // 按顺序应有的编号
+    pub expected: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NumberingReport {
+    pub chapters: Vec<NumberedChapter>,
+    pub issues: Vec<NumberingIssue>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenumberOptions {
+    // 可用 {n}（阿拉伯数字）、{cn}（中文数字）和 {title}（去掉原编号后的标题），如「第{cn}章 {title}」
+    pub template: String,
+    // 第一个编号，默认为 1
+    #[serde(default)]
+    pub start: Option<u64>,
+    // 只改写这一层级的章节（顶级为 0），为空时不限层级
+    #[serde(default)]
+    pub depth: Option<usize>,
+    // 为 true 时改写卷（第X卷、Part N），否则改写章
+    #[serde(default)]
+    pub volumes: bool,
+    // 为 true 时没有编号的章节（前言、后记等）也参与编号
+    #[serde(default)]
+    pub include_unnumbered: bool,
+    // 为 true 时每个父章节下从 start 重新编号，否则全书连续编号
+    #[serde(default)]
+    pub restart_per_parent: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelChange {
+    pub id: i64,
+    pub old_label: String,
+    pub new_label: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenumberResult {
+    pub changes: Vec<LabelChange>,
+    pub toc: Vec<TocItem>,
+}
+
+const CHINESE_DIGITS: [char; 10] = ['零', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+
+// 四位以内的一节：一千零五、三百二十
+fn chinese_section(section: u64) -> String {
+    let mut text = String::new();
+    let mut zero = false;
+    for (digit, unit) in [
+        (section / 1000, "千"),
+        (section / 100 % 10, "百"),
+        (section / 10 % 10, "十"),
+        (section % 10, ""),
+    ] {
+        if digit == 0 {
+            zero = !text.is_empty();
+            continue;
+        }
+        if zero {
+            text.push('零');
+            zero = false;
+        }
+        text.push(CHINESE_DIGITS[digit as usize]);
+        text.push_str(unit);
+    }
+    text
+}
+
+// 数字转为中文小写数字：10 为「十」，105 为「一百零五」，10010 为「一万零一十」
+pub fn format_chinese_number(number: u64) -> String {
+    if number == 0 {
+        return "零".to_string();
+    }
+    let mut sections = Vec::new();
+    let mut rest = number;
+    while rest > 0 {
+        sections.push(rest % 10_000);
+        rest /= 10_000;
+    }
+
+    let units = ["", "万", "亿", "万亿", "亿亿"];
+    let mut text = String::new();
+    let mut zero = false;
+    for (index, section) in sections.iter().enumerate().rev() {
+        if *section == 0 {
+            zero = !text.is_empty();
+            continue;
+        }
+        if !text.is_empty() && (zero || *section < 1000) {
+            text.push('零');
+        }
+        zero = false;
+        text.push_str(&chinese_section(*section));
+        text.push_str(units[index]);
+    }
+    // 十几读作「十X」而不是「一十X」
+    match text.strip_prefix("一十") {
+        Some(rest) => format!("十{}", rest),
+        None => text,
+    }
+}
+
+// 按目录顺序展开：(章节, 层级, 父章节)
+fn flatten_toc<'a>(
+    items: &'a [TocItem],
+    depth: usize,
+    parent_id: Option<i64>,
+    result: &mut Vec<(&'a TocItem, usize, Option<i64>)>,
+) {
+    for item in items {
+        result.push((item, depth, parent_id));
+        if let Some(subitems) = &item.subitems {
+            flatten_toc(subitems, depth + 1, Some(item.href), result);
+        }
+    }
+}
+
+// 检查目录中的章节编号：同一父章节下的卷和章分别检查是否跳号、重号或乱序
+pub fn check_numbering(db: &Connection, book_id: i64) -> Result<NumberingReport, String> {
+    let toc = load_toc(db, book_id).map_err(|e| e.to_string())?;
+    let mut items = Vec::new();
+    flatten_toc(&toc, 0, None, &mut items);
+
+    let mut chapters = Vec::new();
+    let mut issues = Vec::new();
+    // (父章节, 是否为卷) 下已出现的最大编号
+    let mut previous: HashMap<(Option<i64>, bool), u64> = HashMap::new();
+    for (item, depth, parent_id) in items {
+        let parsed = parse_label_number(&item.label);
+        if let Some(parsed) = &parsed {
+            if let Some(last) = previous.get(&(parent_id, parsed.volume)).copied() {
+                let kind = if parsed.number == last {
+                    Some(NumberingIssueKind::Duplicate)
+                } else if parsed.number < last {
+                    Some(NumberingIssueKind::OutOfOrder)
+                } else if parsed.number > last + 1 {
+                    Some(NumberingIssueKind::Gap)
+                } else {
+                    None
+                };
+                if let Some(kind) = kind {
+                    issues.push(NumberingIssue {
+                        chapter_id: item.href,
+                        label: item.label.clone(),
+                        kind,
+                        number: parsed.number,
+                        expected: last + 1,
+                    });
+                }
+            }
+            let last = previous.entry((parent_id, parsed.volume)).or_insert(0);
+            *last = (*last).max(parsed.number);
+        }
+        chapters.push(NumberedChapter {
+            id: item.href,
+            label: item.label.clone(),
+            depth,
+            volume: parsed.as_ref().is_some_and(|parsed| parsed.volume),
+            number: parsed.as_ref().map(|parsed| parsed.number),
+            title: parsed
+                .map(|parsed| parsed.title)
+                .unwrap_or_else(|| item.label.clone()),
+        });
+    }
+    Ok(NumberingReport { chapters, issues })
+}
+
+// 按模板重新编号，dry_run 为 true 时只返回将要进行的修改；标题修改前保存修订记录，与编辑章节时一致
+pub fn renumber_book(
+    db: &Connection,
+    book_id: i64,
+    options: &RenumberOptions,
+    dry_run: bool,
+) -> Result<RenumberResult, String> {
+    if !options.template.contains("{n}") && !options.template.contains("{cn}") {
+        return Err("模板中需要包含 {n} 或 {cn}".to_string());
+    }
+    let toc = load_toc(db, book_id).map_err(|e| e.to_string())?;
+    let mut items = Vec::new();
+    flatten_toc(&toc, 0, None, &mut items);
+
+    let start = options.start.unwrap_or(1);
+    let mut counters: HashMap<Option<i64>, u64> = HashMap::new();
+    let mut changes = Vec::new();
+    for (item, depth, parent_id) in items {
+        if options.depth.is_some_and(|only| only != depth) {
+            continue;
+        }
+        let title = match parse_label_number(&item.label) {
+            Some(parsed) if parsed.volume == options.volumes => parsed.title,
+            Some(_) => continue,
+            None if options.include_unnumbered => item.label.trim().to_string(),
+            None => continue,
+        };
+        let key = if options.restart_per_parent {
+            parent_id
+        } else {
+            None
+        };
+        let counter = counters.entry(key).or_insert(start);
+        let number = *counter;
+        *counter += 1;
+
+        let new_label = options
+            .template
+            .replace("{n}", &number.to_string())
+            .replace("{cn}", &format_chinese_number(number))
+            .replace("{title}", &title)
+            .trim()
+            .to_string();
+        if new_label != item.label {
+            changes.push(LabelChange {
+                id: item.href,
+                old_label: item.label.clone(),
+                new_label,
+            });
+        }
+    }
+
+    if dry_run {
+        return Ok(RenumberResult { changes, toc });
+    }
+    for change in &changes {
+        update_chapter_with_revision(db, change.id, &change.new_label, None)
+            .map_err(|e| e.to_string())?;
+    }
+    let toc = sync_toc(db, book_id).map_err(|e| e.to_string())?;
+    Ok(RenumberResult { changes, toc })
+}
+
+// 检查书籍目录中的章节编号
+#[command]
+pub fn check_chapter_numbering(
+    book_id: i64,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<NumberingReport>, String> {
+    let db = get_db_connection(&state)?;
+    match check_numbering(&db, book_id) {
+        Ok(report) => Ok(DbResponse::success(report)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 按模板改写章节标题中的编号，在一个事务中完成
+#[command]
+pub fn renumber_chapters(
+    book_id: i64,
+    options: RenumberOptions,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<RenumberResult>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match renumber_book(&tx, book_id, &options, dry_run.unwrap_or(false)) {
+        Ok(result) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(result))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_chinese_number;
+    use crate::headings::parse_chinese_number;
+
+    #[test]
+    fn formats_chinese_numbers() {
+        let cases = [
+            (0, "零"),
+            (1, "一"),
+            (10, "十"),
+            (11, "十一"),
+            (20, "二十"),
+            (101, "一百零一"),
+            (110, "一百一十"),
+            (1001, "一千零一"),
+            (1010, "一千零一十"),
+            (10_010, "一万零一十"),
+            (100_000, "十万"),
+            (120_000_000, "一亿二千万"),
+            (123_456_789, "一亿二千三百四十五万六千七百八十九"),
+        ];
+        for (number, expected) in cases {
+            assert_eq!(format_chinese_number(number), expected, "{}", number);
+        }
+    }
+
+    #[test]
+    fn round_trips_chinese_numbers() {
+        for number in (1..=10_000).chain([100_001, 1_000_010, 20_300_000, 100_000_001]) {
+            assert_eq!(
+                parse_chinese_number(&format_chinese_number(number)),
+                Some(number),
+                "{}",
+                number
+            );
+        }
+    }
+}
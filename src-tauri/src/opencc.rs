@@ -0,0 +1,402 @@
+// 简繁转换：使用打包在资源目录中的 OpenCC 格式词典，按词组最长匹配转换
+use crate::database::{get_current_time_string, get_db_connection, Book, DbResponse};
+use crate::exporter::{load_export_book, ExportChapter};
+use crate::fileutil::cover_path;
+use crate::importer::{load_book_images, save_imported_book, ImportedBook, ImportedChapter};
+use crate::revision::update_chapter_with_revision;
+use crate::setup::AppState;
+use crate::textutil::map_text_segments;
+use crate::toc::sync_toc;
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::{command, AppHandle, Manager, State};
+
+// 转换方案，与 OpenCC 的配置同名
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConvertProfile {
+    // 简体到繁体
+    S2t,
+    // 繁体到简体
+    T2s,
+    // 简体到台湾正体，并转换为台湾常用词汇
+    S2tw,
+    // 简体到香港繁体
+    S2hk,
+    // 台湾正体到简体，并转换台湾常用词汇
+    Tw2sp,
+}
+
+// 词典文件，reverse 为 true 时键值互换使用
+struct DictSource {
+    file: &'static str,
+    reverse: bool,
+}
+
+const fn dict(file: &'static str) -> DictSource {
+    DictSource {
+        file,
+        reverse: false,
+    }
+}
+
+const fn reversed(file: &'static str) -> DictSource {
+    DictSource {
+        file,
+        reverse: true,
+    }
+}
+
+impl ConvertProfile {
+    // 依次执行的转换步骤，同一步骤中的词典一起参与最长匹配，长度相同时靠前的优先
+    fn stages(&self) -> Vec<Vec<DictSource>> {
+        let s2t = || vec![dict("STPhrases.txt"), dict("STCharacters.txt")];
+        let t2s = || vec![dict("TSPhrases.txt"), dict("TSCharacters.txt")];
+        match self {
+            ConvertProfile::S2t => vec![s2t()],
+            ConvertProfile::T2s => vec![t2s()],
+            ConvertProfile::S2tw => vec![
+                s2t(),
+                vec![dict("TWPhrases.txt")],
+                vec![dict("TWVariants.txt")],
+            ],
+            ConvertProfile::S2hk => vec![s2t(), vec![dict("HKVariants.txt")]],
+            ConvertProfile::Tw2sp => vec![
+                vec![
+                    reversed("TWPhrases.txt"),
+                    dict("TWVariantsRevPhrases.txt"),
+                    reversed("TWVariants.txt"),
+                ],
+                t2s(),
+            ],
+        }
+    }
+}
+
+// 一个词典：键为词组或单字，只使用第一个候选
+#[derive(Default)]
+struct Dictionary {
+    entries: HashMap<String, String>,
+    // 最长键的字数
+    max_len: usize,
+}
+
+impl Dictionary {
+    // 每行为「键<Tab>候选1 候选2 ...」，空行和格式不对的行跳过
+    fn parse(text: &str, reverse: bool) -> Self {
+        let mut dictionary = Dictionary::default();
+        for line in text.lines() {
+            let Some((key, values)) = line.split_once('\t') else {
+                continue;
+            };
+            let key = key.trim();
+            let mut values = values.split_whitespace();
+            let pairs: Vec<(&str, &str)> = if reverse {
+                values.map(|value| (value, key)).collect()
+            } else {
+                values
+                    .next()
+                    .map(|value| (key, value))
+                    .into_iter()
+                    .collect()
+            };
+            for (from, to) in pairs {
+                if from.is_empty() {
+                    continue;
+                }
+                dictionary.max_len = dictionary.max_len.max(from.chars().count());
+                dictionary
+                    .entries
+                    .entry(from.to_string())
+                    .or_insert_with(|| to.to_string());
+            }
+        }
+        dictionary
+    }
+}
+
+pub struct Converter {
+    stages: Vec<Vec<Dictionary>>,
+}
+
+impl Converter {
+    pub fn load(dir: &Path, profile: ConvertProfile) -> Result<Self, String> {
+        let mut stages = Vec::new();
+        for sources in profile.stages() {
+            let mut dictionaries = Vec::new();
+            for source in sources {
+                let text = fs::read_to_string(dir.join(source.file))
+                    .map_err(|e| format!("读取简繁转换词典 {} 失败: {}", source.file, e))?;
+                dictionaries.push(Dictionary::parse(&text, source.reverse));
+            }
+            stages.push(dictionaries);
+        }
+        Ok(Converter { stages })
+    }
+
+    pub fn convert(&self, text: &str) -> String {
+        self.stages
+            .iter()
+            .fold(text.to_string(), |text, stage| convert_stage(&text, stage))
+    }
+
+    // 只转换文本片段，章节内容中的标签原样保留
+    pub fn convert_content(&self, content: &str) -> String {
+        map_text_segments(content, |text| self.convert(text))
+    }
+}
+
+// 从左到右在各词典中找最长的匹配，没有匹配的字原样保留
+fn convert_stage(text: &str, dictionaries: &[Dictionary]) -> String {
+    let max_len = dictionaries
+        .iter()
+        .map(|dictionary| dictionary.max_len)
+        .max()
+        .unwrap_or(0);
+    if max_len == 0 {
+        return text.to_string();
+    }
+    let bounds: Vec<usize> = text
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let mut result = String::with_capacity(text.len());
+    let mut start = 0;
+    while start + 1 < bounds.len() {
+        let longest = (bounds.len() - 1 - start).min(max_len);
+        let matched = (1..=longest).rev().find_map(|len| {
+            let key = &text[bounds[start]..bounds[start + len]];
+            dictionaries
+                .iter()
+                .filter(|dictionary| dictionary.max_len >= len)
+                .find_map(|dictionary| dictionary.entries.get(key))
+                .map(|value| (len, value))
+        });
+        match matched {
+            Some((len, value)) => {
+                result.push_str(value);
+                start += len;
+            }
+            None => {
+                result.push_str(&text[bounds[start]..bounds[start + 1]]);
+                start += 1;
+            }
+        }
+    }
+    result
+}
+
+// 打包的词典位于资源目录的 opencc 子目录
+fn dictionary_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let resource_dir = app_handle
+        .path()
+        .resource_dir()
+        .map_err(|e| e.to_string())?;
+    Ok(resource_dir.join("opencc"))
+}
+
+// 已加载的转换器，每种方案只解析一次词典
+static CONVERTERS: OnceLock<Mutex<HashMap<ConvertProfile, Arc<Converter>>>> = OnceLock::new();
+
+fn load_converter(
+    app_handle: &AppHandle,
+    profile: ConvertProfile,
+) -> Result<Arc<Converter>, String> {
+    let cache = CONVERTERS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(converter) = cache.lock().map_err(|e| e.to_string())?.get(&profile) {
+        return Ok(converter.clone());
+    }
+    let dir = dictionary_dir(app_handle)?;
+    let converter = Arc::new(Converter::load(&dir, profile)?);
+    cache
+        .lock()
+        .map_err(|e| e.to_string())?
+        .entry(profile)
+        .or_insert(converter.clone());
+    Ok(converter)
+}
+
+// 原地转换：书名、作者、简介和全部章节的标题、内容，章节修改前保存历史版本
+fn convert_book_in_place(
+    db: &Connection,
+    book_id: i64,
+    converter: &Converter,
+) -> Result<Book, String> {
+    let (title, author, description): (String, String, String) = db
+        .query_row(
+            "SELECT title, author, description FROM ee_book WHERE id = ?",
+            params![book_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                ))
+            },
+        )
+        .map_err(|_| format!("书籍 {} 不存在", book_id))?;
+    let title = converter.convert(&title);
+    let author = converter.convert(&author);
+    let description = converter.convert(&description);
+    db.execute(
+        "UPDATE ee_book SET title = ?, author = ?, description = ?, updateTime = ? WHERE id = ?",
+        params![
+            title,
+            author,
+            description,
+            get_current_time_string(),
+            book_id
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let chapters: Vec<(i64, String, String)> = {
+        let mut stmt = db
+            .prepare("SELECT id, label, content FROM ee_chapter WHERE bookId = ? ORDER BY id")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![book_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    for (id, label, content) in chapters {
+        let new_label = converter.convert(&label);
+        let new_content = converter.convert_content(&content);
+        if new_label != label || new_content != content {
+            update_chapter_with_revision(db, id, &new_label, Some(&new_content))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let toc = sync_toc(db, book_id).map_err(|e| e.to_string())?;
+    Ok(Book {
+        id: book_id,
+        title,
+        author,
+        description,
+        toc: serde_json::to_string(&toc).unwrap_or_else(|_| "[]".to_string()),
+    })
+}
+
+fn convert_chapters(chapters: &[ExportChapter], converter: &Converter) -> Vec<ImportedChapter> {
+    chapters
+        .iter()
+        .map(|chapter| ImportedChapter {
+            label: converter.convert(&chapter.label),
+            href: String::new(),
+            content: converter.convert_content(&chapter.content),
+            children: convert_chapters(&chapter.children, converter),
+        })
+        .collect()
+}
+
+// 转换结果另存为新书，图片和封面一并复制，原书不变
+fn convert_book_as_new(
+    db: &mut Connection,
+    app_dir: &Path,
+    book_id: i64,
+    converter: &Converter,
+) -> Result<Book, String> {
+    let source = load_export_book(db, book_id)?;
+    let book = ImportedBook {
+        title: converter.convert(&source.title),
+        author: converter.convert(&source.author),
+        description: converter.convert(&source.description),
+        chapters: convert_chapters(&source.chapters, converter),
+        images: load_book_images(app_dir, book_id)?,
+        cover: fs::read(cover_path(app_dir, book_id)).ok(),
+    };
+    save_imported_book(db, app_dir, &book)
+}
+
+// 转换一段文字
+#[command]
+pub async fn convert_chinese(
+    text: String,
+    profile: ConvertProfile,
+    app_handle: AppHandle,
+) -> Result<DbResponse<String>, String> {
+    match load_converter(&app_handle, profile) {
+        Ok(converter) => Ok(DbResponse::success(converter.convert(&text))),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 简繁转换整本书：书名、作者、简介、目录和全部章节在一个事务中完成；as_new_book 为 true 时另存为新书
+#[command]
+pub async fn convert_book_chinese(
+    book_id: i64,
+    profile: ConvertProfile,
+    as_new_book: Option<bool>,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Book>, String> {
+    let converter = match load_converter(&app_handle, profile) {
+        Ok(converter) => converter,
+        Err(err) => return Ok(DbResponse::error(err)),
+    };
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    let mut db = get_db_connection(&state)?;
+    if as_new_book.unwrap_or(false) {
+        return match convert_book_as_new(&mut db, &app_dir, book_id, &converter) {
+            Ok(book) => Ok(DbResponse::success(book)),
+            Err(err) => Ok(DbResponse::error(err)),
+        };
+    }
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    match convert_book_in_place(&tx, book_id, &converter) {
+        Ok(book) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(book))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundled_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/opencc")
+    }
+
+    #[test]
+    fn converts_known_phrases() {
+        let cases = [
+            (ConvertProfile::S2t, "头发和发展", "頭髮和發展"),
+            (ConvertProfile::S2tw, "软件和头发", "軟體和頭髮"),
+            (ConvertProfile::T2s, "頭髮和發展", "头发和发展"),
+            (ConvertProfile::Tw2sp, "軟體和頭髮", "软件和头发"),
+        ];
+        for (profile, text, expected) in cases {
+            let converter = Converter::load(&bundled_dir(), profile).unwrap();
+            assert_eq!(converter.convert(text), expected, "{:?}", profile);
+        }
+    }
+
+    #[test]
+    fn keeps_tags_when_converting_content() {
+        let converter = Converter::load(&bundled_dir(), ConvertProfile::S2t).unwrap();
+        assert_eq!(
+            converter.convert_content("<p class=\"发\">头发</p>"),
+            "<p class=\"发\">頭髮</p>"
+        );
+    }
+}
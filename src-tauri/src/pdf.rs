@@ -0,0 +1,1439 @@
+use crate::database::{get_current_time_string, get_db_connection, DbResponse};
+use crate::exporter::{format_utc_time, load_export_book, ExportBook, ExportChapter};
+use crate::fileutil::{book_epub_dir, cover_path};
+use crate::font::{Font, Outlines};
+use crate::markup::{content_to_html, is_wide, parse_markup, Element, Node};
+use crate::setup::AppState;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
+use std::path::Path;
+use tauri::{command, AppHandle, Manager, State};
+
+// 毫米转换为 PDF 的点（1/72 英寸）
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+// 图片按 96 DPI 换算为点
+const POINTS_PER_PIXEL: f64 = 0.75;
+// 不能出现在行首的标点，排版时悬挂在上一行末尾
+const NO_LINE_START: &str = "，。、；：！？）》」』】〕〉”’…—·,.;:!?)]}%";
+// 不能出现在行尾的标点，排版时移到下一行
+const NO_LINE_END: &str = "（《「『【〔〈“‘([{";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PdfExportOptions {
+    // 嵌入的字体文件（TTF/OTF/TTC），需要包含书中文字的字形
+    pub font_path: String,
+    // 纸张：A4、A5、B5、Letter，其他值使用 page_width 和 page_height（毫米）
+    pub page_size: String,
+    pub page_width: f64,
+    pub page_height: f64,
+    // 页边距（毫米）
+    pub margin_top: f64,
+    pub margin_bottom: f64,
+    pub margin_left: f64,
+    pub margin_right: f64,
+    // 正文字号（点）和行距（字号的倍数）
+    pub font_size: f64,
+    pub line_height: f64,
+}
+
+impl Default for PdfExportOptions {
+    fn default() -> Self {
+        PdfExportOptions {
+            font_path: String::new(),
+            page_size: "A4".to_string(),
+            page_width: 210.0,
+            page_height: 297.0,
+            margin_top: 20.0,
+            margin_bottom: 20.0,
+            margin_left: 18.0,
+            margin_right: 18.0,
+            font_size: 12.0,
+            line_height: 1.8,
+        }
+    }
+}
+
+impl PdfExportOptions {
+    // 页面尺寸（点）
+    fn page_size(&self) -> (f64, f64) {
+        let (width, height) = match self.page_size.to_ascii_uppercase().as_str() {
+            "A4" => (210.0, 297.0),
+            "A5" => (148.0, 210.0),
+            "B5" => (176.0, 250.0),
+            "LETTER" => (215.9, 279.4),
+            _ => (self.page_width, self.page_height),
+        };
+        (width * POINTS_PER_MM, height * POINTS_PER_MM)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.font_path.trim().is_empty() {
+            return Err("请选择要嵌入的字体文件".to_string());
+        }
+        if !(6.0..=72.0).contains(&self.font_size) {
+            return Err("字号应在 6 到 72 之间".to_string());
+        }
+        if !(1.0..=4.0).contains(&self.line_height) {
+            return Err("行距应在 1 到 4 倍之间".to_string());
+        }
+        let margins = [
+            self.margin_top,
+            self.margin_bottom,
+            self.margin_left,
+            self.margin_right,
+        ];
+        if margins
+            .iter()
+            .any(|margin| !margin.is_finite() || *margin < 0.0)
+        {
+            return Err("页边距不能为负数".to_string());
+        }
+        let (width, height) = self.page_size();
+        let content_width = width - (self.margin_left + self.margin_right) * POINTS_PER_MM;
+        let content_height = height - (self.margin_top + self.margin_bottom) * POINTS_PER_MM;
+        if !width.is_finite() || !height.is_finite() || width <= 0.0 || height <= 0.0 {
+            return Err("纸张尺寸无效".to_string());
+        }
+        if content_width < self.font_size * 4.0 || content_height < self.font_size * 4.0 {
+            return Err("页边距过大，版心容纳不下正文".to_string());
+        }
+        Ok(())
+    }
+}
+
+// 数值写入 PDF 时最多保留两位小数
+fn num(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+// PDF 文本字符串：UTF-16BE 加 BOM 的十六进制形式
+fn pdf_text(text: &str) -> String {
+    let mut hex = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        hex.push_str(&format!("{:04X}", unit));
+    }
+    hex.push('>');
+    hex
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // 写入内存不会失败
+    let _ = encoder.write_all(data);
+    encoder.finish().unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct TextStyle {
+    bold: bool,
+    italic: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Glyph {
+    code: u16,
+    // 字号为 1 时的宽度
+    width: f64,
+    ch: char,
+    style: TextStyle,
+}
+
+// 嵌入字体的使用情况：用到的字形和它们对应的文字
+struct FontUsage {
+    font: Font,
+    glyphs: BTreeSet<u16>,
+    // PDF 编码到文字的映射，写入 ToUnicode
+    unicode: BTreeMap<u16, char>,
+    cache: HashMap<char, (u16, u16, f64)>,
+    total: usize,
+    missing: usize,
+}
+
+impl FontUsage {
+    fn new(font: Font) -> Self {
+        FontUsage {
+            font,
+            glyphs: BTreeSet::new(),
+            unicode: BTreeMap::new(),
+            cache: HashMap::new(),
+            total: 0,
+            missing: 0,
+        }
+    }
+
+    fn glyph(&mut self, ch: char, style: TextStyle) -> Glyph {
+        let ch = match ch {
+            '\t' | '\u{a0}' => ' ',
+            ch => ch,
+        };
+        let (glyph, code, width) = *self.cache.entry(ch).or_insert_with(|| {
+            let glyph = self.font.glyph_id(ch);
+            let width = self.font.advance(glyph) as f64 / self.font.units_per_em as f64;
+            (glyph, self.font.code(glyph), width)
+        });
+        if !ch.is_whitespace() {
+            self.total += 1;
+            if glyph == 0 {
+                self.missing += 1;
+            }
+        }
+        self.glyphs.insert(glyph);
+        if glyph != 0 {
+            self.unicode.entry(code).or_insert(ch);
+        }
+        Glyph {
+            code,
+            width,
+            ch,
+            style,
+        }
+    }
+
+    fn shape(&mut self, runs: &[(String, TextStyle)]) -> Vec<Glyph> {
+        let mut glyphs = Vec::new();
+        for (text, style) in runs {
+            for ch in text.chars().filter(|ch| !ch.is_control() || *ch == '\t') {
+                glyphs.push(self.glyph(ch, *style));
+            }
+        }
+        glyphs
+    }
+}
+
+// 可以在 index 之前换行：中文字符前后、空格之后，且不违反行首行尾禁则
+fn can_break(glyphs: &[Glyph], index: usize) -> bool {
+    let previous = glyphs[index - 1].ch;
+    let current = glyphs[index].ch;
+    if NO_LINE_START.contains(current) || NO_LINE_END.contains(previous) {
+        return false;
+    }
+    previous == ' ' || is_wide(previous) || is_wide(current)
+}
+
+// 两端对齐时可以加宽的位置：空格之后和中文字符前后
+fn stretchable(glyphs: &[Glyph], index: usize) -> bool {
+    index + 1 < glyphs.len()
+        && (glyphs[index].ch == ' ' || is_wide(glyphs[index].ch) || is_wide(glyphs[index + 1].ch))
+}
+
+// 按版心宽度断行，返回每行的字形范围；行首禁则的标点悬挂在行尾
+fn break_lines(
+    glyphs: &[Glyph],
+    size: f64,
+    first_width: f64,
+    width: f64,
+) -> Vec<std::ops::Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while start < glyphs.len() {
+        while start < glyphs.len() && glyphs[start].ch == ' ' && !lines.is_empty() {
+            start += 1;
+        }
+        if start >= glyphs.len() {
+            break;
+        }
+        let available = if lines.is_empty() { first_width } else { width };
+        let mut x = 0.0;
+        let mut end = start;
+        let mut last_break = None;
+        while end < glyphs.len() {
+            if end > start && can_break(glyphs, end) {
+                last_break = Some(end);
+            }
+            let advance = glyphs[end].width * size;
+            if end > start && x + advance > available + 0.01 && glyphs[end].ch != ' ' {
+                break;
+            }
+            x += advance;
+            end += 1;
+        }
+        if end < glyphs.len() {
+            let mut hanging = 0;
+            while end < glyphs.len() && hanging < 2 && NO_LINE_START.contains(glyphs[end].ch) {
+                end += 1;
+                hanging += 1;
+            }
+            if hanging == 0 {
+                if let Some(position) = last_break.filter(|position| *position > start) {
+                    end = position;
+                }
+            }
+        }
+        lines.push(start..end);
+        start = end;
+    }
+    lines
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    Justify,
+    Center,
+}
+
+// 页面内容流和用到的图片
+#[derive(Default)]
+struct Page {
+    content: String,
+    images: BTreeSet<usize>,
+}
+
+// 嵌入的图片 XObject
+struct PdfImage {
+    width: u32,
+    height: u32,
+    // 不含 Length 的字典内容
+    dict: String,
+    data: Vec<u8>,
+    mask: Option<Box<PdfImage>>,
+}
+
+fn jpeg_image(data: &[u8]) -> Option<PdfImage> {
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            pos += 1;
+            continue;
+        }
+        let marker = data[pos + 1];
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let height = u16::from_be_bytes([*data.get(pos + 5)?, *data.get(pos + 6)?]);
+            let width = u16::from_be_bytes([*data.get(pos + 7)?, *data.get(pos + 8)?]);
+            let components = *data.get(pos + 9)?;
+            let color_space = match components {
+                1 => "/DeviceGray",
+                3 => "/DeviceRGB",
+                // Adobe 的 CMYK JPEG 通常是反相保存的
+                4 => "/DeviceCMYK /Decode [1 0 1 0 1 0 1 0]",
+                _ => return None,
+            };
+            return Some(PdfImage {
+                width: width as u32,
+                height: height as u32,
+                dict: format!(
+                    "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent 8 /Filter /DCTDecode",
+                    width, height, color_space
+                ),
+                data: data.to_vec(),
+                mask: None,
+            });
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+// 还原 PNG 扫描行的过滤
+fn unfilter(data: &[u8], width: usize, height: usize, bpp: usize) -> Option<Vec<u8>> {
+    let stride = width * bpp;
+    let mut out = vec![0u8; stride * height];
+    for row in 0..height {
+        let line = data.get(row * (stride + 1)..(row + 1) * (stride + 1))?;
+        let (filter, line) = (line[0], &line[1..]);
+        for i in 0..stride {
+            let a = if i >= bpp {
+                out[row * stride + i - bpp]
+            } else {
+                0
+            };
+            let b = if row > 0 {
+                out[(row - 1) * stride + i]
+            } else {
+                0
+            };
+            let c = if row > 0 && i >= bpp {
+                out[(row - 1) * stride + i - bpp]
+            } else {
+                0
+            };
+            let value = match filter {
+                0 => line[i],
+                1 => line[i].wrapping_add(a),
+                2 => line[i].wrapping_add(b),
+                3 => line[i].wrapping_add(((a as u16 + b as u16) / 2) as u8),
+                4 => line[i].wrapping_add(paeth(a, b, c)),
+                _ => return None,
+            };
+            out[row * stride + i] = value;
+        }
+    }
+    Some(out)
+}
+
+fn png_image(data: &[u8]) -> Option<PdfImage> {
+    let mut pos = 8;
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut compressed = Vec::new();
+    while pos + 8 <= data.len() {
+        let length = u32::from_be_bytes(data[pos..pos + 4].try_into().ok()?) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        let body = data.get(pos + 8..pos + 8 + length)?;
+        match kind {
+            b"IHDR" if length >= 13 => header = Some(body.to_vec()),
+            b"PLTE" => palette = body.to_vec(),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+        pos += 12 + length;
+    }
+    let header = header?;
+    let width = u32::from_be_bytes(header[0..4].try_into().ok()?);
+    let height = u32::from_be_bytes(header[4..8].try_into().ok()?);
+    let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+    // 隔行扫描的图片不支持
+    if interlace != 0 || width == 0 || height == 0 {
+        return None;
+    }
+
+    let (colors, color_space) = match color_type {
+        0 | 4 => (1, "/DeviceGray".to_string()),
+        2 | 6 => (3, "/DeviceRGB".to_string()),
+        3 if !palette.is_empty() => {
+            let hex: String = palette.iter().map(|b| format!("{:02X}", b)).collect();
+            (
+                1,
+                format!("[/Indexed /DeviceRGB {} <{}>]", palette.len() / 3 - 1, hex),
+            )
+        }
+        _ => return None,
+    };
+
+    // 没有透明通道时直接使用 PNG 的压缩数据
+    if matches!(color_type, 0 | 2 | 3) {
+        return Some(PdfImage {
+            width,
+            height,
+            dict: format!(
+                "/Type /XObject /Subtype /Image /Width {w} /Height {h} /ColorSpace {cs} /BitsPerComponent {d} /Filter /FlateDecode /DecodeParms << /Predictor 15 /Colors {c} /BitsPerComponent {d} /Columns {w} >>",
+                w = width,
+                h = height,
+                cs = color_space,
+                d = depth,
+                c = colors
+            ),
+            data: compressed,
+            mask: None,
+        });
+    }
+
+    // 带透明通道的图片拆分为颜色和软蒙版
+    if depth != 8 {
+        return None;
+    }
+    let mut raw = Vec::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .read_to_end(&mut raw)
+        .ok()?;
+    let channels = colors + 1;
+    let pixels = unfilter(&raw, width as usize, height as usize, channels)?;
+    let mut color = Vec::with_capacity(pixels.len() / channels * colors);
+    let mut alpha = Vec::with_capacity(pixels.len() / channels);
+    for pixel in pixels.chunks_exact(channels) {
+        color.extend_from_slice(&pixel[..colors]);
+        alpha.push(pixel[colors]);
+    }
+    let image = |color_space: &str, data: Vec<u8>| {
+        PdfImage {
+        width,
+        height,
+        dict: format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent 8 /Filter /FlateDecode",
+            width, height, color_space
+        ),
+        data: deflate(&data),
+        mask: None,
+    }
+    };
+    let mut result = image(&color_space, color);
+    result.mask = Some(Box::new(image("/DeviceGray", alpha)));
+    Some(result)
+}
+
+// 支持 JPEG 和 PNG，其他格式的图片在 PDF 中省略
+fn load_image(data: &[u8]) -> Option<PdfImage> {
+    if data.starts_with(&[0xFF, 0xD8]) {
+        jpeg_image(data)
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_image(data)
+    } else {
+        None
+    }
+}
+
+// 章节内容中的块
+enum Block {
+    Paragraph(Vec<(String, TextStyle)>),
+    Heading(usize, Vec<(String, TextStyle)>),
+    Image(String),
+}
+
+fn collect_runs(
+    element: &Element,
+    style: TextStyle,
+    runs: &mut Vec<(String, TextStyle)>,
+    blocks: &mut Vec<Block>,
+    heading: Option<usize>,
+) {
+    for node in &element.children {
+        match node {
+            Node::Text(text) => runs.push((text.clone(), style)),
+            Node::Element(child) => match child.name.as_str() {
+                "img" => {
+                    // 图片单独成块，前后的文字分为两段
+                    if let Some(name) = child
+                        .attr("src")
+                        .and_then(|src| src.strip_prefix("images/"))
+                    {
+                        flush_runs(runs, blocks, heading);
+                        blocks.push(Block::Image(name.to_string()));
+                    }
+                }
+                "b" | "strong" => collect_runs(
+                    child,
+                    TextStyle {
+                        bold: true,
+                        ..style
+                    },
+                    runs,
+                    blocks,
+                    heading,
+                ),
+                "i" | "em" => collect_runs(
+                    child,
+                    TextStyle {
+                        italic: true,
+                        ..style
+                    },
+                    runs,
+                    blocks,
+                    heading,
+                ),
+                _ => collect_runs(child, style, runs, blocks, heading),
+            },
+        }
+    }
+}
+
+fn flush_runs(
+    runs: &mut Vec<(String, TextStyle)>,
+    blocks: &mut Vec<Block>,
+    heading: Option<usize>,
+) {
+    if runs.iter().all(|(text, _)| text.trim().is_empty()) {
+        runs.clear();
+        return;
+    }
+    let runs = std::mem::take(runs);
+    blocks.push(match heading {
+        Some(level) => Block::Heading(level, runs),
+        None => Block::Paragraph(runs),
+    });
+}
+
+// 章节内容转换为块；与章节标题相同的开头标题不重复输出
+fn content_blocks(content: &str, label: &str) -> Vec<Block> {
+    let html = parse_markup(&content_to_html(content));
+    let mut blocks = Vec::new();
+    for (index, element) in html.elements().enumerate() {
+        let name = element.name.as_str();
+        let mut runs = Vec::new();
+        match name {
+            "p" => {
+                collect_runs(element, TextStyle::default(), &mut runs, &mut blocks, None);
+                flush_runs(&mut runs, &mut blocks, None);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if index == 0 && element.text().trim() == label.trim() {
+                    continue;
+                }
+                let level = name[1..].parse::<usize>().unwrap_or(3);
+                let heading = Some(level);
+                let style = TextStyle {
+                    bold: true,
+                    ..TextStyle::default()
+                };
+                collect_runs(element, style, &mut runs, &mut blocks, heading);
+                flush_runs(&mut runs, &mut blocks, heading);
+            }
+            "ul" | "ol" => {
+                for (number, item) in element.children_named("li").enumerate() {
+                    let prefix = if name == "ol" {
+                        format!("{}. ", number + 1)
+                    } else {
+                        "• ".to_string()
+                    };
+                    let mut runs = vec![(prefix, TextStyle::default())];
+                    collect_runs(item, TextStyle::default(), &mut runs, &mut blocks, None);
+                    flush_runs(&mut runs, &mut blocks, None);
+                }
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+// 排版状态：当前页面和纵向位置（从页面顶端算起）
+struct Layout<'a> {
+    options: &'a PdfExportOptions,
+    font: FontUsage,
+    page_width: f64,
+    page_height: f64,
+    left: f64,
+    top: f64,
+    bottom: f64,
+    content_width: f64,
+    ascent: f64,
+    pages: Vec<Page>,
+    y: f64,
+    images: Vec<PdfImage>,
+    image_ids: HashMap<String, Option<usize>>,
+    images_dir: std::path::PathBuf,
+    // 章节 id 对应的页面序号和顶端位置（PDF 坐标）
+    destinations: HashMap<i64, (usize, f64)>,
+}
+
+impl Layout<'_> {
+    fn new_page(&mut self) {
+        self.pages.push(Page::default());
+        self.y = self.top;
+    }
+
+    fn page_is_empty(&self) -> bool {
+        self.pages.last().is_none_or(|page| page.content.is_empty())
+    }
+
+    // 剩余空间不足时换页
+    fn ensure(&mut self, height: f64) {
+        if self.pages.is_empty()
+            || (self.y + height > self.page_height - self.bottom && !self.page_is_empty())
+        {
+            self.new_page();
+        }
+    }
+
+    fn page(&mut self) -> &mut Page {
+        if self.pages.is_empty() {
+            self.new_page();
+        }
+        self.pages.last_mut().expect("至少有一页")
+    }
+
+    // spacing 为两端对齐时在词间和中文字间增加的间距
+    fn draw_line(&mut self, glyphs: &[Glyph], x: f64, baseline: f64, size: f64, spacing: f64) {
+        let mut ops = format!("BT\n/F1 {} Tf\n", num(size));
+        let y = self.page_height - baseline;
+        let adjust = num(-spacing * 1000.0 / size);
+        let mut x = x;
+        let mut start = 0;
+        while start < glyphs.len() {
+            let style = glyphs[start].style;
+            let end = glyphs[start..]
+                .iter()
+                .position(|glyph| glyph.style != style)
+                .map_or(glyphs.len(), |offset| start + offset);
+            // 粗体用描边加粗，斜体用倾斜的文字矩阵模拟
+            if style.bold {
+                ops.push_str(&format!("2 Tr {} w\n", num(size * 0.03)));
+            } else {
+                ops.push_str("0 Tr\n");
+            }
+            let skew = if style.italic { "0.21" } else { "0" };
+            ops.push_str(&format!("1 0 {} 1 {} {} Tm\n[<", skew, num(x), num(y)));
+            for index in start..end {
+                ops.push_str(&format!("{:04X}", glyphs[index].code));
+                x += glyphs[index].width * size;
+                if spacing > 0.0 && stretchable(glyphs, index) {
+                    x += spacing;
+                    ops.push_str(&format!("> {} <", adjust));
+                }
+            }
+            ops.push_str(">] TJ\n");
+            start = end;
+        }
+        ops.push_str("ET\n");
+        self.page().content.push_str(&ops);
+    }
+
+    fn text_block(&mut self, runs: &[(String, TextStyle)], size: f64, align: Align, indent: f64) {
+        let glyphs = self.font.shape(runs);
+        let line_height = size * self.options.line_height;
+        let lines = break_lines(
+            &glyphs,
+            size,
+            self.content_width - indent,
+            self.content_width,
+        );
+        let count = lines.len();
+        for (index, range) in lines.into_iter().enumerate() {
+            let mut line = &glyphs[range];
+            while line.last().is_some_and(|glyph| glyph.ch == ' ') {
+                line = &line[..line.len() - 1];
+            }
+            self.ensure(line_height);
+            let first_indent = if index == 0 { indent } else { 0.0 };
+            let width: f64 = line.iter().map(|glyph| glyph.width * size).sum();
+            let available = self.content_width - first_indent;
+            let (x, spacing) = match align {
+                Align::Center => (self.left + ((available - width) / 2.0).max(0.0), 0.0),
+                // 除段落最后一行外两端对齐，间距过大时保持左对齐
+                Align::Justify if index + 1 < count && width < available => {
+                    let gaps = (0..line.len())
+                        .filter(|index| stretchable(line, *index))
+                        .count();
+                    let spacing = match gaps {
+                        0 => 0.0,
+                        gaps => (available - width) / gaps as f64,
+                    };
+                    let spacing = if spacing <= size { spacing } else { 0.0 };
+                    (self.left + first_indent, spacing)
+                }
+                Align::Justify => (self.left + first_indent, 0.0),
+            };
+            let baseline = self.y + (line_height - size) / 2.0 + size * self.ascent;
+            self.draw_line(line, x, baseline, size, spacing);
+            self.y += line_height;
+        }
+    }
+
+    fn image_id(&mut self, name: &str) -> Option<usize> {
+        if let Some(id) = self.image_ids.get(name) {
+            return *id;
+        }
+        let id = (!name.contains(['/', '\\']))
+            .then(|| fs::read(self.images_dir.join(name)).ok())
+            .flatten()
+            .and_then(|data| load_image(&data))
+            .map(|image| {
+                self.images.push(image);
+                self.images.len() - 1
+            });
+        self.image_ids.insert(name.to_string(), id);
+        id
+    }
+
+    // 图片按 96 DPI 显示，超出版心时等比缩小，居中放置
+    fn image(&mut self, name: &str) {
+        let Some(id) = self.image_id(name) else {
+            return;
+        };
+        let image = &self.images[id];
+        let max_height = self.page_height - self.top - self.bottom;
+        let mut width = image.width as f64 * POINTS_PER_PIXEL;
+        let mut height = image.height as f64 * POINTS_PER_PIXEL;
+        let scale = (self.content_width / width)
+            .min(max_height / height)
+            .min(1.0);
+        width *= scale;
+        height *= scale;
+
+        let gap = self.options.font_size * 0.5;
+        self.ensure(height + gap * 2.0);
+        self.y += gap;
+        let x = self.left + (self.content_width - width) / 2.0;
+        let y = self.page_height - self.y - height;
+        let page = self.page();
+        page.images.insert(id);
+        page.content.push_str(&format!(
+            "q\n{} 0 0 {} {} {} cm\n/Im{} Do\nQ\n",
+            num(width),
+            num(height),
+            num(x),
+            num(y),
+            id + 1
+        ));
+        self.y += height + gap;
+    }
+
+    // 封面单独一页，等比缩放铺满页面
+    fn cover(&mut self, data: &[u8]) {
+        let Some(image) = load_image(data) else {
+            return;
+        };
+        let scale =
+            (self.page_width / image.width as f64).min(self.page_height / image.height as f64);
+        let width = image.width as f64 * scale;
+        let height = image.height as f64 * scale;
+        self.images.push(image);
+        let id = self.images.len() - 1;
+        self.new_page();
+        let x = (self.page_width - width) / 2.0;
+        let y = (self.page_height - height) / 2.0;
+        let page = self.page();
+        page.images.insert(id);
+        page.content.push_str(&format!(
+            "q\n{} 0 0 {} {} {} cm\n/Im{} Do\nQ\n",
+            num(width),
+            num(height),
+            num(x),
+            num(y),
+            id + 1
+        ));
+    }
+
+    // 没有封面时的书名页
+    fn title_page(&mut self, book: &ExportBook) {
+        let size = self.options.font_size;
+        self.new_page();
+        self.y = self.page_height * 0.3;
+        let bold = TextStyle {
+            bold: true,
+            ..TextStyle::default()
+        };
+        self.text_block(
+            &[(book.title.clone(), bold)],
+            size * 2.0,
+            Align::Center,
+            0.0,
+        );
+        self.y += size * 2.0;
+        self.text_block(
+            &[(book.author.clone(), TextStyle::default())],
+            size * 1.2,
+            Align::Center,
+            0.0,
+        );
+    }
+
+    fn chapter(&mut self, chapter: &ExportChapter, depth: usize) {
+        let size = self.options.font_size;
+        // 顶级章节另起一页
+        if depth == 0 || self.page_is_empty() {
+            self.new_page();
+        } else {
+            self.ensure(size * 6.0);
+            self.y += size;
+        }
+        let destination = (self.pages.len() - 1, self.page_height - self.y);
+        self.destinations.insert(chapter.id, destination);
+
+        let scale = match depth {
+            0 => 1.6,
+            1 => 1.4,
+            _ => 1.2,
+        };
+        let bold = TextStyle {
+            bold: true,
+            ..TextStyle::default()
+        };
+        self.text_block(
+            &[(chapter.label.clone(), bold)],
+            size * scale,
+            Align::Center,
+            0.0,
+        );
+        self.y += size;
+
+        for block in content_blocks(&chapter.content, &chapter.label) {
+            match block {
+                Block::Paragraph(runs) => {
+                    let runs = trim_runs(runs);
+                    // 内容自带全角空格缩进时不再额外缩进
+                    let indented = runs
+                        .first()
+                        .is_some_and(|(text, _)| text.starts_with('\u{3000}'));
+                    let indent = if indented { 0.0 } else { size * 2.0 };
+                    self.text_block(&runs, size, Align::Justify, indent);
+                }
+                Block::Heading(level, runs) => {
+                    let scale = if level <= 2 { 1.3 } else { 1.15 };
+                    self.ensure(size * scale * self.options.line_height * 3.0);
+                    self.y += size * 0.5;
+                    self.text_block(&trim_runs(runs), size * scale, Align::Center, 0.0);
+                    self.y += size * 0.5;
+                }
+                Block::Image(name) => self.image(&name),
+            }
+        }
+    }
+
+    // 页脚页码，封面页不编号
+    fn page_numbers(&mut self, skip: usize) {
+        let size = self.options.font_size * 0.75;
+        for index in skip..self.pages.len() {
+            let text = (index + 1 - skip).to_string();
+            let glyphs = self.font.shape(&[(text, TextStyle::default())]);
+            let width: f64 = glyphs.iter().map(|glyph| glyph.width * size).sum();
+            let x = (self.page_width - width) / 2.0;
+            let baseline = self.page_height - self.bottom / 2.0 + size / 2.0;
+            let last = self.pages.len() - 1;
+            // draw_line 总是写入最后一页，这里临时交换
+            self.pages.swap(index, last);
+            self.draw_line(&glyphs, x, baseline, size, 0.0);
+            self.pages.swap(index, last);
+        }
+    }
+}
+
+// 去掉段落首尾的空白，换行折叠为空格
+fn trim_runs(runs: Vec<(String, TextStyle)>) -> Vec<(String, TextStyle)> {
+    let mut runs: Vec<(String, TextStyle)> = runs
+        .into_iter()
+        .map(|(text, style)| (text.replace(['\r', '\n'], " "), style))
+        .collect();
+    if let Some((text, _)) = runs.first_mut() {
+        *text = text.trim_start_matches([' ', '\t']).to_string();
+    }
+    if let Some((text, _)) = runs.last_mut() {
+        *text = text.trim_end().to_string();
+    }
+    runs
+}
+
+// 逐个写入对象并记录偏移，最后生成交叉引用表
+struct PdfWriter {
+    buffer: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        PdfWriter {
+            buffer: b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec(),
+            offsets: Vec::new(),
+        }
+    }
+
+    // 预留对象编号
+    fn reserve(&mut self) -> usize {
+        self.offsets.push(0);
+        self.offsets.len()
+    }
+
+    fn object(&mut self, id: usize, body: &str) {
+        self.offsets[id - 1] = self.buffer.len();
+        self.buffer
+            .extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", id, body).as_bytes());
+    }
+
+    fn stream(&mut self, id: usize, dict: &str, data: &[u8]) {
+        self.offsets[id - 1] = self.buffer.len();
+        self.buffer.extend_from_slice(
+            format!(
+                "{} 0 obj\n<< {} /Length {} >>\nstream\n",
+                id,
+                dict,
+                data.len()
+            )
+            .as_bytes(),
+        );
+        self.buffer.extend_from_slice(data);
+        self.buffer.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn finish(mut self, root: usize, info: usize) -> Vec<u8> {
+        let xref = self.buffer.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1,
+            root,
+            info,
+            xref
+        ));
+        self.buffer.extend_from_slice(table.as_bytes());
+        self.buffer
+    }
+}
+
+fn write_image(writer: &mut PdfWriter, image: &PdfImage) -> usize {
+    let mask = image.mask.as_ref().map(|mask| write_image(writer, mask));
+    let id = writer.reserve();
+    let dict = match mask {
+        Some(mask) => format!("{} /SMask {} 0 R", image.dict, mask),
+        None => image.dict.clone(),
+    };
+    writer.stream(id, &dict, &image.data);
+    id
+}
+
+// 嵌入字体：Type0 复合字体，编码为 Identity-H
+fn write_font(writer: &mut PdfWriter, usage: &FontUsage) -> Result<usize, String> {
+    let font = &usage.font;
+    let subset = font.subset(&usage.glyphs)?;
+
+    // 子集字体名前加 6 个大写字母的标记
+    let mut hasher = DefaultHasher::new();
+    usage.glyphs.hash(&mut hasher);
+    let mut hash = hasher.finish();
+    let mut tag = String::new();
+    for _ in 0..6 {
+        tag.push((b'A' + (hash % 26) as u8) as char);
+        hash /= 26;
+    }
+    let name = if font.postscript_name.is_empty() {
+        "EmbeddedFont"
+    } else {
+        font.postscript_name.as_str()
+    };
+    let base_font = format!("{}+{}", tag, name);
+
+    let units = font.units_per_em as f64;
+    let scale = |value: i16| (value as f64 * 1000.0 / units).round() as i64;
+
+    let file_id = writer.reserve();
+    let (file_key, subtype) = match font.outlines {
+        Outlines::TrueType => {
+            writer.stream(
+                file_id,
+                &format!("/Filter /FlateDecode /Length1 {}", subset.len()),
+                &deflate(&subset),
+            );
+            ("FontFile2", "CIDFontType2")
+        }
+        Outlines::Cff => {
+            writer.stream(
+                file_id,
+                "/Subtype /CIDFontType0C /Filter /FlateDecode",
+                &deflate(&subset),
+            );
+            ("FontFile3", "CIDFontType0")
+        }
+    };
+
+    let descriptor = writer.reserve();
+    writer.object(
+        descriptor,
+        &format!(
+            "<< /Type /FontDescriptor /FontName /{} /Flags 4 /FontBBox [{} {} {} {}] /ItalicAngle 0 /Ascent {} /Descent {} /CapHeight {} /StemV 80 /{} {} 0 R >>",
+            base_font,
+            scale(font.bbox[0]),
+            scale(font.bbox[1]),
+            scale(font.bbox[2]),
+            scale(font.bbox[3]),
+            scale(font.ascent),
+            scale(font.descent),
+            scale(font.cap_height),
+            file_key,
+            file_id
+        ),
+    );
+
+    // 字宽表：按编码排序，连续的编码写在同一个数组中
+    let mut widths: BTreeMap<u16, i64> = BTreeMap::new();
+    for glyph in &usage.glyphs {
+        let width = (font.advance(*glyph) as f64 * 1000.0 / units).round() as i64;
+        widths.insert(font.code(*glyph), width);
+    }
+    let mut w = String::new();
+    let mut previous: Option<u16> = None;
+    for (code, width) in &widths {
+        if previous.is_some_and(|previous| previous + 1 == *code) {
+            w.push_str(&format!(" {}", width));
+        } else {
+            if previous.is_some() {
+                w.push_str("] ");
+            }
+            w.push_str(&format!("{} [{}", code, width));
+        }
+        previous = Some(*code);
+    }
+    if previous.is_some() {
+        w.push(']');
+    }
+
+    let cid_font = writer.reserve();
+    let gid_map = if font.outlines == Outlines::TrueType {
+        " /CIDToGIDMap /Identity"
+    } else {
+        ""
+    };
+    writer.object(
+        cid_font,
+        &format!(
+            "<< /Type /Font /Subtype /{} /BaseFont /{} /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /FontDescriptor {} 0 R /DW 1000 /W [{}]{} >>",
+            subtype, base_font, descriptor, w, gid_map
+        ),
+    );
+
+    // ToUnicode 映射，用于复制和搜索文字
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let entries: Vec<(&u16, &char)> = usage.unicode.iter().collect();
+    for chunk in entries.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+        for (code, ch) in chunk {
+            let mut units = [0u16; 2];
+            let hex: String = ch
+                .encode_utf16(&mut units)
+                .iter()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            cmap.push_str(&format!("<{:04X}> <{}>\n", code, hex));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    let to_unicode = writer.reserve();
+    writer.stream(
+        to_unicode,
+        "/Filter /FlateDecode",
+        &deflate(cmap.as_bytes()),
+    );
+
+    let font_id = writer.reserve();
+    writer.object(
+        font_id,
+        &format!(
+            "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+            base_font, cid_font, to_unicode
+        ),
+    );
+    Ok(font_id)
+}
+
+// 书签：按目录层级写出，返回 (第一项, 最后一项, 可见项数)
+fn write_outline_items(
+    writer: &mut PdfWriter,
+    chapters: &[ExportChapter],
+    parent: usize,
+    page_ids: &[usize],
+    destinations: &HashMap<i64, (usize, f64)>,
+) -> Option<(usize, usize, usize)> {
+    let items: Vec<&ExportChapter> = chapters
+        .iter()
+        .filter(|chapter| destinations.contains_key(&chapter.id))
+        .collect();
+    if items.is_empty() {
+        return None;
+    }
+    let ids: Vec<usize> = items.iter().map(|_| writer.reserve()).collect();
+    let mut count = items.len();
+    for (index, chapter) in items.iter().enumerate() {
+        let (page, top) = destinations[&chapter.id];
+        let mut body = format!(
+            "<< /Title {} /Parent {} 0 R /Dest [{} 0 R /XYZ null {} null]",
+            pdf_text(&chapter.label),
+            parent,
+            page_ids[page],
+            num(top)
+        );
+        if index > 0 {
+            body.push_str(&format!(" /Prev {} 0 R", ids[index - 1]));
+        }
+        if index + 1 < ids.len() {
+            body.push_str(&format!(" /Next {} 0 R", ids[index + 1]));
+        }
+        if let Some((first, last, children)) = write_outline_items(
+            writer,
+            &chapter.children,
+            ids[index],
+            page_ids,
+            destinations,
+        ) {
+            // 只展开第一层
+            body.push_str(&format!(
+                " /First {} 0 R /Last {} 0 R /Count -{}",
+                first, last, children
+            ));
+        }
+        body.push_str(" >>");
+        writer.object(ids[index], &body);
+    }
+    count = count.max(1);
+    Some((ids[0], ids[ids.len() - 1], count))
+}
+
+fn build_pdf(
+    book: &ExportBook,
+    app_dir: &Path,
+    options: &PdfExportOptions,
+) -> Result<Vec<u8>, String> {
+    options.validate()?;
+    let data = fs::read(&options.font_path).map_err(|e| format!("读取字体文件失败: {}", e))?;
+    let font = Font::parse(data)?;
+
+    let (page_width, page_height) = options.page_size();
+    let ascent = (font.ascent as f64 / font.units_per_em as f64).clamp(0.7, 1.0);
+    let mut layout = Layout {
+        options,
+        font: FontUsage::new(font),
+        page_width,
+        page_height,
+        left: options.margin_left * POINTS_PER_MM,
+        top: options.margin_top * POINTS_PER_MM,
+        bottom: options.margin_bottom * POINTS_PER_MM,
+        content_width: page_width - (options.margin_left + options.margin_right) * POINTS_PER_MM,
+        ascent,
+        pages: Vec::new(),
+        y: 0.0,
+        images: Vec::new(),
+        image_ids: HashMap::new(),
+        images_dir: book_epub_dir(app_dir, book.id).join("images"),
+        destinations: HashMap::new(),
+    };
+
+    // 第一页为封面，没有封面时为书名页
+    if let Ok(cover) = fs::read(cover_path(app_dir, book.id)) {
+        layout.cover(&cover);
+    }
+    let has_cover = !layout.pages.is_empty();
+    if !has_cover {
+        layout.title_page(book);
+    }
+    for (depth, chapter) in book.flatten() {
+        layout.chapter(chapter, depth);
+    }
+    layout.page_numbers(1);
+
+    let usage = &layout.font;
+    if usage.total > 0 && usage.missing * 2 > usage.total {
+        return Err("所选字体缺少书中大部分文字的字形，请选择支持中文的字体".to_string());
+    }
+
+    let mut writer = PdfWriter::new();
+    let catalog = writer.reserve();
+    let pages_id = writer.reserve();
+    let outlines = writer.reserve();
+    let info = writer.reserve();
+    let font_id = write_font(&mut writer, &layout.font)?;
+    let image_ids: Vec<usize> = layout
+        .images
+        .iter()
+        .map(|image| write_image(&mut writer, image))
+        .collect();
+
+    let mut page_ids = Vec::new();
+    for page in &layout.pages {
+        let content = writer.reserve();
+        writer.stream(
+            content,
+            "/Filter /FlateDecode",
+            &deflate(page.content.as_bytes()),
+        );
+        let xobjects: String = page
+            .images
+            .iter()
+            .map(|index| format!(" /Im{} {} 0 R", index + 1, image_ids[*index]))
+            .collect();
+        let xobjects = if xobjects.is_empty() {
+            String::new()
+        } else {
+            format!(" /XObject <<{} >>", xobjects)
+        };
+        let id = writer.reserve();
+        writer.object(
+            id,
+            &format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 {} 0 R >>{} >> /Contents {} 0 R >>",
+                pages_id,
+                num(page_width),
+                num(page_height),
+                font_id,
+                xobjects,
+                content
+            ),
+        );
+        page_ids.push(id);
+    }
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    writer.object(
+        pages_id,
+        &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            page_ids.len()
+        ),
+    );
+
+    match write_outline_items(
+        &mut writer,
+        &book.chapters,
+        outlines,
+        &page_ids,
+        &layout.destinations,
+    ) {
+        Some((first, last, count)) => writer.object(
+            outlines,
+            &format!(
+                "<< /Type /Outlines /First {} 0 R /Last {} 0 R /Count {} >>",
+                first, last, count
+            ),
+        ),
+        None => writer.object(outlines, "<< /Type /Outlines /Count 0 >>"),
+    }
+    writer.object(
+        catalog,
+        &format!(
+            "<< /Type /Catalog /Pages {} 0 R /Outlines {} 0 R /PageMode /UseOutlines >>",
+            pages_id, outlines
+        ),
+    );
+
+    let now = get_current_time_string().parse::<i64>().unwrap_or(0);
+    let date: String = format_utc_time(now)
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect();
+    writer.object(
+        info,
+        &format!(
+            "<< /Title {} /Author {} /Creator (my-ebooks) /CreationDate (D:{}Z) >>",
+            pdf_text(&book.title),
+            pdf_text(&book.author),
+            date
+        ),
+    );
+    Ok(writer.finish(catalog, info))
+}
+
+// 生成 PDF 并写入文件，失败时不留下不完整的文件
+pub fn write_pdf(
+    book: &ExportBook,
+    app_dir: &Path,
+    output: &Path,
+    options: &PdfExportOptions,
+) -> Result<(), String> {
+    let data = build_pdf(book, app_dir, options)?;
+    if let Err(e) = fs::write(output, data) {
+        let _ = fs::remove_file(output);
+        return Err(format!("写入文件失败: {}", e));
+    }
+    Ok(())
+}
+
+// 导出书籍为 PDF：嵌入所选字体的子集，目录生成书签，封面放在第一页，返回导出的文件路径
+#[command]
+pub async fn export_pdf(
+    book_id: i64,
+    output_path: String,
+    options: PdfExportOptions,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<String>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    // 读取完数据后释放数据库锁，再排版和写入文件
+    let book = {
+        let db = get_db_connection(&state)?;
+        match load_export_book(&db, book_id) {
+            Ok(book) => book,
+            Err(err) => return Ok(DbResponse::error(err)),
+        }
+    };
+
+    match write_pdf(&book, &app_dir, Path::new(&output_path), &options) {
+        Ok(_) => Ok(DbResponse::success(output_path)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{break_lines, is_wide, Glyph, TextStyle, NO_LINE_END, NO_LINE_START};
+
+    // 中文字符宽 1，其他字符宽 0.5
+    fn glyphs(text: &str) -> Vec<Glyph> {
+        text.chars()
+            .map(|ch| Glyph {
+                code: 0,
+                width: if is_wide(ch) { 1.0 } else { 0.5 },
+                ch,
+                style: TextStyle::default(),
+            })
+            .collect()
+    }
+
+    fn lines(text: &str, first_width: f64, width: f64) -> Vec<String> {
+        let glyphs = glyphs(text);
+        break_lines(&glyphs, 10.0, first_width, width)
+            .into_iter()
+            .map(|range| glyphs[range].iter().map(|glyph| glyph.ch).collect())
+            .collect()
+    }
+
+    #[test]
+    fn breaks_at_expected_positions() {
+        let cases: [(&str, f64, f64, &[&str]); 5] = [
+            ("一二三四五六七", 30.0, 30.0, &["一二三", "四五六", "七"]),
+            ("一二三四五六七", 20.0, 40.0, &["一二", "三四五六", "七"]),
+            ("aaa bbb ccc", 30.0, 30.0, &["aaa ", "bbb ", "ccc"]),
+            // 行首禁则的标点悬挂在上一行末尾
+            ("一二三。四五", 30.0, 30.0, &["一二三。", "四五"]),
+            // 行尾禁则的左引号移到下一行
+            ("一二“三四”", 30.0, 30.0, &["一二", "“三四”"]),
+        ];
+        for (text, first_width, width, expected) in cases {
+            assert_eq!(lines(text, first_width, width), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn round_trips_text() {
+        let text = "天下大势，分久必合，合久必分。周末七国分争，并入于秦。\
+                    The quick brown fox jumps over the lazy dog, again and again. \
+                    及秦灭之后，楚、汉分争，又并入于汉（高祖斩白蛇而起义）。";
+        for width in [25.0, 40.0, 55.0, 120.0] {
+            let glyphs = glyphs(text);
+            let ranges = break_lines(&glyphs, 10.0, width, width);
+            assert_eq!(ranges.first().map(|range| range.start), Some(0));
+            assert_eq!(ranges.last().map(|range| range.end), Some(glyphs.len()));
+            for pair in ranges.windows(2) {
+                // 行与行之间只会跳过行首的空格
+                assert!(glyphs[pair[0].end..pair[1].start]
+                    .iter()
+                    .all(|glyph| glyph.ch == ' '));
+            }
+            for range in &ranges {
+                let line = &glyphs[range.clone()];
+                let hanging = line
+                    .iter()
+                    .rev()
+                    .take_while(|glyph| NO_LINE_START.contains(glyph.ch))
+                    .count()
+                    .min(2);
+                let body: f64 = line[..line.len() - hanging]
+                    .iter()
+                    .filter(|glyph| glyph.ch != ' ')
+                    .map(|glyph| glyph.width * 10.0)
+                    .sum();
+                assert!(body <= width + 0.01 || line.len() == 1, "{:?}", range);
+                assert!(!NO_LINE_START.contains(line[0].ch) || range.start == 0);
+                assert!(!NO_LINE_END.contains(line[line.len() - 1].ch));
+            }
+        }
+    }
+}
@@ -0,0 +1,2829 @@
+// PDF 文本导入：解析对象和页面内容流提取文字，按书签切分章节，没有书签时识别标题
+
+use crate::database::{get_db_connection, Book, DbResponse};
+use crate::importer::{
+    save_imported_book, ChapterOutline, ImportedBook, ImportedChapter, PREFACE_LABEL,
+};
+use crate::markup::is_wide;
+use crate::setup::AppState;
+use crate::txt::split_by_headings;
+use encoding_rs::{Encoding, BIG5, EUC_JP, EUC_KR, GB18030, MACINTOSH, SHIFT_JIS, WINDOWS_1252};
+use flate2::read::{DeflateDecoder, ZlibDecoder};
+use regex::Regex;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
+use tauri::{command, AppHandle, Manager, State};
+
+const IDENTITY: [f64; 6] = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+// 表单 XObject 的最大嵌套层数
+const MAX_FORM_DEPTH: usize = 12;
+// 标题行的最大长度（字符数）
+const MAX_HEADING_LENGTH: usize = 40;
+// 页眉页脚的最大长度（字符数），更长的重复行视为正文
+const MAX_FURNITURE_LENGTH: usize = 60;
+// 字号比正文大这么多倍时视为标题
+const HEADING_SCALE: f64 = 1.2;
+
+// Differences 中常见的字形名称，字母、数字和 uniXXXX 形式的名称另行处理
+const GLYPH_NAMES: &[(&str, &str)] = &[
+    ("space", " "),
+    ("exclam", "!"),
+    ("quotedbl", "\""),
+    ("numbersign", "#"),
+    ("dollar", "$"),
+    ("percent", "%"),
+    ("ampersand", "&"),
+    ("quotesingle", "'"),
+    ("quoteright", "’"),
+    ("parenleft", "("),
+    ("parenright", ")"),
+    ("asterisk", "*"),
+    ("plus", "+"),
+    ("comma", ","),
+    ("hyphen", "-"),
+    ("period", "."),
+    ("slash", "/"),
+    ("zero", "0"),
+    ("one", "1"),
+    ("two", "2"),
+    ("three", "3"),
+    ("four", "4"),
+    ("five", "5"),
+    ("six", "6"),
+    ("seven", "7"),
+    ("eight", "8"),
+    ("nine", "9"),
+    ("colon", ":"),
+    ("semicolon", ";"),
+    ("less", "<"),
+    ("equal", "="),
+    ("greater", ">"),
+    ("question", "?"),
+    ("at", "@"),
+    ("bracketleft", "["),
+    ("backslash", "\\"),
+    ("bracketright", "]"),
+    ("asciicircum", "^"),
+    ("underscore", "_"),
+    ("grave", "`"),
+    ("quoteleft", "‘"),
+    ("braceleft", "{"),
+    ("bar", "|"),
+    ("braceright", "}"),
+    ("asciitilde", "~"),
+    ("Euro", "€"),
+    ("quotesinglbase", "‚"),
+    ("florin", "ƒ"),
+    ("quotedblbase", "„"),
+    ("ellipsis", "…"),
+    ("dagger", "†"),
+    ("daggerdbl", "‡"),
+    ("circumflex", "ˆ"),
+    ("perthousand", "‰"),
+    ("Scaron", "Š"),
+    ("guilsinglleft", "‹"),
+    ("OE", "Œ"),
+    ("Zcaron", "Ž"),
+    ("quotedblleft", "“"),
+    ("quotedblright", "”"),
+    ("bullet", "•"),
+    ("endash", "–"),
+    ("emdash", "—"),
+    ("tilde", "˜"),
+    ("trademark", "™"),
+    ("scaron", "š"),
+    ("guilsinglright", "›"),
+    ("oe", "œ"),
+    ("zcaron", "ž"),
+    ("Ydieresis", "Ÿ"),
+    ("fi", "fi"),
+    ("fl", "fl"),
+    ("ff", "ff"),
+    ("ffi", "ffi"),
+    ("ffl", "ffl"),
+    ("dotlessi", "ı"),
+    ("minus", "−"),
+    ("fraction", "⁄"),
+    ("Lslash", "Ł"),
+    ("lslash", "ł"),
+    ("nbspace", " "),
+    ("sfthyphen", "\u{ad}"),
+];
+
+// Latin-1 补充区 0xA1..=0xFF 的字形名称
+const LATIN1_NAMES: [&str; 95] = [
+    "exclamdown",
+    "cent",
+    "sterling",
+    "currency",
+    "yen",
+    "brokenbar",
+    "section",
+    "dieresis",
+    "copyright",
+    "ordfeminine",
+    "guillemotleft",
+    "logicalnot",
+    "softhyphen",
+    "registered",
+    "macron",
+    "degree",
+    "plusminus",
+    "twosuperior",
+    "threesuperior",
+    "acute",
+    "mu",
+    "paragraph",
+    "periodcentered",
+    "cedilla",
+    "onesuperior",
+    "ordmasculine",
+    "guillemotright",
+    "onequarter",
+    "onehalf",
+    "threequarters",
+    "questiondown",
+    "Agrave",
+    "Aacute",
+    "Acircumflex",
+    "Atilde",
+    "Adieresis",
+    "Aring",
+    "AE",
+    "Ccedilla",
+    "Egrave",
+    "Eacute",
+    "Ecircumflex",
+    "Edieresis",
+    "Igrave",
+    "Iacute",
+    "Icircumflex",
+    "Idieresis",
+    "Eth",
+    "Ntilde",
+    "Ograve",
+    "Oacute",
+    "Ocircumflex",
+    "Otilde",
+    "Odieresis",
+    "multiply",
+    "Oslash",
+    "Ugrave",
+    "Uacute",
+    "Ucircumflex",
+    "Udieresis",
+    "Yacute",
+    "Thorn",
+    "germandbls",
+    "agrave",
+    "aacute",
+    "acircumflex",
+    "atilde",
+    "adieresis",
+    "aring",
+    "ae",
+    "ccedilla",
+    "egrave",
+    "eacute",
+    "ecircumflex",
+    "edieresis",
+    "igrave",
+    "iacute",
+    "icircumflex",
+    "idieresis",
+    "eth",
+    "ntilde",
+    "ograve",
+    "oacute",
+    "ocircumflex",
+    "otilde",
+    "odieresis",
+    "divide",
+    "oslash",
+    "ugrave",
+    "uacute",
+    "ucircumflex",
+    "udieresis",
+    "yacute",
+    "thorn",
+    "ydieresis",
+];
+
+type Dict = HashMap<String, Object>;
+// 对象流解析出的对象：(对象编号, 对象)
+type ObjectList = Rc<Vec<(u32, Object)>>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Object {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Real(f64),
+    Name(String),
+    Str(Vec<u8>),
+    Array(Vec<Object>),
+    Dict(Dict),
+    Stream(Stream),
+    Ref(u32),
+    // 内容流中的操作符和其他关键字
+    Op(String),
+}
+
+// 流对象，数据为文件中的字节范围
+#[derive(Debug, Clone, PartialEq)]
+struct Stream {
+    dict: Dict,
+    range: Range<usize>,
+}
+
+impl Object {
+    fn number(&self) -> Option<f64> {
+        match self {
+            Object::Int(value) => Some(*value as f64),
+            Object::Real(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn int(&self) -> Option<i64> {
+        match self {
+            Object::Int(value) => Some(*value),
+            Object::Real(value) => Some(*value as i64),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self {
+            Object::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+fn is_white(b: u8) -> bool {
+    matches!(b, b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len())
+        .position(|window| window == needle)
+}
+
+// 名称中的 #xx 为十六进制转义
+fn decode_name(raw: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(raw.len());
+    let mut index = 0;
+    while index < raw.len() {
+        if raw[index] == b'#' && index + 2 < raw.len() {
+            let digits = String::from_utf8_lossy(&raw[index + 1..index + 3]);
+            if let Ok(value) = u8::from_str_radix(&digits, 16) {
+                bytes.push(value);
+                index += 3;
+                continue;
+            }
+        }
+        bytes.push(raw[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// 文件和内容流共用的词法和语法分析
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Lexer { data, pos }
+    }
+
+    fn skip_space(&mut self) {
+        while let Some(&b) = self.data.get(self.pos) {
+            if is_white(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while let Some(&b) = self.data.get(self.pos) {
+                    if b == b'\n' || b == b'\r' {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn regular(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while let Some(&b) = self.data.get(self.pos) {
+            if is_white(b) || is_delimiter(b) {
+                break;
+            }
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+
+    fn object(&mut self) -> Option<Object> {
+        self.object_at_depth(0)
+    }
+
+    fn object_at_depth(&mut self, depth: usize) -> Option<Object> {
+        if depth > 64 {
+            return None;
+        }
+        self.skip_space();
+        let b = *self.data.get(self.pos)?;
+        match b {
+            b'/' => {
+                self.pos += 1;
+                Some(Object::Name(decode_name(self.regular())))
+            }
+            b'(' => Some(Object::Str(self.literal())),
+            b'<' if self.data.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                let mut dict = Dict::new();
+                loop {
+                    self.skip_space();
+                    match self.data.get(self.pos) {
+                        None => break,
+                        Some(b'>') => {
+                            self.pos += 2;
+                            break;
+                        }
+                        _ => {}
+                    }
+                    // 跳过不是名称的键
+                    if let Object::Name(key) = self.object_at_depth(depth + 1)? {
+                        let value = self.object_at_depth(depth + 1)?;
+                        dict.insert(key, value);
+                    }
+                }
+                Some(Object::Dict(dict))
+            }
+            b'<' => Some(Object::Str(self.hex())),
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_space();
+                    match self.data.get(self.pos) {
+                        None => break,
+                        Some(b']') => {
+                            self.pos += 1;
+                            break;
+                        }
+                        _ => items.push(self.object_at_depth(depth + 1)?),
+                    }
+                }
+                Some(Object::Array(items))
+            }
+            b'0'..=b'9' | b'+' | b'-' | b'.' => self.number(),
+            b')' | b'>' | b']' | b'{' | b'}' => {
+                self.pos += 1;
+                Some(Object::Op((b as char).to_string()))
+            }
+            _ => Some(match self.regular() {
+                b"true" => Object::Bool(true),
+                b"false" => Object::Bool(false),
+                b"null" => Object::Null,
+                word => Object::Op(String::from_utf8_lossy(word).into_owned()),
+            }),
+        }
+    }
+
+    fn number(&mut self) -> Option<Object> {
+        let word = self.regular();
+        let text = std::str::from_utf8(word).unwrap_or("0");
+        if let Ok(value) = text.parse::<i64>() {
+            // 检查是否为间接引用「编号 版本 R」
+            let save = self.pos;
+            self.skip_space();
+            let generation = self.regular();
+            if !generation.is_empty() && generation.iter().all(u8::is_ascii_digit) {
+                self.skip_space();
+                let next = self.data.get(self.pos + 1);
+                if self.data.get(self.pos) == Some(&b'R')
+                    && next.is_none_or(|b| is_white(*b) || is_delimiter(*b))
+                {
+                    self.pos += 1;
+                    return Some(Object::Ref(value as u32));
+                }
+            }
+            self.pos = save;
+            return Some(Object::Int(value));
+        }
+        // 容错处理 "--1"、"1.2.3" 之类的写法
+        let value = text
+            .parse::<f64>()
+            .or_else(|_| {
+                text.trim_start_matches(['-', '+'])
+                    .parse::<f64>()
+                    .map(|v| -v)
+            })
+            .unwrap_or(0.0);
+        Some(Object::Real(value))
+    }
+
+    fn literal(&mut self) -> Vec<u8> {
+        self.pos += 1;
+        let mut out = Vec::new();
+        let mut depth = 1;
+        while let Some(&b) = self.data.get(self.pos) {
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    depth += 1;
+                    out.push(b);
+                }
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    out.push(b);
+                }
+                b'\\' => {
+                    let Some(&next) = self.data.get(self.pos) else {
+                        break;
+                    };
+                    self.pos += 1;
+                    match next {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(8),
+                        b'f' => out.push(12),
+                        b'\r' => {
+                            if self.data.get(self.pos) == Some(&b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        b'0'..=b'7' => {
+                            let mut value = (next - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.data.get(self.pos) {
+                                    Some(&digit @ b'0'..=b'7') => {
+                                        value = value * 8 + (digit - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(value as u8);
+                        }
+                        other => out.push(other),
+                    }
+                }
+                _ => out.push(b),
+            }
+        }
+        out
+    }
+
+    fn hex(&mut self) -> Vec<u8> {
+        self.pos += 1;
+        let mut digits = Vec::new();
+        while let Some(&b) = self.data.get(self.pos) {
+            self.pos += 1;
+            if b == b'>' {
+                break;
+            }
+            if let Some(digit) = (b as char).to_digit(16) {
+                digits.push(digit as u8);
+            }
+        }
+        if digits.len() % 2 == 1 {
+            digits.push(0);
+        }
+        digits
+            .chunks(2)
+            .map(|pair| pair[0] << 4 | pair[1])
+            .collect()
+    }
+
+    // 跳过内联图片 ID 和 EI 之间的数据
+    fn skip_inline_image(&mut self) {
+        self.pos += 1;
+        while self.pos + 2 <= self.data.len() {
+            if &self.data[self.pos..self.pos + 2] == b"EI"
+                && is_white(self.data[self.pos - 1])
+                && self.data.get(self.pos + 2).is_none_or(|b| is_white(*b))
+            {
+                self.pos += 2;
+                return;
+            }
+            self.pos += 1;
+        }
+        self.pos = self.data.len();
+    }
+}
+
+fn read_be(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |value, b| value << 8 | *b as u64)
+}
+
+fn inflate(data: &[u8]) -> Vec<u8> {
+    // 数据损坏时保留已解压的部分
+    let mut out = Vec::new();
+    if ZlibDecoder::new(data).read_to_end(&mut out).is_err() && out.is_empty() {
+        let _ = DeflateDecoder::new(data).read_to_end(&mut out);
+    }
+    out
+}
+
+fn lzw_decode(data: &[u8], early_change: bool) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut table: Vec<Vec<u8>> = Vec::new();
+    let reset = |table: &mut Vec<Vec<u8>>| {
+        table.clear();
+        table.extend((0..=255u8).map(|b| vec![b]));
+        table.push(Vec::new());
+        table.push(Vec::new());
+    };
+    reset(&mut table);
+    let mut width = 9;
+    let mut previous: Option<Vec<u8>> = None;
+    let (mut buffer, mut bits, mut pos) = (0u32, 0, 0);
+    loop {
+        while bits < width && pos < data.len() {
+            buffer = buffer << 8 | data[pos] as u32;
+            bits += 8;
+            pos += 1;
+        }
+        if bits < width {
+            break;
+        }
+        let code = ((buffer >> (bits - width)) & ((1 << width) - 1)) as usize;
+        bits -= width;
+        match code {
+            256 => {
+                reset(&mut table);
+                width = 9;
+                previous = None;
+                continue;
+            }
+            257 => break,
+            _ => {}
+        }
+        let entry = if code < table.len() {
+            table[code].clone()
+        } else if let Some(previous) = &previous {
+            let mut entry = previous.clone();
+            entry.push(previous[0]);
+            entry
+        } else {
+            break;
+        };
+        out.extend_from_slice(&entry);
+        if let Some(mut previous) = previous.take() {
+            if table.len() < 4096 {
+                previous.push(entry[0]);
+                table.push(previous);
+            }
+        }
+        previous = Some(entry);
+        width = match table.len() + early_change as usize {
+            n if n >= 2048 => 12,
+            n if n >= 1024 => 11,
+            n if n >= 512 => 10,
+            _ => 9,
+        };
+    }
+    out
+}
+
+fn ascii_hex_decode(data: &[u8]) -> Vec<u8> {
+    let end = data.iter().position(|b| *b == b'>').unwrap_or(data.len());
+    let mut source = vec![b'<'];
+    source.extend_from_slice(&data[..end]);
+    source.push(b'>');
+    Lexer::new(&source, 0).hex()
+}
+
+fn ascii85_decode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut group = Vec::with_capacity(5);
+    let data = data.strip_prefix(b"<~").unwrap_or(data);
+    for &b in data {
+        match b {
+            b'~' => break,
+            b'z' if group.is_empty() => out.extend_from_slice(&[0; 4]),
+            b'!'..=b'u' => {
+                group.push(b - b'!');
+                if group.len() == 5 {
+                    let value = group
+                        .iter()
+                        .fold(0u32, |v, d| v.wrapping_mul(85).wrapping_add(*d as u32));
+                    out.extend_from_slice(&value.to_be_bytes());
+                    group.clear();
+                }
+            }
+            _ => {}
+        }
+    }
+    if group.len() > 1 {
+        let count = group.len();
+        group.resize(5, 84);
+        let value = group
+            .iter()
+            .fold(0u32, |v, d| v.wrapping_mul(85).wrapping_add(*d as u32));
+        out.extend_from_slice(&value.to_be_bytes()[..count - 1]);
+    }
+    out
+}
+
+fn run_length_decode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let length = data[pos] as usize;
+        pos += 1;
+        match length {
+            128 => break,
+            0..=127 => {
+                let end = (pos + length + 1).min(data.len());
+                out.extend_from_slice(&data[pos..end]);
+                pos = end;
+            }
+            _ => {
+                if let Some(&b) = data.get(pos) {
+                    out.extend(std::iter::repeat_n(b, 257 - length));
+                }
+                pos += 1;
+            }
+        }
+    }
+    out
+}
+
+// PNG 预测器，交叉引用流和部分内容流会用到
+fn png_predictor(data: &[u8], colors: usize, bits: usize, columns: usize) -> Vec<u8> {
+    let bpp = (colors * bits).div_ceil(8).max(1);
+    let stride = (colors * bits * columns).div_ceil(8);
+    let mut out = Vec::with_capacity(data.len());
+    let mut previous = vec![0u8; stride];
+    for row in data.chunks(stride + 1) {
+        let (filter, row) = (row[0], &row[1..]);
+        let mut current = vec![0u8; stride];
+        for i in 0..row.len() {
+            let a = if i >= bpp { current[i - bpp] } else { 0 };
+            let b = previous[i];
+            let c = if i >= bpp { previous[i - bpp] } else { 0 };
+            current[i] = match filter {
+                1 => row[i].wrapping_add(a),
+                2 => row[i].wrapping_add(b),
+                3 => row[i].wrapping_add(((a as u16 + b as u16) / 2) as u8),
+                4 => {
+                    let p = a as i16 + b as i16 - c as i16;
+                    let (pa, pb, pc) = (
+                        (p - a as i16).abs(),
+                        (p - b as i16).abs(),
+                        (p - c as i16).abs(),
+                    );
+                    let predicted = if pa <= pb && pa <= pc {
+                        a
+                    } else if pb <= pc {
+                        b
+                    } else {
+                        c
+                    };
+                    row[i].wrapping_add(predicted)
+                }
+                _ => row[i],
+            };
+        }
+        out.extend_from_slice(&current[..row.len()]);
+        previous = current;
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy)]
+enum XrefEntry {
+    Offset(usize),
+    // 压缩在对象流中：(对象流编号, 序号)
+    Compressed(u32, usize),
+}
+
+struct Document {
+    data: Vec<u8>,
+    xref: HashMap<u32, XrefEntry>,
+    trailer: Dict,
+    cache: RefCell<HashMap<u32, Object>>,
+    object_streams: RefCell<HashMap<u32, ObjectList>>,
+    // 正在读取的对象，防止循环引用
+    loading: RefCell<HashSet<u32>>,
+}
+
+impl Document {
+    fn load(data: Vec<u8>) -> Result<Document, String> {
+        let header = &data[..data.len().min(1024)];
+        if find(header, b"%PDF-").is_none() {
+            return Err("不是有效的 PDF 文件".to_string());
+        }
+        let mut document = Document {
+            data,
+            xref: HashMap::new(),
+            trailer: Dict::new(),
+            cache: RefCell::new(HashMap::new()),
+            object_streams: RefCell::new(HashMap::new()),
+            loading: RefCell::new(HashSet::new()),
+        };
+        // 交叉引用表损坏时扫描全文重建
+        if document.read_xref_chain().is_none() || document.catalog().is_none() {
+            document.rebuild_xref();
+        }
+        if document.trailer.contains_key("Encrypt") {
+            return Err("不支持导入加密的 PDF 文件".to_string());
+        }
+        if document.catalog().is_none() {
+            return Err("PDF 文件已损坏，找不到文档目录".to_string());
+        }
+        Ok(document)
+    }
+
+    fn startxref(&self) -> Option<usize> {
+        let tail_start = self.data.len().saturating_sub(2048);
+        let tail = &self.data[tail_start..];
+        let position = tail.windows(9).rposition(|window| window == b"startxref")?;
+        let mut lexer = Lexer::new(tail, position + 9);
+        lexer.object()?.int().map(|offset| offset as usize)
+    }
+
+    fn read_xref_chain(&mut self) -> Option<()> {
+        let mut offset = self.startxref()?;
+        let mut visited = HashSet::new();
+        while offset < self.data.len() && visited.insert(offset) {
+            let mut lexer = Lexer::new(&self.data, offset);
+            lexer.skip_space();
+            let trailer = if self.data[lexer.pos..].starts_with(b"xref") {
+                let trailer = self.read_xref_table(lexer.pos)?;
+                // 混合型文件的交叉引用流
+                if let Some(stream) = trailer.get("XRefStm").and_then(Object::int) {
+                    self.read_xref_stream(stream as usize);
+                }
+                trailer
+            } else {
+                self.read_xref_stream(offset)?
+            };
+            for (key, value) in &trailer {
+                if key != "Prev" && key != "XRefStm" {
+                    self.trailer
+                        .entry(key.clone())
+                        .or_insert_with(|| value.clone());
+                }
+            }
+            match trailer.get("Prev").and_then(Object::int) {
+                Some(prev) => offset = prev as usize,
+                None => break,
+            }
+        }
+        Some(())
+    }
+
+    // 较新的修订先读取，已有的条目不覆盖
+    fn read_xref_table(&mut self, offset: usize) -> Option<Dict> {
+        let mut lexer = Lexer::new(&self.data, offset + 4);
+        let mut entries = Vec::new();
+        loop {
+            let start = match lexer.object()? {
+                Object::Int(start) => start as u32,
+                Object::Op(word) if word == "trailer" => break,
+                _ => return None,
+            };
+            let count = lexer.object()?.int()?;
+            for index in 0..count as u32 {
+                let position = lexer.object()?.int()?;
+                lexer.object()?;
+                if lexer.object()? == Object::Op("n".to_string()) {
+                    entries.push((start + index, position as usize));
+                }
+            }
+        }
+        let Object::Dict(trailer) = lexer.object()? else {
+            return None;
+        };
+        for (id, position) in entries {
+            self.xref.entry(id).or_insert(XrefEntry::Offset(position));
+        }
+        Some(trailer)
+    }
+
+    fn read_xref_stream(&mut self, offset: usize) -> Option<Dict> {
+        let (_, Object::Stream(stream)) = self.read_object_at(offset)? else {
+            return None;
+        };
+        if stream.dict.get("Type").and_then(Object::name) != Some("XRef") {
+            return None;
+        }
+        let data = self.stream_data(&stream)?;
+        let widths: Vec<usize> = match stream.dict.get("W") {
+            Some(Object::Array(items)) => items
+                .iter()
+                .map(|item| item.int().unwrap_or(0) as usize)
+                .collect(),
+            _ => return None,
+        };
+        if widths.len() < 3 || widths.iter().any(|width| *width > 8) {
+            return None;
+        }
+        let size = stream.dict.get("Size").and_then(Object::int).unwrap_or(0);
+        let index: Vec<i64> = match stream.dict.get("Index") {
+            Some(Object::Array(items)) => items.iter().filter_map(Object::int).collect(),
+            _ => vec![0, size],
+        };
+        let row = widths.iter().sum::<usize>();
+        let mut pos = 0;
+        for pair in index.chunks_exact(2) {
+            for id in pair[0]..pair[0] + pair[1] {
+                let Some(fields) = data.get(pos..pos + row) else {
+                    break;
+                };
+                pos += row;
+                let kind = if widths[0] == 0 {
+                    1
+                } else {
+                    read_be(&fields[..widths[0]])
+                };
+                let first = read_be(&fields[widths[0]..widths[0] + widths[1]]);
+                let second = read_be(&fields[widths[0] + widths[1]..]);
+                let entry = match kind {
+                    1 => XrefEntry::Offset(first as usize),
+                    2 => XrefEntry::Compressed(first as u32, second as usize),
+                    _ => continue,
+                };
+                self.xref.entry(id as u32).or_insert(entry);
+            }
+        }
+        Some(stream.dict)
+    }
+
+    // 扫描所有「编号 版本 obj」重建交叉引用
+    fn rebuild_xref(&mut self) {
+        let pattern =
+            regex::bytes::Regex::new(r"(\d+)[\x00\t\n\x0c\r ]+\d+[\x00\t\n\x0c\r ]+obj(?-u:\b)")
+                .expect("内置正则表达式有误");
+        self.xref.clear();
+        self.cache.borrow_mut().clear();
+        self.object_streams.borrow_mut().clear();
+        for caps in pattern.captures_iter(&self.data) {
+            let (Some(id), Some(whole)) = (caps.get(1), caps.get(0)) else {
+                continue;
+            };
+            if let Ok(id) = String::from_utf8_lossy(id.as_bytes()).parse::<u32>() {
+                self.xref.insert(id, XrefEntry::Offset(whole.start()));
+            }
+        }
+
+        if let Some(position) = self.data.windows(7).rposition(|w| w == b"trailer") {
+            if let Some(Object::Dict(trailer)) = Lexer::new(&self.data, position + 7).object() {
+                for (key, value) in trailer {
+                    self.trailer.entry(key).or_insert(value);
+                }
+            }
+        }
+
+        let mut ids: Vec<u32> = self.xref.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let Some(object) = self.get(id) else {
+                continue;
+            };
+            let dict = match &object {
+                Object::Dict(dict) => dict,
+                Object::Stream(stream) => &stream.dict,
+                _ => continue,
+            };
+            match dict.get("Type").and_then(Object::name) {
+                Some("ObjStm") => {
+                    if let Some(objects) = self.object_stream(id) {
+                        for (index, (inner, _)) in objects.iter().enumerate() {
+                            self.xref
+                                .entry(*inner)
+                                .or_insert(XrefEntry::Compressed(id, index));
+                        }
+                    }
+                }
+                Some("XRef") => {
+                    for key in ["Root", "Info"] {
+                        if let Some(value) = dict.get(key) {
+                            self.trailer
+                                .entry(key.to_string())
+                                .or_insert_with(|| value.clone());
+                        }
+                    }
+                }
+                Some("Catalog") => {
+                    self.trailer
+                        .entry("Root".to_string())
+                        .or_insert(Object::Ref(id));
+                }
+                _ => {}
+            }
+        }
+        self.cache.borrow_mut().clear();
+    }
+
+    fn read_object_at(&self, offset: usize) -> Option<(u32, Object)> {
+        let mut lexer = Lexer::new(&self.data, offset);
+        let id = lexer.object()?.int()? as u32;
+        lexer.object()?.int()?;
+        if lexer.object()? != Object::Op("obj".to_string()) {
+            return None;
+        }
+        let object = lexer.object()?;
+        let Object::Dict(dict) = object else {
+            return Some((id, object));
+        };
+        lexer.skip_space();
+        if !self.data[lexer.pos..].starts_with(b"stream") {
+            return Some((id, Object::Dict(dict)));
+        }
+
+        let mut start = lexer.pos + 6;
+        if self.data.get(start) == Some(&b'\r') {
+            start += 1;
+        }
+        if self.data.get(start) == Some(&b'\n') {
+            start += 1;
+        }
+        // Length 不可靠时查找 endstream
+        let length = match dict.get("Length") {
+            Some(Object::Ref(length)) => self.get(*length).and_then(|o| o.int()),
+            Some(length) => length.int(),
+            None => None,
+        };
+        let end = length
+            .map(|length| start + length.max(0) as usize)
+            .filter(|end| {
+                *end <= self.data.len() && {
+                    let mut lexer = Lexer::new(&self.data, *end);
+                    lexer.skip_space();
+                    self.data[lexer.pos..].starts_with(b"endstream")
+                }
+            })
+            .or_else(|| {
+                let mut end = start + find(&self.data[start..], b"endstream")?;
+                if end > start && self.data[end - 1] == b'\n' {
+                    end -= 1;
+                }
+                if end > start && self.data[end - 1] == b'\r' {
+                    end -= 1;
+                }
+                Some(end)
+            })?;
+        Some((
+            id,
+            Object::Stream(Stream {
+                dict,
+                range: start..end,
+            }),
+        ))
+    }
+
+    fn object_stream(&self, id: u32) -> Option<ObjectList> {
+        if let Some(objects) = self.object_streams.borrow().get(&id) {
+            return Some(objects.clone());
+        }
+        let Object::Stream(stream) = self.get(id)? else {
+            return None;
+        };
+        let data = self.stream_data(&stream)?;
+        let count = stream.dict.get("N").and_then(Object::int)? as usize;
+        let first = stream.dict.get("First").and_then(Object::int)? as usize;
+        let mut header = Lexer::new(&data, 0);
+        let mut objects = Vec::new();
+        for _ in 0..count {
+            let (Some(number), Some(offset)) = (
+                header.object().and_then(|o| o.int()),
+                header.object().and_then(|o| o.int()),
+            ) else {
+                break;
+            };
+            let object = Lexer::new(&data, first + offset as usize)
+                .object()
+                .unwrap_or(Object::Null);
+            objects.push((number as u32, object));
+        }
+        let objects = Rc::new(objects);
+        self.object_streams.borrow_mut().insert(id, objects.clone());
+        Some(objects)
+    }
+
+    fn get(&self, id: u32) -> Option<Object> {
+        if let Some(object) = self.cache.borrow().get(&id) {
+            return Some(object.clone());
+        }
+        if !self.loading.borrow_mut().insert(id) {
+            return None;
+        }
+        let object = match self.xref.get(&id).copied() {
+            Some(XrefEntry::Offset(offset)) => self.read_object_at(offset).map(|(_, o)| o),
+            Some(XrefEntry::Compressed(stream, index)) => {
+                self.object_stream(stream).and_then(|objects| {
+                    objects
+                        .get(index)
+                        .filter(|(number, _)| *number == id)
+                        .or_else(|| objects.iter().find(|(number, _)| *number == id))
+                        .map(|(_, object)| object.clone())
+                })
+            }
+            None => None,
+        };
+        self.loading.borrow_mut().remove(&id);
+        if let Some(object) = &object {
+            self.cache.borrow_mut().insert(id, object.clone());
+        }
+        object
+    }
+
+    fn resolve(&self, object: &Object) -> Object {
+        let mut object = object.clone();
+        for _ in 0..8 {
+            match object {
+                Object::Ref(id) => object = self.get(id).unwrap_or(Object::Null),
+                _ => break,
+            }
+        }
+        object
+    }
+
+    fn lookup(&self, dict: &Dict, key: &str) -> Object {
+        dict.get(key)
+            .map(|object| self.resolve(object))
+            .unwrap_or(Object::Null)
+    }
+
+    fn lookup_dict(&self, dict: &Dict, key: &str) -> Option<Dict> {
+        match self.lookup(dict, key) {
+            Object::Dict(dict) => Some(dict),
+            Object::Stream(stream) => Some(stream.dict),
+            _ => None,
+        }
+    }
+
+    fn catalog(&self) -> Option<Dict> {
+        self.lookup_dict(&self.trailer, "Root")
+    }
+
+    // 按 Filter 依次解码，图片编码的流返回 None
+    fn stream_data(&self, stream: &Stream) -> Option<Vec<u8>> {
+        let mut data = self.data.get(stream.range.clone())?.to_vec();
+        let filters = match self.lookup(&stream.dict, "Filter") {
+            Object::Name(name) => vec![name],
+            Object::Array(items) => items
+                .iter()
+                .filter_map(|item| self.resolve(item).name().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let params: Vec<Option<Dict>> = match self.lookup(&stream.dict, "DecodeParms") {
+            Object::Dict(dict) => vec![Some(dict)],
+            Object::Array(items) => items
+                .iter()
+                .map(|item| match self.resolve(item) {
+                    Object::Dict(dict) => Some(dict),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        for (index, filter) in filters.iter().enumerate() {
+            let params = params.get(index).cloned().flatten().unwrap_or_default();
+            let param =
+                |key: &str, default: i64| params.get(key).and_then(Object::int).unwrap_or(default);
+            data = match filter.as_str() {
+                "FlateDecode" | "Fl" => inflate(&data),
+                "LZWDecode" | "LZW" => lzw_decode(&data, param("EarlyChange", 1) != 0),
+                "ASCIIHexDecode" | "AHx" => ascii_hex_decode(&data),
+                "ASCII85Decode" | "A85" => ascii85_decode(&data),
+                "RunLengthDecode" | "RL" => run_length_decode(&data),
+                _ => return None,
+            };
+            if matches!(filter.as_str(), "FlateDecode" | "Fl" | "LZWDecode" | "LZW")
+                && param("Predictor", 1) >= 10
+            {
+                data = png_predictor(
+                    &data,
+                    param("Colors", 1).max(1) as usize,
+                    param("BitsPerComponent", 8).max(1) as usize,
+                    param("Columns", 1).max(1) as usize,
+                );
+            }
+        }
+        Some(data)
+    }
+
+    // 按顺序列出页面：(对象编号, 页面字典)，继承的 Resources 写入页面字典
+    fn pages(&self) -> Vec<(u32, Dict)> {
+        let mut pages = Vec::new();
+        let Some(catalog) = self.catalog() else {
+            return pages;
+        };
+        let mut visited = HashSet::new();
+        if let Some(root) = catalog.get("Pages") {
+            self.collect_pages(root, None, &mut pages, &mut visited, 0);
+        }
+        pages
+    }
+
+    fn collect_pages(
+        &self,
+        node: &Object,
+        resources: Option<&Object>,
+        pages: &mut Vec<(u32, Dict)>,
+        visited: &mut HashSet<u32>,
+        depth: usize,
+    ) {
+        let id = match node {
+            Object::Ref(id) => {
+                if !visited.insert(*id) {
+                    return;
+                }
+                *id
+            }
+            _ => 0,
+        };
+        let Object::Dict(mut dict) = self.resolve(node) else {
+            return;
+        };
+        let resources = dict.get("Resources").or(resources).cloned();
+        match dict.get("Kids") {
+            Some(kids) if depth < 64 => {
+                if let Object::Array(kids) = self.resolve(kids) {
+                    for kid in &kids {
+                        self.collect_pages(kid, resources.as_ref(), pages, visited, depth + 1);
+                    }
+                }
+            }
+            _ => {
+                if let Some(resources) = resources {
+                    dict.insert("Resources".to_string(), resources);
+                }
+                pages.push((id, dict));
+            }
+        }
+    }
+
+    fn page_content(&self, page: &Dict) -> Vec<u8> {
+        let streams = match page.get("Contents") {
+            Some(Object::Array(items)) => items.clone(),
+            Some(contents) => match self.resolve(contents) {
+                Object::Array(items) => items,
+                other => vec![other],
+            },
+            None => Vec::new(),
+        };
+        let mut content = Vec::new();
+        for stream in &streams {
+            if let Object::Stream(stream) = self.resolve(stream) {
+                if let Some(data) = self.stream_data(&stream) {
+                    content.extend_from_slice(&data);
+                    content.push(b'\n');
+                }
+            }
+        }
+        content
+    }
+}
+
+// 文本字符串：带 BOM 的 UTF-16/UTF-8，否则按 PDFDocEncoding；部分中文文件直接写入 GBK
+fn text_string(bytes: &[u8]) -> String {
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = rest
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(rest).into_owned();
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+    if let Some(text) = GB18030.decode_without_bom_handling_and_without_replacement(bytes) {
+        if text.chars().any(is_wide) {
+            return text.into_owned();
+        }
+    }
+    WINDOWS_1252
+        .decode_without_bom_handling(bytes)
+        .0
+        .into_owned()
+}
+
+fn utf16_text(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+// 字形名称对应的文字：uniXXXX、uXXXXX、常用名称，连字用下划线连接
+fn glyph_name_text(name: &str) -> Option<String> {
+    let name = name.split('.').next().unwrap_or(name);
+    let mut text = String::new();
+    for part in name.split('_') {
+        if let Some(hex) = part.strip_prefix("uni").filter(|hex| {
+            !hex.is_empty() && hex.len() % 4 == 0 && hex.chars().all(|c| c.is_ascii_hexdigit())
+        }) {
+            for index in (0..hex.len()).step_by(4) {
+                let value = u32::from_str_radix(&hex[index..index + 4], 16).ok()?;
+                text.push(char::from_u32(value)?);
+            }
+        } else if let Some(hex) = part.strip_prefix('u').filter(|hex| {
+            (4..=6).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
+        }) {
+            text.push(char::from_u32(u32::from_str_radix(hex, 16).ok()?)?);
+        } else if part.len() == 1 && part.chars().all(|c| c.is_ascii_alphanumeric()) {
+            text.push_str(part);
+        } else if let Some((_, value)) = GLYPH_NAMES.iter().find(|(glyph, _)| *glyph == part) {
+            text.push_str(value);
+        } else if let Some(index) = LATIN1_NAMES.iter().position(|glyph| *glyph == part) {
+            text.push(char::from_u32(0xA1 + index as u32)?);
+        } else {
+            return None;
+        }
+    }
+    Some(text)
+}
+
+// StandardEncoding 与 ASCII 不同的部分
+fn standard_encoding(code: u8) -> Option<&'static str> {
+    Some(match code {
+        0x27 => "’",
+        0x60 => "‘",
+        0x20..=0x7E => return None,
+        0xA1 => "¡",
+        0xA2 => "¢",
+        0xA3 => "£",
+        0xA5 => "¥",
+        0xA7 => "§",
+        0xA9 => "'",
+        0xAA => "“",
+        0xAB => "«",
+        0xAE => "fi",
+        0xAF => "fl",
+        0xB1 => "–",
+        0xB2 => "†",
+        0xB3 => "‡",
+        0xB4 => "·",
+        0xB6 => "¶",
+        0xB7 => "•",
+        0xB8 => "‚",
+        0xB9 => "„",
+        0xBA => "”",
+        0xBB => "»",
+        0xBC => "…",
+        0xBD => "‰",
+        0xBF => "¿",
+        0xD0 => "—",
+        0xE1 => "Æ",
+        0xE8 => "Ł",
+        0xE9 => "Ø",
+        0xEA => "Œ",
+        0xF1 => "æ",
+        0xF5 => "ı",
+        0xF8 => "ł",
+        0xF9 => "ø",
+        0xFA => "œ",
+        0xFB => "ß",
+        _ => "",
+    })
+}
+
+fn base_encoding_text(encoding: &str, code: u8) -> Option<String> {
+    if code < 0x20 {
+        return None;
+    }
+    let text = match encoding {
+        "StandardEncoding" => match standard_encoding(code) {
+            Some("") => return None,
+            Some(text) => text.to_string(),
+            None => (code as char).to_string(),
+        },
+        "MacRomanEncoding" => MACINTOSH
+            .decode_without_bom_handling(&[code])
+            .0
+            .into_owned(),
+        _ => WINDOWS_1252
+            .decode_without_bom_handling(&[code])
+            .0
+            .into_owned(),
+    };
+    Some(text)
+}
+
+// 预定义 CMap 对应的多字节编码，文字可以直接按编码解码
+fn legacy_cmap_encoding(name: &str) -> Option<&'static Encoding> {
+    if name.contains("RKSJ") {
+        Some(SHIFT_JIS)
+    } else if name.starts_with("GB") {
+        Some(GB18030)
+    } else if name.contains("B5") {
+        Some(BIG5)
+    } else if name.starts_with("KSC") {
+        Some(EUC_KR)
+    } else if name.contains("EUC") {
+        Some(EUC_JP)
+    } else {
+        None
+    }
+}
+
+#[derive(Default)]
+struct CMap {
+    codespace: Vec<(Vec<u8>, Vec<u8>)>,
+    map: HashMap<u32, String>,
+}
+
+// 解析 CMap 的 codespacerange、bfchar 和 bfrange
+fn parse_cmap(data: &[u8]) -> CMap {
+    let mut cmap = CMap::default();
+    let mut lexer = Lexer::new(data, 0);
+    let mut operands: Vec<Object> = Vec::new();
+    while let Some(token) = lexer.object() {
+        let Object::Op(op) = token else {
+            operands.push(token);
+            continue;
+        };
+        match op.as_str() {
+            "endcodespacerange" => {
+                for pair in operands.chunks_exact(2) {
+                    if let [Object::Str(low), Object::Str(high)] = pair {
+                        cmap.codespace.push((low.clone(), high.clone()));
+                    }
+                }
+            }
+            "endbfchar" => {
+                for pair in operands.chunks_exact(2) {
+                    let Object::Str(code) = &pair[0] else {
+                        continue;
+                    };
+                    let text = match &pair[1] {
+                        Object::Str(text) => utf16_text(text),
+                        Object::Name(name) => glyph_name_text(name).unwrap_or_default(),
+                        _ => continue,
+                    };
+                    cmap.map.insert(read_be(code) as u32, text);
+                }
+            }
+            "endbfrange" => {
+                for triple in operands.chunks_exact(3) {
+                    let (Object::Str(low), Object::Str(high)) = (&triple[0], &triple[1]) else {
+                        continue;
+                    };
+                    let (low, high) = (read_be(low) as u32, read_be(high) as u32);
+                    if high < low || high - low > 0xFFFF {
+                        continue;
+                    }
+                    match &triple[2] {
+                        Object::Str(start) => {
+                            let mut units: Vec<u16> = start
+                                .chunks_exact(2)
+                                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                                .collect();
+                            for code in low..=high {
+                                cmap.map.insert(code, String::from_utf16_lossy(&units));
+                                if let Some(last) = units.last_mut() {
+                                    *last = last.wrapping_add(1);
+                                }
+                            }
+                        }
+                        Object::Array(items) => {
+                            for (code, item) in (low..=high).zip(items) {
+                                if let Object::Str(text) = item {
+                                    cmap.map.insert(code, utf16_text(text));
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        operands.clear();
+    }
+    cmap
+}
+
+// 字符编码的切分方式
+enum CodeScheme {
+    Single,
+    Double,
+    Ranges(Vec<(Vec<u8>, Vec<u8>)>),
+    // UniGB-UCS2-H 等以 Unicode 编码的预定义 CMap
+    Unicode,
+    Legacy(&'static Encoding),
+}
+
+struct PdfFont {
+    scheme: CodeScheme,
+    to_unicode: HashMap<u32, String>,
+    // 简单字体的编码表
+    simple: Vec<Option<String>>,
+    widths: HashMap<u32, f64>,
+    default_width: f64,
+    // 字宽单位：Type3 字体由 FontMatrix 决定，其他字体为 1/1000
+    width_scale: f64,
+}
+
+impl PdfFont {
+    fn load(document: &Document, dict: &Dict) -> PdfFont {
+        let to_unicode = match document.lookup(dict, "ToUnicode") {
+            Object::Stream(stream) => document
+                .stream_data(&stream)
+                .map(|data| parse_cmap(&data).map)
+                .unwrap_or_default(),
+            _ => HashMap::new(),
+        };
+        let subtype = document.lookup(dict, "Subtype");
+        if subtype.name() == Some("Type0") {
+            let scheme = match document.lookup(dict, "Encoding") {
+                Object::Name(name) if name.starts_with("Identity") => CodeScheme::Double,
+                Object::Name(name) if name.contains("UCS2") || name.contains("UTF16") => {
+                    CodeScheme::Unicode
+                }
+                Object::Name(name) => legacy_cmap_encoding(&name)
+                    .map(CodeScheme::Legacy)
+                    .unwrap_or(CodeScheme::Double),
+                Object::Stream(stream) => {
+                    let codespace = document
+                        .stream_data(&stream)
+                        .map(|data| parse_cmap(&data).codespace)
+                        .unwrap_or_default();
+                    if codespace.is_empty() {
+                        CodeScheme::Double
+                    } else {
+                        CodeScheme::Ranges(codespace)
+                    }
+                }
+                _ => CodeScheme::Double,
+            };
+            let descendant = match document.lookup(dict, "DescendantFonts") {
+                Object::Array(items) => items.first().map(|item| document.resolve(item)),
+                _ => None,
+            };
+            let mut widths = HashMap::new();
+            let mut default_width = 1000.0;
+            if let Some(Object::Dict(descendant)) = descendant {
+                default_width = document
+                    .lookup(&descendant, "DW")
+                    .number()
+                    .unwrap_or(1000.0);
+                if let Object::Array(items) = document.lookup(&descendant, "W") {
+                    read_cid_widths(document, &items, &mut widths);
+                }
+            }
+            return PdfFont {
+                scheme,
+                to_unicode,
+                simple: Vec::new(),
+                widths,
+                default_width,
+                width_scale: 0.001,
+            };
+        }
+
+        let mut widths = HashMap::new();
+        let first = document.lookup(dict, "FirstChar").int().unwrap_or(0);
+        if let Object::Array(items) = document.lookup(dict, "Widths") {
+            for (index, item) in items.iter().enumerate() {
+                if let Some(width) = document.resolve(item).number() {
+                    widths.insert((first + index as i64) as u32, width);
+                }
+            }
+        }
+        let missing = document
+            .lookup_dict(dict, "FontDescriptor")
+            .and_then(|descriptor| document.lookup(&descriptor, "MissingWidth").number())
+            .filter(|width| *width > 0.0);
+        let width_scale = match document.lookup(dict, "FontMatrix") {
+            Object::Array(items) if subtype.name() == Some("Type3") => {
+                items.first().and_then(Object::number).unwrap_or(0.001)
+            }
+            _ => 0.001,
+        };
+        PdfFont {
+            scheme: CodeScheme::Single,
+            to_unicode,
+            simple: simple_encoding(document, dict, subtype.name()),
+            default_width: missing.unwrap_or(if widths.is_empty() { 500.0 } else { 0.0 }),
+            widths,
+            width_scale,
+        }
+    }
+
+    fn code_length(&self, bytes: &[u8]) -> usize {
+        let length = match &self.scheme {
+            CodeScheme::Single => 1,
+            CodeScheme::Double | CodeScheme::Unicode => 2,
+            CodeScheme::Ranges(ranges) => ranges
+                .iter()
+                .filter(|(low, high)| {
+                    low.len() <= bytes.len()
+                        && (0..low.len()).all(|i| low[i] <= bytes[i] && bytes[i] <= high[i])
+                })
+                .map(|(low, _)| low.len())
+                .min()
+                .unwrap_or(1),
+            CodeScheme::Legacy(encoding) => {
+                let lead = bytes[0];
+                let double = if *encoding == SHIFT_JIS {
+                    matches!(lead, 0x81..=0x9F | 0xE0..=0xFC)
+                } else {
+                    lead >= 0x81 && lead != 0xFF
+                };
+                if double {
+                    2
+                } else {
+                    1
+                }
+            }
+        };
+        length.min(bytes.len())
+    }
+
+    // 字符串切分为编码，返回 (编码, 文字, 是否为单字节空格)
+    fn decode(&self, bytes: &[u8]) -> Vec<(u32, String, bool)> {
+        let mut codes = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let length = self.code_length(&bytes[pos..]);
+            let raw = &bytes[pos..pos + length];
+            let code = read_be(raw) as u32;
+            let text = self
+                .to_unicode
+                .get(&code)
+                .cloned()
+                .or_else(|| match &self.scheme {
+                    CodeScheme::Single => self.simple.get(code as usize).cloned().flatten(),
+                    CodeScheme::Unicode => char::from_u32(code).map(String::from),
+                    CodeScheme::Legacy(encoding) => encoding
+                        .decode_without_bom_handling_and_without_replacement(raw)
+                        .map(|text| text.into_owned()),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            codes.push((code, text, length == 1 && raw[0] == b' '));
+            pos += length;
+        }
+        codes
+    }
+
+    fn width(&self, code: u32) -> f64 {
+        let width = match self.widths.get(&code) {
+            Some(width) => *width,
+            // 多字节编码没有可用的字宽，单字节字符按半角处理
+            None if matches!(self.scheme, CodeScheme::Legacy(_) | CodeScheme::Unicode)
+                && code < 0x100 =>
+            {
+                self.default_width / 2.0
+            }
+            None => self.default_width,
+        };
+        width * self.width_scale
+    }
+}
+
+// CID 字体的 W 数组：「起始 [宽度...]」或「起始 结束 宽度」
+fn read_cid_widths(document: &Document, items: &[Object], widths: &mut HashMap<u32, f64>) {
+    let items: Vec<Object> = items.iter().map(|item| document.resolve(item)).collect();
+    let mut index = 0;
+    while index + 1 < items.len() {
+        let Some(first) = items[index].int() else {
+            index += 1;
+            continue;
+        };
+        match &items[index + 1] {
+            Object::Array(list) => {
+                for (offset, width) in list.iter().enumerate() {
+                    if let Some(width) = width.number() {
+                        widths.insert((first + offset as i64) as u32, width);
+                    }
+                }
+                index += 2;
+            }
+            last => {
+                let (Some(last), Some(width)) =
+                    (last.int(), items.get(index + 2).and_then(Object::number))
+                else {
+                    break;
+                };
+                if last >= first && last - first <= 0xFFFF {
+                    for code in first..=last {
+                        widths.insert(code as u32, width);
+                    }
+                }
+                index += 3;
+            }
+        }
+    }
+}
+
+// 简单字体的编码表：基础编码加 Differences
+fn simple_encoding(document: &Document, dict: &Dict, subtype: Option<&str>) -> Vec<Option<String>> {
+    let (base, differences) = match document.lookup(dict, "Encoding") {
+        Object::Name(name) => (Some(name), None),
+        Object::Dict(encoding) => (
+            document
+                .lookup(&encoding, "BaseEncoding")
+                .name()
+                .map(str::to_string),
+            match document.lookup(&encoding, "Differences") {
+                Object::Array(items) => Some(items),
+                _ => None,
+            },
+        ),
+        _ => (None, None),
+    };
+    let base = base.unwrap_or_else(|| {
+        if subtype == Some("TrueType") {
+            "WinAnsiEncoding".to_string()
+        } else {
+            "StandardEncoding".to_string()
+        }
+    });
+    let mut table: Vec<Option<String>> = (0..=255u8)
+        .map(|code| base_encoding_text(&base, code))
+        .collect();
+    if let Some(differences) = differences {
+        let mut code = 0usize;
+        for item in &differences {
+            match document.resolve(item) {
+                Object::Int(value) => code = value.max(0) as usize,
+                Object::Name(name) => {
+                    if let Some(slot) = table.get_mut(code) {
+                        *slot = glyph_name_text(&name);
+                    }
+                    code += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+    table
+}
+
+fn multiply(m: [f64; 6], n: [f64; 6]) -> [f64; 6] {
+    [
+        m[0] * n[0] + m[1] * n[2],
+        m[0] * n[1] + m[1] * n[3],
+        m[2] * n[0] + m[3] * n[2],
+        m[2] * n[1] + m[3] * n[3],
+        m[4] * n[0] + m[5] * n[2] + n[4],
+        m[4] * n[1] + m[5] * n[3] + n[5],
+    ]
+}
+
+fn translate(x: f64, y: f64) -> [f64; 6] {
+    [1.0, 0.0, 0.0, 1.0, x, y]
+}
+
+fn matrix(objects: &[Object]) -> Option<[f64; 6]> {
+    let values: Vec<f64> = objects.iter().filter_map(Object::number).collect();
+    values.get(values.len().checked_sub(6)?..)?.try_into().ok()
+}
+
+// 一次文字绘制的结果，坐标为页面坐标（y 向上）
+struct TextChunk {
+    x: f64,
+    y: f64,
+    end: f64,
+    size: f64,
+    text: String,
+}
+
+#[derive(Clone)]
+struct GraphicsState {
+    ctm: [f64; 6],
+    font: Option<Rc<PdfFont>>,
+    size: f64,
+    char_spacing: f64,
+    word_spacing: f64,
+    scale: f64,
+    leading: f64,
+    rise: f64,
+}
+
+// 执行页面内容流，收集文字
+struct Extractor<'a> {
+    document: &'a Document,
+    fonts: HashMap<u32, Rc<PdfFont>>,
+    chunks: Vec<TextChunk>,
+    forms: Vec<u32>,
+}
+
+impl Extractor<'_> {
+    fn font(&mut self, resources: &Dict, name: &str) -> Option<Rc<PdfFont>> {
+        let fonts = self.document.lookup_dict(resources, "Font")?;
+        let entry = fonts.get(name)?;
+        if let Object::Ref(id) = entry {
+            if let Some(font) = self.fonts.get(id) {
+                return Some(font.clone());
+            }
+        }
+        let Object::Dict(dict) = self.document.resolve(entry) else {
+            return None;
+        };
+        let font = Rc::new(PdfFont::load(self.document, &dict));
+        if let Object::Ref(id) = entry {
+            self.fonts.insert(*id, font.clone());
+        }
+        Some(font)
+    }
+
+    fn show(&mut self, bytes: &[u8], state: &GraphicsState, tm: &mut [f64; 6]) {
+        let Some(font) = state.font.clone() else {
+            return;
+        };
+        let render = [
+            state.size * state.scale,
+            0.0,
+            0.0,
+            state.size,
+            0.0,
+            state.rise,
+        ];
+        let start = multiply(render, multiply(*tm, state.ctm));
+        let mut text = String::new();
+        for (code, piece, space) in font.decode(bytes) {
+            let mut advance = font.width(code) * state.size + state.char_spacing;
+            if space {
+                advance += state.word_spacing;
+            }
+            *tm = multiply(translate(advance * state.scale, 0.0), *tm);
+            text.push_str(&piece);
+        }
+        let end = multiply(render, multiply(*tm, state.ctm));
+        if text.is_empty() {
+            return;
+        }
+        self.chunks.push(TextChunk {
+            x: start[4],
+            y: start[5],
+            end: end[4],
+            size: start[2].hypot(start[3]).max(0.1),
+            text,
+        });
+    }
+
+    fn run(&mut self, content: &[u8], resources: &Dict, ctm: [f64; 6], depth: usize) {
+        let mut lexer = Lexer::new(content, 0);
+        let mut operands: Vec<Object> = Vec::new();
+        let mut state = GraphicsState {
+            ctm,
+            font: None,
+            size: 0.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            scale: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+        };
+        let mut stack: Vec<GraphicsState> = Vec::new();
+        let (mut tm, mut lm) = (IDENTITY, IDENTITY);
+        while let Some(object) = lexer.object() {
+            let Object::Op(op) = object else {
+                operands.push(object);
+                continue;
+            };
+            let last = operands.last().and_then(Object::number).unwrap_or(0.0);
+            let next_line = |state: &GraphicsState, tm: &mut [f64; 6], lm: &mut [f64; 6]| {
+                *lm = multiply(translate(0.0, -state.leading), *lm);
+                *tm = *lm;
+            };
+            match op.as_str() {
+                "q" => stack.push(state.clone()),
+                "Q" => {
+                    if let Some(saved) = stack.pop() {
+                        state = saved;
+                    }
+                }
+                "cm" => {
+                    if let Some(m) = matrix(&operands) {
+                        state.ctm = multiply(m, state.ctm);
+                    }
+                }
+                "BT" => {
+                    tm = IDENTITY;
+                    lm = IDENTITY;
+                }
+                "Tf" => {
+                    if let [.., Object::Name(name), size] = operands.as_slice() {
+                        state.font = self.font(resources, name);
+                        state.size = size.number().unwrap_or(0.0);
+                    }
+                }
+                "Tc" => state.char_spacing = last,
+                "Tw" => state.word_spacing = last,
+                "Tz" => state.scale = last / 100.0,
+                "TL" => state.leading = last,
+                "Ts" => state.rise = last,
+                "Td" | "TD" => {
+                    if let [.., tx, ty] = operands.as_slice() {
+                        let (tx, ty) = (tx.number().unwrap_or(0.0), ty.number().unwrap_or(0.0));
+                        if op == "TD" {
+                            state.leading = -ty;
+                        }
+                        lm = multiply(translate(tx, ty), lm);
+                        tm = lm;
+                    }
+                }
+                "Tm" => {
+                    if let Some(m) = matrix(&operands) {
+                        tm = m;
+                        lm = m;
+                    }
+                }
+                "T*" => next_line(&state, &mut tm, &mut lm),
+                "Tj" | "'" | "\"" => {
+                    if op == "\"" {
+                        if let [aw, ac, _] = operands.as_slice() {
+                            state.word_spacing = aw.number().unwrap_or(0.0);
+                            state.char_spacing = ac.number().unwrap_or(0.0);
+                        }
+                    }
+                    if op != "Tj" {
+                        next_line(&state, &mut tm, &mut lm);
+                    }
+                    if let Some(Object::Str(bytes)) = operands.last() {
+                        self.show(bytes, &state, &mut tm);
+                    }
+                }
+                "TJ" => {
+                    if let Some(Object::Array(items)) = operands.last() {
+                        for item in items {
+                            match item {
+                                Object::Str(bytes) => self.show(bytes, &state, &mut tm),
+                                item => {
+                                    let adjust = item.number().unwrap_or(0.0);
+                                    let tx = -adjust / 1000.0 * state.size * state.scale;
+                                    tm = multiply(translate(tx, 0.0), tm);
+                                }
+                            }
+                        }
+                    }
+                }
+                "Do" => {
+                    if let Some(Object::Name(name)) = operands.last() {
+                        self.form(resources, name, state.ctm, depth);
+                    }
+                }
+                "BI" => {
+                    while let Some(object) = lexer.object() {
+                        if object == Object::Op("ID".to_string()) {
+                            lexer.skip_inline_image();
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            operands.clear();
+        }
+    }
+
+    // 表单 XObject 中的文字，图片忽略
+    fn form(&mut self, resources: &Dict, name: &str, ctm: [f64; 6], depth: usize) {
+        if depth >= MAX_FORM_DEPTH {
+            return;
+        }
+        let Some(xobjects) = self.document.lookup_dict(resources, "XObject") else {
+            return;
+        };
+        let Some(entry) = xobjects.get(name) else {
+            return;
+        };
+        let id = match entry {
+            Object::Ref(id) if self.forms.contains(id) => return,
+            Object::Ref(id) => *id,
+            _ => 0,
+        };
+        let Object::Stream(stream) = self.document.resolve(entry) else {
+            return;
+        };
+        if stream.dict.get("Subtype").and_then(Object::name) != Some("Form") {
+            return;
+        }
+        let m = match self.document.lookup(&stream.dict, "Matrix") {
+            Object::Array(items) => matrix(&items).unwrap_or(IDENTITY),
+            _ => IDENTITY,
+        };
+        let form_resources = self
+            .document
+            .lookup_dict(&stream.dict, "Resources")
+            .unwrap_or_else(|| resources.clone());
+        let Some(data) = self.document.stream_data(&stream) else {
+            return;
+        };
+        self.forms.push(id);
+        self.run(&data, &form_resources, multiply(m, ctm), depth + 1);
+        self.forms.pop();
+    }
+}
+
+// 页面中的一行文字，y 为基线位置（向上）
+#[derive(Debug, Clone)]
+struct TextLine {
+    page: usize,
+    y: f64,
+    left: f64,
+    right: f64,
+    size: f64,
+    text: String,
+}
+
+// 按基线把文字块归为行，行内按横坐标排序，间距较大处补空格
+fn page_lines(page: usize, chunks: Vec<TextChunk>) -> Vec<TextLine> {
+    let mut groups: Vec<Vec<TextChunk>> = Vec::new();
+    for chunk in chunks {
+        let group = groups.iter_mut().rev().find(|group| {
+            let tolerance = group[0].size.min(chunk.size) * 0.5;
+            (group[0].y - chunk.y).abs() < tolerance
+        });
+        match group {
+            Some(group) => group.push(chunk),
+            None => groups.push(vec![chunk]),
+        }
+    }
+    groups.sort_by(|a, b| b[0].y.total_cmp(&a[0].y));
+
+    let mut lines = Vec::new();
+    for mut group in groups {
+        group.sort_by(|a, b| a.x.total_cmp(&b.x));
+        let mut text = String::new();
+        let mut previous: Option<&TextChunk> = None;
+        for chunk in &group {
+            if let Some(previous) = previous {
+                // 伪粗体会把同一段文字错开一点重复绘制
+                if previous.text == chunk.text && (chunk.x - previous.x).abs() < chunk.size * 0.3 {
+                    continue;
+                }
+                let gap = chunk.x - previous.end;
+                let last = text.chars().last();
+                let first = chunk.text.chars().next();
+                let both_wide = last.is_some_and(is_wide) && first.is_some_and(is_wide);
+                if gap > chunk.size * 0.2
+                    && last.is_some_and(|c| !c.is_whitespace())
+                    && first.is_some_and(|c| !c.is_whitespace())
+                    && !(both_wide && gap < chunk.size * 1.5)
+                {
+                    text.push(' ');
+                }
+            }
+            text.push_str(&chunk.text);
+            previous = Some(chunk);
+        }
+
+        let mut normalized = String::with_capacity(text.len());
+        for c in text.chars() {
+            let c = match c {
+                '\u{a0}' | '\t' | '\r' | '\n' => ' ',
+                c if c.is_control() => continue,
+                c => c,
+            };
+            if c == ' ' && (normalized.is_empty() || normalized.ends_with(' ')) {
+                continue;
+            }
+            normalized.push(c);
+        }
+        let text = normalized.trim_end().to_string();
+        if text.trim_matches('\u{3000}').is_empty() {
+            continue;
+        }
+        // 行的字号取文字最多的文字块
+        let size = group
+            .iter()
+            .max_by_key(|chunk| chunk.text.chars().count())
+            .map_or(0.0, |chunk| chunk.size);
+        lines.push(TextLine {
+            page,
+            y: group[0].y,
+            left: group.iter().map(|chunk| chunk.x).fold(f64::MAX, f64::min),
+            right: group.iter().map(|chunk| chunk.end).fold(f64::MIN, f64::max),
+            size,
+            text,
+        });
+    }
+    lines
+}
+
+// 正文字号：按字数统计最多的字号
+fn body_size(lines: &[TextLine]) -> f64 {
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+    for line in lines {
+        *counts.entry((line.size * 2.0).round() as i64).or_default() += line.text.chars().count();
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(size, count)| (*count, *size))
+        .map_or(12.0, |(size, _)| size as f64 / 2.0)
+}
+
+fn is_body_line(line: &TextLine, body: f64) -> bool {
+    (line.size - body).abs() <= body * 0.1
+}
+
+// 规范写法的罗马数字（不区分大小写），ill、civil 这类单词返回 None
+fn roman_value(text: &str) -> Option<u32> {
+    let text = text.to_ascii_lowercase();
+    let digits = text
+        .chars()
+        .map(|c| match c {
+            'i' => Some(1),
+            'v' => Some(5),
+            'x' => Some(10),
+            'l' => Some(50),
+            'c' => Some(100),
+            'd' => Some(500),
+            'm' => Some(1000),
+            _ => None,
+        })
+        .collect::<Option<Vec<i64>>>()?;
+    let mut value = 0;
+    for (index, digit) in digits.iter().enumerate() {
+        if digits.get(index + 1).is_some_and(|next| next > digit) {
+            value -= digit;
+        } else {
+            value += digit;
+        }
+    }
+    if !(1..4000).contains(&value) {
+        return None;
+    }
+
+    let mut canonical = String::new();
+    let mut rest = value;
+    for (unit, symbol) in [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ] {
+        while rest >= unit {
+            canonical.push_str(symbol);
+            rest -= unit;
+        }
+    }
+    (canonical == text).then_some(value as u32)
+}
+
+enum PageNumber {
+    Arabic,
+    Roman(u32),
+}
+
+// 识别页码行：阿拉伯数字直接认定，罗马数字须是规范写法
+fn parse_page_number(pattern: &Regex, text: &str) -> Option<PageNumber> {
+    let number = pattern.captures(text.trim())?.get(1)?.as_str();
+    if number.chars().all(|c| c.is_ascii_digit()) {
+        Some(PageNumber::Arabic)
+    } else {
+        roman_value(number).map(PageNumber::Roman)
+    }
+}
+
+// 删除页眉、页脚和页码：每页开头和末尾两行中与正文隔开、重复出现在多页的文字，字号明显大的视为标题保留；
+// 罗马数字页码容易与 CC、Mix 这类文字混淆，只有相邻页同一位置是相连的罗马数字时才删除
+fn remove_page_furniture(pages: &mut [Vec<TextLine>], spacing: f64, page_number: &Regex) {
+    let body = body_size(&pages.concat());
+    let key = |text: &str| -> String {
+        text.chars()
+            .filter(|c| !c.is_ascii_digit() && !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    // (行序号, 靠近正文一侧的相邻行)，由内向外排列
+    let edges = |count: usize| -> Vec<(usize, usize)> {
+        let mut indexes: Vec<(usize, usize)> = (0..count.min(2))
+            .rev()
+            .map(|index| (index, index + 1))
+            .collect();
+        indexes.extend((count.saturating_sub(2).max(2)..count).map(|index| (index, index - 1)));
+        indexes
+    };
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for lines in pages.iter() {
+        let keys: HashSet<String> = edges(lines.len())
+            .into_iter()
+            .map(|(index, _)| key(&lines[index].text))
+            .filter(|key| !key.is_empty())
+            .collect();
+        for key in keys {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+    // 边缘行的位置：(是否在页首, 距页首或页尾的行数)
+    let slot = |index: usize, count: usize| {
+        if index < 2 {
+            (true, index)
+        } else {
+            (false, count - 1 - index)
+        }
+    };
+    let roman_numbers: Vec<HashMap<(bool, usize), u32>> = pages
+        .iter()
+        .map(|lines| {
+            edges(lines.len())
+                .into_iter()
+                .filter_map(
+                    |(index, _)| match parse_page_number(page_number, &lines[index].text) {
+                        Some(PageNumber::Roman(value)) => Some((slot(index, lines.len()), value)),
+                        _ => None,
+                    },
+                )
+                .collect()
+        })
+        .collect();
+
+    let threshold = (pages.len() / 5).max(3);
+    for (page, lines) in pages.iter_mut().enumerate() {
+        let mut remove = HashSet::new();
+        for (index, neighbour) in edges(lines.len()) {
+            let line = &lines[index];
+            let numbered = match parse_page_number(page_number, &line.text) {
+                Some(PageNumber::Arabic) => true,
+                Some(PageNumber::Roman(value)) => {
+                    let position = slot(index, lines.len());
+                    (page > 0 && roman_numbers[page - 1].get(&position) == Some(&(value - 1)))
+                        || roman_numbers
+                            .get(page + 1)
+                            .is_some_and(|next| next.get(&position) == Some(&(value + 1)))
+                }
+                None => false,
+            };
+            let isolated = remove.contains(&neighbour)
+                || lines
+                    .get(neighbour)
+                    .is_none_or(|other| (other.y - line.y).abs() > spacing * 1.3);
+            let repeated = counts
+                .get(&key(&line.text))
+                .is_some_and(|count| *count >= threshold);
+            if numbered
+                || (repeated
+                    && isolated
+                    && line.size <= body * 1.15
+                    && line.text.chars().count() <= MAX_FURNITURE_LENGTH)
+            {
+                remove.insert(index);
+            }
+        }
+        let mut index = 0;
+        lines.retain(|_| {
+            index += 1;
+            !remove.contains(&(index - 1))
+        });
+    }
+}
+
+// 版面度量：正文字号、行距和每页的左右边界
+struct Layout {
+    body: f64,
+    spacing: f64,
+    margins: Vec<(f64, f64)>,
+}
+
+impl Layout {
+    fn measure(pages: &[Vec<TextLine>]) -> Layout {
+        let all: Vec<TextLine> = pages.concat();
+        let body = body_size(&all);
+
+        let mut spacings: HashMap<i64, usize> = HashMap::new();
+        for lines in pages {
+            for pair in lines.windows(2) {
+                let diff = pair[0].y - pair[1].y;
+                if is_body_line(&pair[0], body)
+                    && is_body_line(&pair[1], body)
+                    && diff > body * 0.8
+                    && diff < body * 3.0
+                {
+                    *spacings.entry((diff * 2.0).round() as i64).or_default() += 1;
+                }
+            }
+        }
+        let spacing = spacings
+            .into_iter()
+            .max_by_key(|(spacing, count)| (*count, -*spacing))
+            .map_or(body * 1.5, |(spacing, _)| spacing as f64 / 2.0);
+
+        // 左边界取正文行最常见的起点，右边界取最靠右的终点
+        let measure = |lines: &[&TextLine]| -> Option<(f64, f64)> {
+            let mut lefts: HashMap<i64, usize> = HashMap::new();
+            for line in lines {
+                *lefts.entry(line.left.round() as i64).or_default() += 1;
+            }
+            let left = lefts
+                .into_iter()
+                .max_by_key(|(left, count)| (*count, -*left))?
+                .0 as f64;
+            let right = lines.iter().map(|line| line.right).fold(f64::MIN, f64::max);
+            Some((left, right))
+        };
+        let body_lines: Vec<&TextLine> =
+            all.iter().filter(|line| is_body_line(line, body)).collect();
+        let fallback = measure(&body_lines).unwrap_or((0.0, 0.0));
+        let margins = pages
+            .iter()
+            .map(|lines| {
+                let lines: Vec<&TextLine> = lines
+                    .iter()
+                    .filter(|line| is_body_line(line, body))
+                    .collect();
+                if lines.len() >= 3 {
+                    measure(&lines).unwrap_or(fallback)
+                } else {
+                    fallback
+                }
+            })
+            .collect();
+        Layout {
+            body,
+            spacing,
+            margins,
+        }
+    }
+
+    fn margins(&self, page: usize) -> (f64, f64) {
+        self.margins.get(page).copied().unwrap_or((0.0, 0.0))
+    }
+}
+
+// 重建的段落
+struct Paragraph {
+    text: String,
+    page: usize,
+    size: f64,
+}
+
+// 把下一行接到段落末尾：去掉行尾连字符，中文之间不加空格
+fn join_line(text: &mut String, line: &str) {
+    let line = line.trim_start_matches(' ');
+    let mut chars = text.chars().rev();
+    let (last, before) = (chars.next(), chars.next());
+    let first = line.chars().next();
+    let hyphenated = last == Some('-')
+        && before.is_some_and(char::is_alphabetic)
+        && first.is_some_and(char::is_lowercase);
+    if last == Some('\u{ad}') || hyphenated {
+        text.pop();
+    } else if !(last.is_some_and(is_wide) && first.is_some_and(is_wide)) {
+        text.push(' ');
+    }
+    text.push_str(line);
+}
+
+// 按缩进、行距、行尾留白和字号变化把行合并为段落，跨页的段落也会接上
+fn build_paragraphs(lines: &[&TextLine], layout: &Layout) -> Vec<Paragraph> {
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+    let mut previous: Option<&TextLine> = None;
+    for line in lines {
+        let starts_paragraph = match previous {
+            None => true,
+            Some(previous) => {
+                let (left, _) = layout.margins(line.page);
+                let (_, right) = layout.margins(previous.page);
+                let size_changed =
+                    (line.size - previous.size).abs() > previous.size.min(line.size) * 0.15;
+                let spacing = layout.spacing * line.size / layout.body;
+                let gap = previous.page == line.page && previous.y - line.y > spacing * 1.6;
+                let indented =
+                    line.left > left + line.size * 0.8 || line.text.starts_with('\u{3000}');
+                // 上一行末尾的空白放得下本行第一个词时，上一行是段落结尾
+                let per_char = (line.right - line.left) / line.text.chars().count().max(1) as f64;
+                let word = match line.text.chars().next() {
+                    Some(c) if is_wide(c) => 1,
+                    _ => line.text.split(' ').next().map_or(0, |w| w.chars().count()) + 1,
+                };
+                let short = previous.right + per_char * (word as f64) < right - line.size * 0.5
+                    && !previous.text.ends_with(['-', '\u{ad}']);
+                size_changed || gap || indented || short
+            }
+        };
+        match paragraphs.last_mut() {
+            Some(paragraph) if !starts_paragraph => join_line(&mut paragraph.text, &line.text),
+            _ => paragraphs.push(Paragraph {
+                text: line.text.clone(),
+                page: line.page,
+                size: line.size,
+            }),
+        }
+        previous = Some(line);
+    }
+    paragraphs
+}
+
+fn paragraphs_content(paragraphs: &[Paragraph]) -> String {
+    paragraphs
+        .iter()
+        .map(|paragraph| paragraph.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn page_href(page: usize) -> String {
+    format!("pdf-page-{}", page + 1)
+}
+
+// 书签项，target 为 (页面序号, 顶端位置)
+struct OutlineItem {
+    title: String,
+    target: Option<(usize, Option<f64>)>,
+    children: Vec<OutlineItem>,
+}
+
+struct OutlineReader<'a> {
+    document: &'a Document,
+    catalog: Dict,
+    page_index: HashMap<u32, usize>,
+    visited: HashSet<u32>,
+}
+
+impl OutlineReader<'_> {
+    fn read(&mut self) -> Vec<OutlineItem> {
+        let Some(outlines) = self.document.lookup_dict(&self.catalog, "Outlines") else {
+            return Vec::new();
+        };
+        self.items(outlines.get("First"), 0)
+    }
+
+    fn items(&mut self, first: Option<&Object>, depth: usize) -> Vec<OutlineItem> {
+        let mut items = Vec::new();
+        let mut next = first.cloned();
+        while let Some(Object::Ref(id)) = next {
+            if depth > 16 || items.len() > 10000 || !self.visited.insert(id) {
+                break;
+            }
+            let Some(Object::Dict(dict)) = self.document.get(id) else {
+                break;
+            };
+            let title = match self.document.lookup(&dict, "Title") {
+                Object::Str(bytes) => text_string(&bytes).trim().to_string(),
+                _ => String::new(),
+            };
+            let destination = match dict.get("Dest") {
+                Some(dest) => Some(self.document.resolve(dest)),
+                None => self.document.lookup_dict(&dict, "A").and_then(|action| {
+                    (self.document.lookup(&action, "S").name() == Some("GoTo"))
+                        .then(|| self.document.lookup(&action, "D"))
+                }),
+            };
+            let target = destination.and_then(|dest| self.destination(dest, 0));
+            let children = self.items(dict.get("First"), depth + 1);
+            items.push(OutlineItem {
+                title,
+                target,
+                children,
+            });
+            next = dict.get("Next").cloned();
+        }
+        items
+    }
+
+    // 目标可以是数组、命名目标（名称或字符串）或带 D 的字典
+    fn destination(&self, dest: Object, depth: usize) -> Option<(usize, Option<f64>)> {
+        if depth > 4 {
+            return None;
+        }
+        match dest {
+            Object::Array(items) => {
+                let page = match items.first()? {
+                    Object::Ref(id) => *self.page_index.get(id)?,
+                    Object::Int(index) => *index as usize,
+                    _ => return None,
+                };
+                let top = match items.get(1).and_then(Object::name) {
+                    Some("XYZ") => items.get(3).and_then(Object::number),
+                    Some("FitH") | Some("FitBH") => items.get(2).and_then(Object::number),
+                    Some("FitR") => items.get(5).and_then(Object::number),
+                    _ => None,
+                };
+                Some((page, top))
+            }
+            Object::Dict(dict) => self.destination(self.document.lookup(&dict, "D"), depth + 1),
+            Object::Name(name) => self.destination(self.named(name.as_bytes())?, depth + 1),
+            Object::Str(name) => self.destination(self.named(&name)?, depth + 1),
+            _ => None,
+        }
+    }
+
+    fn named(&self, name: &[u8]) -> Option<Object> {
+        if let Some(dests) = self.document.lookup_dict(&self.catalog, "Dests") {
+            if let Some(dest) = dests.get(String::from_utf8_lossy(name).as_ref()) {
+                return Some(self.document.resolve(dest));
+            }
+        }
+        let names = self.document.lookup_dict(&self.catalog, "Names")?;
+        let tree = self.document.lookup_dict(&names, "Dests")?;
+        self.name_tree(&tree, name, 0)
+    }
+
+    fn name_tree(&self, node: &Dict, name: &[u8], depth: usize) -> Option<Object> {
+        if depth > 32 {
+            return None;
+        }
+        if let Object::Array(pairs) = self.document.lookup(node, "Names") {
+            for pair in pairs.chunks_exact(2) {
+                if let Object::Str(key) = self.document.resolve(&pair[0]) {
+                    if key == name {
+                        return Some(self.document.resolve(&pair[1]));
+                    }
+                }
+            }
+        }
+        let Object::Array(kids) = self.document.lookup(node, "Kids") else {
+            return None;
+        };
+        for kid in &kids {
+            let Object::Dict(kid) = self.document.resolve(kid) else {
+                continue;
+            };
+            // 根据 Limits 跳过不包含该名称的子树
+            if let Object::Array(limits) = self.document.lookup(&kid, "Limits") {
+                if let [Object::Str(low), Object::Str(high)] = limits.as_slice() {
+                    if name < low.as_slice() || name > high.as_slice() {
+                        continue;
+                    }
+                }
+            }
+            if let Some(found) = self.name_tree(&kid, name, depth + 1) {
+                return Some(found);
+            }
+        }
+        None
+    }
+}
+
+fn normalize_title(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// 章节开头与书签标题相同的行（可能折成多行）不重复写入正文
+fn skip_title_lines<'a>(lines: &'a [&'a TextLine], title: &str) -> &'a [&'a TextLine] {
+    let title = normalize_title(title);
+    if title.is_empty() {
+        return lines;
+    }
+    let mut joined = String::new();
+    for (index, line) in lines.iter().take(3).enumerate() {
+        joined.push_str(&normalize_title(&line.text));
+        if joined == title {
+            return &lines[index + 1..];
+        }
+        if !title.starts_with(&joined) {
+            break;
+        }
+    }
+    lines
+}
+
+// 按书签切分：每行归入位置在它之前的最后一个书签
+fn chapters_from_outline(
+    outline: Vec<OutlineItem>,
+    lines: &[TextLine],
+    layout: &Layout,
+) -> Vec<ImportedChapter> {
+    struct Entry {
+        depth: usize,
+        title: String,
+        target: Option<(usize, f64)>,
+    }
+    fn flatten(items: Vec<OutlineItem>, depth: usize, entries: &mut Vec<Entry>) {
+        for item in items {
+            entries.push(Entry {
+                depth,
+                title: item.title,
+                target: item
+                    .target
+                    .map(|(page, top)| (page, top.unwrap_or(f64::INFINITY))),
+            });
+            flatten(item.children, depth + 1, entries);
+        }
+    }
+    let mut entries = Vec::new();
+    flatten(outline, 0, &mut entries);
+
+    // 没有目标的书签（如只用于分组的卷）使用其后第一个书签的位置
+    let mut next = None;
+    for entry in entries.iter_mut().rev() {
+        match entry.target {
+            Some(target) => next = Some(target),
+            None => entry.target = next,
+        }
+    }
+    let mut order: Vec<usize> = (0..entries.len())
+        .filter(|index| entries[*index].target.is_some())
+        .collect();
+    order.sort_by(|a, b| {
+        let (page_a, top_a) = entries[*a].target.unwrap_or_default();
+        let (page_b, top_b) = entries[*b].target.unwrap_or_default();
+        page_a.cmp(&page_b).then(top_b.total_cmp(&top_a))
+    });
+
+    let mut preface: Vec<&TextLine> = Vec::new();
+    let mut assigned: Vec<Vec<&TextLine>> = entries.iter().map(|_| Vec::new()).collect();
+    let mut current = None;
+    let mut cursor = 0;
+    for line in lines {
+        while let Some(&index) = order.get(cursor) {
+            let (page, top) = entries[index].target.unwrap_or_default();
+            if line.page > page || (line.page == page && line.y <= top + line.size) {
+                current = Some(index);
+                cursor += 1;
+            } else {
+                break;
+            }
+        }
+        match current {
+            Some(index) => assigned[index].push(line),
+            None => preface.push(line),
+        }
+    }
+
+    let mut chapters = entries
+        .iter()
+        .zip(&assigned)
+        .map(|(entry, lines)| {
+            let lines = skip_title_lines(lines, &entry.title);
+            let label = if entry.title.is_empty() {
+                PREFACE_LABEL.to_string()
+            } else {
+                entry.title.clone()
+            };
+            (
+                entry.depth,
+                ImportedChapter {
+                    label,
+                    href: entry
+                        .target
+                        .map(|(page, _)| page_href(page))
+                        .unwrap_or_default(),
+                    content: paragraphs_content(&build_paragraphs(lines, layout)),
+                    children: Vec::new(),
+                },
+            )
+        })
+        .peekable();
+
+    fn tree(
+        entries: &mut std::iter::Peekable<impl Iterator<Item = (usize, ImportedChapter)>>,
+        depth: usize,
+    ) -> Vec<ImportedChapter> {
+        let mut chapters = Vec::new();
+        while let Some((_, mut chapter)) = entries.next_if(|(level, _)| *level >= depth) {
+            chapter.children = tree(entries, depth + 1);
+            chapters.push(chapter);
+        }
+        chapters
+    }
+    let mut tree = tree(&mut chapters, 0);
+
+    let preface = paragraphs_content(&build_paragraphs(&preface, layout));
+    if !preface.trim().is_empty() {
+        tree.insert(
+            0,
+            ImportedChapter {
+                label: PREFACE_LABEL.to_string(),
+                href: page_href(0),
+                content: preface,
+                children: Vec::new(),
+            },
+        );
+    }
+    tree
+}
+
+// 没有书签时识别标题：先按字号，字号看不出标题时按章节标题规则
+fn chapters_from_headings(
+    lines: &[TextLine],
+    layout: &Layout,
+    title: &str,
+) -> Vec<ImportedChapter> {
+    let lines: Vec<&TextLine> = lines.iter().collect();
+    let paragraphs = build_paragraphs(&lines, layout);
+    let is_heading = |paragraph: &Paragraph| {
+        paragraph.size >= layout.body * HEADING_SCALE
+            && paragraph.text.chars().count() <= MAX_HEADING_LENGTH
+    };
+
+    let mut sizes: Vec<f64> = Vec::new();
+    for paragraph in paragraphs.iter().filter(|p| is_heading(p)) {
+        if !sizes.iter().any(|size| (size - paragraph.size).abs() < 0.5) {
+            sizes.push(paragraph.size);
+        }
+    }
+    sizes.sort_by(|a, b| b.total_cmp(a));
+    let heading_count = paragraphs.iter().filter(|p| is_heading(p)).count();
+
+    if heading_count >= 2 {
+        let level = |size: f64| {
+            sizes
+                .iter()
+                .position(|heading| (heading - size).abs() < 0.5)
+                .map(|index| index as u8 + 1)
+        };
+        let mut outline = ChapterOutline::default();
+        for paragraph in &paragraphs {
+            match level(paragraph.size).filter(|_| is_heading(paragraph)) {
+                Some(level @ (1 | 2)) => {
+                    outline.heading(level, paragraph.text.clone());
+                    outline.set_href(page_href(paragraph.page));
+                }
+                _ => outline.line(&paragraph.text),
+            }
+        }
+        return outline.finish(title);
+    }
+
+    split_by_headings(
+        &paragraphs_content(&paragraphs),
+        title,
+        &|line| match line {
+            0 => String::new(),
+            line => paragraphs
+                .get(line - 1)
+                .map(|paragraph| page_href(paragraph.page))
+                .unwrap_or_default(),
+        },
+    )
+}
+
+pub fn read_pdf(path: &Path) -> Result<ImportedBook, String> {
+    let data = fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let document = Document::load(data)?;
+    let pages = document.pages();
+    if pages.is_empty() {
+        return Err("PDF 文件中没有页面".to_string());
+    }
+
+    let mut extractor = Extractor {
+        document: &document,
+        fonts: HashMap::new(),
+        chunks: Vec::new(),
+        forms: Vec::new(),
+    };
+    let mut page_texts = Vec::with_capacity(pages.len());
+    for (index, (_, page)) in pages.iter().enumerate() {
+        let resources = document.lookup_dict(page, "Resources").unwrap_or_default();
+        let content = document.page_content(page);
+        extractor.run(&content, &resources, IDENTITY, 0);
+        page_texts.push(page_lines(index, std::mem::take(&mut extractor.chunks)));
+    }
+    if page_texts.iter().all(Vec::is_empty) {
+        return Err("PDF 中没有可提取的文字，可能是扫描版或图片 PDF".to_string());
+    }
+
+    let page_number = Regex::new(
+        r"(?i)^[-–—\s\[(（]*(?:page\s*)?(?:第\s*)?(\d+|[ivxlcdm]{1,15})(?:\s*页)?(?:\s*(?:/|of|共)\s*\d+\s*页?)?[-–—\s\])）]*$",
+    )
+    .expect("内置正则表达式有误");
+    let spacing = Layout::measure(&page_texts).spacing;
+    remove_page_furniture(&mut page_texts, spacing, &page_number);
+    let layout = Layout::measure(&page_texts);
+    let lines: Vec<TextLine> = page_texts.concat();
+
+    let info = document
+        .lookup_dict(&document.trailer, "Info")
+        .unwrap_or_default();
+    let info_text = |key: &str| match document.lookup(&info, key) {
+        Object::Str(bytes) => text_string(&bytes).trim().to_string(),
+        _ => String::new(),
+    };
+    let mut title = info_text("Title");
+    // Office 转换的文件标题常带有程序名前缀
+    if let Some(rest) = title.strip_prefix("Microsoft Word - ") {
+        title = rest
+            .trim_end_matches(".docx")
+            .trim_end_matches(".doc")
+            .to_string();
+    }
+    if title.is_empty() {
+        title = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+    }
+    let author = info_text("Author");
+
+    let page_index = pages
+        .iter()
+        .enumerate()
+        .filter(|(_, (id, _))| *id != 0)
+        .map(|(index, (id, _))| (*id, index))
+        .collect();
+    let outline = match document.catalog() {
+        Some(catalog) => OutlineReader {
+            document: &document,
+            catalog,
+            page_index,
+            visited: HashSet::new(),
+        }
+        .read(),
+        None => Vec::new(),
+    };
+    let chapters = if outline
+        .iter()
+        .any(|item| item.target.is_some() || !item.children.is_empty())
+    {
+        chapters_from_outline(outline, &lines, &layout)
+    } else {
+        chapters_from_headings(&lines, &layout, &title)
+    };
+
+    Ok(ImportedBook {
+        title,
+        author: if author.is_empty() {
+            "佚名".to_string()
+        } else {
+            author
+        },
+        description: "暂缺".to_string(),
+        chapters,
+        ..Default::default()
+    })
+}
+
+// 导入 PDF 文件：提取文字，按书签或识别出的标题切分章节，在一个事务中写入书籍和章节
+#[command]
+pub async fn import_pdf(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Book>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+
+    let book = match read_pdf(Path::new(&path)) {
+        Ok(book) => book,
+        Err(err) => return Ok(DbResponse::error(err)),
+    };
+
+    let mut db = get_db_connection(&state)?;
+    match save_imported_book(&mut db, &app_dir, &book) {
+        Ok(book) => Ok(DbResponse::success(book)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::roman_value;
+
+    #[test]
+    fn parses_only_canonical_roman_numerals() {
+        let cases = [
+            ("i", Some(1)),
+            ("iv", Some(4)),
+            ("IX", Some(9)),
+            ("xiv", Some(14)),
+            ("xl", Some(40)),
+            ("CC", Some(200)),
+            ("mcmxcix", Some(1999)),
+            ("ill", None),
+            ("civil", None),
+            ("iiii", None),
+            ("vx", None),
+            ("il", None),
+            ("mix", Some(1009)),
+            ("", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(roman_value(text), expected, "{}", text);
+        }
+    }
+}
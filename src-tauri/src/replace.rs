@@ -0,0 +1,459 @@
+use crate::database::{get_db_connection, DbResponse};
+use crate::revision::update_chapter_with_revision;
+use crate::setup::AppState;
+use crate::textutil::{split_tags, Segment};
+use crate::toc::sync_toc;
+use regex::{Captures, Regex, RegexBuilder};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tauri::{command, State};
+
+// 预览中匹配项前后保留的字符数，以及最多返回的匹配项数
+const CONTEXT_CHARS: usize = 20;
+const MAX_PREVIEW_MATCHES: usize = 500;
+
+// 替换范围：单个章节、整本书或整个书库（不含回收站中的书籍）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ReplaceScope {
+    Chapter { id: i64 },
+    Book { id: i64 },
+    Library,
+}
+
+// 替换作用的字段
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReplaceField {
+    #[default]
+    All,
+    Label,
+    Content,
+}
+
+impl ReplaceField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplaceField::All => "all",
+            ReplaceField::Label => "label",
+            ReplaceField::Content => "content",
+        }
+    }
+
+    // 无法识别的值按 All 处理
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "label" => ReplaceField::Label,
+            "content" => ReplaceField::Content,
+            _ => ReplaceField::All,
+        }
+    }
+
+    pub fn includes(&self, field: ReplaceField) -> bool {
+        *self == ReplaceField::All || *self == field
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindReplaceOptions {
+    pub pattern: String,
+    // 正则模式下可以使用 $1、${name} 引用捕获组
+    #[serde(default)]
+    pub replacement: String,
+    #[serde(default)]
+    pub is_regex: bool,
+    #[serde(default)]
+    pub ignore_case: bool,
+    pub scope: ReplaceScope,
+    #[serde(default)]
+    pub field: ReplaceField,
+}
+
+// 预览中的一个匹配项
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceMatch {
+    pub book_id: i64,
+    pub chapter_id: i64,
+    pub label: String,
+    pub field: ReplaceField,
+    pub before: String,
+    pub matched: String,
+    pub replacement: String,
+    pub after: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterReplaceCount {
+    pub book_id: i64,
+    pub chapter_id: i64,
+    pub label: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindReplaceResult {
+    pub total_matches: usize,
+    pub chapters: Vec<ChapterReplaceCount>,
+    // 只在预览时返回
+    pub matches: Vec<ReplaceMatch>,
+}
+
+// 编译查找模式：字面量模式会转义所有正则元字符；多行模式下 ^ $ 匹配每一行
+pub fn build_pattern(pattern: &str, is_regex: bool, ignore_case: bool) -> Result<Regex, String> {
+    if pattern.is_empty() {
+        return Err("查找内容不能为空".to_string());
+    }
+    let source = if is_regex {
+        pattern.to_string()
+    } else {
+        regex::escape(pattern)
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(ignore_case)
+        .multi_line(true)
+        .build()
+        .map_err(|e| format!("正则表达式错误: {}", e))
+}
+
+// 计算一次匹配的替换结果
+fn expand(caps: &Captures, replacement: &str, is_regex: bool) -> String {
+    if is_regex {
+        let mut result = String::new();
+        caps.expand(replacement, &mut result);
+        result
+    } else {
+        replacement.to_string()
+    }
+}
+
+fn tail_chars(text: &str, count: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    chars[chars.len().saturating_sub(count)..].iter().collect()
+}
+
+fn head_chars(text: &str, count: usize) -> String {
+    text.chars().take(count).collect()
+}
+
+// 去掉标签后的文本，offsets 记录每个文本片段在 text 和原文中的起点
+struct PlainText {
+    text: String,
+    offsets: Vec<(usize, usize)>,
+}
+
+impl PlainText {
+    fn new(raw: &str) -> Self {
+        let mut text = String::with_capacity(raw.len());
+        let mut offsets = Vec::new();
+        let mut raw_pos = 0;
+        for segment in split_tags(raw) {
+            match segment {
+                Segment::Text(part) => {
+                    offsets.push((text.len(), raw_pos));
+                    text.push_str(part);
+                    raw_pos += part.len();
+                }
+                Segment::Tag(tag) => raw_pos += tag.len(),
+            }
+        }
+        PlainText { text, offsets }
+    }
+
+    // 匹配起点在原文中的位置：落在片段开头时不包含前面的标签
+    fn raw_start(&self, pos: usize) -> usize {
+        let index = self.offsets.partition_point(|(start, _)| *start <= pos);
+        let (start, raw) = self.offsets[index.saturating_sub(1)];
+        raw + pos - start
+    }
+
+    // 匹配终点在原文中的位置：落在片段末尾时不包含后面的标签
+    fn raw_end(&self, pos: usize) -> usize {
+        let index = self.offsets.partition_point(|(start, _)| *start < pos);
+        let (start, raw) = self.offsets[index.saturating_sub(1)];
+        raw + pos - start
+    }
+}
+
+// 在一段文本中执行替换，返回替换后的文本和匹配次数；preview 不为空时收集匹配项及上下文
+// 匹配在去掉标签后的文本上进行，^ $ 仍按行匹配，也能匹配跨越行内标签的文字；
+// 被替换的文字中夹着的标签保留在替换结果之后，不会破坏标签的配对
+pub fn replace_in_text(
+    re: &Regex,
+    text: &str,
+    replacement: &str,
+    is_regex: bool,
+    mut preview: Option<&mut Vec<(String, String, String, String)>>,
+) -> (String, usize) {
+    let plain = PlainText::new(text);
+    let mut output = String::with_capacity(text.len());
+    let mut last = 0;
+    let mut count = 0;
+    for caps in re.captures_iter(&plain.text) {
+        let Some(whole) = caps.get(0) else {
+            continue;
+        };
+        // 跳过空匹配，避免 ^ 之类的模式在每一行插入内容时无法预期
+        if whole.is_empty() {
+            continue;
+        }
+        let replaced = expand(&caps, replacement, is_regex);
+        if let Some(preview) = preview.as_deref_mut() {
+            preview.push((
+                tail_chars(&plain.text[..whole.start()], CONTEXT_CHARS),
+                whole.as_str().to_string(),
+                replaced.clone(),
+                head_chars(&plain.text[whole.end()..], CONTEXT_CHARS),
+            ));
+        }
+        let (start, end) = (plain.raw_start(whole.start()), plain.raw_end(whole.end()));
+        output.push_str(&text[last..start]);
+        output.push_str(&replaced);
+        for segment in split_tags(&text[start..end]) {
+            if let Segment::Tag(tag) = segment {
+                output.push_str(tag);
+            }
+        }
+        last = end;
+        count += 1;
+    }
+    output.push_str(&text[last..]);
+    (output, count)
+}
+
+// 按范围读取章节 (id, bookId, label, content)
+fn load_scope_chapters(
+    db: &Connection,
+    scope: &ReplaceScope,
+) -> Result<Vec<(i64, i64, String, String)>, rusqlite::Error> {
+    let (condition, values) = match scope {
+        ReplaceScope::Chapter { id } => ("c.id = ?", vec![Value::Integer(*id)]),
+        ReplaceScope::Book { id } => ("c.bookId = ?", vec![Value::Integer(*id)]),
+        ReplaceScope::Library => ("b.isDel = 0", Vec::new()),
+    };
+    let sql = format!(
+        "SELECT c.id, c.bookId, c.label, c.content FROM ee_chapter c \
+         JOIN ee_book b ON b.id = c.bookId \
+         WHERE {} ORDER BY c.bookId, c.position, c.id",
+        condition
+    );
+    let mut stmt = db.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values), |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+        ))
+    })?;
+    rows.collect()
+}
+
+pub fn find_and_replace(
+    db: &Connection,
+    options: &FindReplaceOptions,
+    dry_run: bool,
+) -> Result<FindReplaceResult, String> {
+    let re = build_pattern(&options.pattern, options.is_regex, options.ignore_case)?;
+    let chapters = load_scope_chapters(db, &options.scope).map_err(|e| e.to_string())?;
+
+    let mut result = FindReplaceResult::default();
+    let mut changed_books = BTreeSet::new();
+
+    for (id, book_id, label, content) in chapters {
+        let mut count = 0;
+        let mut new_label = label.clone();
+        let mut new_content = content.clone();
+
+        for field in [ReplaceField::Label, ReplaceField::Content] {
+            if !options.field.includes(field) {
+                continue;
+            }
+            let source = if field == ReplaceField::Label {
+                &label
+            } else {
+                &content
+            };
+            let mut found = Vec::new();
+            let collect = dry_run && result.matches.len() < MAX_PREVIEW_MATCHES;
+            let (replaced, n) = replace_in_text(
+                &re,
+                source,
+                &options.replacement,
+                options.is_regex,
+                if collect { Some(&mut found) } else { None },
+            );
+            count += n;
+            for (before, matched, replacement, after) in found {
+                if result.matches.len() >= MAX_PREVIEW_MATCHES {
+                    break;
+                }
+                result.matches.push(ReplaceMatch {
+                    book_id,
+                    chapter_id: id,
+                    label: label.clone(),
+                    field,
+                    before,
+                    matched,
+                    replacement,
+                    after,
+                });
+            }
+            if field == ReplaceField::Label {
+                new_label = replaced;
+            } else {
+                new_content = replaced;
+            }
+        }
+
+        if count == 0 {
+            continue;
+        }
+        if !dry_run && (new_label != label || new_content != content) {
+            update_chapter_with_revision(db, id, &new_label, Some(&new_content))
+                .map_err(|e| e.to_string())?;
+            if new_label != label {
+                changed_books.insert(book_id);
+            }
+        }
+        result.total_matches += count;
+        result.chapters.push(ChapterReplaceCount {
+            book_id,
+            chapter_id: id,
+            label: new_label,
+            count,
+        });
+    }
+
+    // 标题有变化的书籍需要重新生成目录
+    for book_id in changed_books {
+        sync_toc(db, book_id).map_err(|e| e.to_string())?;
+    }
+    Ok(result)
+}
+
+// 查找替换：dry_run 时只返回匹配预览，否则在一个事务中执行替换
+#[command]
+pub async fn find_replace(
+    options: FindReplaceOptions,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<FindReplaceResult>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match find_and_replace(&tx, &options, dry_run.unwrap_or(false)) {
+        Ok(result) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(result))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace(pattern: &str, is_regex: bool, text: &str, replacement: &str) -> (String, usize) {
+        let re = build_pattern(pattern, is_regex, false).unwrap();
+        replace_in_text(&re, text, replacement, is_regex, None)
+    }
+
+    #[test]
+    fn replaces_around_tags() {
+        let cases = [
+            // 紧挨着标签的匹配不吞掉标签
+            (
+                "foo",
+                false,
+                "<p>foo</p><p>foo bar</p>",
+                "baz",
+                "<p>baz</p><p>baz bar</p>",
+                2,
+            ),
+            // 跨越行内标签的匹配，夹在中间的标签保留在替换结果之后
+            (
+                "hello",
+                false,
+                "<p>hel<b>lo</b> world</p>",
+                "hi",
+                "<p>hi<b></b> world</p>",
+                1,
+            ),
+            // 标签名和属性不参与匹配
+            (
+                "p",
+                false,
+                "<p class=\"p\">up</p>",
+                "P",
+                "<p class=\"p\">uP</p>",
+                1,
+            ),
+            // 捕获组替换
+            (
+                r"(\d+)章",
+                true,
+                "<p>12章 与 3章</p>",
+                "第$1章",
+                "<p>第12章 与 第3章</p>",
+                2,
+            ),
+            // 非正则模式下 $1 按字面替换
+            ("12", false, "<p>12</p>", "$1", "<p>$1</p>", 1),
+            // 多字节文字
+            (
+                "天下",
+                false,
+                "<p>天下第一</p>\n<p>“天下”</p>",
+                "世界",
+                "<p>世界第一</p>\n<p>“世界”</p>",
+                2,
+            ),
+            (
+                r"^第.章",
+                true,
+                "<h2>第一章</h2>\n<p>第二章说</p>",
+                "",
+                "<h2></h2>\n<p>说</p>",
+                2,
+            ),
+        ];
+        for (pattern, is_regex, text, replacement, expected, count) in cases {
+            assert_eq!(
+                replace(pattern, is_regex, text, replacement),
+                (expected.to_string(), count),
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn collects_preview_context() {
+        let re = build_pattern("天下", false, false).unwrap();
+        let mut preview = Vec::new();
+        let (_, count) = replace_in_text(
+            &re,
+            "<p>他说<em>天下</em>第一</p>",
+            "世界",
+            false,
+            Some(&mut preview),
+        );
+        assert_eq!(count, 1);
+        assert_eq!(
+            preview,
+            vec![(
+                "他说".to_string(),
+                "天下".to_string(),
+                "世界".to_string(),
+                "第一".to_string()
+            )]
+        );
+    }
+}
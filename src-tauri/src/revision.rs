@@ -0,0 +1,385 @@
+use crate::database::{get_current_time_string, get_db_connection, DbResponse};
+use crate::setup::AppState;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+use tauri::{command, State};
+
+// 编辑器会频繁自动保存，同一章节在该时间窗口内只保留一份修改前的快照
+pub const AUTOSAVE_COALESCE_SECS: i64 = 60;
+
+// 章节修订记录（列表中不包含正文）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionInfo {
+    pub id: i64,
+    pub chapter_id: i64,
+    pub book_id: i64,
+    pub label: String,
+    // 快照时间（秒级时间戳）
+    pub create_time: i64,
+    // 正文字符数
+    pub length: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Revision {
+    pub id: i64,
+    pub chapter_id: i64,
+    pub book_id: i64,
+    pub label: String,
+    pub content: String,
+    pub create_time: i64,
+}
+
+// 差异中的一行：tag 为 equal / insert / delete，行号从 1 开始
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub tag: String,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub text: String,
+}
+
+fn now_secs() -> i64 {
+    get_current_time_string().parse().unwrap_or(0)
+}
+
+// 更新章节前保存快照：内容没有变化时不保存；coalesce_secs 内已有快照时也不重复保存
+pub fn snapshot_before_update(
+    db: &Connection,
+    chapter_id: i64,
+    new_label: &str,
+    new_content: Option<&str>,
+    coalesce_secs: i64,
+) -> Result<(), rusqlite::Error> {
+    let current: Option<(i64, Option<String>, Option<String>)> = db
+        .query_row(
+            "SELECT bookId, label, content FROM ee_chapter WHERE id = ?",
+            params![chapter_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    let Some((book_id, label, content)) = current else {
+        return Ok(());
+    };
+    let label = label.unwrap_or_default();
+    let content = content.unwrap_or_default();
+
+    let unchanged = label == new_label && new_content.is_none_or(|new| new == content);
+    if unchanged {
+        return Ok(());
+    }
+
+    let now = now_secs();
+    if coalesce_secs > 0 {
+        // 合并到最近的快照时记下这次保存的时间，回滚时据此判断哪些时间点的内容没有保留
+        let coalesced = db.execute(
+            "UPDATE ee_chapter_revision SET updateTime = ? \
+             WHERE id = (SELECT id FROM ee_chapter_revision \
+                         WHERE chapterId = ? AND CAST(createTime AS INTEGER) > ? \
+                         ORDER BY id DESC LIMIT 1)",
+            params![now.to_string(), chapter_id, now - coalesce_secs],
+        )?;
+        if coalesced > 0 {
+            return Ok(());
+        }
+    }
+
+    db.execute(
+        "INSERT INTO ee_chapter_revision (chapterId, bookId, label, content, createTime, updateTime) \
+         VALUES (?, ?, ?, ?, ?, ?)",
+        params![chapter_id, book_id, label, content, now.to_string(), now.to_string()],
+    )?;
+    Ok(())
+}
+
+// 先保存快照再更新章节，批量操作使用（总是保存快照）
+pub fn update_chapter_with_revision(
+    db: &Connection,
+    chapter_id: i64,
+    label: &str,
+    content: Option<&str>,
+) -> Result<usize, rusqlite::Error> {
+    snapshot_before_update(db, chapter_id, label, content, 0)?;
+    let current_time = get_current_time_string();
+    match content {
+        Some(content) => db.execute(
+            "UPDATE ee_chapter SET label = ?, content = ?, updateTime = ? WHERE id = ?",
+            params![label, content, current_time, chapter_id],
+        ),
+        None => db.execute(
+            "UPDATE ee_chapter SET label = ?, updateTime = ? WHERE id = ?",
+            params![label, current_time, chapter_id],
+        ),
+    }
+}
+
+pub fn list_revisions(
+    db: &Connection,
+    chapter_id: i64,
+) -> Result<Vec<RevisionInfo>, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "SELECT id, chapterId, bookId, label, CAST(createTime AS INTEGER), length(content) \
+         FROM ee_chapter_revision WHERE chapterId = ? ORDER BY id DESC",
+    )?;
+    let rows = stmt.query_map(params![chapter_id], |row| {
+        Ok(RevisionInfo {
+            id: row.get(0)?,
+            chapter_id: row.get(1)?,
+            book_id: row.get(2)?,
+            label: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            create_time: row.get::<_, Option<i64>>(4)?.unwrap_or(0),
+            length: row.get::<_, Option<i64>>(5)?.unwrap_or(0),
+        })
+    })?;
+    rows.collect()
+}
+
+pub fn load_revision(db: &Connection, id: i64) -> Result<Option<Revision>, rusqlite::Error> {
+    db.query_row(
+        "SELECT id, chapterId, bookId, label, content, CAST(createTime AS INTEGER) \
+         FROM ee_chapter_revision WHERE id = ?",
+        params![id],
+        |row| {
+            Ok(Revision {
+                id: row.get(0)?,
+                chapter_id: row.get(1)?,
+                book_id: row.get(2)?,
+                label: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                content: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                create_time: row.get::<_, Option<i64>>(5)?.unwrap_or(0),
+            })
+        },
+    )
+    .optional()
+}
+
+// 读取某个版本的正文：revision_id 为空时表示章节当前内容
+fn load_version_text(
+    db: &Connection,
+    chapter_id: i64,
+    revision_id: Option<i64>,
+) -> Result<String, String> {
+    match revision_id {
+        Some(id) => {
+            let revision = load_revision(db, id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("修订记录 {} 不存在", id))?;
+            if revision.chapter_id != chapter_id {
+                return Err(format!("修订记录 {} 不属于章节 {}", id, chapter_id));
+            }
+            Ok(revision.content)
+        }
+        None => db
+            .query_row(
+                "SELECT content FROM ee_chapter WHERE id = ?",
+                params![chapter_id],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .map(|content| content.unwrap_or_default())
+            .ok_or_else(|| format!("章节 {} 不存在", chapter_id)),
+    }
+}
+
+// 按行比较两段文本，末行是否有换行符不算差异
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let with_newline = |text: &str| {
+        if text.is_empty() || text.ends_with('\n') {
+            text.to_string()
+        } else {
+            format!("{}\n", text)
+        }
+    };
+    let (old, new) = (with_newline(old), with_newline(new));
+    let diff = TextDiff::from_lines(&old, &new);
+    diff.iter_all_changes()
+        .map(|change| DiffLine {
+            tag: match change.tag() {
+                ChangeTag::Equal => "equal",
+                ChangeTag::Insert => "insert",
+                ChangeTag::Delete => "delete",
+            }
+            .to_string(),
+            old_line: change.old_index().map(|index| index + 1),
+            new_line: change.new_index().map(|index| index + 1),
+            text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+        })
+        .collect()
+}
+
+// 把章节恢复到某个修订版本（恢复前会保存当前内容，恢复操作本身也可撤销）
+pub fn rollback_chapter_to(
+    db: &Connection,
+    chapter_id: i64,
+    revision_id: i64,
+) -> Result<(), String> {
+    let revision = load_revision(db, revision_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("修订记录 {} 不存在", revision_id))?;
+    if revision.chapter_id != chapter_id {
+        return Err(format!(
+            "修订记录 {} 不属于章节 {}",
+            revision_id, chapter_id
+        ));
+    }
+    update_chapter_with_revision(db, chapter_id, &revision.label, Some(&revision.content))
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// 整本书回滚的结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookRollbackResult {
+    // 被恢复的章节数
+    pub restored: usize,
+    // 该时刻处于自动保存合并窗口内、没有保留对应内容的章节，这些章节恢复为窗口结束时的内容
+    pub inexact_chapter_ids: Vec<i64>,
+}
+
+// 把整本书恢复到 timestamp 时刻的状态
+// 快照保存的是修改前的内容，所以 timestamp 之后的第一份快照就是该时刻的内容；
+// 但如果某份快照之后的自动保存被合并（createTime < timestamp < updateTime），该时刻的中间内容已经丢失
+pub fn rollback_book_to(
+    db: &Connection,
+    book_id: i64,
+    timestamp: i64,
+) -> Result<BookRollbackResult, String> {
+    let rows = {
+        let mut stmt = db
+            .prepare(
+                "SELECT c.id, \
+                        (SELECT r.id FROM ee_chapter_revision r \
+                         WHERE r.chapterId = c.id AND CAST(r.createTime AS INTEGER) >= ?1 \
+                         ORDER BY CAST(r.createTime AS INTEGER), r.id LIMIT 1), \
+                        EXISTS(SELECT 1 FROM ee_chapter_revision r \
+                               WHERE r.chapterId = c.id AND CAST(r.createTime AS INTEGER) < ?1 \
+                               AND CAST(COALESCE(r.updateTime, r.createTime) AS INTEGER) > ?1) \
+                 FROM ee_chapter c WHERE c.bookId = ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![timestamp, book_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, bool>(2)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    let mut result = BookRollbackResult {
+        restored: 0,
+        inexact_chapter_ids: Vec::new(),
+    };
+    for (chapter_id, revision_id, inexact) in rows {
+        if inexact {
+            result.inexact_chapter_ids.push(chapter_id);
+        }
+        let Some(revision_id) = revision_id else {
+            continue;
+        };
+        let revision = load_revision(db, revision_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("修订记录 {} 不存在", revision_id))?;
+        update_chapter_with_revision(
+            db,
+            revision.chapter_id,
+            &revision.label,
+            Some(&revision.content),
+        )
+        .map_err(|e| e.to_string())?;
+        result.restored += 1;
+    }
+    Ok(result)
+}
+
+// 获取章节的修订记录列表（按时间倒序）
+#[command]
+pub fn get_chapter_revisions(
+    chapter_id: i64,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<RevisionInfo>>, String> {
+    let db = get_db_connection(&state)?;
+
+    match list_revisions(&db, chapter_id) {
+        Ok(revisions) => Ok(DbResponse::success(revisions)),
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
+}
+
+// 获取某个修订版本的完整内容
+#[command]
+pub fn get_revision(id: i64, state: State<'_, AppState>) -> Result<DbResponse<Revision>, String> {
+    let db = get_db_connection(&state)?;
+
+    match load_revision(&db, id) {
+        Ok(Some(revision)) => Ok(DbResponse::success(revision)),
+        Ok(None) => Ok(DbResponse::error(format!("修订记录 {} 不存在", id))),
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
+}
+
+// 比较章节的两个版本，版本 id 为空时表示章节当前内容
+#[command]
+pub fn diff_revisions(
+    chapter_id: i64,
+    from_id: Option<i64>,
+    to_id: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<DiffLine>>, String> {
+    let db = get_db_connection(&state)?;
+
+    let texts = load_version_text(&db, chapter_id, from_id)
+        .and_then(|old| load_version_text(&db, chapter_id, to_id).map(|new| (old, new)));
+    match texts {
+        Ok((old, new)) => Ok(DbResponse::success(diff_lines(&old, &new))),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 把章节恢复到指定的修订版本
+#[command]
+pub fn rollback_chapter(
+    chapter_id: i64,
+    revision_id: i64,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<()>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match rollback_chapter_to(&tx, chapter_id, revision_id) {
+        Ok(_) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(()))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 把整本书的所有章节恢复到某个时间点（秒级时间戳），返回恢复的章节数和无法精确恢复的章节
+#[command]
+pub fn rollback_book(
+    book_id: i64,
+    timestamp: i64,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<BookRollbackResult>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match rollback_book_to(&tx, book_id, timestamp) {
+        Ok(result) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(result))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
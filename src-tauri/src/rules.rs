@@ -0,0 +1,448 @@
+use crate::database::{get_current_time_string, get_db_connection, DbResponse};
+use crate::replace::{build_pattern, replace_in_text, ReplaceField};
+use crate::revision::update_chapter_with_revision;
+use crate::setup::AppState;
+use crate::textutil::{preview_diff, ChapterTransformResult};
+use crate::toc::sync_toc;
+use regex::Regex;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{command, State};
+
+// 导出文件的格式版本
+const EXPORT_FORMAT_VERSION: i64 = 1;
+
+fn default_enabled() -> bool {
+    true
+}
+
+// 一条清理规则，与查找替换的选项一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupRule {
+    #[serde(default)]
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub replacement: String,
+    #[serde(default)]
+    pub is_regex: bool,
+    #[serde(default)]
+    pub ignore_case: bool,
+    #[serde(default)]
+    pub field: ReplaceField,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+// 规则集：规则按顺序依次执行，id 为空表示新建
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleSet {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub rules: Vec<CleanupRule>,
+}
+
+// 导出文件：{ "version": 1, "ruleSets": [...] }
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleSetExport {
+    version: i64,
+    rule_sets: Vec<RuleSet>,
+}
+
+fn load_rules(db: &Connection, rule_set_id: i64) -> Result<Vec<CleanupRule>, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "SELECT name, pattern, replacement, isRegex, ignoreCase, field, enabled \
+         FROM ee_rule WHERE ruleSetId = ? ORDER BY position, id",
+    )?;
+    let rows = stmt.query_map(params![rule_set_id], |row| {
+        Ok(CleanupRule {
+            name: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+            pattern: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            replacement: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            is_regex: row.get::<_, Option<bool>>(3)?.unwrap_or(false),
+            ignore_case: row.get::<_, Option<bool>>(4)?.unwrap_or(false),
+            field: ReplaceField::parse(&row.get::<_, Option<String>>(5)?.unwrap_or_default()),
+            enabled: row.get::<_, Option<bool>>(6)?.unwrap_or(true),
+        })
+    })?;
+    rows.collect()
+}
+
+pub fn load_rule_set(db: &Connection, id: i64) -> Result<Option<RuleSet>, rusqlite::Error> {
+    let rule_set = db
+        .query_row(
+            "SELECT id, name, description FROM ee_rule_set WHERE id = ?",
+            params![id],
+            |row| {
+                Ok(RuleSet {
+                    id: Some(row.get(0)?),
+                    name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    description: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    rules: Vec::new(),
+                })
+            },
+        )
+        .optional()?;
+    match rule_set {
+        Some(mut rule_set) => {
+            rule_set.rules = load_rules(db, id)?;
+            Ok(Some(rule_set))
+        }
+        None => Ok(None),
+    }
+}
+
+pub fn list_rule_sets(db: &Connection) -> Result<Vec<RuleSet>, rusqlite::Error> {
+    let ids = {
+        let mut stmt = db.prepare("SELECT id FROM ee_rule_set ORDER BY name, id")?;
+        let rows = stmt.query_map([], |row| row.get::<_, i64>(0))?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+    let mut rule_sets = Vec::new();
+    for id in ids {
+        if let Some(rule_set) = load_rule_set(db, id)? {
+            rule_sets.push(rule_set);
+        }
+    }
+    Ok(rule_sets)
+}
+
+// 编译规则集中启用的规则，任何一条规则无效时返回错误
+fn compile_rules(rules: &[CleanupRule]) -> Result<Vec<(&CleanupRule, Regex)>, String> {
+    let mut compiled = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        if !rule.enabled {
+            continue;
+        }
+        let re = build_pattern(&rule.pattern, rule.is_regex, rule.ignore_case).map_err(|e| {
+            let name = if rule.name.is_empty() {
+                format!("第 {} 条规则", index + 1)
+            } else {
+                format!("规则「{}」", rule.name)
+            };
+            format!("{}: {}", name, e)
+        })?;
+        compiled.push((rule, re));
+    }
+    Ok(compiled)
+}
+
+// 保存规则集：id 为空时新建，否则覆盖原有的名称、描述和全部规则，返回规则集 id
+pub fn save_rule_set_record(db: &Connection, rule_set: &RuleSet) -> Result<i64, String> {
+    let name = rule_set.name.trim();
+    if name.is_empty() {
+        return Err("规则集名称不能为空".to_string());
+    }
+    // 保存前检查所有规则（包括未启用的）都能编译
+    let all_enabled: Vec<CleanupRule> = rule_set
+        .rules
+        .iter()
+        .cloned()
+        .map(|rule| CleanupRule {
+            enabled: true,
+            ..rule
+        })
+        .collect();
+    compile_rules(&all_enabled)?;
+
+    let duplicate: Option<i64> = db
+        .query_row(
+            "SELECT id FROM ee_rule_set WHERE name = ? AND id IS NOT ?",
+            params![name, rule_set.id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if duplicate.is_some() {
+        return Err(format!("规则集「{}」已存在", name));
+    }
+
+    let now = get_current_time_string();
+    let id = match rule_set.id {
+        Some(id) => {
+            let changed = db
+                .execute(
+                    "UPDATE ee_rule_set SET name = ?, description = ?, updateTime = ? WHERE id = ?",
+                    params![name, rule_set.description, now, id],
+                )
+                .map_err(|e| e.to_string())?;
+            if changed == 0 {
+                return Err(format!("规则集 {} 不存在", id));
+            }
+            id
+        }
+        None => {
+            db.execute(
+                "INSERT INTO ee_rule_set (name, description, createTime, updateTime) VALUES (?, ?, ?, ?)",
+                params![name, rule_set.description, now, now],
+            )
+            .map_err(|e| e.to_string())?;
+            db.last_insert_rowid()
+        }
+    };
+
+    db.execute("DELETE FROM ee_rule WHERE ruleSetId = ?", params![id])
+        .map_err(|e| e.to_string())?;
+    let mut stmt = db
+        .prepare(
+            "INSERT INTO ee_rule (ruleSetId, position, name, pattern, replacement, isRegex, ignoreCase, field, enabled) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .map_err(|e| e.to_string())?;
+    for (position, rule) in rule_set.rules.iter().enumerate() {
+        stmt.execute(params![
+            id,
+            position as i64,
+            rule.name,
+            rule.pattern,
+            rule.replacement,
+            rule.is_regex,
+            rule.ignore_case,
+            rule.field.as_str(),
+            rule.enabled
+        ])
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(id)
+}
+
+// 对整本书执行规则集，启用的规则按顺序依次作用于每个章节
+pub fn apply_rule_set_to_book(
+    db: &Connection,
+    rule_set_id: i64,
+    book_id: i64,
+    dry_run: bool,
+) -> Result<Vec<ChapterTransformResult>, String> {
+    let rule_set = load_rule_set(db, rule_set_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("规则集 {} 不存在", rule_set_id))?;
+    let rules = compile_rules(&rule_set.rules)?;
+
+    let chapters = {
+        let mut stmt = db
+            .prepare(
+                "SELECT id, label, content FROM ee_chapter WHERE bookId = ? ORDER BY position, id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![book_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    let mut results = Vec::new();
+    let mut label_changed = false;
+    for (id, label, content) in chapters {
+        let mut new_label = label.clone();
+        let mut new_content = content.clone();
+        let mut changes = 0;
+        for (rule, re) in &rules {
+            if rule.field.includes(ReplaceField::Label) {
+                let (replaced, count) =
+                    replace_in_text(re, &new_label, &rule.replacement, rule.is_regex, None);
+                new_label = replaced;
+                changes += count;
+            }
+            if rule.field.includes(ReplaceField::Content) {
+                let (replaced, count) =
+                    replace_in_text(re, &new_content, &rule.replacement, rule.is_regex, None);
+                new_content = replaced;
+                changes += count;
+            }
+        }
+        if new_label == label && new_content == content {
+            continue;
+        }
+
+        let preview = if dry_run {
+            Some(preview_diff(&content, &new_content))
+        } else {
+            update_chapter_with_revision(db, id, &new_label, Some(&new_content))
+                .map_err(|e| e.to_string())?;
+            label_changed |= new_label != label;
+            None
+        };
+        results.push(ChapterTransformResult {
+            chapter_id: id,
+            label: new_label,
+            changes,
+            preview,
+        });
+    }
+
+    if label_changed {
+        sync_toc(db, book_id).map_err(|e| e.to_string())?;
+    }
+    Ok(results)
+}
+
+// 导入规则集：同名的规则集会被覆盖，返回导入后的规则集 id
+pub fn import_rule_set_records(db: &Connection, json: &str) -> Result<Vec<i64>, String> {
+    // 兼容直接导出的规则集数组
+    let rule_sets = match serde_json::from_str::<RuleSetExport>(json) {
+        Ok(export) => {
+            if export.version > EXPORT_FORMAT_VERSION {
+                return Err(format!("不支持的规则集文件版本: {}", export.version));
+            }
+            export.rule_sets
+        }
+        Err(_) => serde_json::from_str::<Vec<RuleSet>>(json)
+            .map_err(|e| format!("规则集文件格式错误: {}", e))?,
+    };
+
+    let mut ids = Vec::new();
+    for rule_set in rule_sets {
+        let existing: Option<i64> = db
+            .query_row(
+                "SELECT id FROM ee_rule_set WHERE name = ?",
+                params![rule_set.name.trim()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let id = save_rule_set_record(
+            db,
+            &RuleSet {
+                id: existing,
+                ..rule_set
+            },
+        )?;
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+// 导出规则集为 JSON，ids 为空时导出全部
+pub fn export_rule_set_records(db: &Connection, ids: Option<&[i64]>) -> Result<String, String> {
+    let rule_sets = list_rule_sets(db)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|rule_set| match (ids, rule_set.id) {
+            (Some(ids), Some(id)) => ids.contains(&id),
+            _ => true,
+        })
+        .map(|rule_set| RuleSet {
+            id: None,
+            ..rule_set
+        })
+        .collect();
+    let export = RuleSetExport {
+        version: EXPORT_FORMAT_VERSION,
+        rule_sets,
+    };
+    serde_json::to_string_pretty(&export).map_err(|e| e.to_string())
+}
+
+// 获取所有规则集
+#[command]
+pub fn get_rule_sets(state: State<'_, AppState>) -> Result<DbResponse<Vec<RuleSet>>, String> {
+    let db = get_db_connection(&state)?;
+
+    match list_rule_sets(&db) {
+        Ok(rule_sets) => Ok(DbResponse::success(rule_sets)),
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
+}
+
+// 新建或修改规则集
+#[command]
+pub fn save_rule_set(
+    rule_set: RuleSet,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<i64>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match save_rule_set_record(&tx, &rule_set) {
+        Ok(id) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(id))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 删除规则集及其规则
+#[command]
+pub fn delete_rule_set(id: i64, state: State<'_, AppState>) -> Result<DbResponse<i64>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    let result = tx
+        .execute("DELETE FROM ee_rule WHERE ruleSetId = ?", params![id])
+        .and_then(|_| tx.execute("DELETE FROM ee_rule_set WHERE id = ?", params![id]));
+    match result {
+        Ok(0) => Ok(DbResponse::error(format!("规则集 {} 不存在", id))),
+        Ok(_) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(id))
+        }
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
+}
+
+// 对书籍执行规则集，dry_run 时只返回预览不写入数据库
+#[command]
+pub async fn apply_rule_set(
+    rule_set_id: i64,
+    book_id: i64,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<ChapterTransformResult>>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match apply_rule_set_to_book(&tx, rule_set_id, book_id, dry_run.unwrap_or(false)) {
+        Ok(results) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(results))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 导入 JSON 格式的规则集
+#[command]
+pub fn import_rule_sets(
+    json: String,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<i64>>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match import_rule_set_records(&tx, &json) {
+        Ok(ids) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(ids))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 导出规则集为 JSON 字符串，由前端保存到文件
+#[command]
+pub fn export_rule_sets(
+    ids: Option<Vec<i64>>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<String>, String> {
+    let db = get_db_connection(&state)?;
+
+    match export_rule_set_records(&db, ids.as_deref()) {
+        Ok(json) => Ok(DbResponse::success(json)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
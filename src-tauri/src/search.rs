@@ -0,0 +1,270 @@
+use crate::database::{get_db_connection, DbResponse};
+use crate::setup::AppState;
+use crate::textutil::strip_tags;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use tauri::{command, State};
+
+// 高亮标记和摘要长度；摘要先用私用区字符标记，转义后再换成 HTML 标签
+const MARK_START: &str = "<mark>";
+const MARK_END: &str = "</mark>";
+const PLACEHOLDER_START: &str = "\u{E000}";
+const PLACEHOLDER_END: &str = "\u{E001}";
+const SNIPPET_CONTEXT: usize = 24;
+const DEFAULT_LIMIT: i64 = 100;
+
+// trigram 分词至少需要 3 个字符才能走全文索引
+const MIN_FTS_CHARS: usize = 3;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub book_id: i64,
+    pub chapter_id: i64,
+    pub label: String,
+    pub snippet: String,
+    // 越小越相关（与 FTS5 的 bm25 排序一致）
+    pub rank: f64,
+}
+
+// 注册索引触发器使用的 strip_tags 函数，每个数据库连接在执行迁移和写入章节前都需要注册
+pub fn register_functions(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.create_scalar_function(
+        "strip_tags",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            Ok(ctx
+                .get::<Option<String>>(0)?
+                .map(|content| strip_tags(&content)))
+        },
+    )
+}
+
+// 摘要是纯文本，转义其中的尖括号后再加上高亮标签
+fn escape_snippet(snippet: &str) -> String {
+    snippet
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace(PLACEHOLDER_START, MARK_START)
+        .replace(PLACEHOLDER_END, MARK_END)
+}
+
+// 把用户输入转换为 FTS5 短语查询，避免其中的引号和运算符被当作查询语法
+fn to_fts_phrase(keyword: &str) -> String {
+    format!("\"{}\"", keyword.replace('"', "\"\""))
+}
+
+pub fn search_chapter_hits(
+    db: &Connection,
+    keyword: &str,
+    book_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<SearchHit>, rusqlite::Error> {
+    if keyword.chars().count() < MIN_FTS_CHARS {
+        return search_short_keyword(db, keyword, book_id, limit);
+    }
+
+    let mut stmt = db.prepare(
+        "SELECT c.bookId, c.id, c.label, \
+                snippet(ee_chapter_fts, -1, ?, ?, '…', 16), ee_chapter_fts.rank \
+         FROM ee_chapter_fts \
+         JOIN ee_chapter c ON c.id = ee_chapter_fts.rowid \
+         JOIN ee_book b ON b.id = c.bookId \
+         WHERE ee_chapter_fts MATCH ? AND b.isDel = 0 AND (?4 IS NULL OR c.bookId = ?4) \
+         ORDER BY ee_chapter_fts.rank \
+         LIMIT ?",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            PLACEHOLDER_START,
+            PLACEHOLDER_END,
+            to_fts_phrase(keyword),
+            book_id,
+            limit
+        ],
+        |row| {
+            Ok(SearchHit {
+                book_id: row.get(0)?,
+                chapter_id: row.get(1)?,
+                label: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                snippet: escape_snippet(&row.get::<_, Option<String>>(3)?.unwrap_or_default()),
+                rank: row.get(4)?,
+            })
+        },
+    )?;
+    rows.collect()
+}
+
+// 一到两个字的关键字无法使用 trigram 索引，退化为逐章扫描
+fn search_short_keyword(
+    db: &Connection,
+    keyword: &str,
+    book_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<SearchHit>, rusqlite::Error> {
+    if keyword.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = db.prepare(
+        "SELECT bookId, id, label, content FROM ( \
+             SELECT c.bookId, c.id, c.label, strip_tags(c.content) AS content \
+             FROM ee_chapter c \
+             JOIN ee_book b ON b.id = c.bookId \
+             WHERE b.isDel = 0 AND (?2 IS NULL OR c.bookId = ?2)) \
+         WHERE instr(label, ?1) > 0 OR instr(content, ?1) > 0",
+    )?;
+    let rows = stmt.query_map(params![keyword, book_id], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+        ))
+    })?;
+
+    let mut hits = Vec::new();
+    for row in rows {
+        let (book_id, chapter_id, label, content) = row?;
+        let occurrences = label.matches(keyword).count() + content.matches(keyword).count();
+        let source = if content.contains(keyword) {
+            &content
+        } else {
+            &label
+        };
+        hits.push(SearchHit {
+            book_id,
+            chapter_id,
+            snippet: make_snippet(source, keyword),
+            label,
+            rank: -(occurrences as f64),
+        });
+    }
+
+    // 出现次数多的排在前面
+    hits.sort_by(|a, b| a.rank.total_cmp(&b.rank));
+    hits.truncate(limit.max(0) as usize);
+    Ok(hits)
+}
+
+// 截取关键字前后若干字符作为摘要，并高亮关键字
+fn make_snippet(text: &str, keyword: &str) -> String {
+    let Some(byte_pos) = text.find(keyword) else {
+        return String::new();
+    };
+    let before: Vec<char> = text[..byte_pos].chars().collect();
+    let after: Vec<char> = text[byte_pos + keyword.len()..].chars().collect();
+
+    let start = before.len().saturating_sub(SNIPPET_CONTEXT);
+    let end = after.len().min(SNIPPET_CONTEXT);
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    snippet.extend(&before[start..]);
+    snippet.push_str(PLACEHOLDER_START);
+    snippet.push_str(keyword);
+    snippet.push_str(PLACEHOLDER_END);
+    snippet.extend(&after[..end]);
+    if end < after.len() {
+        snippet.push('…');
+    }
+    escape_snippet(&snippet)
+}
+
+// 全文搜索章节，book_id 为空时搜索整个书库（不含已删除的书籍）
+#[command]
+pub fn search_chapters(
+    keyword: String,
+    book_id: Option<i64>,
+    limit: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<SearchHit>>, String> {
+    let db = get_db_connection(&state)?;
+    let keyword = keyword.trim();
+
+    match search_chapter_hits(&db, keyword, book_id, limit.unwrap_or(DEFAULT_LIMIT)) {
+        Ok(hits) => Ok(DbResponse::success(hits)),
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::open_test_db;
+
+    fn insert_chapter(db: &Connection, content: &str) {
+        db.execute(
+            "INSERT INTO ee_chapter (bookId, label, content) VALUES (1, '第一章', ?)",
+            params![content],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn search_ignores_tags_and_escapes_snippets() {
+        let db = open_test_db();
+        db.execute(
+            "INSERT INTO ee_book (id, title, isDel) VALUES (1, '书', 0)",
+            [],
+        )
+        .unwrap();
+        insert_chapter(&db, r#"<p class="footnote">他说 1 < 2 之后离开了</p>"#);
+
+        // 标签名和属性不参与检索
+        assert!(search_chapter_hits(&db, "footnote", None, 10)
+            .unwrap()
+            .is_empty());
+        assert!(search_chapter_hits(&db, "p", None, 10).unwrap().is_empty());
+
+        let cases = [
+            ("之后离开", "之后离开"),
+            ("离开", "离开"),
+            ("1 < 2", "1 &lt; 2"),
+        ];
+        for (keyword, marked) in cases {
+            let hits = search_chapter_hits(&db, keyword, None, 10).unwrap();
+            assert_eq!(hits.len(), 1, "{}", keyword);
+            assert!(!hits[0].snippet.contains("<p"), "{}", hits[0].snippet);
+            assert!(
+                hits[0]
+                    .snippet
+                    .contains(&format!("{}{}{}", MARK_START, marked, MARK_END)),
+                "{}",
+                hits[0].snippet
+            );
+        }
+    }
+
+    #[test]
+    fn index_follows_chapter_updates() {
+        let db = open_test_db();
+        db.execute(
+            "INSERT INTO ee_book (id, title, isDel) VALUES (1, '书', 0)",
+            [],
+        )
+        .unwrap();
+        insert_chapter(&db, "<p>旧的内容</p>");
+        db.execute("UPDATE ee_chapter SET content = '<p>新的文字</p>'", [])
+            .unwrap();
+
+        assert!(search_chapter_hits(&db, "旧的内容", None, 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            search_chapter_hits(&db, "新的文字", None, 10)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        db.execute("DELETE FROM ee_chapter", []).unwrap();
+        assert!(search_chapter_hits(&db, "新的文字", None, 10)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::database::init_db;
+use crate::trash::auto_purge_trash;
 use std::error::Error;
 use std::sync::Mutex;
 use tauri::{App, Manager};
@@ -10,7 +11,13 @@ pub struct AppState {
 
 pub fn setup_app(app: &mut App) -> Result<(), Box<dyn Error>> {
     // 调用 数据库初始化
-    let db = init_db(app.handle())?;
+    let mut db = init_db(app.handle())?;
+
+    // 按设置的保留天数自动清理回收站，失败时不影响启动
+    let app_dir = app.path().app_data_dir()?;
+    if let Err(err) = auto_purge_trash(&mut db, &app_dir) {
+        log::warn!("自动清理回收站失败: {}", err);
+    }
 
     // 将数据库连接存储在应用状态中
     app.manage(AppState { db: Mutex::new(db) });
@@ -0,0 +1,491 @@
+use crate::database::{get_db_connection, DbResponse};
+use crate::revision::{diff_lines, update_chapter_with_revision, DiffLine};
+use crate::setup::AppState;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{command, State};
+
+// 预览时每个章节最多返回的差异行数
+const PREVIEW_DIFF_LINES: usize = 20;
+
+// 文本处理操作，按顺序依次作用于章节内容
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum TextOperation {
+    // 去掉每行首尾空白
+    TrimLines,
+    // 去掉行首空白后统一加上 indent（空行保持为空）
+    IndentLines { indent: String },
+    // 连续空行最多保留 max 行，max 为 0 时删除所有空行
+    CollapseBlankLines { max: usize },
+    // 统一换行符，去掉零宽字符和行尾空白，行内连续空白合并为一个空格（保留行首缩进）
+    NormalizeWhitespace,
+    // 标点符号转换为全角或半角
+    Punctuation { to: PunctuationWidth },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PunctuationWidth {
+    Full,
+    Half,
+}
+
+// 内容片段：章节内容中保留的 HTML 标签不参与文本转换
+pub enum Segment<'a> {
+    Text(&'a str),
+    Tag(&'a str),
+}
+
+// 把内容切分为文本和 <...> 标签片段
+pub fn split_tags(content: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut text_start = 0;
+    let mut search = 0;
+    while let Some(offset) = content[search..].find('<') {
+        let start = search + offset;
+        let Some(len) = tag_len(&content[start..]) else {
+            search = start + 1;
+            continue;
+        };
+        if start > text_start {
+            segments.push(Segment::Text(&content[text_start..start]));
+        }
+        segments.push(Segment::Tag(&content[start..start + len]));
+        text_start = start + len;
+        search = text_start;
+    }
+    if text_start < content.len() {
+        segments.push(Segment::Text(&content[text_start..]));
+    }
+    segments
+}
+
+// 以 < 开头的标签长度：< 后必须是字母、/、! 或 ?，否则是正文中的小于号
+fn tag_len(rest: &str) -> Option<usize> {
+    let next = rest[1..].chars().next()?;
+    if !(next.is_ascii_alphabetic() || matches!(next, '/' | '!' | '?')) {
+        return None;
+    }
+    rest.find('>').map(|end| end + 1)
+}
+
+// 去掉标签后的文字，换行和空白原样保留
+pub fn strip_tags(content: &str) -> String {
+    split_tags(content)
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Text(text) => Some(*text),
+            Segment::Tag(_) => None,
+        })
+        .collect()
+}
+
+// 一行中去掉标签和首尾空白后的文字
+pub fn line_text(line: &str) -> String {
+    strip_tags(line).trim().to_string()
+}
+
+// 只转换文本片段，标签原样保留
+pub fn map_text_segments(content: &str, mut convert: impl FnMut(&str) -> String) -> String {
+    let mut result = String::with_capacity(content.len());
+    for segment in split_tags(content) {
+        match segment {
+            Segment::Text(text) => result.push_str(&convert(text)),
+            Segment::Tag(tag) => result.push_str(tag),
+        }
+    }
+    result
+}
+
+// 逐行处理，返回新文本和发生变化的行数
+fn map_lines(content: &str, convert: impl Fn(&str) -> String) -> (String, usize) {
+    let mut changed = 0;
+    let lines: Vec<String> = content
+        .split('\n')
+        .map(|line| {
+            let converted = convert(line);
+            if converted != line {
+                changed += 1;
+            }
+            converted
+        })
+        .collect();
+    (lines.join("\n"), changed)
+}
+
+fn is_blank(line: &str) -> bool {
+    line.chars().all(char::is_whitespace)
+}
+
+fn trim_lines(content: &str) -> (String, usize) {
+    map_lines(content, |line| line.trim().to_string())
+}
+
+fn indent_lines(content: &str, indent: &str) -> (String, usize) {
+    map_lines(content, |line| {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            String::new()
+        } else {
+            format!("{}{}", indent, trimmed)
+        }
+    })
+}
+
+fn collapse_blank_lines(content: &str, max: usize) -> (String, usize) {
+    let mut removed = 0;
+    let mut blank_run = 0;
+    let mut lines = Vec::new();
+    for line in content.split('\n') {
+        if is_blank(line) {
+            blank_run += 1;
+            if blank_run > max {
+                removed += 1;
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        lines.push(line);
+    }
+    (lines.join("\n"), removed)
+}
+
+fn is_zero_width(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}'
+    )
+}
+
+fn normalize_whitespace(content: &str) -> (String, usize) {
+    let unified = content.replace("\r\n", "\n").replace('\r', "\n");
+    let line_break_changes = if unified != content { 1 } else { 0 };
+
+    let (text, changed) = map_lines(&unified, |line| {
+        let line: String = line.chars().filter(|c| !is_zero_width(*c)).collect();
+        let body_start = line.len() - line.trim_start().len();
+        let (indent, body) = line.split_at(body_start);
+
+        let mut normalized = String::with_capacity(line.len());
+        normalized.push_str(indent);
+        let mut in_space = false;
+        for c in body.trim_end().chars() {
+            if c.is_whitespace() {
+                if !in_space {
+                    normalized.push(' ');
+                }
+                in_space = true;
+            } else {
+                normalized.push(c);
+                in_space = false;
+            }
+        }
+        normalized
+    });
+    (text, changed + line_break_changes)
+}
+
+// 中日韩文字（含全角标点），半角转全角时只转换与这些字符相邻的标点
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3000..=0x303F
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xF900..=0xFAFF
+            | 0xFF00..=0xFFEF
+            | 0x20000..=0x2FA1F
+    )
+}
+
+fn half_to_full(c: char) -> Option<char> {
+    Some(match c {
+        ',' => '，',
+        '.' => '。',
+        '?' => '？',
+        '!' => '！',
+        ':' => '：',
+        ';' => '；',
+        '(' => '（',
+        ')' => '）',
+        '[' => '【',
+        ']' => '】',
+        '~' => '～',
+        _ => return None,
+    })
+}
+
+fn full_to_half(c: char) -> Option<char> {
+    Some(match c {
+        '，' | '、' => ',',
+        '。' => '.',
+        '？' => '?',
+        '！' => '!',
+        '：' => ':',
+        '；' => ';',
+        '（' => '(',
+        '）' => ')',
+        '【' => '[',
+        '】' => ']',
+        '～' => '~',
+        '“' | '”' => '"',
+        '‘' | '’' => '\'',
+        _ => return None,
+    })
+}
+
+// 半角标点转全角：只有前后有中文时才转换，避免破坏数字、网址和英文
+fn punctuation_to_full(text: &str) -> (String, usize) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+    let mut count = 0;
+    // 是否处在一对引号之内，用来决定转换为左引号还是右引号
+    let mut in_double = false;
+    let mut in_single = false;
+    for (i, &c) in chars.iter().enumerate() {
+        // 前一个字符按转换后的结果判断，使 “你好！" 这类引号也能被转换
+        let prev_cjk = result.chars().next_back().is_some_and(is_cjk);
+        let next_cjk = chars.get(i + 1).is_some_and(|next| is_cjk(*next));
+        let converted = match c {
+            '"' if prev_cjk || next_cjk => {
+                in_double = !in_double;
+                Some(if in_double { '“' } else { '”' })
+            }
+            '\'' if prev_cjk || next_cjk => {
+                in_single = !in_single;
+                Some(if in_single { '‘' } else { '’' })
+            }
+            _ if prev_cjk || next_cjk => half_to_full(c),
+            _ => None,
+        };
+        match converted {
+            Some(full) => {
+                result.push(full);
+                count += 1;
+            }
+            None => result.push(c),
+        }
+    }
+    (result, count)
+}
+
+fn punctuation_to_half(text: &str) -> (String, usize) {
+    let mut count = 0;
+    let result = text
+        .chars()
+        .map(|c| match full_to_half(c) {
+            Some(half) => {
+                count += 1;
+                half
+            }
+            None => c,
+        })
+        .collect();
+    (result, count)
+}
+
+fn convert_punctuation(content: &str, to: PunctuationWidth) -> (String, usize) {
+    let mut count = 0;
+    let result = map_text_segments(content, |text| {
+        // 引号按行配对
+        text.split('\n')
+            .map(|line| {
+                let (converted, changed) = match to {
+                    PunctuationWidth::Full => punctuation_to_full(line),
+                    PunctuationWidth::Half => punctuation_to_half(line),
+                };
+                count += changed;
+                converted
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+    (result, count)
+}
+
+// 依次执行所有操作，返回处理后的文本和修改次数（行数或转换的字符数）
+pub fn apply_operations(content: &str, operations: &[TextOperation]) -> (String, usize) {
+    let mut text = content.to_string();
+    let mut total = 0;
+    for operation in operations {
+        let (next, changes) = match operation {
+            TextOperation::TrimLines => trim_lines(&text),
+            TextOperation::IndentLines { indent } => indent_lines(&text, indent),
+            TextOperation::CollapseBlankLines { max } => collapse_blank_lines(&text, *max),
+            TextOperation::NormalizeWhitespace => normalize_whitespace(&text),
+            TextOperation::Punctuation { to } => convert_punctuation(&text, *to),
+        };
+        text = next;
+        total += changes;
+    }
+    (text, total)
+}
+
+// 单个章节的处理结果，preview 只在预览时返回
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterTransformResult {
+    pub chapter_id: i64,
+    pub label: String,
+    pub changes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<Vec<DiffLine>>,
+}
+
+// 预览用的差异：只保留有变化的行，最多 PREVIEW_DIFF_LINES 行
+pub(crate) fn preview_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    diff_lines(old, new)
+        .into_iter()
+        .filter(|line| line.tag != "equal")
+        .take(PREVIEW_DIFF_LINES)
+        .collect()
+}
+
+pub fn transform_chapters(
+    db: &Connection,
+    book_id: i64,
+    operations: &[TextOperation],
+    dry_run: bool,
+) -> Result<Vec<ChapterTransformResult>, rusqlite::Error> {
+    let chapters = {
+        let mut stmt = db.prepare(
+            "SELECT id, label, content FROM ee_chapter WHERE bookId = ? ORDER BY position, id",
+        )?;
+        let rows = stmt.query_map(params![book_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            ))
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut results = Vec::new();
+    for (id, label, content) in chapters {
+        let (converted, changes) = apply_operations(&content, operations);
+        if converted == content {
+            continue;
+        }
+        let preview = if dry_run {
+            Some(preview_diff(&content, &converted))
+        } else {
+            update_chapter_with_revision(db, id, &label, Some(&converted))?;
+            None
+        };
+        results.push(ChapterTransformResult {
+            chapter_id: id,
+            label,
+            changes,
+            preview,
+        });
+    }
+    Ok(results)
+}
+
+// 对整本书的所有章节执行文本处理，dry_run 时只返回预览不写入数据库
+#[command]
+pub async fn transform_book_text(
+    book_id: i64,
+    operations: Vec<TextOperation>,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<ChapterTransformResult>>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match transform_chapters(&tx, book_id, &operations, dry_run.unwrap_or(false)) {
+        Ok(results) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(results))
+        }
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_each_operation() {
+        let cases = [
+            (TextOperation::TrimLines, "  a  \n\tb\nc", "a\nb\nc", 2),
+            (
+                TextOperation::IndentLines {
+                    indent: "　　".to_string(),
+                },
+                " a\n  \nb",
+                "　　a\n\n　　b",
+                3,
+            ),
+            (
+                TextOperation::CollapseBlankLines { max: 1 },
+                "a\n\n \n\nb\n\nc",
+                "a\n\nb\n\nc",
+                2,
+            ),
+            (
+                TextOperation::CollapseBlankLines { max: 0 },
+                "a\n\nb",
+                "a\nb",
+                1,
+            ),
+            (
+                TextOperation::NormalizeWhitespace,
+                "a\r\nb  c \u{200B}d \n  x\t y",
+                "a\nb c d\n  x y",
+                3,
+            ),
+            (
+                TextOperation::Punctuation {
+                    to: PunctuationWidth::Full,
+                },
+                "<p class=\"a.b\">他说:\"你好.\"</p>",
+                "<p class=\"a.b\">他说：“你好。”</p>",
+                4,
+            ),
+            (
+                TextOperation::Punctuation {
+                    to: PunctuationWidth::Full,
+                },
+                "<p>1.5, ok. 第3.5节</p>",
+                "<p>1.5, ok. 第3.5节</p>",
+                0,
+            ),
+            (
+                TextOperation::Punctuation {
+                    to: PunctuationWidth::Half,
+                },
+                "<p class=\"a，b\">你好，“世界”。</p>",
+                "<p class=\"a，b\">你好,\"世界\".</p>",
+                4,
+            ),
+        ];
+        for (operation, content, expected, changes) in cases {
+            let (text, count) = apply_operations(content, std::slice::from_ref(&operation));
+            assert_eq!(text, expected, "{:?}", operation);
+            assert_eq!(count, changes, "{:?}", operation);
+        }
+    }
+
+    #[test]
+    fn applies_operations_in_order() {
+        let operations = [
+            TextOperation::TrimLines,
+            TextOperation::CollapseBlankLines { max: 0 },
+            TextOperation::IndentLines {
+                indent: "　　".to_string(),
+            },
+        ];
+        let (text, count) = apply_operations(" 一 \n\n\n 二", &operations);
+        assert_eq!(text, "　　一\n　　二");
+        assert_eq!(count, 2 + 2 + 2);
+    }
+}
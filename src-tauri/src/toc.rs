@@ -0,0 +1,530 @@
+use crate::database::{get_db_connection, DbResponse};
+use crate::setup::AppState;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::{command, State};
+
+// 目录节点，与前端 toc JSON 的结构一致：href 为章节 id，没有子节点时 subitems 为 null
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TocItem {
+    pub label: String,
+    pub href: i64,
+    pub subitems: Option<Vec<TocItem>>,
+}
+
+// 解析前端保存的 toc JSON，href 可能是数字或数字字符串，无法识别的节点会被忽略
+pub fn parse_toc(json: &str) -> Vec<TocItem> {
+    match serde_json::from_str::<serde_json::Value>(json) {
+        Ok(serde_json::Value::Array(items)) => parse_toc_items(&items),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_toc_items(items: &[serde_json::Value]) -> Vec<TocItem> {
+    let mut result = Vec::new();
+    for item in items {
+        let href = match item.get("href") {
+            Some(serde_json::Value::Number(n)) => n.as_i64(),
+            Some(serde_json::Value::String(s)) => s.trim().parse::<i64>().ok(),
+            _ => None,
+        };
+        let Some(href) = href else {
+            continue;
+        };
+        let label = item
+            .get("label")
+            .and_then(|label| label.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let subitems = match item.get("subitems") {
+            Some(serde_json::Value::Array(children)) if !children.is_empty() => {
+                Some(parse_toc_items(children))
+            }
+            _ => None,
+        };
+        result.push(TocItem {
+            label,
+            href,
+            subitems,
+        });
+    }
+    result
+}
+
+// 章节在目录中的 (parentId, position)
+type TocSlot = (Option<i64>, Option<i64>);
+
+// 按 toc 树写入章节的 parentId / position；原本在目录中但 JSON 里缺失的章节追加到原父章节末尾（父章节不在树中时追加到顶级），
+// 原本不在目录中的章节仍不在目录中；只改写位置有变化的章节
+pub fn apply_toc(db: &Connection, book_id: i64, items: &[TocItem]) -> Result<(), rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "SELECT id, parentId, position FROM ee_chapter WHERE bookId = ? ORDER BY position, id",
+    )?;
+    let rows = stmt
+        .query_map(params![book_id], |row| {
+            Ok((row.get::<_, i64>(0)?, (row.get(1)?, row.get(2)?)))
+        })?
+        .collect::<Result<Vec<(i64, TocSlot)>, _>>()?;
+    let current: HashMap<i64, TocSlot> = rows.iter().copied().collect();
+
+    let mut slots: HashMap<i64, TocSlot> = current.keys().map(|id| (*id, (None, None))).collect();
+    let mut visited = HashSet::new();
+    collect_toc_slots(None, items, &mut slots, &mut visited);
+
+    let missing: Vec<(i64, Option<i64>)> = rows
+        .iter()
+        .filter(|(id, (_, position))| position.is_some() && !visited.contains(id))
+        .map(|(id, (parent_id, _))| (*id, *parent_id))
+        .collect();
+    let placed: HashSet<i64> = visited
+        .iter()
+        .copied()
+        .chain(missing.iter().map(|(id, _)| *id))
+        .collect();
+    let mut next: HashMap<Option<i64>, i64> = HashMap::new();
+    for (parent_id, position) in slots.values() {
+        if let Some(position) = position {
+            let entry = next.entry(*parent_id).or_default();
+            *entry = (*entry).max(position + 1);
+        }
+    }
+    for (id, parent_id) in missing {
+        let parent_id = parent_id.filter(|parent| placed.contains(parent) && *parent != id);
+        let position = next.entry(parent_id).or_default();
+        slots.insert(id, (parent_id, Some(*position)));
+        *position += 1;
+    }
+
+    let mut update = db.prepare("UPDATE ee_chapter SET parentId = ?, position = ? WHERE id = ?")?;
+    for (id, slot) in slots {
+        if current.get(&id) != Some(&slot) {
+            update.execute(params![slot.0, slot.1, id])?;
+        }
+    }
+    Ok(())
+}
+
+// slots 中只有本书的章节，其他 id 的节点连同子节点一起忽略
+fn collect_toc_slots(
+    parent_id: Option<i64>,
+    items: &[TocItem],
+    slots: &mut HashMap<i64, TocSlot>,
+    visited: &mut HashSet<i64>,
+) {
+    let mut position = 0;
+    for item in items {
+        // 同一章节在 JSON 中重复出现时只保留第一次
+        if !slots.contains_key(&item.href) || !visited.insert(item.href) {
+            continue;
+        }
+        slots.insert(item.href, (parent_id, Some(position)));
+        position += 1;
+        if let Some(children) = &item.subitems {
+            collect_toc_slots(Some(item.href), children, slots, visited);
+        }
+    }
+}
+
+// 从 ee_chapter 的 parentId / position 构建目录树
+pub fn load_toc(db: &Connection, book_id: i64) -> Result<Vec<TocItem>, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "SELECT id, label, parentId FROM ee_chapter \
+         WHERE bookId = ? AND position IS NOT NULL \
+         ORDER BY position, id",
+    )?;
+    let rows = stmt
+        .query_map(params![book_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                row.get::<_, Option<i64>>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let ids: HashSet<i64> = rows.iter().map(|(id, _, _)| *id).collect();
+    let mut labels: HashMap<i64, String> = HashMap::new();
+    let mut children: HashMap<Option<i64>, Vec<i64>> = HashMap::new();
+    for (id, label, parent_id) in rows {
+        // 父章节不在树中时挂到顶级
+        let parent_id = parent_id.filter(|parent| ids.contains(parent) && *parent != id);
+        labels.insert(id, label);
+        children.entry(parent_id).or_default().push(id);
+    }
+
+    let mut visited = HashSet::new();
+    Ok(build_toc_items(None, &children, &labels, &mut visited))
+}
+
+fn build_toc_items(
+    parent_id: Option<i64>,
+    children: &HashMap<Option<i64>, Vec<i64>>,
+    labels: &HashMap<i64, String>,
+    visited: &mut HashSet<i64>,
+) -> Vec<TocItem> {
+    let Some(ids) = children.get(&parent_id) else {
+        return Vec::new();
+    };
+    let mut items = Vec::new();
+    for id in ids {
+        // 防止错误数据形成环
+        if !visited.insert(*id) {
+            continue;
+        }
+        let subitems = build_toc_items(Some(*id), children, labels, visited);
+        items.push(TocItem {
+            label: labels.get(id).cloned().unwrap_or_default(),
+            href: *id,
+            subitems: if subitems.is_empty() {
+                None
+            } else {
+                Some(subitems)
+            },
+        });
+    }
+    items
+}
+
+// 根据章节表重新生成 ee_book.toc，返回最新的目录树
+pub fn sync_toc(db: &Connection, book_id: i64) -> Result<Vec<TocItem>, rusqlite::Error> {
+    let toc = load_toc(db, book_id)?;
+    let json = serde_json::to_string(&toc).unwrap_or_else(|_| "[]".to_string());
+    db.execute(
+        "UPDATE ee_book SET toc = ? WHERE id = ?",
+        params![json, book_id],
+    )?;
+    Ok(toc)
+}
+
+// 某个父节点下追加章节时使用的位置
+pub fn next_position(
+    db: &Connection,
+    book_id: i64,
+    parent_id: Option<i64>,
+) -> Result<i64, rusqlite::Error> {
+    db.query_row(
+        "SELECT COALESCE(MAX(position) + 1, 0) FROM ee_chapter WHERE bookId = ? AND parentId IS ?",
+        params![book_id, parent_id],
+        |row| row.get(0),
+    )
+}
+
+// 某个父节点下的子章节 id，按位置排序
+pub fn sibling_ids(
+    db: &Connection,
+    book_id: i64,
+    parent_id: Option<i64>,
+) -> Result<Vec<i64>, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "SELECT id FROM ee_chapter \
+         WHERE bookId = ? AND parentId IS ? AND position IS NOT NULL \
+         ORDER BY position, id",
+    )?;
+    let ids = stmt
+        .query_map(params![book_id, parent_id], |row| row.get(0))?
+        .collect::<Result<Vec<i64>, _>>()?;
+    Ok(ids)
+}
+
+// 按给定顺序重写一组兄弟章节的位置
+pub fn write_positions(
+    db: &Connection,
+    parent_id: Option<i64>,
+    ids: &[i64],
+) -> Result<(), rusqlite::Error> {
+    let mut stmt = db.prepare("UPDATE ee_chapter SET parentId = ?, position = ? WHERE id = ?")?;
+    for (position, id) in ids.iter().enumerate() {
+        stmt.execute(params![parent_id, position as i64, id])?;
+    }
+    Ok(())
+}
+
+// 章节在目录中的位置：(bookId, parentId, position)
+type ChapterLocation = (i64, Option<i64>, Option<i64>);
+
+fn chapter_location(db: &Connection, id: i64) -> Result<Option<ChapterLocation>, rusqlite::Error> {
+    db.query_row(
+        "SELECT bookId, parentId, position FROM ee_chapter WHERE id = ?",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .optional()
+}
+
+// 把章节移动到 parent_id 下的第 position 个位置（position 为空时追加到末尾）
+pub fn move_chapter_in_toc(
+    db: &Connection,
+    id: i64,
+    parent_id: Option<i64>,
+    position: Option<i64>,
+) -> Result<Vec<TocItem>, String> {
+    let (book_id, old_parent, _) = chapter_location(db, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("章节 {} 不存在", id))?;
+
+    // 新的父章节必须属于同一本书，且不能是自身或自身的子孙章节
+    let mut ancestor = parent_id;
+    let mut seen = HashSet::new();
+    while let Some(current) = ancestor {
+        if current == id || !seen.insert(current) {
+            return Err("不能把章节移动到自身或其子章节下".to_string());
+        }
+        let (parent_book, next, parent_position) = chapter_location(db, current)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("父章节 {} 不存在", current))?;
+        if parent_book != book_id || parent_position.is_none() {
+            return Err(format!("父章节 {} 不在当前书籍目录中", current));
+        }
+        ancestor = next;
+    }
+
+    if old_parent != parent_id {
+        let old_siblings: Vec<i64> = sibling_ids(db, book_id, old_parent)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|sibling| *sibling != id)
+            .collect();
+        write_positions(db, old_parent, &old_siblings).map_err(|e| e.to_string())?;
+    }
+
+    let mut siblings: Vec<i64> = sibling_ids(db, book_id, parent_id)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|sibling| *sibling != id)
+        .collect();
+    let index = position
+        .map(|p| p.clamp(0, siblings.len() as i64) as usize)
+        .unwrap_or(siblings.len());
+    siblings.insert(index, id);
+    write_positions(db, parent_id, &siblings).map_err(|e| e.to_string())?;
+
+    sync_toc(db, book_id).map_err(|e| e.to_string())
+}
+
+// 按 ids 的顺序重排 parent_id 下的子章节，未列出的子章节保持原有顺序排在后面
+pub fn reorder_chapters_in_toc(
+    db: &Connection,
+    book_id: i64,
+    parent_id: Option<i64>,
+    ids: &[i64],
+) -> Result<Vec<TocItem>, String> {
+    let siblings = sibling_ids(db, book_id, parent_id).map_err(|e| e.to_string())?;
+    if let Some(unknown) = ids.iter().find(|id| !siblings.contains(id)) {
+        return Err(format!("章节 {} 不是该节点的子章节", unknown));
+    }
+
+    let mut ordered: Vec<i64> = Vec::with_capacity(siblings.len());
+    for id in ids {
+        if !ordered.contains(id) {
+            ordered.push(*id);
+        }
+    }
+    ordered.extend(siblings.iter().filter(|id| !ids.contains(id)));
+    write_positions(db, parent_id, &ordered).map_err(|e| e.to_string())?;
+
+    sync_toc(db, book_id).map_err(|e| e.to_string())
+}
+
+// 获取书籍的章节目录树
+#[command]
+pub fn get_chapter_tree(
+    book_id: i64,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<TocItem>>, String> {
+    let db = get_db_connection(&state)?;
+
+    match load_toc(&db, book_id) {
+        Ok(toc) => Ok(DbResponse::success(toc)),
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
+}
+
+// 移动章节（同级调整位置或更换父章节），返回新的目录树
+#[command]
+pub fn move_chapter(
+    id: i64,
+    parent_id: Option<i64>,
+    position: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<TocItem>>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match move_chapter_in_toc(&tx, id, parent_id, position) {
+        Ok(toc) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(toc))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 重排同一父章节下的子章节顺序，返回新的目录树
+#[command]
+pub fn reorder_chapters(
+    book_id: i64,
+    parent_id: Option<i64>,
+    ids: Vec<i64>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<TocItem>>, String> {
+    let mut db = get_db_connection(&state)?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    match reorder_chapters_in_toc(&tx, book_id, parent_id, &ids) {
+        Ok(toc) => {
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(DbResponse::success(toc))
+        }
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::open_test_db;
+
+    // 新建一本书和若干顶级章节，返回章节 id
+    fn setup_book(db: &Connection, labels: &[&str]) -> Vec<i64> {
+        db.execute(
+            "INSERT INTO ee_book (id, title, isDel) VALUES (1, '书', 0)",
+            [],
+        )
+        .unwrap();
+        let mut ids = Vec::new();
+        for (position, label) in labels.iter().enumerate() {
+            db.execute(
+                "INSERT INTO ee_chapter (bookId, label, content, position) VALUES (1, ?, '', ?)",
+                params![label, position as i64],
+            )
+            .unwrap();
+            ids.push(db.last_insert_rowid());
+        }
+        ids
+    }
+
+    fn slot(db: &Connection, id: i64) -> TocSlot {
+        db.query_row(
+            "SELECT parentId, position FROM ee_chapter WHERE id = ?",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap()
+    }
+
+    // 目录树简写为 (id, 子节点)，便于比较
+    fn shape(items: &[TocItem]) -> Vec<(i64, Vec<i64>)> {
+        items
+            .iter()
+            .map(|item| {
+                let children = item.subitems.iter().flatten().map(|c| c.href).collect();
+                (item.href, children)
+            })
+            .collect()
+    }
+
+    fn stored_toc(db: &Connection) -> Vec<TocItem> {
+        let json: String = db
+            .query_row("SELECT toc FROM ee_book WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        parse_toc(&json)
+    }
+
+    fn item(href: i64, subitems: Option<Vec<TocItem>>) -> TocItem {
+        TocItem {
+            label: String::new(),
+            href,
+            subitems,
+        }
+    }
+
+    #[test]
+    fn apply_then_sync_round_trips() {
+        let db = open_test_db();
+        let ids = setup_book(&db, &["一", "二", "三"]);
+        let items = vec![
+            item(ids[2], None),
+            item(ids[0], Some(vec![item(ids[1], None)])),
+        ];
+
+        apply_toc(&db, 1, &items).unwrap();
+        let toc = sync_toc(&db, 1).unwrap();
+
+        assert_eq!(shape(&toc), vec![(ids[2], vec![]), (ids[0], vec![ids[1]])]);
+        assert_eq!(toc[0].label, "三");
+        assert_eq!(shape(&stored_toc(&db)), shape(&toc));
+
+        // 再次应用同一棵树不改变任何位置
+        apply_toc(&db, 1, &stored_toc(&db)).unwrap();
+        assert_eq!(shape(&load_toc(&db, 1).unwrap()), shape(&toc));
+    }
+
+    #[test]
+    fn apply_reparents_chapters() {
+        let db = open_test_db();
+        let ids = setup_book(&db, &["一", "二", "三"]);
+        apply_toc(
+            &db,
+            1,
+            &[item(
+                ids[0],
+                Some(vec![item(ids[1], None), item(ids[2], None)]),
+            )],
+        )
+        .unwrap();
+        assert_eq!(slot(&db, ids[2]), (Some(ids[0]), Some(1)));
+
+        apply_toc(
+            &db,
+            1,
+            &[
+                item(ids[0], None),
+                item(ids[2], Some(vec![item(ids[1], None)])),
+            ],
+        )
+        .unwrap();
+        assert_eq!(slot(&db, ids[1]), (Some(ids[2]), Some(0)));
+        assert_eq!(slot(&db, ids[2]), (None, Some(1)));
+        assert_eq!(
+            shape(&sync_toc(&db, 1).unwrap()),
+            vec![(ids[0], vec![]), (ids[2], vec![ids[1]])]
+        );
+    }
+
+    #[test]
+    fn apply_appends_missing_chapters() {
+        let db = open_test_db();
+        let ids = setup_book(&db, &["一", "二", "三", "四"]);
+        apply_toc(
+            &db,
+            1,
+            &[
+                item(ids[0], Some(vec![item(ids[1], None), item(ids[2], None)])),
+                item(ids[3], None),
+            ],
+        )
+        .unwrap();
+        // 不在目录中的章节
+        db.execute(
+            "INSERT INTO ee_chapter (bookId, label, content) VALUES (1, '附录', '')",
+            [],
+        )
+        .unwrap();
+        let hidden = db.last_insert_rowid();
+
+        // JSON 中缺少子章节 ids[1] 和顶级章节 ids[0]（连同未列出的子章节 ids[2]）
+        apply_toc(&db, 1, &[item(ids[3], None)]).unwrap();
+        let toc = sync_toc(&db, 1).unwrap();
+
+        assert_eq!(
+            shape(&toc),
+            vec![(ids[3], vec![]), (ids[0], vec![ids[1], ids[2]])]
+        );
+        assert_eq!(slot(&db, hidden), (None, None));
+
+        // 其他书的章节不受影响
+        apply_toc(&db, 2, &[item(ids[0], None)]).unwrap();
+        assert_eq!(slot(&db, ids[0]), (None, Some(1)));
+    }
+}
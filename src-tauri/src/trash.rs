@@ -0,0 +1,247 @@
+use crate::database::{get_current_time_string, get_db_connection, DbResponse};
+use crate::fileutil::{book_epub_dir, cover_path};
+use crate::setup::AppState;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tauri::{command, AppHandle, Manager, State};
+
+// 设置表中保存回收站保留天数的键
+const TRASH_RETENTION_KEY: &str = "trashRetentionDays";
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+// 回收站中的书籍
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedBook {
+    pub id: i64,
+    pub title: String,
+    pub author: String,
+    pub description: String,
+    // 放入回收站的时间（秒级时间戳）
+    pub delete_time: i64,
+    pub chapter_count: i64,
+}
+
+pub fn list_trashed_books(db: &Connection) -> Result<Vec<TrashedBook>, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "SELECT b.id, b.title, b.author, b.description, CAST(b.deleteTime AS INTEGER), \
+                (SELECT COUNT(*) FROM ee_chapter c WHERE c.bookId = b.id) \
+         FROM ee_book b WHERE b.isDel = 1 \
+         ORDER BY CAST(b.deleteTime AS INTEGER) DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TrashedBook {
+            id: row.get(0)?,
+            title: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            author: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            description: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            delete_time: row.get::<_, Option<i64>>(4)?.unwrap_or(0),
+            chapter_count: row.get(5)?,
+        })
+    })?;
+    rows.collect()
+}
+
+// 彻底删除一本回收站中的书籍：章节、修订记录、书籍记录，以及封面和图片文件
+pub fn purge_trashed_book(db: &mut Connection, app_dir: &Path, id: i64) -> Result<(), String> {
+    let is_deleted: Option<i64> = db
+        .query_row(
+            "SELECT isDel FROM ee_book WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match is_deleted {
+        None => return Err(format!("书籍 {} 不存在", id)),
+        Some(1) => {}
+        Some(_) => return Err(format!("书籍 {} 不在回收站中", id)),
+    }
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM ee_chapter WHERE bookId = ?", params![id])
+        .map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM ee_chapter_revision WHERE bookId = ?",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM ee_book WHERE id = ?", params![id])
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    // 数据库记录删除成功后再删除文件，文件删除失败不影响数据库
+    remove_book_files(app_dir, id)
+}
+
+fn remove_book_files(app_dir: &Path, id: i64) -> Result<(), String> {
+    let cover = cover_path(app_dir, id);
+    if cover.exists() {
+        fs::remove_file(&cover).map_err(|e| format!("删除封面失败: {}", e))?;
+    }
+    let epub_dir = book_epub_dir(app_dir, id);
+    if epub_dir.exists() {
+        fs::remove_dir_all(&epub_dir).map_err(|e| format!("删除图片目录失败: {}", e))?;
+    }
+    Ok(())
+}
+
+// 读取回收站保留天数，未设置或不大于 0 表示不自动清理
+pub fn get_trash_retention_days(db: &Connection) -> Result<Option<i64>, rusqlite::Error> {
+    let value: Option<String> = db
+        .query_row(
+            "SELECT value FROM ee_setting WHERE key = ?",
+            params![TRASH_RETENTION_KEY],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(value
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|days| *days > 0))
+}
+
+// 彻底删除放入回收站超过 max_age_days 天的书籍，返回被删除的书籍 id
+pub fn purge_expired_books(
+    db: &mut Connection,
+    app_dir: &Path,
+    max_age_days: i64,
+) -> Result<Vec<i64>, String> {
+    let now: i64 = get_current_time_string().parse().unwrap_or(0);
+    let cutoff = now - max_age_days * SECONDS_PER_DAY;
+
+    let ids = {
+        let mut stmt = db
+            .prepare(
+                "SELECT id FROM ee_book \
+                 WHERE isDel = 1 AND CAST(COALESCE(deleteTime, 0) AS INTEGER) <= ?",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![cutoff], |row| row.get::<_, i64>(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    for id in &ids {
+        purge_trashed_book(db, app_dir, *id)?;
+    }
+    Ok(ids)
+}
+
+// 启动时按设置的保留天数自动清理回收站
+pub fn auto_purge_trash(db: &mut Connection, app_dir: &Path) -> Result<Vec<i64>, String> {
+    match get_trash_retention_days(db).map_err(|e| e.to_string())? {
+        Some(days) => purge_expired_books(db, app_dir, days),
+        None => Ok(Vec::new()),
+    }
+}
+
+// 获取回收站中的书籍
+#[command]
+pub fn get_trashed_books(
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<TrashedBook>>, String> {
+    let db = get_db_connection(&state)?;
+
+    match list_trashed_books(&db) {
+        Ok(books) => Ok(DbResponse::success(books)),
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
+}
+
+// 从回收站恢复书籍
+#[command]
+pub fn restore_book(id: i64, state: State<'_, AppState>) -> Result<DbResponse<i64>, String> {
+    let db = get_db_connection(&state)?;
+
+    match db.execute(
+        "UPDATE ee_book SET isDel = 0, deleteTime = NULL, updateTime = datetime('now', 'localtime') \
+         WHERE id = ? AND isDel = 1",
+        params![id],
+    ) {
+        Ok(0) => Ok(DbResponse::error(format!("书籍 {} 不在回收站中", id))),
+        Ok(_) => Ok(DbResponse::success(id)),
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
+}
+
+// 彻底删除回收站中的书籍
+#[command]
+pub fn purge_book(
+    id: i64,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<i64>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+    let mut db = get_db_connection(&state)?;
+
+    match purge_trashed_book(&mut db, &app_dir, id) {
+        Ok(_) => Ok(DbResponse::success(id)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 清理放入回收站超过指定天数的书籍，未指定天数时使用设置中的保留天数
+#[command]
+pub fn purge_expired_trash(
+    max_age_days: Option<i64>,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Vec<i64>>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+    let mut db = get_db_connection(&state)?;
+
+    let result = match max_age_days {
+        Some(days) => purge_expired_books(&mut db, &app_dir, days.max(0)),
+        None => auto_purge_trash(&mut db, &app_dir),
+    };
+    match result {
+        Ok(ids) => Ok(DbResponse::success(ids)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 获取回收站保留天数
+#[command]
+pub fn get_trash_retention(state: State<'_, AppState>) -> Result<DbResponse<Option<i64>>, String> {
+    let db = get_db_connection(&state)?;
+
+    match get_trash_retention_days(&db) {
+        Ok(days) => Ok(DbResponse::success(days)),
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
+}
+
+// 设置回收站保留天数，为空或不大于 0 时关闭自动清理
+#[command]
+pub fn set_trash_retention(
+    days: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<()>, String> {
+    let db = get_db_connection(&state)?;
+
+    let result = match days.filter(|days| *days > 0) {
+        Some(days) => db.execute(
+            "INSERT INTO ee_setting (key, value) VALUES (?, ?) \
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![TRASH_RETENTION_KEY, days.to_string()],
+        ),
+        None => db.execute(
+            "DELETE FROM ee_setting WHERE key = ?",
+            params![TRASH_RETENTION_KEY],
+        ),
+    };
+    match result {
+        Ok(_) => Ok(DbResponse::success(())),
+        Err(err) => Ok(DbResponse::error(err.to_string())),
+    }
+}
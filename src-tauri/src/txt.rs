@@ -0,0 +1,488 @@
+use crate::database::{get_db_connection, Book, DbResponse};
+use crate::importer::{save_imported_book, ImportedBook, ImportedChapter, PREFACE_LABEL};
+use crate::setup::AppState;
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, GB18030, GBK, UTF_16BE, UTF_16LE, UTF_8};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::{command, AppHandle, Manager, State};
+
+// 标题行的最大长度（字符数），超过的行即使匹配也按正文处理
+pub const DEFAULT_MAX_HEADING_LENGTH: usize = 40;
+// 预览中每个章节返回的正文摘要长度
+const EXCERPT_CHARS: usize = 60;
+
+// 章节标题规则，volume 为 true 时匹配的行作为卷，之后的章节挂在其下
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadingPattern {
+    #[serde(default)]
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub volume: bool,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// 内置的标题规则，数字编号规则容易误判，默认不启用
+pub fn default_heading_patterns() -> Vec<HeadingPattern> {
+    let pattern = |name: &str, pattern: &str, volume: bool, enabled: bool| HeadingPattern {
+        name: name.to_string(),
+        pattern: pattern.to_string(),
+        volume,
+        enabled,
+    };
+    vec![
+        pattern(
+            "第X卷",
+            r"^[\s　]*第[0-9０-９零〇一二两三四五六七八九十百千万壹贰叁肆伍陆柒捌玖拾佰仟]+[卷部集篇](?:[\s　:：·].*)?$",
+            true,
+            true,
+        ),
+        pattern(
+            "第X章/回/节",
+            r"^[\s　]*第[0-9０-９零〇一二两三四五六七八九十百千万壹贰叁肆伍陆柒捌玖拾佰仟]+[章回节].*$",
+            false,
+            true,
+        ),
+        pattern(
+            "序章/楔子/番外",
+            r"^[\s　]*(?:序章|序言|序|楔子|引子|前言|后记|尾声|终章|番外)(?:[\s　:：·].*)?$",
+            false,
+            true,
+        ),
+        pattern(
+            "Chapter N",
+            r"(?i)^\s*(?:chapter|part)\s+(?:[0-9]+|[ivxlcdm]+)\b.*$",
+            false,
+            true,
+        ),
+        pattern(
+            "数字编号",
+            r"^[\s　]*[0-9０-９]{1,4}[、.．][\s　]*\S.*$",
+            false,
+            false,
+        ),
+    ]
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TxtImportOptions {
+    // 指定编码（如 GBK、Big5），为空时自动检测
+    pub encoding: Option<String>,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    // 为空时使用内置规则
+    pub patterns: Option<Vec<HeadingPattern>>,
+    pub max_heading_length: Option<usize>,
+}
+
+// 预览中的一个章节
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxtChapterPreview {
+    pub label: String,
+    pub volume: bool,
+    // 标题所在的行号（从 1 开始），前言为 0
+    pub line: usize,
+    pub chars: usize,
+    pub excerpt: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxtPreview {
+    pub title: String,
+    pub encoding: String,
+    pub chapters: Vec<TxtChapterPreview>,
+}
+
+// 切分出的章节
+struct TxtChapter {
+    label: String,
+    volume: bool,
+    line: usize,
+    content: String,
+}
+
+// 检测并解码文本：BOM -> 无 BOM 的 UTF-16 -> UTF-8 -> 按内容猜测 GBK/GB18030/Big5 等
+pub fn decode_text(
+    bytes: &[u8],
+    encoding: Option<&str>,
+) -> Result<(String, &'static Encoding), String> {
+    let encoding = match encoding.filter(|label| !label.trim().is_empty()) {
+        Some(label) => Encoding::for_label(label.trim().as_bytes())
+            .ok_or_else(|| format!("不支持的编码: {}", label))?,
+        None => detect_encoding(bytes),
+    };
+    // GBK 按其超集 GB18030 解码
+    let encoding = if encoding == GBK { GB18030 } else { encoding };
+
+    let (text, _, _) = encoding.decode(bytes);
+    Ok((text.into_owned(), encoding))
+}
+
+fn detect_encoding(bytes: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+
+    // 没有 BOM 的 UTF-16：先看换行符的字节序，再看 ASCII 字符高位或低位的 0 字节
+    let sample = &bytes[..bytes.len().min(4096) & !1];
+    if sample.len() >= 4 && sample.contains(&0) {
+        let units = sample.chunks_exact(2);
+        let le_newlines = units.clone().filter(|unit| unit == &[b'\n', 0]).count();
+        let be_newlines = units.filter(|unit| unit == &[0, b'\n']).count();
+        if le_newlines != be_newlines {
+            return if le_newlines > be_newlines {
+                UTF_16LE
+            } else {
+                UTF_16BE
+            };
+        }
+
+        let half = sample.len() / 2;
+        let even_zeros = sample.iter().step_by(2).filter(|b| **b == 0).count();
+        let odd_zeros = sample
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .filter(|b| **b == 0)
+            .count();
+        if odd_zeros * 10 > half * 3 && even_zeros * 20 < half {
+            return UTF_16LE;
+        }
+        if even_zeros * 10 > half * 3 && odd_zeros * 20 < half {
+            return UTF_16BE;
+        }
+    }
+
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+pub fn compile_patterns(patterns: &[HeadingPattern]) -> Result<Vec<(Regex, bool)>, String> {
+    patterns
+        .iter()
+        .filter(|pattern| pattern.enabled)
+        .map(|pattern| {
+            Regex::new(&pattern.pattern)
+                .map(|re| (re, pattern.volume))
+                .map_err(|e| format!("标题规则「{}」错误: {}", pattern.name, e))
+        })
+        .collect()
+}
+
+fn trim_heading(line: &str) -> String {
+    line.trim_matches(|c: char| c.is_whitespace()).to_string()
+}
+
+fn finish_content(lines: &[&str]) -> String {
+    lines.join("\n").trim_matches('\n').to_string()
+}
+
+// 按标题规则切分文本，没有匹配到任何标题时整本书作为一个章节
+fn split_chapters(
+    text: &str,
+    rules: &[(Regex, bool)],
+    max_heading_length: usize,
+    title: &str,
+) -> Vec<TxtChapter> {
+    let mut chapters = Vec::new();
+    let mut label = PREFACE_LABEL.to_string();
+    let mut volume = false;
+    let mut line_number = 0;
+    let mut lines: Vec<&str> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let heading = trim_heading(line);
+        let matched = if heading.is_empty() || heading.chars().count() > max_heading_length {
+            None
+        } else {
+            rules
+                .iter()
+                .find(|(re, _)| re.is_match(line))
+                .map(|(_, is_volume)| *is_volume)
+        };
+        match matched {
+            Some(is_volume) => {
+                let content = finish_content(&lines);
+                // 前言只有空白时丢弃
+                if line_number > 0 || !content.trim().is_empty() {
+                    chapters.push(TxtChapter {
+                        label: std::mem::take(&mut label),
+                        volume,
+                        line: line_number,
+                        content,
+                    });
+                }
+                label = heading;
+                volume = is_volume;
+                line_number = index + 1;
+                lines.clear();
+            }
+            None => lines.push(line),
+        }
+    }
+
+    let content = finish_content(&lines);
+    if chapters.is_empty() && line_number == 0 {
+        return vec![TxtChapter {
+            label: title.to_string(),
+            volume: false,
+            line: 0,
+            content,
+        }];
+    }
+    chapters.push(TxtChapter {
+        label,
+        volume,
+        line: line_number,
+        content,
+    });
+    chapters
+}
+
+// 卷作为顶级章节，其后的章节挂在卷下；href 根据标题所在行号生成章节位置
+fn build_chapter_tree(
+    chapters: Vec<TxtChapter>,
+    href: &dyn Fn(usize) -> String,
+) -> Vec<ImportedChapter> {
+    let mut tree: Vec<ImportedChapter> = Vec::new();
+    let mut in_volume = false;
+    for chapter in chapters {
+        let imported = ImportedChapter {
+            href: href(chapter.line),
+            label: chapter.label,
+            content: chapter.content,
+            children: Vec::new(),
+        };
+        if chapter.volume {
+            in_volume = true;
+            tree.push(imported);
+        } else if in_volume {
+            if let Some(volume) = tree.last_mut() {
+                volume.children.push(imported);
+            }
+        } else {
+            tree.push(imported);
+        }
+    }
+    tree
+}
+
+// 按内置标题规则切分文本，供没有目录信息的格式使用
+pub fn split_by_headings(
+    text: &str,
+    title: &str,
+    href: &dyn Fn(usize) -> String,
+) -> Vec<ImportedChapter> {
+    let rules = compile_patterns(&default_heading_patterns()).unwrap_or_default();
+    build_chapter_tree(
+        split_chapters(text, &rules, DEFAULT_MAX_HEADING_LENGTH, title),
+        href,
+    )
+}
+
+fn read_txt(
+    path: &Path,
+    options: &TxtImportOptions,
+) -> Result<(TxtPreview, Vec<TxtChapter>), String> {
+    let bytes = fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let (text, encoding) = decode_text(&bytes, options.encoding.as_deref())?;
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+
+    let title = options
+        .title
+        .clone()
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+    let patterns = options
+        .patterns
+        .clone()
+        .unwrap_or_else(default_heading_patterns);
+    let rules = compile_patterns(&patterns)?;
+    let max_heading_length = options
+        .max_heading_length
+        .unwrap_or(DEFAULT_MAX_HEADING_LENGTH);
+    let chapters = split_chapters(&text, &rules, max_heading_length, &title);
+
+    let preview = TxtPreview {
+        title,
+        encoding: encoding.name().to_string(),
+        chapters: chapters
+            .iter()
+            .map(|chapter| TxtChapterPreview {
+                label: chapter.label.clone(),
+                volume: chapter.volume,
+                line: chapter.line,
+                chars: chapter.content.chars().count(),
+                excerpt: chapter
+                    .content
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .chars()
+                    .take(EXCERPT_CHARS)
+                    .collect(),
+            })
+            .collect(),
+    };
+    Ok((preview, chapters))
+}
+
+// 获取内置的章节标题规则
+#[command]
+pub fn get_txt_heading_patterns() -> Result<DbResponse<Vec<HeadingPattern>>, String> {
+    Ok(DbResponse::success(default_heading_patterns()))
+}
+
+// 预览 TXT 的编码和章节切分结果，不写入数据库
+#[command]
+pub async fn preview_txt(
+    path: String,
+    options: Option<TxtImportOptions>,
+) -> Result<DbResponse<TxtPreview>, String> {
+    match read_txt(Path::new(&path), &options.unwrap_or_default()) {
+        Ok((preview, _)) => Ok(DbResponse::success(preview)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+// 导入 TXT 文件：检测编码、按标题规则切分章节，在一个事务中写入书籍和章节
+#[command]
+pub async fn import_txt(
+    path: String,
+    options: Option<TxtImportOptions>,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DbResponse<Book>, String> {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+    let options = options.unwrap_or_default();
+
+    let (preview, chapters) = match read_txt(Path::new(&path), &options) {
+        Ok(result) => result,
+        Err(err) => return Ok(DbResponse::error(err)),
+    };
+    let book = ImportedBook {
+        title: preview.title,
+        author: options
+            .author
+            .filter(|author| !author.trim().is_empty())
+            .unwrap_or_else(|| "佚名".to_string()),
+        description: "暂缺".to_string(),
+        chapters: build_chapter_tree(chapters, &|line| format!("txt-line-{}", line)),
+        ..Default::default()
+    };
+
+    let mut db = get_db_connection(&state)?;
+    match save_imported_book(&mut db, &app_dir, &book) {
+        Ok(book) => Ok(DbResponse::success(book)),
+        Err(err) => Ok(DbResponse::error(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "第一章 天下大势\n话说天下大势，分久必合，合久必分。\n" 的 GBK 编码
+    const GBK_BYTES: &[u8] = &[
+        0xB5, 0xDA, 0xD2, 0xBB, 0xD5, 0xC2, 0x20, 0xCC, 0xEC, 0xCF, 0xC2, 0xB4, 0xF3, 0xCA, 0xC6,
+        0x0A, 0xBB, 0xB0, 0xCB, 0xB5, 0xCC, 0xEC, 0xCF, 0xC2, 0xB4, 0xF3, 0xCA, 0xC6, 0xA3, 0xAC,
+        0xB7, 0xD6, 0xBE, 0xC3, 0xB1, 0xD8, 0xBA, 0xCF, 0xA3, 0xAC, 0xBA, 0xCF, 0xBE, 0xC3, 0xB1,
+        0xD8, 0xB7, 0xD6, 0xA1, 0xA3, 0x0A,
+    ];
+    const GBK_TEXT: &str = "第一章 天下大势\n话说天下大势，分久必合，合久必分。\n";
+
+    #[test]
+    fn decodes_fixtures() {
+        let cases: [(&[u8], Option<&str>, &str, &Encoding); 5] = [
+            // UTF-8 BOM 被去掉
+            (
+                b"\xEF\xBB\xBF\xE7\xAC\xAC\xE4\xB8\x80\xE7\xAB\xA0\nab",
+                None,
+                "第一章\nab",
+                UTF_8,
+            ),
+            // 没有 BOM 的 UTF-16LE："第一章\nab\n"
+            (
+                &[
+                    0x2C, 0x7B, 0x00, 0x4E, 0xE0, 0x7A, 0x0A, 0x00, 0x61, 0x00, 0x62, 0x00, 0x0A,
+                    0x00,
+                ],
+                None,
+                "第一章\nab\n",
+                UTF_16LE,
+            ),
+            (GBK_BYTES, None, GBK_TEXT, GB18030),
+            // 指定的 GBK 按 GB18030 解码
+            (GBK_BYTES, Some(" gbk "), GBK_TEXT, GB18030),
+            (b"plain ascii", Some(""), "plain ascii", UTF_8),
+        ];
+        for (bytes, label, expected, encoding) in cases {
+            let (text, detected) = decode_text(bytes, label).unwrap();
+            assert_eq!(text, expected);
+            assert_eq!(detected, encoding, "{}", expected);
+        }
+        assert!(decode_text(b"abc", Some("no-such-encoding")).is_err());
+    }
+
+    fn split(text: &str) -> Vec<(String, bool, usize, String)> {
+        let rules = compile_patterns(&default_heading_patterns()).unwrap();
+        split_chapters(text, &rules, DEFAULT_MAX_HEADING_LENGTH, "书名")
+            .into_iter()
+            .map(|c| (c.label, c.volume, c.line, c.content))
+            .collect()
+    }
+
+    #[test]
+    fn splits_chapters_with_preface() {
+        let chapter = |label: &str, volume, line, content: &str| {
+            (label.to_string(), volume, line, content.to_string())
+        };
+        let cases = [
+            (
+                "作者的话\n\n第一卷 起\n第一章 开始\n正文一\n\n　第二章 继续\n正文二",
+                vec![
+                    chapter(PREFACE_LABEL, false, 0, "作者的话"),
+                    chapter("第一卷 起", true, 3, ""),
+                    chapter("第一章 开始", false, 4, "正文一"),
+                    chapter("第二章 继续", false, 7, "正文二"),
+                ],
+            ),
+            // 只有空白的前言被丢弃
+            (
+                "\n  \n第一章 开始\n正文",
+                vec![chapter("第一章 开始", false, 3, "正文")],
+            ),
+            // 没有标题时整本书作为一个章节
+            (
+                "只有正文\n第二行",
+                vec![chapter("书名", false, 0, "只有正文\n第二行")],
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(split(text), expected, "{}", text);
+        }
+    }
+}
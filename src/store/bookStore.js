@@ -71,7 +71,7 @@ export const useBookStore = defineStore("bookStore", {
     },
     // 插入数据库中 并更新目录以及当前章节
     async addTocByHref(href, tocItem) {
-      await invoke("add_chapter", tocItem).then((res) => {
+      await invoke("add_chapter", { ...tocItem, parentId: href || null }).then((res) => {
         if (res.success) {
           const item = {
             label: tocItem.label,